@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::Fragment;
+
+// Measures of a CNF formula for telling what kind of instance it is before
+// solving it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FormulaStats {
+    // Variables that occur, and the largest of them
+    pub num_vars: usize,
+    pub max_var: i32,
+    pub num_clauses: usize,
+    pub num_literals: usize,
+    // Clauses by length
+    pub lengths: BTreeMap<usize, usize>,
+    pub positive_occurrences: usize,
+    pub negative_occurrences: usize,
+    pub binary_clauses: usize,
+    // Clauses with at most one positive literal
+    pub horn_clauses: usize,
+    // Literals of the unit clauses, and those whose negation occurs nowhere,
+    // both sorted by variable
+    pub units: Vec<i32>,
+    pub pure_literals: Vec<i32>,
+    pub fragment: Option<Fragment>,
+}
+
+impl FormulaStats {
+    // Positive occurrences per literal occurrence, 0.5 when balanced
+    pub fn positive_fraction(&self) -> f64 {
+        fraction(self.positive_occurrences, self.num_literals)
+    }
+
+    pub fn binary_fraction(&self) -> f64 {
+        fraction(self.binary_clauses, self.num_clauses)
+    }
+
+    pub fn horn_fraction(&self) -> f64 {
+        fraction(self.horn_clauses, self.num_clauses)
+    }
+}
+
+fn fraction(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+pub fn analyze(formula: &[Vec<i32>]) -> FormulaStats {
+    let mut stats = FormulaStats {
+        num_clauses: formula.len(),
+        fragment: Fragment::detect(formula),
+        ..Default::default()
+    };
+    // Positive and negative occurrences of every variable
+    let mut occurrences: HashMap<i32, (usize, usize)> = HashMap::new();
+    for clause in formula.iter() {
+        *stats.lengths.entry(clause.len()).or_default() += 1;
+        let positive = clause.iter().filter(|&&lit| lit > 0).count();
+        stats.positive_occurrences += positive;
+        stats.negative_occurrences += clause.len() - positive;
+        stats.binary_clauses += usize::from(clause.len() == 2);
+        stats.horn_clauses += usize::from(positive <= 1);
+        if let [lit] = clause[..] {
+            stats.units.push(lit);
+        }
+        for &lit in clause.iter() {
+            let entry = occurrences.entry(lit.abs()).or_default();
+            if lit > 0 {
+                entry.0 += 1;
+            } else {
+                entry.1 += 1;
+            }
+        }
+    }
+    stats.num_literals = stats.positive_occurrences + stats.negative_occurrences;
+    stats.num_vars = occurrences.len();
+    stats.max_var = occurrences.keys().copied().max().unwrap_or(0);
+    stats.units.sort_unstable_by_key(|lit| (lit.abs(), *lit));
+    stats.units.dedup();
+    stats.pure_literals = occurrences
+        .iter()
+        .filter_map(|(&var, &(positive, negative))| match (positive, negative) {
+            (_, 0) => Some(var),
+            (0, _) => Some(-var),
+            _ => None,
+        })
+        .collect();
+    stats.pure_literals.sort_unstable_by_key(|lit| lit.abs());
+    stats
+}
+
+impl fmt::Display for FormulaStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let percent = |fraction: f64| 100.0 * fraction;
+        writeln!(f, "Variables: {}", self.num_vars)?;
+        writeln!(f, "Max variable: {}", self.max_var)?;
+        writeln!(f, "Clauses: {}", self.num_clauses)?;
+        writeln!(f, "Literals: {}", self.num_literals)?;
+        let lengths: Vec<String> =
+            self.lengths.iter().map(|(length, count)| format!("{}: {}", length, count)).collect();
+        writeln!(f, "Clause lengths: {}", lengths.join(", "))?;
+        writeln!(f, "Positive occurrences: {} ({:.1}%)", self.positive_occurrences, percent(self.positive_fraction()))?;
+        writeln!(f, "Negative occurrences: {}", self.negative_occurrences)?;
+        writeln!(f, "Binary clauses: {} ({:.1}%)", self.binary_clauses, percent(self.binary_fraction()))?;
+        writeln!(f, "Horn clauses: {} ({:.1}%)", self.horn_clauses, percent(self.horn_fraction()))?;
+        writeln!(f, "Unit literals: {}", self.units.len())?;
+        write!(f, "Pure literals: {}", self.pure_literals.len())?;
+        if let Some(fragment) = self.fragment {
+            write!(f, "\nFragment: {}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let formula = vec![vec![1, -2, 5], vec![-1, 2], vec![3], vec![-1, -3, -5, 2], vec![3], vec![5, 1]];
+        let stats = analyze(&formula);
+        assert_eq!((stats.num_vars, stats.max_var, stats.num_clauses, stats.num_literals), (4, 5, 6, 13));
+        assert_eq!(stats.lengths, BTreeMap::from([(1, 2), (2, 2), (3, 1), (4, 1)]));
+        assert_eq!((stats.positive_occurrences, stats.negative_occurrences), (8, 5));
+        assert_eq!((stats.binary_clauses, stats.horn_clauses), (2, 4));
+        assert_eq!(stats.units, vec![3]);
+        assert_eq!(stats.pure_literals, Vec::<i32>::new());
+        assert_eq!(stats.fragment, None);
+        assert!(stats.to_string().contains("Clause lengths: 1: 2, 2: 2, 3: 1, 4: 1"));
+
+        let stats = analyze(&[vec![-1, 2], vec![-2, -3], vec![4]]);
+        assert_eq!(stats.pure_literals, vec![-1, -3, 4]);
+        assert_eq!(stats.horn_fraction(), 1.0);
+        assert_eq!(stats.fragment, Some(Fragment::TwoSat));
+        assert_eq!(analyze(&[]), FormulaStats { fragment: Fragment::detect(&[]), ..Default::default() });
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::{Enumerate, FilterMap};
+use std::ops::Index;
+use std::vec;
+
+// Values of the variables of a search in a vector indexed by variable, None
+// while a variable is unassigned. Variables that were never added are
+// absent, get tells them apart from unassigned ones like a map would.
+//
+// Every change is also recorded on a trail with the value it replaced, so a
+// search can keep one assignment, remember the mark of the state it branches
+// from and undo back to it instead of copying the assignment per node.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Slots", into = "Slots"))]
+pub struct Assignment {
+    values: Vec<Option<Option<bool>>>,
+    len: usize,
+    trail: Vec<(i32, Option<Option<bool>>)>,
+}
+
+impl Assignment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // An empty assignment with room for the variables 1 to num_vars, as a
+    // DIMACS header declares them
+    pub fn with_num_vars(num_vars: usize) -> Self {
+        Assignment {
+            values: vec![None; num_vars + 1],
+            len: 0,
+            trail: Vec::new(),
+        }
+    }
+
+    // Number of variables present, assigned or not
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The value of var, None if var is absent
+    pub fn get(&self, var: &i32) -> Option<&Option<bool>> {
+        self.values.get(usize::try_from(*var).ok()?)?.as_ref()
+    }
+
+    pub fn contains_key(&self, var: &i32) -> bool {
+        self.get(var).is_some()
+    }
+
+    // Set the value of var, adding it if it is absent, and return the old one
+    pub fn insert(&mut self, var: i32, val: Option<bool>) -> Option<Option<bool>> {
+        assert!(var > 0, "Not a variable: {}", var);
+        let index = var as usize;
+        if index >= self.values.len() {
+            self.values.resize(index + 1, None);
+        }
+        let old = self.values[index].replace(val);
+        if old.is_none() {
+            self.len += 1;
+        }
+        if old != Some(val) {
+            self.trail.push((var, old));
+        }
+        old
+    }
+
+    // Add var unassigned unless it is present already
+    pub fn add_var(&mut self, var: i32) {
+        if !self.contains_key(&var) {
+            self.insert(var, None);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.len = 0;
+        self.trail.clear();
+    }
+
+    pub fn remove(&mut self, var: &i32) -> Option<Option<bool>> {
+        let old = self.values.get_mut(usize::try_from(*var).ok()?)?.take();
+        if old.is_some() {
+            self.len -= 1;
+            self.trail.push((*var, old));
+        }
+        old
+    }
+
+    // The position on the trail, undo returns to the assignment as it is now
+    pub fn mark(&self) -> usize {
+        self.trail.len()
+    }
+
+    // Restore the values that the changes after mark replaced, newest first
+    pub fn undo(&mut self, mark: usize) {
+        while self.trail.len() > mark {
+            let (var, old) = self.trail.pop().unwrap();
+            self.restore(var, old);
+        }
+    }
+
+    fn restore(&mut self, var: i32, old: Option<Option<bool>>) {
+        let entry = &mut self.values[var as usize];
+        match (entry.is_some(), old.is_some()) {
+            (true, false) => self.len -= 1,
+            (false, true) => self.len += 1,
+            _ => {}
+        }
+        *entry = old;
+    }
+
+    // Forget the changes so far, they can no longer be undone
+    pub fn clear_trail(&mut self) {
+        self.trail.clear();
+    }
+
+    // The variables changed after mark with the values they had before, in
+    // the order of the changes
+    pub fn changes(&self, mark: usize) -> impl Iterator<Item = (i32, Option<Option<bool>>)> + '_ {
+        self.trail[mark..].iter().copied()
+    }
+
+    // A copy of the assignment as it was at mark, with an empty trail
+    pub fn at_mark(&self, mark: usize) -> Assignment {
+        let mut copy = Assignment {
+            values: self.values.clone(),
+            len: self.len,
+            trail: Vec::new(),
+        };
+        for &(var, old) in self.trail[mark..].iter().rev() {
+            copy.restore(var, old);
+        }
+        copy
+    }
+
+    // The present variables and their values, by increasing variable
+    pub fn iter(&self) -> impl Iterator<Item = (i32, Option<bool>)> + '_ {
+        self.values.iter().enumerate().filter_map(|(var, val)| Some((var as i32, (*val)?)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = i32> + '_ {
+        self.iter().map(|(var, _)| var)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Option<bool>> {
+        self.values.iter().flatten()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Option<bool>> {
+        self.values.iter_mut().flatten()
+    }
+}
+
+impl PartialEq for Assignment {
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Assignment {}
+
+impl fmt::Debug for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl Index<&i32> for Assignment {
+    type Output = Option<bool>;
+
+    fn index(&self, var: &i32) -> &Option<bool> {
+        self.get(var).unwrap_or_else(|| panic!("Variable {} is not in the assignment", var))
+    }
+}
+
+impl Extend<(i32, Option<bool>)> for Assignment {
+    fn extend<T: IntoIterator<Item = (i32, Option<bool>)>>(&mut self, iter: T) {
+        for (var, val) in iter {
+            self.insert(var, val);
+        }
+    }
+}
+
+impl FromIterator<(i32, Option<bool>)> for Assignment {
+    fn from_iter<T: IntoIterator<Item = (i32, Option<bool>)>>(iter: T) -> Self {
+        let mut assignment = Assignment::new();
+        assignment.extend(iter);
+        assignment
+    }
+}
+
+impl From<HashMap<i32, Option<bool>>> for Assignment {
+    fn from(map: HashMap<i32, Option<bool>>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+type Present = fn((usize, Option<Option<bool>>)) -> Option<(i32, Option<bool>)>;
+
+impl IntoIterator for Assignment {
+    type Item = (i32, Option<bool>);
+    type IntoIter = FilterMap<Enumerate<vec::IntoIter<Option<Option<bool>>>>, Present>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter().enumerate().filter_map(|(var, val)| Some((var as i32, val?)))
+    }
+}
+
+// A value of the assignment as it is serialized. Formats like JSON write
+// both None and Some(None) as null, so an absent variable and an unassigned
+// one would read back the same.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+enum Slot {
+    Absent,
+    Unassigned,
+    True,
+    False,
+}
+
+#[cfg(feature = "serde")]
+impl From<Option<Option<bool>>> for Slot {
+    fn from(value: Option<Option<bool>>) -> Self {
+        match value {
+            None => Slot::Absent,
+            Some(None) => Slot::Unassigned,
+            Some(Some(true)) => Slot::True,
+            Some(Some(false)) => Slot::False,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Slot> for Option<Option<bool>> {
+    fn from(slot: Slot) -> Self {
+        match slot {
+            Slot::Absent => None,
+            Slot::Unassigned => Some(None),
+            Slot::True => Some(Some(true)),
+            Slot::False => Some(Some(false)),
+        }
+    }
+}
+
+// The serialized form of an Assignment, the trail included
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Slots {
+    values: Vec<Slot>,
+    trail: Vec<(i32, Slot)>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Assignment> for Slots {
+    fn from(assignment: Assignment) -> Self {
+        Slots {
+            values: assignment.values.into_iter().map(Slot::from).collect(),
+            trail: assignment.trail.into_iter().map(|(var, old)| (var, Slot::from(old))).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Slots> for Assignment {
+    fn from(slots: Slots) -> Self {
+        let values: Vec<Option<Option<bool>>> = slots.values.into_iter().map(Slot::into).collect();
+        Assignment {
+            len: values.iter().filter(|value| value.is_some()).count(),
+            values,
+            trail: slots.trail.into_iter().map(|(var, old)| (var, old.into())).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assignment() {
+        let mut assignment = Assignment::with_num_vars(3);
+        assert!(assignment.is_empty());
+        assert_eq!(assignment.get(&2), None);
+        assignment.add_var(2);
+        assert_eq!(assignment.get(&2), Some(&None));
+        assert_eq!(assignment.insert(2, Some(true)), Some(None));
+        assignment.add_var(2);
+        assert_eq!(assignment[&2], Some(true));
+        // Variables past the declared ones are added as well
+        assert_eq!(assignment.insert(7, Some(false)), None);
+        assert_eq!(assignment.len(), 2);
+        assert_eq!(assignment.iter().collect::<Vec<_>>(), vec![(2, Some(true)), (7, Some(false))]);
+        assert_eq!(assignment.get(&0), None);
+        assert_eq!(assignment.get(&-2), None);
+
+        let same: Assignment = [(7, Some(false)), (2, Some(true))].into_iter().collect();
+        assert_eq!(assignment, same);
+        assert_eq!(assignment.remove(&7), Some(Some(false)));
+        assert_ne!(assignment, same);
+        assert_eq!(assignment.len(), 1);
+        assert_eq!(format!("{:?}", assignment), "{2: Some(true)}");
+    }
+
+    #[test]
+    fn test_trail() {
+        let mut assignment: Assignment = [(1, None), (2, None)].into_iter().collect();
+        assignment.clear_trail();
+        let root = assignment.clone();
+        assignment.insert(1, Some(true));
+        let mark = assignment.mark();
+        assignment.insert(2, Some(false));
+        // Setting a value again changes nothing and is not recorded
+        assignment.insert(2, Some(false));
+        assignment.insert(5, Some(true));
+        assert_eq!(assignment.changes(mark).collect::<Vec<_>>(), vec![(2, Some(None)), (5, None)]);
+        let copy = assignment.at_mark(mark);
+        assert_eq!(copy.iter().collect::<Vec<_>>(), vec![(1, Some(true)), (2, None)]);
+        assert_eq!(copy.mark(), 0);
+
+        assignment.undo(mark);
+        assert_eq!(assignment, copy);
+        assert_eq!(assignment.len(), 2);
+        assignment.undo(0);
+        assert_eq!(assignment, root);
+    }
+}
@@ -0,0 +1,114 @@
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use clap::Parser;
+use dpll::*;
+
+/// Solve a bundled corpus of instances under a few solver configurations and
+/// report the wall time and search speed of each run
+#[derive(Parser)]
+#[command(name = "bench")]
+struct Cli {
+    /// Runs per instance and configuration, the median time is reported
+    #[arg(long, default_value_t = 3)]
+    repeat: usize,
+
+    /// Only the instances whose name contains this
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Give up on a run after this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    timeout: u64,
+}
+
+// An instance of the corpus: its name, DIMACS text and whether it is satisfiable
+struct Instance {
+    name: &'static str,
+    text: &'static str,
+    sat: bool,
+}
+
+const CORPUS: [Instance; 5] = [
+    Instance { name: "testcnf", text: include_str!("../../testcnf.cnf"), sat: false },
+    Instance { name: "500250", text: include_str!("../../500250.cnf"), sat: false },
+    Instance { name: "72test", text: include_str!("../../72test.cnf"), sat: false },
+    Instance { name: "bigtest", text: include_str!("../../bigtest.cnf"), sat: true },
+    Instance { name: "124test", text: include_str!("../../124test.cnf"), sat: false },
+];
+
+// The configurations every instance is solved under, by name
+fn configs() -> Vec<(&'static str, SolverConfig)> {
+    vec![
+        ("default", SolverConfig::default()),
+        ("dlis", SolverConfig { heuristic: "dlis".to_string(), ..Default::default() }),
+        ("jw-phases", SolverConfig { heuristic: "jw-two-sided".to_string(), phase_saving: true, ..Default::default() }),
+        ("learn-luby", SolverConfig { learn: true, restart_unit: Some(100), ..Default::default() }),
+    ]
+}
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let repeat = cli.repeat.max(1);
+    let timeout = Duration::from_secs(cli.timeout);
+    let mut wrong = 0;
+    println!("{:<10} {:<12} {:>7} {:>10} {:>10} {:>12}", "instance", "config", "result", "nodes", "ms", "nodes/s");
+    for instance in CORPUS.iter() {
+        if cli.filter.as_ref().is_some_and(|filter| !instance.name.contains(filter.as_str())) {
+            continue;
+        }
+        let mut parse_times = Vec::new();
+        let mut dimacs = Dimacs::default();
+        for _ in 0..repeat {
+            let start = Instant::now();
+            dimacs = parse_dimacs(instance.text.as_bytes(), ParseMode::Lenient)
+                .expect("Bundled instance does not parse");
+            parse_times.push(start.elapsed());
+        }
+        let parse_time = median(parse_times).as_secs_f64() * 1e3;
+        println!("{:<10} {:<12} {:>7} {:>10} {:>10.3}", instance.name, "parse", "", "", parse_time);
+        for (name, config) in configs() {
+            let mut times = Vec::new();
+            let mut result = SolveResult::Unknown;
+            let mut nodes = 0;
+            for _ in 0..repeat {
+                let mut solver = Solver::from_clauses(&dimacs.formula);
+                config.apply(&mut solver);
+                let start = Instant::now();
+                result = solver.solve_with_timeout(timeout);
+                times.push(start.elapsed());
+                nodes = solver.stats().nodes;
+            }
+            let time = median(times);
+            let label = match result {
+                SolveResult::Sat(_) => "SAT",
+                SolveResult::Unsat => "UNSAT",
+                SolveResult::Unknown => "TIMEOUT",
+            };
+            let expected = if instance.sat { "SAT" } else { "UNSAT" };
+            if label != expected && label != "TIMEOUT" {
+                eprintln!("{} under {}: expected {}, got {}", instance.name, name, expected, label);
+                wrong += 1;
+            }
+            let speed = nodes as f64 / time.as_secs_f64().max(1e-9);
+            println!(
+                "{:<10} {:<12} {:>7} {:>10} {:>10.3} {:>12.0}",
+                instance.name,
+                name,
+                label,
+                nodes,
+                time.as_secs_f64() * 1e3,
+                speed,
+            );
+        }
+    }
+    if wrong > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
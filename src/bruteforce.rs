@@ -0,0 +1,108 @@
+use crate::Model;
+
+// Reference solver that tries every assignment of the variables of the
+// formula in turn, for checking the real search on small formulas. The model
+// gives a value to exactly the variables that occur.
+pub fn bruteforce_solve(formula: &[Vec<i32>]) -> Option<Model> {
+    let mut vars: Vec<i32> = formula.iter().flatten().map(|lit| lit.abs()).collect();
+    vars.sort_unstable();
+    vars.dedup();
+    assert!(vars.len() <= 25, "{} variables are too many to enumerate", vars.len());
+    let mut values = vec![false; vars.last().map_or(0, |&var| var as usize + 1)];
+    for bits in 0..1u32 << vars.len() {
+        for (i, &var) in vars.iter().enumerate() {
+            values[var as usize] = bits >> i & 1 == 1;
+        }
+        let value = |lit: i32| values[lit.unsigned_abs() as usize] == (lit > 0);
+        if formula.iter().all(|clause| clause.iter().any(|&lit| value(lit))) {
+            return Some(vars.iter().map(|&var| (var, values[var as usize])).collect());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        initial_assignment, pure_literal_elimination, unit_propagation, verify_model, Formula, SolveResult, Solver,
+        SolverConfig,
+    };
+    use maplit::hashmap;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Small formulas with the edge cases the search has to get right: empty
+    // clauses, repeated literals, tautologies and variables that occur with
+    // one polarity only
+    fn random_formula(rng: &mut StdRng) -> Formula {
+        let num_vars = rng.gen_range(1..=8);
+        (0..rng.gen_range(0..=25))
+            .map(|_| {
+                let len = if rng.gen_bool(0.02) { 0 } else { rng.gen_range(1..=4) };
+                (0..len).map(|_| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 }).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_bruteforce_solve() {
+        assert_eq!(bruteforce_solve(&[]), Some(Model::new()));
+        assert_eq!(bruteforce_solve(&[vec![]]), None);
+        assert_eq!(bruteforce_solve(&[vec![1], vec![-1]]), None);
+        assert_eq!(bruteforce_solve(&[vec![-1, 3], vec![1], vec![1, -1]]), Some(hashmap! { 1 => true, 3 => true }));
+    }
+
+    #[test]
+    fn test_search_agrees_with_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(575);
+        let configs = SolverConfig::diverse(6);
+        for _ in 0..300 {
+            let formula = random_formula(&mut rng);
+            let expected = bruteforce_solve(&formula);
+            for config in configs.iter() {
+                let mut solver = Solver::from_clauses(&formula);
+                config.apply(&mut solver);
+                match solver.solve_limited() {
+                    SolveResult::Sat(model) => {
+                        assert!(expected.is_some(), "{:?} is unsatisfiable, {:?} found {:?}", formula, config, model);
+                        assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?} {:?}", formula, config, model);
+                    }
+                    SolveResult::Unsat => assert!(expected.is_none(), "{:?} is satisfiable, {:?}", formula, config),
+                    SolveResult::Unknown => panic!("{:?} gave up on {:?}", config, formula),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_simplifications_agree_with_bruteforce() {
+        let mut rng = StdRng::seed_from_u64(576);
+        for _ in 0..300 {
+            let formula = random_formula(&mut rng);
+            let expected = bruteforce_solve(&formula);
+
+            // Pure literals only ever take values that some model shares
+            let mut assignment = initial_assignment(&formula);
+            let simplified = pure_literal_elimination(&formula, &mut assignment);
+            match bruteforce_solve(&simplified) {
+                Some(rest) => {
+                    let mut model: Model = assignment.iter().filter_map(|(var, val)| Some((var, val?))).collect();
+                    model.extend(rest);
+                    for var in formula.iter().flatten().map(|lit| lit.abs()) {
+                        model.entry(var).or_insert(true);
+                    }
+                    assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?}", formula, model);
+                }
+                None => assert!(expected.is_none(), "{:?} became {:?}", formula, simplified),
+            }
+
+            // Unit propagation only reports conflicts that are there
+            let mut assignment = initial_assignment(&formula);
+            let (result, _, _) = unit_propagation(formula.clone(), &mut assignment);
+            if result == 0 {
+                assert!(expected.is_none(), "{:?}", formula);
+            }
+        }
+    }
+}
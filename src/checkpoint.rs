@@ -0,0 +1,176 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Assignment, ClauseId, Formula, Node, PbConstraint, Stats, XorConstraint};
+
+// The constraints a search runs on, a snapshot only resumes a solver that
+// has the same ones
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    pub formula: Formula,
+    pub xors: Vec<XorConstraint>,
+    pub pbs: Vec<PbConstraint>,
+}
+
+// A node of the tasklist without the clause database, which every node of a
+// search shares with the root
+#[derive(Serialize, Deserialize)]
+struct Task {
+    clauses: Vec<ClauseId>,
+    value: Option<bool>,
+    variable: i32,
+    assignment: Assignment,
+    decisions: Vec<i32>,
+}
+
+// Everything a search needs to go on where it was: the root it restarts
+// from, the nodes still to expand and the counters so far. Learned clauses,
+// saved phases and the state of the heuristic and the restart policy are
+// not kept, the resumed search starts them afresh.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Snapshot {
+    pub(crate) problem: Problem,
+    pub(crate) root: Node,
+    tasks: Vec<Task>,
+    pub(crate) stats: Stats,
+}
+
+impl Snapshot {
+    // The tasklist with every node detached from the search's assignment
+    fn new(problem: Problem, root: &Node, tasklist: &[Node], assignment: &Assignment, stats: &Stats) -> Self {
+        let tasks = tasklist
+            .iter()
+            .map(|node| {
+                debug_assert!(Arc::ptr_eq(&node.db, &root.db), "A task of another clause database");
+                let node = node.clone().detach(assignment);
+                Task {
+                    clauses: node.clauses,
+                    value: node.value,
+                    variable: node.variable,
+                    assignment: node.assignment.unwrap_or_default(),
+                    decisions: node.decisions,
+                }
+            })
+            .collect();
+        Snapshot {
+            problem,
+            root: root.clone(),
+            tasks,
+            stats: stats.clone(),
+        }
+    }
+
+    pub(crate) fn read(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader).map_err(io::Error::from)
+    }
+
+    // Write to a file next to path and rename it over path, so a crash
+    // while writing leaves the previous snapshot in place
+    fn write(&self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::from)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&temporary, path)
+    }
+
+    // The tasklist as nodes of the root's database again
+    pub(crate) fn tasklist(self) -> Vec<Node> {
+        let db = self.root.db;
+        self.tasks
+            .into_iter()
+            .map(|task| Node {
+                db: db.clone(),
+                clauses: task.clauses,
+                value: task.value,
+                variable: task.variable,
+                assignment: Some(task.assignment),
+                mark: 0,
+                decisions: task.decisions,
+            })
+            .collect()
+    }
+}
+
+// Writes a snapshot of the search to a file whenever the interval has passed
+// since the last one
+pub struct Checkpointer {
+    path: PathBuf,
+    interval: Duration,
+    last: Instant,
+    // The constraints of the running search, set when it starts
+    pub(crate) problem: Problem,
+    // The latest write that failed, the search goes on without it
+    pub(crate) error: Option<io::Error>,
+}
+
+impl Checkpointer {
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Checkpointer {
+            path: path.into(),
+            interval,
+            last: Instant::now(),
+            problem: Problem::default(),
+            error: None,
+        }
+    }
+
+    // Called between two nodes of the search, when every node left is on
+    // the tasklist
+    pub(crate) fn tick(&mut self, root: &Node, tasklist: &[Node], assignment: &Assignment, stats: &Stats) {
+        if self.last.elapsed() < self.interval {
+            return;
+        }
+        let snapshot = Snapshot::new(self.problem.clone(), root, tasklist, assignment, stats);
+        if let Err(error) = snapshot.write(&self.path) {
+            self.error = Some(error);
+        }
+        self.last = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bruteforce_solve, random_ksat, verify_model, Budget, SolveResult, Solver};
+
+    #[test]
+    fn test_checkpoint_and_resume() {
+        let path = std::env::temp_dir().join(format!("dpll-checkpoint-{}.json", std::process::id()));
+        for seed in 0..8 {
+            let formula = random_ksat(20, 88, 3, seed);
+            let mut solver = Solver::from_clauses(&formula);
+            solver.checkpoint(&path, Duration::ZERO);
+            solver.set_budget(Budget { max_nodes: Some(20), ..Default::default() });
+            if solver.solve_limited() != SolveResult::Unknown {
+                continue;
+            }
+            assert!(solver.checkpoint_error().is_none());
+
+            let mut resumed = Solver::from_clauses(&formula);
+            resumed.resume(&path).unwrap();
+            assert_eq!(resumed.stats().nodes, solver.stats().nodes);
+            match resumed.solve_limited() {
+                SolveResult::Sat(model) => assert!(verify_model(&formula, &model).is_ok()),
+                SolveResult::Unsat => assert_eq!(bruteforce_solve(&formula), None),
+                SolveResult::Unknown => panic!("The resumed search gave up"),
+            }
+            // The counters went on from the saved ones
+            assert!(resumed.stats().nodes > solver.stats().nodes);
+
+            // A snapshot only resumes the formula it was taken of
+            let mut other = Solver::from_clauses(&formula[1..]);
+            assert_eq!(other.resume(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        // Some budget ran out, or there would be no file
+        fs::remove_file(&path).unwrap();
+    }
+}
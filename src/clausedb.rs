@@ -0,0 +1,187 @@
+use std::iter::FusedIterator;
+use std::slice;
+
+use crate::Formula;
+
+// Index of a clause in a ClauseDb
+pub type ClauseId = u32;
+
+// Every clause of a formula stored once, the literals back to back in one
+// buffer. Clause i takes up literals[starts[i]..starts[i + 1]]. Search nodes
+// keep the ids of the clauses they have left instead of copies of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClauseDb {
+    literals: Vec<i32>,
+    starts: Vec<usize>,
+}
+
+impl ClauseDb {
+    pub fn new(formula: &[Vec<i32>]) -> Self {
+        let mut db = ClauseDb {
+            literals: Vec::with_capacity(formula.iter().map(Vec::len).sum()),
+            starts: Vec::with_capacity(formula.len() + 1),
+        };
+        for clause in formula.iter() {
+            db.push(clause);
+        }
+        db
+    }
+
+    // Append a clause and return its id
+    pub fn push(&mut self, clause: &[i32]) -> ClauseId {
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+        self.literals.extend_from_slice(clause);
+        self.starts.push(self.literals.len());
+        (self.len() - 1) as ClauseId
+    }
+
+    pub fn len(&self) -> usize {
+        self.starts.len().saturating_sub(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Number of literals of all the clauses together
+    pub fn num_literals(&self) -> usize {
+        self.literals.len()
+    }
+
+    // Heap memory the buffers hold on to
+    pub fn bytes(&self) -> usize {
+        self.literals.capacity() * size_of::<i32>() + self.starts.capacity() * size_of::<usize>()
+    }
+
+    pub fn clause(&self, id: ClauseId) -> &[i32] {
+        let id = id as usize;
+        &self.literals[self.starts[id]..self.starts[id + 1]]
+    }
+
+    // Every clause, in order
+    pub fn iter(&self) -> impl Iterator<Item = &[i32]> + '_ {
+        self.starts.windows(2).map(|bounds| &self.literals[bounds[0]..bounds[1]])
+    }
+
+    // The ids of every clause, in order
+    pub fn ids(&self) -> Vec<ClauseId> {
+        (0..self.len() as ClauseId).collect()
+    }
+
+    // Copies of the clauses behind ids
+    pub fn formula(&self, ids: &[ClauseId]) -> Formula {
+        ids.iter().map(|&id| self.clause(id).to_vec()).collect()
+    }
+
+    // A database of only the clauses behind ids, packed in their order, so
+    // the clause of ids[i] gets id i
+    pub fn compact(&self, ids: &[ClauseId]) -> ClauseDb {
+        let mut db = ClauseDb {
+            literals: Vec::with_capacity(ids.iter().map(|&id| self.clause(id).len()).sum()),
+            starts: Vec::with_capacity(ids.len() + 1),
+        };
+        for &id in ids.iter() {
+            db.push(self.clause(id));
+        }
+        db
+    }
+}
+
+// Some clauses of a clause database, as a node has them left
+#[derive(Clone, Copy, Debug)]
+pub struct Clauses<'a> {
+    db: &'a ClauseDb,
+    ids: &'a [ClauseId],
+}
+
+impl<'a> Clauses<'a> {
+    pub fn new(db: &'a ClauseDb, ids: &'a [ClauseId]) -> Self {
+        Clauses { db, ids }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'a> {
+        Iter { db: self.db, ids: self.ids.iter() }
+    }
+}
+
+impl<'a> IntoIterator for Clauses<'a> {
+    type Item = &'a [i32];
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+// The literals of each clause of a Clauses, in the order of the ids
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+    db: &'a ClauseDb,
+    ids: slice::Iter<'a, ClauseId>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [i32];
+
+    fn next(&mut self) -> Option<&'a [i32]> {
+        self.ids.next().map(|&id| self.db.clause(id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ids.next_back().map(|&id| self.db.clause(id))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl FusedIterator for Iter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clause_db() {
+        let formula = vec![vec![1, -2], vec![], vec![3], vec![-1, 2, -3]];
+        let db = ClauseDb::new(&formula);
+        assert_eq!(db.len(), 4);
+        assert_eq!(db.clause(1), &[] as &[i32]);
+        assert_eq!(db.clause(3), &[-1, 2, -3]);
+        assert_eq!(db.formula(&db.ids()), formula);
+        let ids = [3, 0];
+        let clauses = Clauses::new(&db, &ids);
+        assert_eq!(clauses.iter().collect::<Vec<_>>(), vec![&[-1, 2, -3][..], &[1, -2][..]]);
+        assert_eq!(clauses.iter().next_back(), Some(&[1, -2][..]));
+        assert_eq!(clauses.into_iter().len(), 2);
+        assert_eq!(db.iter().collect::<Vec<_>>(), formula.iter().map(Vec::as_slice).collect::<Vec<_>>());
+        assert_eq!(db.num_literals(), 6);
+
+        let compact = db.compact(&[3, 1]);
+        assert_eq!(compact.formula(&compact.ids()), vec![vec![-1, 2, -3], vec![]]);
+        assert!(compact.bytes() < db.bytes());
+
+        let mut db = ClauseDb::new(&[]);
+        assert!(db.is_empty());
+        assert_eq!(db.push(&[2, 3]), 0);
+        assert_eq!(db.push(&[-3]), 1);
+        assert_eq!(db.clause(1), &[-3]);
+        assert_eq!(db.formula(&db.ids()), vec![vec![2, 3], vec![-3]]);
+    }
+}
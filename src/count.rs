@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+
+use num_bigint::BigUint;
+use rand::Rng;
+
+use crate::encodings::xor_clauses;
+use crate::{Formula, Solver};
+
+// Variables occurring in the clauses
+fn variables(formula: &[Vec<i32>]) -> HashSet<i32> {
+    formula.iter().flatten().map(|lit| lit.abs()).collect()
+}
+
+// Assign lit and every literal it forces. Returns the remaining clauses with
+// false literals removed and the number of assigned variables, or None on a
+// conflict.
+fn assign(formula: &[Vec<i32>], lit: i32) -> Option<(Formula, usize)> {
+    let mut units = vec![lit];
+    let mut values: HashMap<i32, bool> = HashMap::new();
+    let mut formula = formula.to_vec();
+    while let Some(lit) = units.pop() {
+        match values.insert(lit.abs(), lit > 0) {
+            Some(val) if val != (lit > 0) => return None,
+            Some(_) => continue,
+            None => {}
+        }
+        let mut simplified = Vec::with_capacity(formula.len());
+        for clause in formula.into_iter() {
+            if clause.contains(&lit) {
+                continue;
+            }
+            let clause: Vec<i32> = clause.into_iter().filter(|&other| other != -lit).collect();
+            match clause.len() {
+                0 => return None,
+                1 => units.push(clause[0]),
+                _ => {}
+            }
+            simplified.push(clause);
+        }
+        formula = simplified;
+    }
+    Some((formula, values.len()))
+}
+
+// Split the clauses into groups that share no variable
+fn components(formula: Formula) -> Vec<Formula> {
+    let mut owner: HashMap<i32, usize> = HashMap::new();
+    let mut parent: Vec<usize> = (0..formula.len()).collect();
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        parent[i] = root;
+        root
+    }
+    for (i, clause) in formula.iter().enumerate() {
+        for lit in clause.iter() {
+            if let Some(&j) = owner.get(&lit.abs()) {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            } else {
+                owner.insert(lit.abs(), i);
+            }
+        }
+    }
+    let mut groups: HashMap<usize, Formula> = HashMap::new();
+    for (i, clause) in formula.into_iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(clause);
+    }
+    let mut groups: Vec<Formula> = groups.into_values().collect();
+    groups.sort();
+    groups
+}
+
+fn power_of_two(exponent: usize) -> BigUint {
+    BigUint::from(1u32) << exponent
+}
+
+// Models of a component over its own variables, cached by its sorted clauses
+fn count_component(mut formula: Formula, cache: &mut HashMap<Formula, BigUint>) -> BigUint {
+    formula.sort();
+    if let Some(count) = cache.get(&formula) {
+        return count.clone();
+    }
+    let num_vars = variables(&formula).len();
+    let mut occurrences: HashMap<i32, usize> = HashMap::new();
+    for lit in formula.iter().flatten() {
+        *occurrences.entry(lit.abs()).or_default() += 1;
+    }
+    let var = occurrences.into_iter().max_by_key(|&(var, count)| (count, -var)).map(|(var, _)| var).unwrap();
+
+    let mut count = BigUint::from(0u32);
+    for lit in [var, -var] {
+        if let Some((rest, assigned)) = assign(&formula, lit) {
+            // Variables that vanished without being assigned are free
+            let free = num_vars - assigned - variables(&rest).len();
+            count += count_formula(rest, cache) * power_of_two(free);
+        }
+    }
+    cache.insert(formula, count.clone());
+    count
+}
+
+// Models of the clauses over their variables
+fn count_formula(formula: Formula, cache: &mut HashMap<Formula, BigUint>) -> BigUint {
+    let mut count = BigUint::from(1u32);
+    for component in components(formula) {
+        count *= count_component(component, cache);
+    }
+    count
+}
+
+// Exact number of models over the variables that occur in the formula.
+// Counting runs the DPLL tree without stopping at the first model,
+// multiplies the counts of independent components and caches the count of
+// every component it has seen.
+pub fn count_models(formula: &[Vec<i32>]) -> BigUint {
+    let mut clauses = Vec::new();
+    for clause in formula.iter() {
+        let mut clause = clause.clone();
+        clause.sort_unstable();
+        clause.dedup();
+        if clause.is_empty() {
+            return BigUint::from(0u32);
+        }
+        // Tautologies constrain nothing, their variables may end up free
+        if !clause.iter().any(|&lit| clause.contains(&-lit)) {
+            clauses.push(clause);
+        }
+    }
+    let free = variables(formula).len() - variables(&clauses).len();
+    count_formula(clauses, &mut HashMap::new()) * power_of_two(free)
+}
+
+// Number of models of the formula, counted up to limit
+fn bounded_count(formula: &[Vec<i32>], limit: usize) -> usize {
+    let mut solver = Solver::new();
+    for clause in formula.iter() {
+        solver.add_clause(clause);
+    }
+    solver.solutions().take(limit).count()
+}
+
+// One ApproxMC round: the smallest number m of nested random XOR
+// constraints on the variables that leaves fewer than threshold models, and
+// that count scaled by 2^m. The search starts from the m of the previous
+// round, which is usually close. None if even n constraints leave too many.
+fn approx_round(
+    formula: &[Vec<i32>],
+    vars: &[i32],
+    threshold: usize,
+    start: usize,
+    rng: &mut impl Rng,
+) -> Option<(usize, BigUint)> {
+    let first_link = vars.iter().copied().max().unwrap_or(0);
+    let rows: Vec<(Vec<i32>, bool)> = (0..vars.len())
+        .map(|_| (vars.iter().copied().filter(|_| rng.gen_bool(0.5)).collect(), rng.gen_bool(0.5)))
+        .collect();
+    let count = |m: usize| {
+        let mut constrained = formula.to_vec();
+        let mut next_var = first_link;
+        for (row, parity) in rows[..m].iter() {
+            constrained.extend(xor_clauses(row, *parity, &mut next_var));
+        }
+        bounded_count(&constrained, threshold)
+    };
+    let mut m = start.clamp(1, vars.len());
+    let mut cell = count(m);
+    if cell < threshold {
+        while m > 1 {
+            let fewer = count(m - 1);
+            if fewer >= threshold {
+                break;
+            }
+            m -= 1;
+            cell = fewer;
+        }
+    } else {
+        while cell >= threshold {
+            if m == vars.len() {
+                return None;
+            }
+            m += 1;
+            cell = count(m);
+        }
+    }
+    Some((m, BigUint::from(cell) << m))
+}
+
+// ApproxMC: an estimate of the number of models over the variables of the
+// formula that lies within a factor 1 + epsilon of the true count with
+// probability at least 1 - delta. Small counts are exact. Every random XOR
+// constraint halves the models in expectation, so the median over enough
+// rounds of surviving models times 2^constraints is close to the count.
+pub fn approx_count_models(formula: &[Vec<i32>], epsilon: f64, delta: f64, rng: &mut impl Rng) -> BigUint {
+    let threshold = (1.0 + 9.84 * (1.0 + epsilon / (1.0 + epsilon)) * (1.0 + 1.0 / epsilon).powi(2)).ceil() as usize;
+    let count = bounded_count(formula, threshold);
+    if count < threshold {
+        return BigUint::from(count);
+    }
+    let mut vars: Vec<i32> = variables(formula).into_iter().collect();
+    vars.sort_unstable();
+    let rounds = (17.0 * (3.0 / delta).log2()).ceil() as usize;
+    let mut estimates = Vec::with_capacity(rounds);
+    let mut m = 1;
+    for _ in 0..rounds {
+        if let Some((cells, estimate)) = approx_round(formula, &vars, threshold, m, rng) {
+            m = cells;
+            estimates.push(estimate);
+        }
+    }
+    estimates.sort();
+    estimates.get(estimates.len() / 2).cloned().unwrap_or_else(|| BigUint::from(count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force_count(formula: &Formula) -> usize {
+        let mut vars: Vec<i32> = variables(formula).into_iter().collect();
+        vars.sort_unstable();
+        (0..1u32 << vars.len())
+            .filter(|bits| {
+                let value = |lit: i32| {
+                    let i = vars.iter().position(|&var| var == lit.abs()).unwrap();
+                    ((bits >> i) & 1 == 1) == (lit > 0)
+                };
+                formula.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_count_models() {
+        let formulas: [Formula; 7] = [
+            vec![],
+            vec![vec![]],
+            vec![vec![1, -1]],
+            vec![vec![1], vec![-1]],
+            vec![vec![1, 2], vec![-1, -2, 3], vec![4, -4]],
+            // Two independent components and a unit
+            vec![vec![1, 2], vec![-1, 3], vec![4, 5, 6], vec![-5, -6], vec![7]],
+            vec![vec![1, 2, 3], vec![-1, -2], vec![-2, -3], vec![-1, -3], vec![1, -2, 3], vec![2, 4, -5], vec![5, 6]],
+        ];
+        for formula in formulas.iter() {
+            assert_eq!(count_models(formula), BigUint::from(brute_force_count(formula)), "{:?}", formula);
+        }
+    }
+
+    #[test]
+    fn test_count_models_beyond_u64() {
+        // 70 independent clauses (2i-1 or 2i), three models each
+        let formula: Formula = (1..=70).map(|i| vec![2 * i - 1, 2 * i]).collect();
+        assert_eq!(count_models(&formula), BigUint::from(3u32).pow(70));
+    }
+
+    #[test]
+    fn test_approx_count_models() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(7);
+        // Few models are counted exactly
+        let formula = vec![vec![1, 2], vec![-1, -2, 3]];
+        assert_eq!(approx_count_models(&formula, 4.0, 0.9, &mut rng), BigUint::from(5u32));
+
+        // 3^4 = 81 models, above the threshold of 29 for epsilon 4
+        let formula: Formula = (1..=4).map(|i| vec![2 * i - 1, 2 * i]).collect();
+        let estimate = approx_count_models(&formula, 4.0, 0.9, &mut rng);
+        assert!(estimate >= BigUint::from(81u32 / 5) && estimate <= BigUint::from(81u32 * 5), "{}", estimate);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::lookahead::{split, Split};
+use crate::portfolio::thread_pool;
+use crate::{initial_assignment, ClauseSharing, Formula, LookAheadConfig, Model, SolveResult, Solver};
+
+// Split the clauses into at most max_cubes cubes, conjunctions of literals
+// that between them cover every model. Cubes are split breadth first, each
+// on the variable whose look-ahead, the unit propagation of either value,
+// removes the most clauses on both sides. A value whose look-ahead fails, or
+// under which some variable fails both ways, is a failed literal and the
+// other value joins the cube instead; cubes where both values fail have no
+// model and are dropped, so no cubes at all means the clauses are
+// unsatisfiable.
+pub fn generate_cubes(formula: &Formula, max_cubes: usize) -> Vec<Vec<i32>> {
+    let mut cubes = Vec::new();
+    let mut frontier = VecDeque::from([(Vec::new(), formula.clone(), initial_assignment(formula))]);
+    while let Some((mut cube, formula, assignment)) = frontier.pop_front() {
+        if cubes.len() + frontier.len() + 1 >= max_cubes {
+            cubes.push(cube);
+            continue;
+        }
+        match split(&formula, &assignment, &LookAheadConfig::default()) {
+            Split::Refuted => {}
+            Split::Forced(lit, formula, assignment) => {
+                cube.push(lit);
+                frontier.push_front((cube, formula, assignment));
+            }
+            Split::Branch(var, [positive, negative]) => {
+                for (lit, (formula, assignment)) in [(var, positive), (-var, negative)] {
+                    let mut cube = cube.clone();
+                    cube.push(lit);
+                    frontier.push_back((cube, formula, assignment));
+                }
+            }
+            Split::Satisfied => cubes.push(cube),
+        }
+    }
+    cubes
+}
+
+impl Solver {
+    // Cube and conquer: split the clauses into at most max_cubes cubes by
+    // look-ahead, then solve under each cube as assumptions on workers
+    // threads, every one taking the next cube that is left. The first model
+    // answers and interrupts the rest, the formula is unsatisfiable once
+    // every cube is refuted. Tripping the solver's interrupter stops every
+    // worker.
+    pub fn solve_cubes(&mut self, max_cubes: usize, workers: usize) -> SolveResult {
+        self.model = None;
+        self.failed.clear();
+        let cubes = generate_cubes(&self.formula, max_cubes);
+        let interrupter = self.interrupter();
+        let next = AtomicUsize::new(0);
+        let refuted = AtomicUsize::new(0);
+        let found: Mutex<Option<Model>> = Mutex::new(None);
+        let template = self.template();
+        let workers = workers.clamp(1, cubes.len().max(1));
+        let exchange = self.clause_exchange(workers);
+        thread_pool(workers).scope(|scope| {
+            for i in 0..workers {
+                let (template, interrupter, cubes, exchange) = (&template, &interrupter, &cubes, &exchange);
+                let (next, refuted, found) = (&next, &refuted, &found);
+                scope.spawn(move |_| {
+                    // Learned clauses carry over between the cubes of a
+                    // worker, the short ones to the other workers as well
+                    let mut solver = template.solver(interrupter);
+                    solver.state.sharing = exchange.clone().map(|exchange| ClauseSharing::new(exchange, i));
+                    while let Some(cube) = cubes.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        match solver.search(cube) {
+                            SolveResult::Sat(model) => {
+                                found.lock().unwrap().get_or_insert(model);
+                                interrupter.interrupt();
+                                return;
+                            }
+                            SolveResult::Unsat => {
+                                refuted.fetch_add(1, Ordering::Relaxed);
+                            }
+                            SolveResult::Unknown => return,
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(model) = found.into_inner().unwrap() {
+            // Only the first model tripped the interrupter, let later calls run
+            interrupter.clear();
+            self.model = Some(model.clone());
+            return SolveResult::Sat(model);
+        }
+        if refuted.into_inner() == cubes.len() {
+            SolveResult::Unsat
+        } else {
+            SolveResult::Unknown
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::verify_model;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Cubes name every variable once at most, so none repeats a literal or
+    // holds both of one variable
+    fn assert_distinct_vars(formula: &Formula, cubes: &[Vec<i32>]) {
+        for cube in cubes.iter() {
+            let vars: HashSet<i32> = cube.iter().map(|lit| lit.abs()).collect();
+            assert_eq!(vars.len(), cube.len(), "{:?} {:?}", formula, cube);
+        }
+    }
+
+    #[test]
+    fn test_cube_and_conquer() {
+        let mut rng = StdRng::seed_from_u64(23);
+        for _ in 0..100 {
+            let num_vars = rng.gen_range(3..=9);
+            let formula: Formula = (0..rng.gen_range(1..=40))
+                .map(|_| (0..3).map(|_| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 }).collect())
+                .collect();
+            let models: Vec<Model> = (0..1u32 << num_vars)
+                .map(|bits| (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect())
+                .filter(|model| verify_model(&formula, model).is_ok())
+                .collect();
+            let satisfiable = !models.is_empty();
+            let max_cubes = rng.gen_range(1..=16);
+            let cubes = generate_cubes(&formula, max_cubes);
+            assert!(cubes.len() <= max_cubes);
+            assert_distinct_vars(&formula, &cubes);
+            // Every model extends some cube
+            for model in models.iter() {
+                let extends = |cube: &Vec<i32>| cube.iter().all(|&lit| model[&lit.abs()] == (lit > 0));
+                assert!(cubes.iter().any(extends), "{:?} {:?} {:?}", formula, cubes, model);
+            }
+            let mut solver = Solver::from_clauses(&formula);
+            let result = solver.solve_cubes(max_cubes, 3);
+            assert_eq!(result.is_sat(), satisfiable, "{:?}", formula);
+            if let SolveResult::Sat(model) = result {
+                assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?}", formula, model);
+            }
+        }
+
+        // The variables a cube assigns stay in the clauses, splitting on
+        // them again made cubes such as [-1, 1]
+        let formula = vec![vec![4, -1, 6], vec![-2, 4, 1, -3], vec![-1, 5, -4]];
+        assert_distinct_vars(&formula, &generate_cubes(&formula, 16));
+        let formula = vec![vec![-5, -6, -1, -4], vec![2, -2, -6, 3]];
+        assert_distinct_vars(&formula, &generate_cubes(&formula, 16));
+    }
+}
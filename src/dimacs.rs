@@ -0,0 +1,258 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::xor::XorConstraint;
+use crate::Formula;
+
+// How closely a DIMACS file has to follow the format. Strict parsing fails on
+// the first deviation, lenient parsing accepts what it can make sense of and
+// records each deviation as a warning.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    Strict,
+    #[default]
+    Lenient,
+}
+
+// A deviation from the DIMACS format, lines are numbered from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DimacsError {
+    // Reading the input failed
+    Io(String),
+    // A "p" line that is not "p cnf <vars> <clauses>"
+    InvalidHeader(usize),
+    // A second "p" line
+    DuplicateHeader(usize),
+    // Clauses without a "p cnf" line before them
+    MissingHeader,
+    // A token that is not a literal
+    InvalidLiteral(usize, String),
+    // A literal on a variable above the number the header declares
+    VariableOutOfRange(usize, i32),
+    // The header declares a different number of clauses than the file has
+    ClauseCount { declared: usize, found: usize },
+    // The last clause is not terminated by 0
+    MissingTerminator,
+    // The "%" line that ends SATLIB files, everything after it is ignored
+    EndMarker(usize),
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DimacsError::Io(error) => write!(f, "failed to read input: {}", error),
+            DimacsError::InvalidHeader(line) => write!(f, "line {}: invalid problem line", line),
+            DimacsError::DuplicateHeader(line) => write!(f, "line {}: second problem line", line),
+            DimacsError::MissingHeader => write!(f, "no problem line before the clauses"),
+            DimacsError::InvalidLiteral(line, token) => write!(f, "line {}: invalid literal {:?}", line, token),
+            DimacsError::VariableOutOfRange(line, lit) => {
+                write!(f, "line {}: literal {} exceeds the declared variables", line, lit)
+            }
+            DimacsError::ClauseCount { declared, found } => {
+                write!(f, "{} clauses declared but {} found", declared, found)
+            }
+            DimacsError::MissingTerminator => write!(f, "the last clause is not terminated by 0"),
+            DimacsError::EndMarker(line) => write!(f, "line {}: input ends with %", line),
+        }
+    }
+}
+
+// A parsed DIMACS file: its clauses and XOR constraints, the number of
+// variables the header declares (or the largest variable without a header)
+// and, in lenient mode, the deviations that were accepted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Dimacs {
+    pub formula: Formula,
+    pub xors: Vec<XorConstraint>,
+    pub num_vars: usize,
+    pub warnings: Vec<DimacsError>,
+}
+
+// Parse DIMACS CNF. Clauses may span lines and share them, every clause ends
+// with 0. A line starting with "x" is an XOR constraint in CryptoMiniSat's
+// syntax, "x1 -2 3 0" for x1 xor not x2 xor x3, which the header counts among
+// the clauses. Invalid problem lines and tokens are errors in either mode.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn parse_dimacs(reader: impl BufRead, mode: ParseMode) -> Result<Dimacs, DimacsError> {
+    let mut dimacs = Dimacs::default();
+    let mut header: Option<(usize, usize)> = None;
+    let mut clause = Vec::new();
+    let mut max_var = 0;
+    // A deviation fails the parse in strict mode and is recorded otherwise
+    let deviate = |dimacs: &mut Dimacs, error: DimacsError| match mode {
+        ParseMode::Strict => Err(error),
+        ParseMode::Lenient => {
+            dimacs.warnings.push(error);
+            Ok(())
+        }
+    };
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| DimacsError::Io(error.to_string()))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('c') {
+            continue;
+        }
+        if line.starts_with('%') {
+            deviate(&mut dimacs, DimacsError::EndMarker(i + 1))?;
+            break;
+        }
+        if let Some(xor) = line.strip_prefix('x') {
+            if header.is_none() && dimacs.formula.is_empty() && dimacs.xors.is_empty() {
+                deviate(&mut dimacs, DimacsError::MissingHeader)?;
+            }
+            let mut lits = Vec::new();
+            let mut terminated = false;
+            for token in xor.split_whitespace() {
+                let lit: i32 = token
+                    .parse()
+                    .map_err(|_| DimacsError::InvalidLiteral(i + 1, token.to_string()))?;
+                if lit == 0 {
+                    terminated = true;
+                    break;
+                }
+                if header.is_some_and(|(vars, _)| lit.unsigned_abs() as usize > vars) {
+                    deviate(&mut dimacs, DimacsError::VariableOutOfRange(i + 1, lit))?;
+                }
+                max_var = max_var.max(lit.unsigned_abs() as usize);
+                lits.push(lit);
+            }
+            if !terminated {
+                deviate(&mut dimacs, DimacsError::MissingTerminator)?;
+            }
+            dimacs.xors.push(XorConstraint::from_lits(&lits));
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens[0] == "p" {
+            let numbers: Option<Vec<usize>> =
+                tokens.get(2..).unwrap_or_default().iter().map(|token| token.parse().ok()).collect();
+            match numbers.as_deref() {
+                Some(&[vars, clauses]) if tokens[1] == "cnf" => {
+                    if header.is_some() {
+                        deviate(&mut dimacs, DimacsError::DuplicateHeader(i + 1))?;
+                    } else {
+                        header = Some((vars, clauses));
+                    }
+                }
+                _ => return Err(DimacsError::InvalidHeader(i + 1)),
+            }
+            continue;
+        }
+        if header.is_none() && dimacs.formula.is_empty() && dimacs.xors.is_empty() && clause.is_empty() {
+            deviate(&mut dimacs, DimacsError::MissingHeader)?;
+        }
+        for token in tokens.iter() {
+            let lit: i32 = token
+                .parse()
+                .map_err(|_| DimacsError::InvalidLiteral(i + 1, token.to_string()))?;
+            if lit == 0 {
+                dimacs.formula.push(std::mem::take(&mut clause));
+                continue;
+            }
+            let var = lit.unsigned_abs() as usize;
+            if header.is_some_and(|(vars, _)| var > vars) {
+                deviate(&mut dimacs, DimacsError::VariableOutOfRange(i + 1, lit))?;
+            }
+            max_var = max_var.max(var);
+            clause.push(lit);
+        }
+    }
+    if !clause.is_empty() {
+        deviate(&mut dimacs, DimacsError::MissingTerminator)?;
+        dimacs.formula.push(clause);
+    }
+    match header {
+        Some((vars, clauses)) => {
+            if clauses != dimacs.formula.len() + dimacs.xors.len() {
+                let found = dimacs.formula.len() + dimacs.xors.len();
+                deviate(&mut dimacs, DimacsError::ClauseCount { declared: clauses, found })?;
+            }
+            dimacs.num_vars = vars.max(max_var);
+        }
+        None => dimacs.num_vars = max_var,
+    }
+    Ok(dimacs)
+}
+
+// Write the clauses as DIMACS CNF, with a header counting the largest
+// variable and the clauses
+pub fn write_cnf(formula: &[Vec<i32>], writer: impl Write) -> io::Result<()> {
+    let num_vars = formula.iter().flatten().map(|lit| lit.unsigned_abs()).max().unwrap_or(0);
+    write_cnf_with_vars(formula, num_vars as usize, writer)
+}
+
+// write_cnf with a header declaring num_vars variables, which can be more
+// than the clauses contain
+pub fn write_cnf_with_vars(formula: &[Vec<i32>], num_vars: usize, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "p cnf {} {}", num_vars, formula.len())?;
+    for clause in formula.iter() {
+        for lit in clause.iter() {
+            write!(writer, "{} ", lit)?;
+        }
+        writeln!(writer, "0")?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, mode: ParseMode) -> Result<Dimacs, DimacsError> {
+        parse_dimacs(text.as_bytes(), mode)
+    }
+
+    #[test]
+    fn test_parse_dimacs() {
+        let dimacs = parse("c comment\np cnf 4 3\n1 -2\n 0 3 0\n-4 0\n", ParseMode::Strict).unwrap();
+        assert_eq!(dimacs.formula, vec![vec![1, -2], vec![3], vec![-4]]);
+        assert_eq!(dimacs.num_vars, 4);
+        assert!(dimacs.warnings.is_empty());
+        assert_eq!(parse("p cnf 2\n", ParseMode::Lenient), Err(DimacsError::InvalidHeader(1)));
+        assert_eq!(
+            parse("p cnf 2 1\n1 x 0\n", ParseMode::Lenient),
+            Err(DimacsError::InvalidLiteral(2, "x".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_strict_and_lenient() {
+        let cases = [
+            ("1 2 0\n", DimacsError::MissingHeader),
+            ("p cnf 2 1\n1 3 0\n", DimacsError::VariableOutOfRange(2, 3)),
+            ("p cnf 2 2\n1 2 0\n", DimacsError::ClauseCount { declared: 2, found: 1 }),
+            ("p cnf 2 1\n1 2\n", DimacsError::MissingTerminator),
+            ("p cnf 2 1\n1 2 0\np cnf 2 1\n", DimacsError::DuplicateHeader(3)),
+            ("p cnf 2 1\n1 2 0\n%\n0\n", DimacsError::EndMarker(3)),
+        ];
+        for (text, error) in cases {
+            assert_eq!(parse(text, ParseMode::Strict), Err(error.clone()), "{:?}", text);
+            let dimacs = parse(text, ParseMode::Lenient).unwrap();
+            assert_eq!(dimacs.warnings, vec![error], "{:?}", text);
+            assert_eq!(dimacs.formula.len(), 1, "{:?}", text);
+        }
+        // Without a header the largest variable counts
+        assert_eq!(parse("1 -7 0\n", ParseMode::Lenient).unwrap().num_vars, 7);
+    }
+
+    #[test]
+    fn test_parse_xor_lines() {
+        let dimacs = parse("p cnf 3 3\nx1 -2 0\n1 3 0\nx 2 3 0\n", ParseMode::Strict).unwrap();
+        assert_eq!(dimacs.formula, vec![vec![1, 3]]);
+        assert_eq!(dimacs.xors, vec![XorConstraint::from_lits(&[1, -2]), XorConstraint::from_lits(&[2, 3])]);
+        assert_eq!(parse("p cnf 3 1\nx1 4 0\n", ParseMode::Strict), Err(DimacsError::VariableOutOfRange(2, 4)));
+    }
+
+    #[test]
+    fn test_write_cnf() {
+        let formula = vec![vec![1, -5], vec![], vec![3]];
+        let mut text = Vec::new();
+        write_cnf(&formula, &mut text).unwrap();
+        assert_eq!(String::from_utf8(text.clone()).unwrap(), "p cnf 5 3\n1 -5 0\n0\n3 0\n");
+        let dimacs = parse_dimacs(&text[..], ParseMode::Strict).unwrap();
+        assert_eq!(dimacs.formula, formula);
+        let mut text = Vec::new();
+        write_cnf_with_vars(&formula, 7, &mut text).unwrap();
+        assert!(text.starts_with(b"p cnf 7 3\n"));
+    }
+}
@@ -0,0 +1,5 @@
+// Puzzles encoded into CNF, with decoders of their models
+pub mod nqueens;
+pub mod sudoku;
+
+pub use nqueens::nqueens;
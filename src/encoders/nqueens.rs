@@ -0,0 +1,143 @@
+use std::fmt;
+
+use crate::encodings::{at_most_one, AmoEncoding};
+use crate::{Formula, Model, Solver};
+
+// The variable true if a queen stands on the square, 1 to n squared
+pub fn queen(n: usize, row: usize, col: usize) -> i32 {
+    (row * n + col + 1) as i32
+}
+
+// Clauses placing n queens on an n by n board so that none attacks
+// another: one queen in every row and every column, at most one on every
+// diagonal. At-most-one constraints are pairwise, so the models are the
+// placements.
+pub fn nqueens(n: usize) -> Formula {
+    nqueens_with(n, AmoEncoding::Pairwise)
+}
+
+// nqueens with at-most-one constraints in the encoding, its fresh variables
+// numbered past the squares
+pub fn nqueens_with(n: usize, encoding: AmoEncoding) -> Formula {
+    let mut next_var = (n * n) as i32;
+    let mut formula = Vec::new();
+    for i in 0..n {
+        let row: Vec<i32> = (0..n).map(|col| queen(n, i, col)).collect();
+        let col: Vec<i32> = (0..n).map(|row| queen(n, row, i)).collect();
+        for line in [row, col] {
+            formula.extend(at_most_one(&line, encoding, &mut next_var));
+            formula.push(line);
+        }
+    }
+    // Diagonals by the difference of row and column, anti-diagonals by
+    // their sum
+    let mut diagonals = vec![Vec::new(); 2 * n.max(1) - 1];
+    let mut anti_diagonals = diagonals.clone();
+    for row in 0..n {
+        for col in 0..n {
+            diagonals[row + n - 1 - col].push(queen(n, row, col));
+            anti_diagonals[row + col].push(queen(n, row, col));
+        }
+    }
+    for diagonal in diagonals.iter().chain(anti_diagonals.iter()) {
+        formula.extend(at_most_one(diagonal, encoding, &mut next_var));
+    }
+    formula
+}
+
+// Queens on a board by row, the column of the queen in each row if there is
+// one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub queens: Vec<Option<usize>>,
+}
+
+impl Board {
+    // The queens a model of nqueens(n) or nqueens_with(n, _) places
+    pub fn decode(n: usize, model: &Model) -> Self {
+        let queens = (0..n).map(|row| (0..n).find(|&col| model.get(&queen(n, row, col)) == Some(&true))).collect();
+        Board { queens }
+    }
+
+    // Whether every row has a queen and no two queens share a column or a
+    // diagonal
+    pub fn is_solution(&self) -> bool {
+        let Some(cols) = self.queens.iter().copied().collect::<Option<Vec<usize>>>() else {
+            return false;
+        };
+        cols.iter().enumerate().all(|(a, &x)| {
+            cols[a + 1..].iter().enumerate().all(|(i, &y)| x != y && x.abs_diff(y) != i + 1)
+        })
+    }
+}
+
+// One line per row, 'Q' for a queen and '.' for an empty square
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = self.queens.len();
+        for (row, col) in self.queens.iter().enumerate() {
+            let squares: Vec<&str> = (0..n).map(|square| if *col == Some(square) { "Q" } else { "." }).collect();
+            if row > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", squares.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+// A placement of n queens, None if there is none
+pub fn solve_nqueens(n: usize, encoding: AmoEncoding) -> Option<Board> {
+    let mut solver = Solver::from_clauses(&nqueens_with(n, encoding));
+    if !solver.solve() {
+        return None;
+    }
+    let board = Board::decode(n, solver.model()?);
+    debug_assert!(board.is_solution());
+    Some(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    use crate::count_models;
+
+    const ENCODINGS: [AmoEncoding; 4] =
+        [AmoEncoding::Pairwise, AmoEncoding::Ladder, AmoEncoding::Commander, AmoEncoding::Bimander];
+
+    #[test]
+    fn test_nqueens() {
+        let counts: Vec<BigUint> = (1..=8).map(|n| count_models(&nqueens(n))).collect();
+        assert_eq!(counts, [1u32, 0, 0, 2, 10, 4, 40, 92].map(BigUint::from));
+
+        for encoding in ENCODINGS {
+            assert_eq!(solve_nqueens(3, encoding), None);
+            for n in [1, 4, 9, 12] {
+                let board = solve_nqueens(n, encoding).expect("Satisfiable");
+                assert!(board.is_solution(), "{:?} {}", encoding, n);
+            }
+
+            // Every encoding has the 10 placements on 5 by 5, told apart
+            // by the squares
+            let mut solver = Solver::from_clauses(&nqueens_with(5, encoding));
+            let mut placements = 0;
+            while solver.solve() {
+                let board = Board::decode(5, solver.model().unwrap());
+                assert!(board.is_solution());
+                let blocking: Vec<i32> =
+                    board.queens.iter().enumerate().map(|(row, col)| -queen(5, row, col.unwrap())).collect();
+                solver.add_clause(&blocking);
+                placements += 1;
+            }
+            assert_eq!(placements, 10);
+        }
+
+        let board = Board { queens: vec![Some(1), Some(3), Some(0), Some(2)] };
+        assert!(board.is_solution());
+        assert_eq!(board.to_string(), ". Q . .\n. . . Q\nQ . . .\n. . Q .");
+        assert!(!Board { queens: vec![Some(0), Some(2), None] }.is_solution());
+        assert!(!Board { queens: vec![Some(0), Some(1), Some(3), Some(2)] }.is_solution());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+
+use crate::encodings::{at_most_one, AmoEncoding};
+use crate::{Formula, Model, Solver};
+
+// A Sudoku grid of size by size cells in boxes of box_size by box_size,
+// size being the square of box_size. Cells are stored row by row, 0 for an
+// empty one and 1 to size for a given value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub size: usize,
+    pub box_size: usize,
+    pub cells: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GridError {
+    // A cell on the line that is neither a value of the grid nor empty
+    Cell(usize),
+    // Rows of different lengths, or a number of rows that is not the
+    // length of every row or not a square
+    Shape,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::Cell(line) => write!(f, "line {}: not a cell value", line),
+            GridError::Shape => write!(f, "not a square grid of square boxes"),
+        }
+    }
+}
+
+// The cells of a row: whitespace-separated numbers, or one character per
+// cell when the row does not split into numbers. '.' and '0' are empty,
+// '|' separates boxes.
+fn parse_row(row: &str, line: usize) -> Result<Vec<usize>, GridError> {
+    let row = row.replace('|', " ");
+    let tokens: Vec<&str> = row.split_whitespace().collect();
+    let numeric = |token: &&str| *token == "." || token.parse::<usize>().is_ok();
+    let cells: Vec<String> = if tokens.len() > 1 && tokens.iter().all(numeric) {
+        tokens.iter().map(|token| token.to_string()).collect()
+    } else {
+        tokens.concat().chars().map(String::from).collect()
+    };
+    let value = |cell: &String| if cell == "." { Ok(0) } else { cell.parse().map_err(|_| GridError::Cell(line)) };
+    cells.iter().map(value).collect()
+}
+
+impl Grid {
+    // An empty grid of the given box size
+    pub fn empty(box_size: usize) -> Self {
+        let size = box_size * box_size;
+        Grid {
+            size,
+            box_size,
+            cells: vec![0; size * size],
+        }
+    }
+
+    // A grid given row by row, or on a single line of all its cells. Blank
+    // lines and lines of box borders such as "---+---+---" are skipped.
+    pub fn parse(text: &str) -> Result<Self, GridError> {
+        // The rows with their line numbers
+        let mut rows = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            if !line.chars().all(|c| c.is_whitespace() || "-+|".contains(c)) {
+                rows.push((i + 1, parse_row(line, i + 1)?));
+            }
+        }
+        if let [(line, row)] = &rows[..] {
+            let size = row.len().isqrt();
+            if size > 1 && size * size == row.len() {
+                rows = row.chunks(size).map(|row| (*line, row.to_vec())).collect();
+            }
+        }
+        let size = rows.len();
+        let box_size = size.isqrt();
+        if box_size * box_size != size || rows.iter().any(|(_, row)| row.len() != size) {
+            return Err(GridError::Shape);
+        }
+        if let Some((line, _)) = rows.iter().find(|(_, row)| row.iter().any(|&value| value > size)) {
+            return Err(GridError::Cell(*line));
+        }
+        let cells = rows.into_iter().flat_map(|(_, row)| row).collect();
+        Ok(Grid { size, box_size, cells })
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> usize {
+        self.cells[row * self.size + col]
+    }
+
+    // The variable true if the cell holds the value, 1 to size cubed
+    pub fn var(&self, row: usize, col: usize, value: usize) -> i32 {
+        ((row * self.size + col) * self.size + value) as i32
+    }
+
+    // The cells of every row, column and box, by position
+    fn units(&self) -> Vec<Vec<(usize, usize)>> {
+        let (n, b) = (self.size, self.box_size);
+        let mut units: Vec<Vec<(usize, usize)>> = Vec::with_capacity(3 * n);
+        units.extend((0..n).map(|row| (0..n).map(|col| (row, col)).collect()));
+        units.extend((0..n).map(|col| (0..n).map(|row| (row, col)).collect()));
+        units.extend((0..n).map(|i| (0..n).map(|j| (i / b * b + j / b, i % b * b + j % b)).collect()));
+        units
+    }
+
+    // Clauses saying that every cell holds exactly one value, every row,
+    // column and box holds every value exactly once, and the given cells
+    // hold their values. At-most-one constraints use the encoding, with its
+    // fresh variables numbered past the cell variables.
+    pub fn encode(&self, encoding: AmoEncoding) -> Formula {
+        let n = self.size;
+        let mut next_var = (n * n * n) as i32;
+        let mut formula = Vec::new();
+        let mut exactly_one = |lits: Vec<i32>, formula: &mut Formula| {
+            formula.extend(at_most_one(&lits, encoding, &mut next_var));
+            formula.push(lits);
+        };
+        for row in 0..n {
+            for col in 0..n {
+                exactly_one((1..=n).map(|value| self.var(row, col, value)).collect(), &mut formula);
+            }
+        }
+        for unit in self.units() {
+            for value in 1..=n {
+                exactly_one(unit.iter().map(|&(row, col)| self.var(row, col, value)).collect(), &mut formula);
+            }
+        }
+        for row in 0..n {
+            for col in (0..n).filter(|&col| self.get(row, col) != 0) {
+                formula.push(vec![self.var(row, col, self.get(row, col))]);
+            }
+        }
+        formula
+    }
+
+    // The grid with every cell set to the value the model makes it hold,
+    // empty where it holds none
+    pub fn decode(&self, model: &Model) -> Grid {
+        let n = self.size;
+        let cells = (0..n * n)
+            .map(|cell| {
+                let value = (1..=n).find(|&value| model.get(&self.var(cell / n, cell % n, value)) == Some(&true));
+                value.unwrap_or(0)
+            })
+            .collect();
+        Grid { cells, ..*self }
+    }
+
+    // A completion of the grid, None if there is none
+    pub fn solve(&self, encoding: AmoEncoding) -> Option<Grid> {
+        let mut solver = Solver::from_clauses(&self.encode(encoding));
+        if !solver.solve() {
+            return None;
+        }
+        let solution = self.decode(solver.model()?);
+        debug_assert!(solution.is_solved() && self.is_completed_by(&solution));
+        Some(solution)
+    }
+
+    // Whether every row, column and box holds every value once
+    pub fn is_solved(&self) -> bool {
+        self.units().iter().all(|unit| {
+            let mut values: Vec<usize> = unit.iter().map(|&(row, col)| self.get(row, col)).collect();
+            values.sort_unstable();
+            values.iter().copied().eq(1..=self.size)
+        })
+    }
+
+    // Whether the other grid keeps the given cells of this one
+    pub fn is_completed_by(&self, other: &Grid) -> bool {
+        self.size == other.size && self.cells.iter().zip(other.cells.iter()).all(|(&a, &b)| a == 0 || a == b)
+    }
+}
+
+// Row by row, one character per cell up to 9 by 9 and numbers separated by
+// spaces beyond, '.' for an empty cell. Grid::parse reads it back.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = if self.size <= 9 { 1 } else { self.size.to_string().len() };
+        for (i, row) in self.cells.chunks(self.size.max(1)).enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|&value| if value == 0 { String::from(".") } else { value.to_string() })
+                .map(|cell| format!("{:>width$}", cell, width = width))
+                .collect();
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", cells.join(if width == 1 { "" } else { " " }))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{count_models, parse_dimacs, write_cnf, ParseMode};
+
+    const PUZZLE: &str = "
+        53..7....
+        6..195...
+        .98....6.
+        8...6...3
+        4..8.3..1
+        7...2...6
+        .6....28.
+        ...419..5
+        ....8..79";
+
+    const SOLUTION: &str = "534678912672195348198342567859761423426853791713924856961537284287419635345286179";
+
+    #[test]
+    fn test_sudoku() {
+        let puzzle = Grid::parse(PUZZLE).unwrap();
+        assert_eq!((puzzle.size, puzzle.box_size, puzzle.get(1, 3)), (9, 3, 1));
+        let solution = Grid::parse(SOLUTION).unwrap();
+        assert!(solution.is_solved() && !puzzle.is_solved() && puzzle.is_completed_by(&solution));
+        for encoding in [AmoEncoding::Pairwise, AmoEncoding::Ladder, AmoEncoding::Commander, AmoEncoding::Bimander] {
+            assert_eq!(puzzle.solve(encoding), Some(solution.clone()));
+        }
+        assert_eq!(Grid::parse(&solution.to_string()), Ok(solution));
+
+        // Through DIMACS and back
+        let mut text = Vec::new();
+        write_cnf(&puzzle.encode(AmoEncoding::Ladder), &mut text).unwrap();
+        let dimacs = parse_dimacs(&text[..], ParseMode::Strict).unwrap();
+        let mut solver = Solver::from_clauses(&dimacs.formula);
+        assert!(solver.solve());
+        assert!(puzzle.decode(solver.model().unwrap()).is_solved());
+
+        // Two 5s in the first row
+        let mut conflicting = puzzle.clone();
+        conflicting.cells[8] = 5;
+        assert_eq!(conflicting.solve(AmoEncoding::Pairwise), None);
+
+        // 4 by 4 grids have 288 solutions, and the given cells are kept
+        assert_eq!(count_models(&Grid::empty(2).encode(AmoEncoding::Pairwise)), 288u32.into());
+        let small = Grid::parse("1 . | . .\n. . | 3 .\n----+----\n. 4 | . .\n. . | . 2").unwrap();
+        let solved = small.solve(AmoEncoding::Pairwise).unwrap();
+        assert!(solved.is_solved() && small.is_completed_by(&solved));
+
+        // Larger grids print their numbers apart
+        let empty = Grid::empty(4);
+        assert_eq!(Grid::parse(&empty.to_string()), Ok(empty));
+        assert_eq!(Grid::parse("12\n21"), Err(GridError::Shape));
+        assert_eq!(Grid::parse("1.\n.x\n"), Err(GridError::Cell(2)));
+    }
+}
@@ -0,0 +1,419 @@
+use crate::Formula;
+
+// Clauses for x1 xor ... xor xk = parity. Short constraints are written out
+// directly, one clause per assignment of the wrong parity; longer ones are cut
+// into a chain of three-variable constraints over fresh variables taken from
+// next_var.
+pub fn xor_clauses(vars: &[i32], parity: bool, next_var: &mut i32) -> Formula {
+    if vars.len() <= 3 {
+        return (0..1u32 << vars.len())
+            .filter(|bits| (bits.count_ones() % 2 == 1) != parity)
+            .map(|bits| {
+                // The only assignment falsifying the clause is bits itself
+                vars.iter().enumerate().map(|(i, &var)| if bits >> i & 1 == 1 { -var } else { var }).collect()
+            })
+            .collect();
+    }
+    *next_var += 1;
+    let link = *next_var;
+    // link = x1 xor x2, so x1 xor x2 xor link = 0
+    let mut clauses = xor_clauses(&[vars[0], vars[1], link], false, next_var);
+    let mut rest = vec![link];
+    rest.extend_from_slice(&vars[2..]);
+    clauses.extend(xor_clauses(&rest, parity, next_var));
+    clauses
+}
+
+// Clauses allowing at most k of the literals to be true, with Sinz's
+// sequential counter: fresh variable s(i, j) taken from next_var is implied
+// when at least j of the first i + 1 literals are true.
+pub fn at_most_k(lits: &[i32], k: usize, next_var: &mut i32) -> Formula {
+    if lits.len() <= k {
+        return Vec::new();
+    }
+    if k == 0 {
+        return lits.iter().map(|&lit| vec![-lit]).collect();
+    }
+    let n = lits.len();
+    let counter: Vec<Vec<i32>> = (0..n - 1)
+        .map(|_| {
+            (0..k)
+                .map(|_| {
+                    *next_var += 1;
+                    *next_var
+                })
+                .collect()
+        })
+        .collect();
+    let mut clauses = vec![vec![-lits[0], counter[0][0]]];
+    clauses.extend(counter[0][1..].iter().map(|&s| vec![-s]));
+    for i in 1..n - 1 {
+        clauses.push(vec![-lits[i], counter[i][0]]);
+        clauses.push(vec![-counter[i - 1][0], counter[i][0]]);
+        for j in 1..k {
+            clauses.push(vec![-lits[i], -counter[i - 1][j - 1], counter[i][j]]);
+            clauses.push(vec![-counter[i - 1][j], counter[i][j]]);
+        }
+        clauses.push(vec![-lits[i], -counter[i - 1][k - 1]]);
+    }
+    clauses.push(vec![-lits[n - 1], -counter[n - 2][k - 1]]);
+    clauses
+}
+
+// The ways at_most_one can encode its constraint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmoEncoding {
+    // A binary clause for every pair, quadratic but without fresh variables
+    Pairwise,
+    // A chain of fresh variables, the i-th true once a literal up to the i-th is
+    Ladder,
+    // Klieber and Kwon's commander encoding over groups of three
+    Commander,
+    // Nguyen and Mai's bimander encoding: pairs, told apart by the binary
+    // number of their pair
+    Bimander,
+}
+
+impl AmoEncoding {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pairwise" => Some(AmoEncoding::Pairwise),
+            "ladder" => Some(AmoEncoding::Ladder),
+            "commander" => Some(AmoEncoding::Commander),
+            "bimander" => Some(AmoEncoding::Bimander),
+            _ => None,
+        }
+    }
+}
+
+fn fresh(next_var: &mut i32) -> i32 {
+    *next_var += 1;
+    *next_var
+}
+
+fn pairwise(lits: &[i32]) -> Formula {
+    let mut clauses = Vec::new();
+    for (i, &a) in lits.iter().enumerate() {
+        for &b in lits[i + 1..].iter() {
+            clauses.push(vec![-a, -b]);
+        }
+    }
+    clauses
+}
+
+// Clauses allowing at most one of the literals to be true, with fresh
+// variables taken from next_var
+pub fn at_most_one(lits: &[i32], encoding: AmoEncoding, next_var: &mut i32) -> Formula {
+    if lits.len() <= 4 || encoding == AmoEncoding::Pairwise {
+        return pairwise(lits);
+    }
+    let mut clauses = Vec::new();
+    match encoding {
+        AmoEncoding::Pairwise => unreachable!(),
+        AmoEncoding::Ladder => {
+            let ladder: Vec<i32> = (1..lits.len()).map(|_| fresh(next_var)).collect();
+            for (i, &rung) in ladder.iter().enumerate() {
+                if let Some(&above) = ladder.get(i + 1) {
+                    clauses.push(vec![-rung, above]);
+                }
+                clauses.push(vec![-lits[i], rung]);
+                clauses.push(vec![-lits[i + 1], -rung]);
+            }
+        }
+        AmoEncoding::Commander => {
+            // Each literal implies the commander of its group, of which at
+            // most one may be true
+            let mut commanders = Vec::new();
+            for group in lits.chunks(3) {
+                let commander = fresh(next_var);
+                clauses.extend(pairwise(group));
+                clauses.extend(group.iter().map(|&lit| vec![-lit, commander]));
+                commanders.push(commander);
+            }
+            clauses.extend(at_most_one(&commanders, encoding, next_var));
+        }
+        AmoEncoding::Bimander => {
+            let groups = lits.len().div_ceil(2);
+            let bits: Vec<i32> = (0..usize::BITS - (groups - 1).leading_zeros()).map(|_| fresh(next_var)).collect();
+            for (i, group) in lits.chunks(2).enumerate() {
+                clauses.extend(pairwise(group));
+                for &lit in group.iter() {
+                    for (j, &bit) in bits.iter().enumerate() {
+                        clauses.push(vec![-lit, if i >> j & 1 == 1 { bit } else { -bit }]);
+                    }
+                }
+            }
+        }
+    }
+    clauses
+}
+
+// Clauses forcing at least k of the literals to be true: at most n - k of
+// their negations may be
+pub fn at_least_k(lits: &[i32], k: usize, next_var: &mut i32) -> Formula {
+    if k > lits.len() {
+        return vec![vec![]];
+    }
+    let negated: Vec<i32> = lits.iter().map(|&lit| -lit).collect();
+    at_most_k(&negated, lits.len() - k, next_var)
+}
+
+// Clauses forcing exactly k of the literals to be true
+pub fn exactly_k(lits: &[i32], k: usize, next_var: &mut i32) -> Formula {
+    let mut clauses = at_most_k(lits, k, next_var);
+    clauses.extend(at_least_k(lits, k, next_var));
+    clauses
+}
+
+// Bailleux and Boufkhad's totalizer: a binary tree of unary adders over the
+// literals whose root outputs count them, outputs[j] is true exactly when at
+// least j + 1 literals are. Any bound is then a unit clause on an output, so
+// one encoding serves every bound of an incremental search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Totalizer {
+    pub outputs: Vec<i32>,
+    pub clauses: Formula,
+}
+
+impl Totalizer {
+    pub fn new(lits: &[i32], next_var: &mut i32) -> Self {
+        let mut clauses = Vec::new();
+        let outputs = Self::count(lits, next_var, &mut clauses);
+        Totalizer { outputs, clauses }
+    }
+
+    // Unary count of the literals, with the clauses of the adders below
+    fn count(lits: &[i32], next_var: &mut i32, clauses: &mut Formula) -> Vec<i32> {
+        if lits.len() <= 1 {
+            return lits.to_vec();
+        }
+        let (left, right) = lits.split_at(lits.len() / 2);
+        let left = Self::count(left, next_var, clauses);
+        let right = Self::count(right, next_var, clauses);
+        let sum: Vec<i32> = (0..lits.len())
+            .map(|_| {
+                *next_var += 1;
+                *next_var
+            })
+            .collect();
+        // With a true and b false beyond the ends of the inputs:
+        // a[i] and b[j] imply sum[i + j], not a[i + 1] and not b[j + 1]
+        // imply not sum[i + j + 1]
+        for i in 0..=left.len() {
+            for j in 0..=right.len() {
+                if i + j > 0 {
+                    let mut clause: Vec<i32> = [left.get(i.wrapping_sub(1)), right.get(j.wrapping_sub(1))]
+                        .into_iter()
+                        .flatten()
+                        .map(|&lit| -lit)
+                        .collect();
+                    clause.push(sum[i + j - 1]);
+                    clauses.push(clause);
+                }
+                if i + j < lits.len() {
+                    let mut clause: Vec<i32> = [left.get(i), right.get(j)].into_iter().flatten().copied().collect();
+                    clause.push(-sum[i + j]);
+                    clauses.push(clause);
+                }
+            }
+        }
+        sum
+    }
+
+    // Unit clause allowing at most k true literals
+    pub fn at_most(&self, k: usize) -> Option<Vec<i32>> {
+        self.outputs.get(k).map(|&output| vec![-output])
+    }
+
+    // Unit clause forcing at least k true literals
+    pub fn at_least(&self, k: usize) -> Option<Vec<i32>> {
+        match k {
+            0 => None,
+            _ => Some(self.outputs.get(k - 1).map_or(vec![], |&output| vec![output])),
+        }
+    }
+}
+
+// Clauses bounding the total weight of the true literals by bound. Fresh
+// variable s(i, j) taken from next_var is implied when the literals up to
+// the i-th weigh at least j + 1, so the encoding grows with the bound.
+pub fn at_most_weighted(lits: &[i32], weights: &[u64], bound: u64, next_var: &mut i32) -> Formula {
+    let mut clauses = Vec::new();
+    let bound = bound as usize;
+    let mut previous: Vec<i32> = Vec::new();
+    for (&lit, &weight) in lits.iter().zip(weights.iter()) {
+        if weight == 0 {
+            continue;
+        }
+        if weight as usize > bound {
+            clauses.push(vec![-lit]);
+            continue;
+        }
+        let weight = weight as usize;
+        let sums: Vec<i32> = (0..bound)
+            .map(|_| {
+                *next_var += 1;
+                *next_var
+            })
+            .collect();
+        for (j, &sum) in sums.iter().enumerate() {
+            if let Some(&before) = previous.get(j) {
+                clauses.push(vec![-before, sum]);
+            }
+            if j < weight {
+                clauses.push(vec![-lit, sum]);
+            }
+        }
+        for (j, &before) in previous.iter().enumerate() {
+            if j + weight < bound {
+                clauses.push(vec![-lit, -before, sums[j + weight]]);
+            } else {
+                clauses.push(vec![-lit, -before]);
+            }
+        }
+        previous = sums;
+    }
+    clauses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    // Number of assignments of 1 to num_vars satisfying the formula
+    fn count(formula: &Formula, num_vars: i32, fixed: &[i32]) -> usize {
+        (0..1u32 << num_vars)
+            .filter(|bits| {
+                let value = |lit: i32| ((bits >> (lit.abs() - 1)) & 1 == 1) == (lit > 0);
+                fixed.iter().all(|&lit| value(lit)) && formula.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+            })
+            .count()
+    }
+
+    #[test]
+    fn test_xor_clauses() {
+        for len in 0..=5 {
+            let vars: Vec<i32> = (1..=len).collect();
+            for parity in [false, true] {
+                let mut next_var = len;
+                let clauses = xor_clauses(&vars, parity, &mut next_var);
+                // Every input assignment of the right parity extends to exactly one model
+                for bits in 0..1u32 << len {
+                    let fixed: Vec<i32> =
+                        vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                    let expected = usize::from((bits.count_ones() % 2 == 1) == parity);
+                    assert_eq!(count(&clauses, next_var, &fixed), expected, "{} {}", len, parity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_at_most_k() {
+        for len in 0..=5 {
+            let vars: Vec<i32> = (1..=len).collect();
+            for k in 0..=len as usize + 1 {
+                let mut next_var = len;
+                let clauses = at_most_k(&vars, k, &mut next_var);
+                for bits in 0..1u32 << len {
+                    let fixed: Vec<i32> =
+                        vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                    let allowed = bits.count_ones() as usize <= k;
+                    assert_eq!(count(&clauses, next_var, &fixed) > 0, allowed, "{} {} {:b}", len, k, bits);
+                }
+            }
+        }
+    }
+
+    fn satisfiable(formula: &Formula, assumptions: &[i32]) -> bool {
+        let mut solver = Solver::new();
+        for clause in formula.iter() {
+            solver.add_clause(clause);
+        }
+        solver.solve_with_assumptions(assumptions)
+    }
+
+    #[test]
+    fn test_at_most_one() {
+        let encodings = ["pairwise", "ladder", "commander", "bimander"].map(|name| AmoEncoding::from_name(name).unwrap());
+        for len in 0..=11 {
+            let vars: Vec<i32> = (1..=len).collect();
+            for encoding in encodings {
+                let mut next_var = len;
+                let clauses = at_most_one(&vars, encoding, &mut next_var);
+                // Every assignment with at most two true literals, and all true
+                let assignments = (0..1u32 << len).filter(|bits| bits.count_ones() <= 2);
+                for bits in assignments.chain([(1u32 << len) - 1]) {
+                    let fixed: Vec<i32> =
+                        vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                    let allowed = bits.count_ones() <= 1;
+                    assert_eq!(satisfiable(&clauses, &fixed), allowed, "{:?} {} {:b}", encoding, len, bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_at_least_and_exactly_k() {
+        for len in 0..=5 {
+            let vars: Vec<i32> = (1..=len).collect();
+            for k in 0..=len as usize + 1 {
+                let mut next_var = len;
+                let at_least = at_least_k(&vars, k, &mut next_var);
+                let exactly = exactly_k(&vars, k, &mut next_var);
+                for bits in 0..1u32 << len {
+                    let fixed: Vec<i32> =
+                        vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                    let ones = bits.count_ones() as usize;
+                    assert_eq!(satisfiable(&at_least, &fixed), ones >= k, "{} {} {:b}", len, k, bits);
+                    assert_eq!(satisfiable(&exactly, &fixed), ones == k, "{} {} {:b}", len, k, bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_totalizer() {
+        for len in 0..=5 {
+            let vars: Vec<i32> = (1..=len).collect();
+            let mut next_var = len;
+            let totalizer = Totalizer::new(&vars, &mut next_var);
+            assert_eq!(totalizer.outputs.len(), len as usize);
+            for bits in 0..1u32 << len {
+                let fixed: Vec<i32> =
+                    vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                // The inputs force the outputs to their count in unary
+                let ones = bits.count_ones() as usize;
+                for (j, &output) in totalizer.outputs.iter().enumerate() {
+                    let wrong = if j < ones { -output } else { output };
+                    assert!(!satisfiable(&totalizer.clauses, &[&fixed[..], &[wrong]].concat()), "{:b} {}", bits, j);
+                }
+                for k in 0..=len as usize + 1 {
+                    let mut bounded = totalizer.clauses.clone();
+                    bounded.extend(totalizer.at_most(k));
+                    bounded.extend(totalizer.at_least(k));
+                    assert_eq!(satisfiable(&bounded, &fixed), ones == k, "{} {:b}", k, bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_at_most_weighted() {
+        let vars = [1, 2, 3, 4];
+        let weights = [3, 1, 2, 0];
+        for bound in 0..=7 {
+            let mut next_var = 4;
+            let mut solver = Solver::new();
+            for clause in at_most_weighted(&vars, &weights, bound, &mut next_var) {
+                solver.add_clause(&clause);
+            }
+            for bits in 0..1u32 << 4 {
+                let fixed: Vec<i32> = vars.iter().map(|&var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+                let weight: u64 = (0..4).filter(|i| bits >> i & 1 == 1).map(|i| weights[i]).sum();
+                assert_eq!(solver.solve_with_assumptions(&fixed), weight <= bound, "{} {:b}", bound, bits);
+            }
+        }
+    }
+}
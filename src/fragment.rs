@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::twosat::{is_2sat, solve_2sat};
+use crate::{Formula, Model};
+
+// A class of formulas that is decided in linear time without search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fragment {
+    // At most two literals per clause
+    TwoSat,
+    // At most one positive literal per clause
+    Horn,
+    // At most one negative literal per clause
+    DualHorn,
+}
+
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fragment::TwoSat => write!(f, "2-SAT"),
+            Fragment::Horn => write!(f, "Horn"),
+            Fragment::DualHorn => write!(f, "dual Horn"),
+        }
+    }
+}
+
+impl Fragment {
+    // The first fragment the formula belongs to, if any
+    pub fn detect(formula: &[Vec<i32>]) -> Option<Self> {
+        let at_most_one = |positive: bool| {
+            formula.iter().all(|clause| clause.iter().filter(|&&lit| (lit > 0) == positive).count() <= 1)
+        };
+        if is_2sat(formula) {
+            Some(Fragment::TwoSat)
+        } else if at_most_one(true) {
+            Some(Fragment::Horn)
+        } else if at_most_one(false) {
+            Some(Fragment::DualHorn)
+        } else {
+            None
+        }
+    }
+
+    // Decide a formula of the fragment, returning a model over its
+    // variables if it is satisfiable
+    pub fn solve(&self, formula: &Formula) -> Option<Model> {
+        match self {
+            Fragment::TwoSat => solve_2sat(formula),
+            Fragment::Horn => solve_horn(formula),
+            // Negating every literal turns dual Horn clauses into Horn ones
+            Fragment::DualHorn => {
+                let negated: Formula = formula.iter().map(|clause| clause.iter().map(|&lit| -lit).collect()).collect();
+                let model = solve_horn(&negated)?;
+                Some(model.into_iter().map(|(var, val)| (var, !val)).collect())
+            }
+        }
+    }
+}
+
+// Decide a Horn formula by forward chaining, in time linear in its size.
+// Every variable starts false; a clause whose negative literals have all
+// become false forces its positive literal, or refutes the formula if it has
+// none. The model is the least one, it makes as few variables true as
+// possible.
+pub fn solve_horn(formula: &Formula) -> Option<Model> {
+    let num_vars = formula.iter().flatten().map(|lit| lit.unsigned_abs() as usize).max().unwrap_or(0);
+    // Clauses of every negative literal, and how many of each clause's
+    // negative literals are not false yet
+    let mut watchers: Vec<Vec<usize>> = vec![Vec::new(); num_vars + 1];
+    let mut remaining = Vec::with_capacity(formula.len());
+    let mut heads = Vec::with_capacity(formula.len());
+    let mut values = vec![false; num_vars + 1];
+    let mut queue = Vec::new();
+    for (i, clause) in formula.iter().enumerate() {
+        let mut positive = clause.iter().filter(|&&lit| lit > 0);
+        let head = positive.next().copied();
+        assert!(positive.next().is_none(), "Not a Horn clause: {:?}", clause);
+        let body: Vec<i32> = clause.iter().copied().filter(|&lit| lit < 0).collect();
+        for lit in body.iter() {
+            watchers[lit.unsigned_abs() as usize].push(i);
+        }
+        remaining.push(body.len());
+        heads.push(head);
+        if body.is_empty() {
+            match head {
+                Some(var) if !values[var as usize] => {
+                    values[var as usize] = true;
+                    queue.push(var as usize);
+                }
+                Some(_) => {}
+                None => return None,
+            }
+        }
+    }
+    while let Some(var) = queue.pop() {
+        for &i in watchers[var].iter() {
+            remaining[i] -= 1;
+            if remaining[i] > 0 {
+                continue;
+            }
+            match heads[i] {
+                Some(head) if !values[head as usize] => {
+                    values[head as usize] = true;
+                    queue.push(head as usize);
+                }
+                Some(_) => {}
+                None => return None,
+            }
+        }
+    }
+    let vars = formula.iter().flatten().map(|lit| lit.abs());
+    Some(vars.map(|var| (var, values[var as usize])).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_model;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_fragments() {
+        // x1, x1 implies x2, x2 and x3 imply x4, and x4 excludes x1
+        let horn = vec![vec![1], vec![-1, 2], vec![-2, -3, 4], vec![-4, -1]];
+        assert_eq!(Fragment::detect(&horn), Some(Fragment::Horn));
+        let model = solve_horn(&horn).unwrap();
+        assert_eq!((model[&1], model[&2], model[&3], model[&4]), (true, true, false, false));
+        assert_eq!(solve_horn(&vec![vec![1], vec![-1, 2], vec![-1, -2]]), None);
+        assert_eq!(Fragment::detect(&[vec![1, 2, -3], vec![-1, -2, 3]]), None);
+        assert_eq!(Fragment::detect(&[vec![1, 2, -3], vec![1]]), Some(Fragment::DualHorn));
+
+        let mut rng = StdRng::seed_from_u64(19);
+        for _ in 0..400 {
+            let num_vars = rng.gen_range(1..=7);
+            let dual = rng.gen_bool(0.5);
+            // Clauses of up to four literals with at most one of the special
+            // polarity, the first
+            let formula: Formula = (0..rng.gen_range(0..=12))
+                .map(|_| {
+                    let mut clause: Vec<i32> = (0..rng.gen_range(0..=3)).map(|_| -rng.gen_range(1..=num_vars)).collect();
+                    if rng.gen_bool(0.6) {
+                        clause.insert(0, rng.gen_range(1..=num_vars));
+                    }
+                    if dual {
+                        clause.iter_mut().for_each(|lit| *lit = -*lit);
+                    }
+                    clause
+                })
+                .collect();
+            let satisfiable = (0..1u32 << num_vars).any(|bits| {
+                let model: Model = (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect();
+                verify_model(&formula, &model).is_ok()
+            });
+            let fragment = Fragment::detect(&formula).unwrap();
+            match fragment.solve(&formula) {
+                Some(model) => assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?}", formula, model),
+                None => assert!(!satisfiable, "{:?} {:?}", fragment, formula),
+            }
+        }
+    }
+}
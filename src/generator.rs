@@ -0,0 +1,183 @@
+use rand::rngs::StdRng;
+use rand::seq::{index, SliceRandom};
+use rand::{Rng, SeedableRng};
+
+use crate::encodings::{at_most_one, xor_clauses, AmoEncoding};
+use crate::Formula;
+
+// A uniform random k-SAT instance: every clause takes k distinct variables out
+// of 1 to num_vars, each negated with probability 1/2. The same seed gives the
+// same instance.
+pub fn random_ksat(num_vars: usize, num_clauses: usize, k: usize, seed: u64) -> Formula {
+    assert!(k <= num_vars, "Clauses of {} distinct variables out of {}", k, num_vars);
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..num_clauses)
+        .map(|_| {
+            index::sample(&mut rng, num_vars, k)
+                .into_iter()
+                .map(|var| if rng.gen_bool(0.5) { var as i32 + 1 } else { -(var as i32 + 1) })
+                .collect()
+        })
+        .collect()
+}
+
+// PHP(n): n + 1 pigeons in n holes, every pigeon in some hole and no two in
+// the same one. Variable i * n + j + 1 puts pigeon i into hole j. Every
+// resolution refutation of it is exponentially long.
+pub fn pigeonhole(n: usize) -> Formula {
+    let var = |pigeon: usize, hole: usize| (pigeon * n + hole + 1) as i32;
+    let mut formula: Formula = (0..=n).map(|pigeon| (0..n).map(|hole| var(pigeon, hole)).collect()).collect();
+    let mut next_var = ((n + 1) * n) as i32;
+    for hole in 0..n {
+        let pigeons: Vec<i32> = (0..=n).map(|pigeon| var(pigeon, hole)).collect();
+        formula.extend(at_most_one(&pigeons, AmoEncoding::Pairwise, &mut next_var));
+    }
+    formula
+}
+
+// x1 xor ... xor xn required to be both 1 and 0, by two chains of XORs over
+// the variables in different random orders. Unsatisfiable, but only by
+// combining the chains as a whole, which clause by clause reasoning without
+// XOR reasoning finds hard.
+pub fn parity_chains(n: usize, seed: u64) -> Formula {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut vars: Vec<i32> = (1..=n as i32).collect();
+    let mut next_var = n as i32;
+    let mut formula = xor_clauses(&vars, true, &mut next_var);
+    vars.shuffle(&mut rng);
+    formula.extend(xor_clauses(&vars, false, &mut next_var));
+    formula
+}
+
+// The Tseitin formula of a graph: a variable per edge, edge i being variable
+// i + 1, and at every vertex the XOR of its edges equal to its charge. It is
+// unsatisfiable exactly when some connected component has an odd total charge.
+pub fn tseitin_formula(num_vertices: usize, edges: &[(usize, usize)], charges: &[bool]) -> Formula {
+    assert_eq!(charges.len(), num_vertices, "A charge for every vertex");
+    let mut incident = vec![Vec::new(); num_vertices];
+    for (i, &(u, v)) in edges.iter().enumerate() {
+        incident[u].push(i as i32 + 1);
+        incident[v].push(i as i32 + 1);
+    }
+    let mut next_var = edges.len() as i32;
+    incident.iter().zip(charges).flat_map(|(vars, &charge)| xor_clauses(vars, charge, &mut next_var)).collect()
+}
+
+// The Tseitin formula of a random d-regular graph on n vertices with only the
+// first one charged, so it is unsatisfiable. Random regular graphs are
+// expanders with high probability, and on expanders every resolution
+// refutation is exponentially long.
+pub fn tseitin_expander(n: usize, d: usize, seed: u64) -> Formula {
+    let edges = random_regular_graph(n, d, seed);
+    let mut charges = vec![false; n];
+    charges[0] = true;
+    tseitin_formula(n, &edges, &charges)
+}
+
+// A connected simple d-regular graph on n vertices, from the configuration
+// model: pair up d copies of every vertex at random until the pairing has no
+// loops or repeated edges and connects the graph
+fn random_regular_graph(n: usize, d: usize, seed: u64) -> Vec<(usize, usize)> {
+    assert!(d >= 2 && d < n && (n * d).is_multiple_of(2), "No connected {}-regular graph on {} vertices", d, n);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut points: Vec<usize> = (0..n).flat_map(|vertex| std::iter::repeat_n(vertex, d)).collect();
+    loop {
+        points.shuffle(&mut rng);
+        let mut edges: Vec<(usize, usize)> =
+            points.chunks(2).map(|pair| (pair[0].min(pair[1]), pair[0].max(pair[1]))).collect();
+        edges.sort();
+        let simple = edges.iter().all(|&(u, v)| u != v) && edges.windows(2).all(|pair| pair[0] != pair[1]);
+        if simple && is_connected(n, &edges) {
+            return edges;
+        }
+    }
+}
+
+fn is_connected(n: usize, edges: &[(usize, usize)]) -> bool {
+    let mut neighbours = vec![Vec::new(); n];
+    for &(u, v) in edges {
+        neighbours[u].push(v);
+        neighbours[v].push(u);
+    }
+    let mut seen = vec![false; n];
+    let mut stack = vec![0];
+    seen[0] = true;
+    while let Some(vertex) = stack.pop() {
+        for &next in neighbours[vertex].iter() {
+            if !seen[next] {
+                seen[next] = true;
+                stack.push(next);
+            }
+        }
+    }
+    seen.into_iter().all(|seen| seen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Solver;
+
+    fn is_sat(formula: &Formula) -> bool {
+        Solver::from_clauses(formula).solve()
+    }
+
+    #[test]
+    fn test_random_ksat() {
+        let formula = random_ksat(10, 43, 3, 7);
+        assert_eq!(formula.len(), 43);
+        for clause in formula.iter() {
+            let mut vars: Vec<i32> = clause.iter().map(|lit| lit.abs()).collect();
+            vars.sort();
+            vars.dedup();
+            assert_eq!(vars.len(), 3);
+            assert!(vars.iter().all(|&var| (1..=10).contains(&var)));
+        }
+        assert_eq!(formula, random_ksat(10, 43, 3, 7));
+        assert_ne!(formula, random_ksat(10, 43, 3, 8));
+        assert!(formula.iter().flatten().any(|&lit| lit < 0));
+        assert_eq!(random_ksat(5, 2, 0, 0), vec![Vec::<i32>::new(); 2]);
+    }
+
+    #[test]
+    fn test_pigeonhole() {
+        // 4 clauses putting each pigeon somewhere, 3 pairs per hole
+        let formula = pigeonhole(3);
+        assert_eq!(formula.len(), 4 + 3 * 6);
+        for n in 1..=4 {
+            assert!(!is_sat(&pigeonhole(n)));
+        }
+        // Without its last pigeon every pigeon gets a hole of its own
+        let mut formula = pigeonhole(3);
+        formula.remove(3);
+        assert!(is_sat(&formula));
+    }
+
+    #[test]
+    fn test_parity_chains() {
+        for n in [1, 2, 5, 9] {
+            assert!(!is_sat(&parity_chains(n, n as u64)));
+        }
+        // One chain alone is satisfiable
+        let mut next_var = 9;
+        assert!(is_sat(&xor_clauses(&(1..=9).collect::<Vec<_>>(), true, &mut next_var)));
+    }
+
+    #[test]
+    fn test_tseitin() {
+        let triangle = [(0, 1), (1, 2), (0, 2)];
+        assert!(!is_sat(&tseitin_formula(3, &triangle, &[true, false, false])));
+        assert!(is_sat(&tseitin_formula(3, &triangle, &[true, true, false])));
+
+        let edges = random_regular_graph(10, 3, 4);
+        assert_eq!(edges.len(), 15);
+        let mut degrees = [0; 10];
+        for &(u, v) in edges.iter() {
+            degrees[u] += 1;
+            degrees[v] += 1;
+        }
+        assert_eq!(degrees, [3; 10]);
+        assert_eq!(edges, random_regular_graph(10, 3, 4));
+        assert!(!is_sat(&tseitin_expander(10, 3, 4)));
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+use crate::{get_assignment_keys, Assignment, Clauses};
+
+// Strategy used by build_search_tree to choose the next branching literal
+pub trait BranchingHeuristic: Send {
+    // Pick the literal to branch on, its sign is the polarity explored first.
+    // By default this is the smallest unassigned variable, tried as true first.
+    fn pick_branch_var(&self, _clauses: Clauses, assignment: &Assignment) -> Option<i32> {
+        get_assignment_keys(assignment).first().copied()
+    }
+}
+
+// Branch on the unassigned variable with the smallest index
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MinIndexHeuristic;
+
+impl BranchingHeuristic for MinIndexHeuristic {}
+
+// Score literals by 2^-|clause| over the clauses they occur in. The one-sided
+// variant branches on the best literal, the two-sided one on the variable with
+// the best combined score of both literals, tried with its stronger polarity.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JeroslowWangHeuristic {
+    pub two_sided: bool,
+}
+
+impl JeroslowWangHeuristic {
+    pub fn one_sided() -> Self {
+        JeroslowWangHeuristic { two_sided: false }
+    }
+
+    pub fn two_sided() -> Self {
+        JeroslowWangHeuristic { two_sided: true }
+    }
+}
+
+impl BranchingHeuristic for JeroslowWangHeuristic {
+    fn pick_branch_var(&self, clauses: Clauses, assignment: &Assignment) -> Option<i32> {
+        let scores = literal_scores(clauses.iter(), assignment, |len| 0.5f64.powi(len as i32));
+        if self.two_sided {
+            let mut var_scores: HashMap<i32, f64> = HashMap::new();
+            for (&lit, &score) in scores.iter() {
+                *var_scores.entry(lit.abs()).or_insert(0.0) += score;
+            }
+            let var = best_literal(&var_scores)?;
+            let pos = scores.get(&var).copied().unwrap_or(0.0);
+            let neg = scores.get(&-var).copied().unwrap_or(0.0);
+            Some(if neg > pos { -var } else { var })
+        } else {
+            best_literal(&scores)
+        }
+    }
+}
+
+// Dynamic Largest Individual Sum: branch on the literal with the most
+// occurrences in the open clauses of the node's formula
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DlisHeuristic;
+
+impl BranchingHeuristic for DlisHeuristic {
+    fn pick_branch_var(&self, clauses: Clauses, assignment: &Assignment) -> Option<i32> {
+        best_literal(&literal_scores(clauses.iter(), assignment, |_| 1.0))
+    }
+}
+
+// Maximum Occurrences in clauses of Minimum Size: only the shortest open
+// clauses are counted and variables are ranked by
+// (f(x) + f(-x)) * 2^k + f(x) * f(-x), so a larger k favours the total count
+// and a smaller one favours variables occurring in both polarities
+#[derive(Clone, Copy, Debug)]
+pub struct MomsHeuristic {
+    pub k: u32,
+}
+
+impl MomsHeuristic {
+    pub fn new(k: u32) -> Self {
+        MomsHeuristic { k }
+    }
+}
+
+impl Default for MomsHeuristic {
+    fn default() -> Self {
+        MomsHeuristic::new(1)
+    }
+}
+
+impl BranchingHeuristic for MomsHeuristic {
+    fn pick_branch_var(&self, clauses: Clauses, assignment: &Assignment) -> Option<i32> {
+        let open_clauses: Vec<Vec<i32>> = clauses
+            .iter()
+            .filter_map(|clause| open_literals(clause, assignment))
+            .filter(|open| !open.is_empty())
+            .collect();
+        let min_len = open_clauses.iter().map(Vec::len).min()?;
+        let shortest = open_clauses.iter().filter(|open| open.len() == min_len).map(Vec::as_slice);
+        let counts = literal_scores(shortest, assignment, |_| 1.0);
+
+        let mut var_scores: HashMap<i32, f64> = HashMap::new();
+        for &lit in counts.keys() {
+            let var = lit.abs();
+            let pos = counts.get(&var).copied().unwrap_or(0.0);
+            let neg = counts.get(&-var).copied().unwrap_or(0.0);
+            var_scores.insert(var, (pos + neg) * 2f64.powi(self.k as i32) + pos * neg);
+        }
+        let var = best_literal(&var_scores)?;
+        let pos = counts.get(&var).copied().unwrap_or(0.0);
+        let neg = counts.get(&-var).copied().unwrap_or(0.0);
+        Some(if neg > pos { -var } else { var })
+    }
+}
+
+// Look up a heuristic by its CLI name, MOMS takes its k as in "moms:3"
+pub fn heuristic_by_name(name: &str) -> Option<Box<dyn BranchingHeuristic>> {
+    if let Some(k) = name.strip_prefix("moms:") {
+        return k.parse().ok().map(|k| Box::new(MomsHeuristic::new(k)) as Box<dyn BranchingHeuristic>);
+    }
+    match name {
+        "moms" => Some(Box::new(MomsHeuristic::default())),
+        "min-index" => Some(Box::new(MinIndexHeuristic)),
+        "jw-one-sided" => Some(Box::new(JeroslowWangHeuristic::one_sided())),
+        "jw-two-sided" => Some(Box::new(JeroslowWangHeuristic::two_sided())),
+        "dlis" => Some(Box::new(DlisHeuristic)),
+        _ => None,
+    }
+}
+
+// Unassigned literals of a clause, or None if the clause is already satisfied
+fn open_literals(clause: &[i32], assignment: &Assignment) -> Option<Vec<i32>> {
+    let mut open = Vec::new();
+    for &lit in clause.iter() {
+        match assignment.get(&lit.abs()) {
+            Some(&Some(val)) if val == (lit > 0) => return None,
+            Some(&None) => open.push(lit),
+            _ => {}
+        }
+    }
+    Some(open)
+}
+
+// Sum weight(|clause|) for every unassigned literal over the open clauses
+fn literal_scores<'a>(
+    clauses: impl Iterator<Item = &'a [i32]>,
+    assignment: &Assignment,
+    weight: impl Fn(usize) -> f64,
+) -> HashMap<i32, f64> {
+    let mut scores = HashMap::new();
+    for clause in clauses {
+        if let Some(open) = open_literals(clause, assignment) {
+            let w = weight(open.len());
+            for lit in open {
+                *scores.entry(lit).or_insert(0.0) += w;
+            }
+        }
+    }
+    scores
+}
+
+// Highest scoring key, ties go to the smallest variable and then to positive
+fn best_literal(scores: &HashMap<i32, f64>) -> Option<i32> {
+    scores
+        .iter()
+        .max_by(|(a, sa), (b, sb)| {
+            sa.total_cmp(sb)
+                .then_with(|| b.abs().cmp(&a.abs()))
+                .then_with(|| a.cmp(b))
+        })
+        .map(|(&lit, _)| lit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClauseDb, Formula};
+    use maplit::hashmap;
+
+    // Pick from every clause of the formula
+    fn pick(heuristic: &dyn BranchingHeuristic, formula: &Formula, assignment: &Assignment) -> Option<i32> {
+        let db = ClauseDb::new(formula);
+        heuristic.pick_branch_var(Clauses::new(&db, &db.ids()), assignment)
+    }
+
+    #[test]
+    fn test_min_index_heuristic() {
+        let formula = vec![vec![3, -2], vec![2, 4]];
+        let assignment: Assignment = hashmap! {
+            1 => Some(false),
+            2 => None,
+            3 => None,
+            4 => None,
+        }.into();
+        assert_eq!(pick(&MinIndexHeuristic, &formula, &assignment), Some(2));
+
+        let assigned: Assignment = hashmap! { 1 => Some(true) }.into();
+        assert_eq!(pick(&MinIndexHeuristic, &formula, &assigned), None);
+    }
+
+    #[test]
+    fn test_jeroslow_wang_heuristic() {
+        let formula = vec![
+            vec![-1, 2],
+            vec![-1, 3],
+            vec![1, 2, 3],
+            vec![1, -2, 4],
+            vec![4, 5],
+            vec![-4, 5],
+        ];
+        let assignment: Assignment = hashmap! {
+            1 => None,
+            2 => None,
+            3 => None,
+            4 => None,
+            5 => None,
+        }.into();
+        // J(-1) = 1/2, J(5) = 1/2, J(1) = 1/4; -1 wins the tie on the smaller index
+        assert_eq!(pick(&JeroslowWangHeuristic::one_sided(), &formula, &assignment), Some(-1));
+        // J(1) + J(-1) = 3/4 is the best combined score, -1 is the stronger side
+        assert_eq!(pick(&JeroslowWangHeuristic::two_sided(), &formula, &assignment), Some(-1));
+
+        // With 1 false, [1, 2, 3] shrinks to two open literals and the
+        // clauses containing -1 are satisfied
+        let mut assignment = assignment;
+        assignment.insert(1, Some(false));
+        // J(4) = J(5) = 1/2, 4 wins the tie
+        assert_eq!(pick(&JeroslowWangHeuristic::one_sided(), &formula, &assignment), Some(4));
+        // J(4) + J(-4) = 3/4 beats J(5) = 1/2 and J(2) + J(-2) = 1/2
+        assert_eq!(pick(&JeroslowWangHeuristic::two_sided(), &formula, &assignment), Some(4));
+    }
+
+    #[test]
+    fn test_dlis_heuristic() {
+        let formula = vec![
+            vec![1, -2],
+            vec![-2, 3],
+            vec![-2, -3],
+            vec![2, 3, 1],
+            vec![1, 4],
+        ];
+        let mut assignment: Assignment = hashmap! {
+            1 => None,
+            2 => None,
+            3 => None,
+            4 => None,
+        }.into();
+        // 1 and -2 both occur three times, 1 has the smaller index
+        assert_eq!(pick(&DlisHeuristic, &formula, &assignment), Some(1));
+        // Once 1 is false it no longer counts and -2 occurs three times
+        assignment.insert(1, Some(false));
+        assert_eq!(pick(&DlisHeuristic, &formula, &assignment), Some(-2));
+    }
+
+    #[test]
+    fn test_heuristic_by_name() {
+        let formula = vec![vec![-2, 1], vec![-2, 3]];
+        let assignment: Assignment = hashmap! {
+            1 => None,
+            2 => None,
+            3 => None,
+        }.into();
+        let dlis = heuristic_by_name("dlis").unwrap();
+        assert_eq!(pick(dlis.as_ref(), &formula, &assignment), Some(-2));
+        assert!(heuristic_by_name("no-such-heuristic").is_none());
+        assert!(heuristic_by_name("moms:4").is_some());
+        assert!(heuristic_by_name("moms:x").is_none());
+    }
+
+    #[test]
+    fn test_moms_heuristic() {
+        let formula = vec![
+            vec![1, 5],
+            vec![1, 6],
+            vec![1, 7],
+            vec![1, 8],
+            vec![2, 9],
+            vec![2, 10],
+            vec![-2, 11],
+            vec![-1, -2, 3],
+        ];
+        let mut assignment: Assignment = (1..=11).map(|var| (var, None)).collect();
+        // Over the binary clauses f(1) = 4 and f(2) = 2, f(-2) = 1, so with k = 0
+        // 1 scores 4 and 2 scores 3 + 2; the ternary clause is not counted
+        assert_eq!(pick(&MomsHeuristic::new(0), &formula, &assignment), Some(2));
+        // With k = 2 the total count dominates: 16 against 12 + 2
+        assert_eq!(pick(&MomsHeuristic::new(2), &formula, &assignment), Some(1));
+
+        // Once 9 is false the unit [2] is the only shortest clause
+        assignment.insert(9, Some(false));
+        assert_eq!(pick(&MomsHeuristic::new(2), &formula, &assignment), Some(2));
+    }
+
+}
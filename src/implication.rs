@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+
+use crate::twosat::{components, node};
+
+// The binary implication graph of a formula: every clause a or b makes -a
+// imply b and -b imply a. The other clauses are left out, so the graph holds
+// what propagating the binary clauses alone derives.
+#[derive(Clone, Debug, Default)]
+pub struct ImplicationGraph {
+    // Literals each literal implies directly, by node, sorted and without
+    // duplicates
+    edges: Vec<Vec<i32>>,
+}
+
+impl ImplicationGraph {
+    pub fn new(formula: &[Vec<i32>]) -> Self {
+        let mut graph = ImplicationGraph::default();
+        for clause in formula.iter() {
+            if let [a, b] = clause[..] {
+                graph.add_binary(a, b);
+            }
+        }
+        graph
+    }
+
+    // Add the implications of the clause a or b. A tautology implies
+    // nothing, a or a makes -a imply a.
+    pub fn add_binary(&mut self, a: i32, b: i32) {
+        if a == -b {
+            return;
+        }
+        let needed = node(a.abs()).max(node(b.abs())) + 2;
+        if self.edges.len() < needed {
+            self.edges.resize(needed, Vec::new());
+        }
+        for (from, to) in [(-a, b), (-b, a)] {
+            let edges = &mut self.edges[node(from)];
+            if let Err(i) = edges.binary_search(&to) {
+                edges.insert(i, to);
+            }
+        }
+    }
+
+    // Largest variable with a node in the graph
+    pub fn num_vars(&self) -> usize {
+        self.edges.len() / 2
+    }
+
+    // Number of implications, two per binary clause
+    pub fn num_edges(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    // The literals lit implies directly
+    pub fn implied(&self, lit: i32) -> &[i32] {
+        self.edges.get(node(lit)).map_or(&[], Vec::as_slice)
+    }
+
+    // Every literal lit implies through a chain of implications, closest
+    // first. lit itself is only among them if it lies on a cycle.
+    pub fn implied_transitively(&self, lit: i32) -> Vec<i32> {
+        let mut seen = vec![false; self.edges.len()];
+        let mut found = Vec::new();
+        let mut queue: VecDeque<i32> = self.implied(lit).iter().copied().collect();
+        for &next in queue.iter() {
+            seen[node(next)] = true;
+        }
+        while let Some(current) = queue.pop_front() {
+            found.push(current);
+            for &next in self.implied(current) {
+                if !seen[node(next)] {
+                    seen[node(next)] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        found
+    }
+
+    // Whether setting a true forces b true by the binary clauses
+    pub fn implies(&self, a: i32, b: i32) -> bool {
+        a == b || self.implied_transitively(a).contains(&b)
+    }
+
+    // Literals whose implications reach their own negation, so they are
+    // false in every model
+    pub fn failed_literals(&self) -> Vec<i32> {
+        (1..=self.num_vars() as i32)
+            .flat_map(|var| [var, -var])
+            .filter(|&lit| self.implied_transitively(lit).contains(&-lit))
+            .collect()
+    }
+
+    // Literals on a common cycle imply each other, so they are equivalent.
+    // By variable, the literal of the smallest variable each variable is
+    // equivalent to, the variable itself if there is no other. None if some
+    // literal is equivalent to its negation, so no model exists.
+    pub fn equivalences(&self) -> Option<Vec<i32>> {
+        let edges: Vec<Vec<usize>> = self
+            .edges
+            .iter()
+            .map(|list| list.iter().map(|&lit| node(lit)).collect())
+            .collect();
+        let component = components(&edges);
+        // The first literal met of each component, 0 before any
+        let mut first = vec![0; component.len()];
+        for var in 1..=self.num_vars() as i32 {
+            if component[node(var)] == component[node(-var)] {
+                return None;
+            }
+            for lit in [var, -var] {
+                let first = &mut first[component[node(lit)]];
+                if *first == 0 {
+                    *first = lit;
+                }
+            }
+        }
+        Some((0..=self.num_vars() as i32).map(|var| if var == 0 { 0 } else { first[component[node(var)]] }).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_implication_graph() {
+        // 1 implies 2 implies 3, the tautology implies nothing and -5 or -5
+        // makes 5 imply -5
+        let formula = vec![vec![-1, 2], vec![-2, 3], vec![2, -1], vec![1, 2, 3], vec![4, -4], vec![-5, -5]];
+        let graph = ImplicationGraph::new(&formula);
+        assert_eq!(graph.num_vars(), 5);
+        assert_eq!(graph.num_edges(), 5);
+        assert_eq!(graph.implied(1), &[2]);
+        assert_eq!(graph.implied(-3), &[-2]);
+        assert_eq!(graph.implied(4), &[] as &[i32]);
+        assert_eq!(graph.implied(9), &[] as &[i32]);
+        assert_eq!(graph.implied_transitively(1), vec![2, 3]);
+        assert_eq!(graph.implied_transitively(-3), vec![-2, -1]);
+        assert!(graph.implies(1, 3) && graph.implies(-3, -1) && graph.implies(3, 3));
+        assert!(!graph.implies(3, 1));
+        assert_eq!(graph.failed_literals(), vec![5]);
+
+        let mut cycle = ImplicationGraph::new(&[vec![-1, 2], vec![-2, 1]]);
+        assert_eq!(cycle.implied_transitively(1), vec![2, 1]);
+        assert_eq!(cycle.equivalences(), Some(vec![0, 1, 1]));
+        cycle.add_binary(-1, -2);
+        assert_eq!(cycle.failed_literals(), vec![1, 2]);
+        assert_eq!(cycle.equivalences(), Some(vec![0, 1, 1]));
+        cycle.add_binary(1, 2);
+        assert_eq!(cycle.equivalences(), None);
+
+        // 3 implies -4 implies 2 implies 3, 1 occurs in no clause
+        let graph = ImplicationGraph::new(&[vec![-3, -4], vec![4, 2], vec![-2, 3]]);
+        assert_eq!(graph.equivalences(), Some(vec![0, 1, 2, 2, -2]));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::preprocess::{self, ProbeConfig, Reconstruction, VivifyConfig};
+use crate::proof::Proof;
+use crate::Formula;
+
+// The simplifications an inprocessing round can run, in the order it runs
+// them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Technique {
+    // Subsumption and self-subsuming resolution
+    Subsumption,
+    Probing,
+    Vivification,
+    // Bounded variable elimination
+    Elimination,
+}
+
+const TECHNIQUES: [Technique; 4] = [
+    Technique::Subsumption,
+    Technique::Probing,
+    Technique::Vivification,
+    Technique::Elimination,
+];
+
+// How often inprocessing runs and how much work each technique may do
+#[derive(Clone, Copy, Debug)]
+pub struct InprocessConfig {
+    // Restarts between rounds
+    pub interval: usize,
+    pub probe: ProbeConfig,
+    pub vivify: VivifyConfig,
+    // Largest product of the occurrence counts of an eliminated variable
+    pub max_product: usize,
+    // Most rounds a technique that keeps changing nothing is put off for
+    pub max_delay: usize,
+}
+
+impl Default for InprocessConfig {
+    fn default() -> Self {
+        InprocessConfig {
+            interval: 8,
+            probe: ProbeConfig {
+                max_probes: 100,
+                max_effort: 1_000_000,
+            },
+            vivify: VivifyConfig {
+                max_clauses: 200,
+                max_effort: 1_000_000,
+            },
+            max_product: 16,
+            max_delay: 16,
+        }
+    }
+}
+
+// When a technique runs next
+#[derive(Clone, Copy, Debug, Default)]
+struct Schedule {
+    // Rounds left to skip
+    wait: usize,
+    // Rounds skipped after the latest run, doubling while runs change nothing
+    delay: usize,
+}
+
+// Interleaves bounded rounds of simplification of the root formula with the
+// search, one every interval restarts. A technique whose run changes nothing
+// sits out twice as many rounds as before, up to max_delay, one that does
+// change something runs in every round again.
+#[derive(Clone, Debug)]
+pub struct Inprocessor {
+    pub config: InprocessConfig,
+    restarts: usize,
+    schedules: [Schedule; 4],
+}
+
+impl Inprocessor {
+    pub fn new(config: InprocessConfig) -> Self {
+        Inprocessor {
+            config,
+            restarts: 0,
+            schedules: [Schedule::default(); 4],
+        }
+    }
+
+    // Count a restart, true if a round is due
+    pub fn on_restart(&mut self) -> bool {
+        self.restarts += 1;
+        self.restarts.is_multiple_of(self.config.interval.max(1))
+    }
+
+    // The techniques due this round
+    fn due(&mut self) -> Vec<Technique> {
+        let mut due = Vec::new();
+        for (&technique, schedule) in TECHNIQUES.iter().zip(self.schedules.iter_mut()) {
+            if schedule.wait == 0 {
+                due.push(technique);
+            } else {
+                schedule.wait -= 1;
+            }
+        }
+        due
+    }
+
+    fn ran(&mut self, technique: Technique, changed: usize) {
+        let schedule = &mut self.schedules[TECHNIQUES.iter().position(|&other| other == technique).unwrap()];
+        schedule.delay = if changed > 0 { 0 } else { (2 * schedule.delay).clamp(1, self.config.max_delay) };
+        schedule.wait = schedule.delay;
+    }
+
+    // Run the techniques that are due on the formula, variables keep returns
+    // true for are not eliminated. Clauses eliminated variables took part in
+    // go onto the reconstruction stack, derived clauses to the proof, if
+    // any. Returns the techniques run with the changes each made, a formula
+    // found unsatisfiable ends up with an empty clause.
+    pub fn round(
+        &mut self,
+        formula: &mut Formula,
+        reconstruction: &mut Reconstruction,
+        keep: impl Fn(i32) -> bool,
+        mut proof: Option<&mut Proof>,
+    ) -> Vec<(Technique, usize)> {
+        let mut changes = Vec::new();
+        for technique in self.due() {
+            if formula.iter().any(Vec::is_empty) {
+                break;
+            }
+            let proof = proof.as_deref_mut();
+            let changed = match technique {
+                Technique::Subsumption => preprocess::subsume(formula) + preprocess::self_subsume(formula, proof),
+                Technique::Probing => preprocess::probe_failed_literals(formula, &self.config.probe, proof),
+                Technique::Vivification => preprocess::vivify(formula, &self.config.vivify, proof),
+                Technique::Elimination => {
+                    preprocess::eliminate_variables(formula, reconstruction, self.config.max_product, &keep, proof)
+                }
+            };
+            log::debug!("inprocessing: {:?} changed {}", technique, changed);
+            self.ran(technique, changed);
+            changes.push((technique, changed));
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_model, Assignment};
+
+    #[test]
+    fn test_schedule() {
+        let mut inprocessor = Inprocessor::new(InprocessConfig {
+            interval: 2,
+            max_delay: 2,
+            ..Default::default()
+        });
+        assert_eq!((inprocessor.on_restart(), inprocessor.on_restart()), (false, true));
+
+        // [1, 2] subsumes [1, 2, 3] and eliminating 3 leaves 1 and 2
+        let mut formula = vec![vec![1, 2], vec![1, 2, 3], vec![-3, 2], vec![3, -2]];
+        let mut reconstruction = Reconstruction::default();
+        let changes = inprocessor.round(&mut formula, &mut reconstruction, |_| false, None);
+        let techniques: Vec<Technique> = changes.iter().map(|&(technique, _)| technique).collect();
+        assert_eq!(techniques, TECHNIQUES);
+        assert!(changes[0].1 > 0 && changes[3].1 > 0);
+        let mut model: Assignment = [(1, Some(true)), (2, Some(true)), (3, Some(false))].into_iter().collect();
+        reconstruction.extend_model(&mut model);
+        let model = model.iter().map(|(var, val)| (var, val == Some(true))).collect();
+        assert_eq!(verify_model(&[vec![1, 2], vec![1, 2, 3], vec![-3, 2], vec![3, -2]], &model), Ok(()));
+
+        // Techniques that change nothing sit out one round, then two at most
+        let mut inprocessor = Inprocessor::new(InprocessConfig {
+            max_delay: 2,
+            ..Default::default()
+        });
+        let mut formula = vec![vec![1, 2], vec![-1, -2]];
+        let rounds: Vec<usize> = (0..8)
+            .map(|_| inprocessor.round(&mut formula, &mut Reconstruction::default(), |_| true, None).len())
+            .collect();
+        assert_eq!(rounds, vec![4, 0, 4, 0, 0, 4, 0, 0]);
+        assert_eq!(formula, vec![vec![1, 2], vec![-2, -1]]);
+    }
+
+    #[test]
+    fn test_unsatisfiable_round() {
+        let mut inprocessor = Inprocessor::new(InprocessConfig::default());
+        let mut formula = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]];
+        inprocessor.round(&mut formula, &mut Reconstruction::default(), |_| false, None);
+        assert!(formula.iter().any(Vec::is_empty));
+    }
+}
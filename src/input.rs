@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+
+use crate::{parse_gcnf, parse_wcnf, read_cnf, Formula, GroupedFormula, Wcnf, WcnfError};
+
+// Wrap a reader in a decoder if its first bytes are the magic number of gzip,
+// xz or bzip2 data
+pub fn decompress<'a>(reader: impl BufRead + 'a) -> io::Result<Box<dyn BufRead + 'a>> {
+    let mut reader = reader;
+    let magic = reader.fill_buf()?;
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+    } else if magic.starts_with(b"BZh") {
+        Box::new(BufReader::new(MultiBzDecoder::new(reader)))
+    } else {
+        Box::new(reader)
+    })
+}
+
+// Open a file, or standard input if the path is "-", decompressing either on
+// the fly
+pub fn open_input(path: &str) -> Box<dyn BufRead> {
+    let reader: Box<dyn BufRead> = if path == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        Box::new(BufReader::new(File::open(path).expect("Failed to open file")))
+    };
+    decompress(reader).expect("Failed to read input")
+}
+
+// Read a DIMACS CNF file, or standard input if the path is "-". Compressed
+// input is recognized by its magic number.
+pub fn read_cnf_file(path: &str) -> Formula {
+    read_cnf(open_input(path))
+}
+
+// Read a MaxSAT instance in WCNF, or in DIMACS CNF with every clause soft,
+// from standard input if the path is "-"
+pub fn read_wcnf_file(path: &str) -> Result<Wcnf, WcnfError> {
+    let text = io::read_to_string(open_input(path)).expect("Failed to read input");
+    parse_wcnf(&text)
+}
+
+// Read a group-CNF file, or DIMACS CNF with every clause a group, from
+// standard input if the path is "-"
+pub fn read_gcnf_file(path: &str) -> GroupedFormula {
+    let text = io::read_to_string(open_input(path)).expect("Failed to read input");
+    parse_gcnf(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decompress() {
+        use std::io::Write;
+
+        let text = b"p cnf 2 2\n1 2 0\n-1 0\n";
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(text).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(text).unwrap();
+        let mut bz = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        bz.write_all(text).unwrap();
+        let inputs = [gz.finish().unwrap(), xz.finish().unwrap(), bz.finish().unwrap(), text.to_vec()];
+        for input in inputs.iter() {
+            let formula = read_cnf(decompress(&input[..]).unwrap());
+            assert_eq!(formula, vec![vec![1, 2], vec![-1]]);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::proof::SharedBuffer;
+use crate::{Formula, Model, Proof, ProofFormat, Solver};
+
+// A gate of an interpolant circuit, its inputs are earlier gates
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Gate {
+    Const(bool),
+    Lit(i32),
+    And(usize, usize),
+    Or(usize, usize),
+}
+
+// A Boolean circuit whose output is its last gate. Gates are shared, so
+// the circuit can be exponentially smaller than the formula it spells out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interpolant {
+    pub gates: Vec<Gate>,
+    // Position of every gate, to share equal ones
+    positions: HashMap<Gate, usize>,
+}
+
+impl Interpolant {
+    fn gate(&mut self, gate: Gate) -> usize {
+        let gate = match gate {
+            Gate::And(a, b) | Gate::Or(a, b) if a == b => return a,
+            Gate::And(a, b) => match (self.gates[a], self.gates[b]) {
+                (Gate::Const(false), _) | (_, Gate::Const(true)) => return a,
+                (_, Gate::Const(false)) | (Gate::Const(true), _) => return b,
+                _ => Gate::And(a.min(b), a.max(b)),
+            },
+            Gate::Or(a, b) => match (self.gates[a], self.gates[b]) {
+                (Gate::Const(true), _) | (_, Gate::Const(false)) => return a,
+                (_, Gate::Const(true)) | (Gate::Const(false), _) => return b,
+                _ => Gate::Or(a.min(b), a.max(b)),
+            },
+            gate => gate,
+        };
+        if let Some(&position) = self.positions.get(&gate) {
+            return position;
+        }
+        self.gates.push(gate);
+        self.positions.insert(gate, self.gates.len() - 1);
+        self.gates.len() - 1
+    }
+
+    // The disjunction of the literals
+    fn clause(&mut self, lits: impl Iterator<Item = i32>) -> usize {
+        let mut output = self.gate(Gate::Const(false));
+        for lit in lits {
+            let lit = self.gate(Gate::Lit(lit));
+            output = self.gate(Gate::Or(output, lit));
+        }
+        output
+    }
+
+    // Make the gate the output
+    fn output(mut self, output: usize) -> Self {
+        let gate = self.gates[output];
+        self.gates.push(gate);
+        self
+    }
+
+    // The gates the output depends on
+    fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.gates.len()];
+        if let Some(last) = reachable.last_mut() {
+            *last = true;
+        }
+        for i in (0..self.gates.len()).rev() {
+            if let (true, Gate::And(a, b) | Gate::Or(a, b)) = (reachable[i], self.gates[i]) {
+                reachable[a] = true;
+                reachable[b] = true;
+            }
+        }
+        reachable
+    }
+
+    // The value of the output, variables the model leaves out count as false
+    pub fn eval(&self, model: &Model) -> bool {
+        let mut values: Vec<bool> = Vec::with_capacity(self.gates.len());
+        for gate in self.gates.iter() {
+            let value = match *gate {
+                Gate::Const(value) => value,
+                Gate::Lit(lit) => model.get(&lit.abs()) == Some(&(lit > 0)),
+                Gate::And(a, b) => values[a] && values[b],
+                Gate::Or(a, b) => values[a] || values[b],
+            };
+            values.push(value);
+        }
+        values.last().copied().unwrap_or(false)
+    }
+
+    // The variables the output depends on
+    pub fn vars(&self) -> Vec<i32> {
+        let mut vars: Vec<i32> = self
+            .gates
+            .iter()
+            .zip(self.reachable())
+            .filter_map(|(gate, reachable)| match gate {
+                Gate::Lit(lit) if reachable => Some(lit.abs()),
+                _ => None,
+            })
+            .collect();
+        vars.sort_unstable();
+        vars.dedup();
+        vars
+    }
+
+    // Tseitin clauses of the gates the output depends on, with gate
+    // variables numbered on from next_var, and the literal equivalent to
+    // the output under them
+    pub fn to_cnf(&self, next_var: &mut i32) -> (Formula, i32) {
+        let mut formula = Vec::new();
+        let mut lits: Vec<i32> = Vec::with_capacity(self.gates.len());
+        for (gate, reachable) in self.gates.iter().zip(self.reachable()) {
+            if !reachable {
+                lits.push(0);
+                continue;
+            }
+            let lit = match *gate {
+                Gate::Lit(lit) => lit,
+                gate => {
+                    *next_var += 1;
+                    let g = *next_var;
+                    match gate {
+                        Gate::Const(value) => formula.push(vec![if value { g } else { -g }]),
+                        Gate::And(a, b) => {
+                            formula.extend([vec![-g, lits[a]], vec![-g, lits[b]], vec![g, -lits[a], -lits[b]]]);
+                        }
+                        Gate::Or(a, b) => {
+                            formula.extend([vec![g, -lits[a]], vec![g, -lits[b]], vec![-g, lits[a], lits[b]]]);
+                        }
+                        Gate::Lit(_) => unreachable!(),
+                    }
+                    g
+                }
+            };
+            lits.push(lit);
+        }
+        (formula, lits.last().copied().unwrap_or(0))
+    }
+}
+
+// A Craig interpolant of two sets of clauses that are unsatisfiable
+// together: a circuit over the variables both of them mention that a
+// implies and that is unsatisfiable with b. None if a and b have a common
+// model.
+//
+// The search writes an LRAT refutation, and each lemma is replayed as the
+// chain of resolutions its hints make up: the last hint is the clause
+// falsified, every hint before it became unit on a literal whose negation
+// the clause so far is resolved on. The partial interpolants follow
+// McMillan's system. A clause of a is the disjunction of its literals over
+// variables of b, one of b is true, and a resolvent is the disjunction of
+// the partial interpolants of its antecedents if the pivot only occurs in
+// a, their conjunction otherwise.
+pub fn interpolant(a: &[Vec<i32>], b: &[Vec<i32>]) -> Option<Interpolant> {
+    let mut interpolant = Interpolant::default();
+    if a.iter().any(Vec::is_empty) {
+        let output = interpolant.gate(Gate::Const(false));
+        return Some(interpolant.output(output));
+    }
+    if b.iter().any(Vec::is_empty) {
+        let output = interpolant.gate(Gate::Const(true));
+        return Some(interpolant.output(output));
+    }
+    let formula: Formula = a.iter().chain(b.iter()).cloned().collect();
+    let buffer = SharedBuffer::default();
+    let mut solver = Solver::from_clauses(&formula);
+    solver.state.proof = Some(Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), &formula));
+    if solver.solve() {
+        return None;
+    }
+    let in_b: HashSet<i32> = b.iter().flatten().map(|lit| lit.abs()).collect();
+    // The live clauses by id with their partial interpolants, the proof
+    // numbers the clauses of a and then those of b from 1
+    let mut clauses: HashMap<usize, (Vec<i32>, usize)> = HashMap::new();
+    for (i, clause) in formula.iter().enumerate() {
+        let partial = if i < a.len() {
+            interpolant.clause(clause.iter().copied().filter(|lit| in_b.contains(&lit.abs())))
+        } else {
+            interpolant.gate(Gate::Const(true))
+        };
+        clauses.insert(i + 1, (clause.clone(), partial));
+    }
+    for line in buffer.text().lines() {
+        let numbers: Vec<&str> = line.split_whitespace().collect();
+        if numbers.get(1) == Some(&"d") {
+            for id in numbers[2..].iter().map(|id| id.parse::<usize>().unwrap()) {
+                clauses.remove(&id);
+            }
+            continue;
+        }
+        let numbers: Vec<i64> = numbers.iter().map(|number| number.parse().unwrap()).collect();
+        let end = numbers[1..].iter().position(|&number| number == 0).unwrap() + 1;
+        let lemma: Vec<i32> = numbers[1..end].iter().map(|&lit| lit as i32).collect();
+        let hints: Vec<usize> = numbers[end + 1..numbers.len() - 1].iter().map(|&id| id as usize).collect();
+
+        // The literal each hint before the last becomes unit on
+        let mut values: HashMap<i32, bool> = lemma.iter().map(|&lit| (lit.abs(), lit < 0)).collect();
+        let mut units = Vec::new();
+        for id in hints[..hints.len() - 1].iter() {
+            let open = clauses[id].0.iter().copied().find(|lit| !values.contains_key(&lit.abs()));
+            let unit = open.expect("LRAT hints propagate");
+            values.insert(unit.abs(), unit > 0);
+            units.push(unit);
+        }
+        let (last, partial) = &clauses[hints.last().unwrap()];
+        let mut resolvent: HashSet<i32> = last.iter().copied().collect();
+        let mut partial = *partial;
+        for (id, &unit) in hints.iter().zip(units.iter()).rev() {
+            if !resolvent.remove(&-unit) {
+                continue;
+            }
+            let (clause, other) = &clauses[id];
+            resolvent.extend(clause.iter().copied().filter(|&lit| lit != unit));
+            partial = interpolant.gate(if in_b.contains(&unit.abs()) {
+                Gate::And(partial, *other)
+            } else {
+                Gate::Or(partial, *other)
+            });
+        }
+        if lemma.is_empty() {
+            return Some(interpolant.output(partial));
+        }
+        clauses.insert(numbers[0] as usize, (lemma, partial));
+    }
+    panic!("The refutation ends without the empty clause");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pigeonhole, random_ksat};
+
+    fn models(formula: &[Vec<i32>], num_vars: i32) -> Vec<Model> {
+        (0..1u32 << num_vars)
+            .map(|bits| (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect::<Model>())
+            .filter(|model| formula.iter().all(|clause| clause.iter().any(|&lit| model[&lit.abs()] == (lit > 0))))
+            .collect()
+    }
+
+    fn check(a: &[Vec<i32>], b: &[Vec<i32>], num_vars: i32) {
+        let interpolant = interpolant(a, b).expect("Unsatisfiable");
+        assert!(models(a, num_vars).iter().all(|model| interpolant.eval(model)), "{:?} {:?}", a, b);
+        assert!(models(b, num_vars).iter().all(|model| !interpolant.eval(model)), "{:?} {:?}", a, b);
+        let in_a: HashSet<i32> = a.iter().flatten().map(|lit| lit.abs()).collect();
+        let in_b: HashSet<i32> = b.iter().flatten().map(|lit| lit.abs()).collect();
+        assert!(interpolant.vars().iter().all(|var| in_a.contains(var) && in_b.contains(var)));
+
+        // In clauses: b with the interpolant is unsatisfiable, and so is a
+        // with its negation
+        let mut next_var = num_vars;
+        let (clauses, output) = interpolant.to_cnf(&mut next_var);
+        let mut with_b = Solver::from_clauses(&clauses);
+        b.iter().for_each(|clause| with_b.add_clause(clause));
+        assert!(!with_b.solve_with_assumptions(&[output]));
+        let mut with_a = Solver::from_clauses(&clauses);
+        a.iter().for_each(|clause| with_a.add_clause(clause));
+        assert!(!with_a.solve_with_assumptions(&[-output]));
+    }
+
+    #[test]
+    fn test_interpolant() {
+        // a says 1 and 1 implies 2, b says 2 implies 3 and not 3, 2 is the
+        // only shared variable
+        let a = vec![vec![1], vec![-1, 2]];
+        let b = vec![vec![-2, 3], vec![-3]];
+        check(&a, &b, 3);
+        assert_eq!(interpolant(&a, &b).unwrap().vars(), vec![2]);
+        assert_eq!(interpolant(&a, &[vec![3]]), None);
+
+        // Either side alone may be unsatisfiable
+        check(&pigeonhole(2), &[vec![1, 7]], 7);
+        check(&[vec![1, 7]], &pigeonhole(2), 7);
+        check(&[vec![]], &[vec![1]], 1);
+        check(&[vec![1]], &[vec![]], 1);
+
+        // Random 3-SAT split in two where the whole is unsatisfiable
+        let mut checked = 0;
+        for seed in 0..40 {
+            let formula = random_ksat(10, 60, 3, seed);
+            let (a, b) = formula.split_at(30);
+            if interpolant(a, b).is_some() {
+                check(a, b, 10);
+                checked += 1;
+            }
+        }
+        assert!(checked > 10);
+    }
+}
@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// A flag another thread or a signal handler can set to stop a running search
+// at its next node, which then ends with Unknown. Clones share the flag.
+#[derive(Clone, Debug, Default)]
+pub struct Interrupter {
+    flag: Arc<AtomicBool>,
+}
+
+impl Interrupter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    // Let the next search run again, the flag stays set until cleared
+    pub fn clear(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+}
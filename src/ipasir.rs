@@ -0,0 +1,204 @@
+use std::ffi::{c_char, c_int, c_void};
+
+use crate::{Lit, SolveResult, Solver};
+
+// The IPASIR interface of incremental SAT solvers, so C and C++ programs
+// written against ipasir.h can link the solver in place of another. The
+// handle the functions pass around is a Box of this.
+pub struct IpasirSolver {
+    solver: Solver,
+    // The literals of the clause ipasir_add is adding
+    clause: Vec<Lit>,
+    // Assumptions for the next ipasir_solve
+    assumptions: Vec<Lit>,
+}
+
+// The user data pointer of a callback. IPASIR leaves it to the caller to
+// keep it valid while the solver may call back with it.
+struct Data(*mut c_void);
+
+unsafe impl Send for Data {}
+
+#[no_mangle]
+pub extern "C" fn ipasir_signature() -> *const c_char {
+    concat!("dpll-", env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+#[no_mangle]
+pub extern "C" fn ipasir_init() -> Box<IpasirSolver> {
+    Box::new(IpasirSolver {
+        solver: Solver::new(),
+        clause: Vec::new(),
+        assumptions: Vec::new(),
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ipasir_release(solver: Box<IpasirSolver>) {
+    drop(solver);
+}
+
+// Add a literal to the clause being built, 0 ends the clause
+#[no_mangle]
+pub extern "C" fn ipasir_add(solver: &mut IpasirSolver, lit_or_zero: c_int) {
+    if lit_or_zero == 0 {
+        let clause = std::mem::take(&mut solver.clause);
+        solver.solver.add_clause(&clause);
+    } else {
+        solver.clause.push(lit_or_zero);
+    }
+}
+
+// Assume the literal for the next ipasir_solve only
+#[no_mangle]
+pub extern "C" fn ipasir_assume(solver: &mut IpasirSolver, lit: c_int) {
+    solver.assumptions.push(lit);
+}
+
+// 10 if satisfiable, 20 if unsatisfiable and 0 if the search was terminated
+#[no_mangle]
+pub extern "C" fn ipasir_solve(solver: &mut IpasirSolver) -> c_int {
+    let assumptions = std::mem::take(&mut solver.assumptions);
+    if let Some(interrupter) = solver.solver.state.interrupter.as_ref() {
+        interrupter.clear();
+    }
+    match solver.solver.search(&assumptions) {
+        SolveResult::Sat(_) => 10,
+        SolveResult::Unsat => 20,
+        SolveResult::Unknown => 0,
+    }
+}
+
+// After a satisfiable answer, lit if it is true in the model, -lit if it is
+// false and 0 if its variable takes no part in the formula
+#[no_mangle]
+pub extern "C" fn ipasir_val(solver: &IpasirSolver, lit: c_int) -> c_int {
+    match solver.solver.model().and_then(|model| model.get(&lit.abs())) {
+        Some(&val) if val == (lit > 0) => lit,
+        Some(_) => -lit,
+        None => 0,
+    }
+}
+
+// After an unsatisfiable answer, 1 if the assumption lit took part in it
+#[no_mangle]
+pub extern "C" fn ipasir_failed(solver: &IpasirSolver, lit: c_int) -> c_int {
+    c_int::from(solver.solver.failed(lit))
+}
+
+// Stop the search as soon as terminate returns nonzero, it is polled at
+// every node. A null callback polls nothing.
+#[no_mangle]
+pub extern "C" fn ipasir_set_terminate(
+    solver: &mut IpasirSolver,
+    data: *mut c_void,
+    terminate: Option<extern "C" fn(*mut c_void) -> c_int>,
+) {
+    let Some(terminate) = terminate else {
+        solver.solver.state.progress = None;
+        return;
+    };
+    let interrupter = solver.solver.interrupter();
+    let data = Data(data);
+    solver.solver.set_progress_callback(1, move |_| {
+        let data = &data;
+        if terminate(data.0) != 0 {
+            interrupter.interrupt();
+        }
+    });
+}
+
+// Call back with every learned clause of at most max_length literals, as a
+// zero-terminated array that is only valid during the call. This turns
+// clause learning on. A null callback learns on without reporting.
+#[no_mangle]
+pub extern "C" fn ipasir_set_learn(
+    solver: &mut IpasirSolver,
+    data: *mut c_void,
+    max_length: c_int,
+    learn: Option<extern "C" fn(*mut c_void, *const c_int)>,
+) {
+    let Some(learn) = learn else {
+        solver.solver.state.on_learn = None;
+        return;
+    };
+    let data = Data(data);
+    let mut buffer = Vec::new();
+    solver.solver.set_learn_callback(move |clause| {
+        let data = &data;
+        if clause.len() <= max_length.max(0) as usize {
+            buffer.clear();
+            buffer.extend_from_slice(clause);
+            buffer.push(0);
+            learn(data.0, buffer.as_ptr());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pigeonhole;
+    use std::ffi::CStr;
+
+    fn add_formula(solver: &mut IpasirSolver, formula: &[Vec<i32>]) {
+        for clause in formula.iter() {
+            for &lit in clause.iter() {
+                ipasir_add(solver, lit);
+            }
+            ipasir_add(solver, 0);
+        }
+    }
+
+    #[test]
+    fn test_incremental() {
+        let signature = unsafe { CStr::from_ptr(ipasir_signature()) };
+        assert!(signature.to_str().unwrap().starts_with("dpll-"));
+        let mut solver = ipasir_init();
+        add_formula(&mut solver, &[vec![1, 2], vec![-1, 2]]);
+        ipasir_assume(&mut solver, -2);
+        assert_eq!(ipasir_solve(&mut solver), 20);
+        assert_eq!(ipasir_failed(&solver, -2), 1);
+        // Assumptions only hold for one call
+        assert_eq!(ipasir_solve(&mut solver), 10);
+        assert_eq!(ipasir_val(&solver, 2), 2);
+        assert_eq!(ipasir_val(&solver, -2), 2);
+        assert_eq!(ipasir_val(&solver, 7), 0);
+        add_formula(&mut solver, &[vec![-2, 3], vec![-3, -1]]);
+        assert_eq!(ipasir_solve(&mut solver), 10);
+        assert_eq!((ipasir_val(&solver, 1), ipasir_val(&solver, 3)), (-1, 3));
+        ipasir_release(solver);
+    }
+
+    extern "C" fn stop(_: *mut c_void) -> c_int {
+        1
+    }
+
+    extern "C" fn collect(data: *mut c_void, clause: *const c_int) {
+        let clauses = unsafe { &mut *data.cast::<Vec<Vec<i32>>>() };
+        let mut lits = Vec::new();
+        for i in 0.. {
+            match unsafe { *clause.add(i) } {
+                0 => break,
+                lit => lits.push(lit),
+            }
+        }
+        clauses.push(lits);
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let mut solver = ipasir_init();
+        add_formula(&mut solver, &pigeonhole(4));
+        ipasir_set_terminate(&mut solver, std::ptr::null_mut(), Some(stop));
+        assert_eq!(ipasir_solve(&mut solver), 0);
+        ipasir_set_terminate(&mut solver, std::ptr::null_mut(), None);
+
+        let mut learned: Vec<Vec<i32>> = Vec::new();
+        ipasir_set_learn(&mut solver, (&mut learned as *mut Vec<Vec<i32>>).cast(), 3, Some(collect));
+        assert_eq!(ipasir_solve(&mut solver), 20);
+        ipasir_release(solver);
+        assert!(!learned.is_empty());
+        assert!(learned.iter().all(|clause| clause.len() <= 3));
+    }
+}
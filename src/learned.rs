@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use crate::proof::Antecedents;
+use crate::{Assignment, ClauseDb, ClauseId, Occurrences};
+
+// Number of distinct decision levels among the literals of a clause
+pub fn lbd(clause: &[i32], level: impl Fn(i32) -> usize) -> usize {
+    let mut levels: Vec<usize> = clause.iter().map(|&lit| level(lit)).collect();
+    levels.sort_unstable();
+    levels.dedup();
+    levels.len()
+}
+
+// Thresholds for reducing the learned clause database
+#[derive(Clone, Copy, Debug)]
+pub struct ReductionConfig {
+    // Clauses with at most this LBD are core clauses and are always kept
+    pub core_lbd: usize,
+    // Clauses with at most this LBD otherwise are tier-2 clauses, kept as
+    // long as propagation uses them between reductions
+    pub tier2_lbd: usize,
+    // Conflicts before the first reduction
+    pub first_reduce: usize,
+    // Added to the number of conflicts between reductions after each one
+    pub reduce_increment: usize,
+    // Fraction of the local clauses kept by a reduction, the ones used since
+    // the last reduction first, then lowest LBD
+    pub keep_fraction: f64,
+}
+
+impl Default for ReductionConfig {
+    fn default() -> Self {
+        ReductionConfig {
+            core_lbd: 2,
+            tier2_lbd: 6,
+            first_reduce: 2000,
+            reduce_increment: 300,
+            keep_fraction: 0.5,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LearnedClause {
+    pub literals: Vec<i32>,
+    pub lbd: usize,
+}
+
+// Where a learned clause is kept, which decides how long it stays
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tier {
+    Core,
+    Tier2,
+    // The rest, and tier-2 clauses left unused for a reduction interval
+    Local,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    clause: LearnedClause,
+    tier: Tier,
+    // Whether propagation used the clause since the last reduction, new
+    // clauses count as used
+    used: bool,
+}
+
+// Clauses learned from conflicts. They are implied by the formula, so they can
+// be propagated in every node and survive restarts.
+#[derive(Clone, Debug)]
+pub struct LearnedClauses {
+    pub config: ReductionConfig,
+    clauses: Vec<Entry>,
+    // Position in clauses of the latest clause each literal occurs in
+    latest: HashMap<i32, usize>,
+    interval: usize,
+    conflicts: usize,
+    pub reductions: usize,
+    // Clause visits minimizing one learned clause may take, 0 turns
+    // minimization off
+    pub minimize_effort: usize,
+}
+
+impl LearnedClauses {
+    pub fn new(config: ReductionConfig) -> Self {
+        LearnedClauses {
+            config,
+            clauses: Vec::new(),
+            latest: HashMap::new(),
+            interval: config.first_reduce,
+            conflicts: 0,
+            reductions: 0,
+            minimize_effort: 10_000,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LearnedClause> {
+        self.clauses.iter().map(|entry| &entry.clause)
+    }
+
+    // Number of clauses kept in the tier
+    pub fn tier_len(&self, tier: Tier) -> usize {
+        self.clauses.iter().filter(|entry| entry.tier == tier).count()
+    }
+
+    // Store the clause learned from a conflict and reduce the database when due
+    pub fn learn(&mut self, literals: Vec<i32>, lbd: usize) {
+        self.push(LearnedClause { literals, lbd });
+        self.conflicts += 1;
+        if self.conflicts >= self.interval {
+            self.reduce();
+            self.conflicts = 0;
+            self.interval += self.config.reduce_increment;
+        }
+    }
+
+    // Store a clause some other solver learned, it counts as no conflict here
+    pub fn import(&mut self, clause: LearnedClause) {
+        self.push(clause);
+    }
+
+    fn push(&mut self, clause: LearnedClause) {
+        let tier = match clause.lbd {
+            lbd if lbd <= self.config.core_lbd => Tier::Core,
+            lbd if lbd <= self.config.tier2_lbd => Tier::Tier2,
+            _ => Tier::Local,
+        };
+        self.insert(Entry { clause, tier, used: true });
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        for &lit in entry.clause.literals.iter() {
+            self.latest.insert(lit, self.clauses.len());
+        }
+        self.clauses.push(entry);
+    }
+
+    // Keep the core clauses and the tier-2 clauses used since the last
+    // reduction. The unused tier-2 clauses move to the local tier, whose
+    // clauses are kept up to keep_fraction of them.
+    pub fn reduce(&mut self) {
+        let (mut kept, mut local) = (Vec::new(), Vec::new());
+        for mut entry in self.clauses.drain(..) {
+            match entry.tier {
+                Tier::Core => kept.push(entry),
+                Tier::Tier2 if entry.used => kept.push(entry),
+                Tier::Tier2 | Tier::Local => {
+                    entry.tier = Tier::Local;
+                    local.push(entry);
+                }
+            }
+        }
+        local.sort_by_key(|entry| (!entry.used, entry.clause.lbd, entry.clause.literals.len()));
+        local.truncate((local.len() as f64 * self.config.keep_fraction) as usize);
+        self.latest.clear();
+        for mut entry in kept.into_iter().chain(local) {
+            entry.used = false;
+            self.insert(entry);
+        }
+        self.reductions += 1;
+    }
+
+    // Forget the clauses f returns false for
+    pub fn retain(&mut self, mut f: impl FnMut(&LearnedClause) -> bool) {
+        let clauses = std::mem::take(&mut self.clauses);
+        self.latest.clear();
+        for entry in clauses.into_iter().filter(|entry| f(&entry.clause)) {
+            self.insert(entry);
+        }
+    }
+
+    // Drop the deepest literals of a clause negating decisions in order for
+    // as long as the latest learned clause with the decision forces it from
+    // the ones kept, which resolves the two. A decision that flipped a
+    // branch the latest conflict refuted goes this way. Returns the learned
+    // clauses that forced the dropped literals.
+    pub(crate) fn strengthen(&self, clause: &mut Vec<i32>) -> Vec<Vec<i32>> {
+        let mut reasons = Vec::new();
+        while let Some((&last, others)) = clause.split_last() {
+            let reason = self.latest.get(&-last).map(|&i| &self.clauses[i].clause.literals).filter(|reason| {
+                reason.len() <= clause.len() && reason.iter().all(|&lit| lit == -last || others.contains(&lit))
+            });
+            let Some(reason) = reason else {
+                break;
+            };
+            reasons.push(reason.clone());
+            clause.pop();
+        }
+        reasons
+    }
+
+    // Assign the literals forced by learned clauses until nothing changes,
+    // marking the clauses that force one or are falsified as used. Returns
+    // the number of new assignments, or None if a clause is falsified.
+    pub fn propagate(&mut self, assignment: &mut Assignment) -> Option<usize> {
+        self.propagate_recording(assignment, None)
+    }
+
+    // propagate, recording the clause that forces each literal and the one
+    // that is falsified
+    pub(crate) fn propagate_recording(
+        &mut self,
+        assignment: &mut Assignment,
+        mut antecedents: Option<&mut Antecedents>,
+    ) -> Option<usize> {
+        let mut assigned = 0;
+        loop {
+            let mut changed = false;
+            for entry in self.clauses.iter_mut() {
+                let clause = &entry.clause;
+                let mut open = None;
+                let mut open_count = 0;
+                let mut satisfied = false;
+                for &lit in clause.literals.iter() {
+                    match assignment.get(&lit.abs()) {
+                        Some(&Some(val)) if val == (lit > 0) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(&Some(_)) => {}
+                        _ => {
+                            open = Some(lit);
+                            open_count += 1;
+                        }
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match (open_count, open) {
+                    (0, _) => {
+                        entry.used = true;
+                        if let Some(antecedents) = antecedents {
+                            antecedents.record_conflict(&clause.literals);
+                        }
+                        return None;
+                    }
+                    (1, Some(lit)) => {
+                        entry.used = true;
+                        if let Some(antecedents) = antecedents.as_deref_mut() {
+                            antecedents.record(lit, &clause.literals);
+                        }
+                        assignment.insert(lit.abs(), Some(lit > 0));
+                        assigned += 1;
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                return Some(assigned);
+            }
+        }
+    }
+}
+
+// Deepest chain of reasons minimization follows back from a decision
+const MAX_REASON_DEPTH: usize = 2;
+
+// Looks for reasons in the formula that force a literal from kept decisions
+struct Reasons<'a> {
+    db: &'a ClauseDb,
+    occurrences: &'a Occurrences,
+    assignment: &'a Assignment,
+    kept: &'a [i32],
+    // The decision being tested, it cannot force itself
+    excluded: i32,
+    // Whether each literal looked at so far is forced, false while it is
+    // being looked at so cycles count as not forced. Chains are short, a
+    // list is quicker to search than a map is to fill.
+    forced: Vec<(i32, bool)>,
+    // The clauses that forced a literal
+    used: Vec<ClauseId>,
+    effort: usize,
+    max_effort: usize,
+}
+
+impl Reasons<'_> {
+    fn is_forced(&mut self, lit: i32, depth: usize) -> bool {
+        if lit != self.excluded && self.kept.contains(&-lit) {
+            return true;
+        }
+        if let Some(&(_, forced)) = self.forced.iter().find(|&&(other, _)| other == lit) {
+            return forced;
+        }
+        if depth == 0 || self.effort >= self.max_effort {
+            return false;
+        }
+        let slot = self.forced.len();
+        self.forced.push((lit, false));
+        let (db, assignment) = (self.db, self.assignment);
+        let reason = self.occurrences.clauses_of(lit).iter().find(|&&id| {
+            let reason = db.clause(id);
+            self.effort += 1;
+            let others = || reason.iter().copied().filter(|&other| other != lit);
+            others().all(|other| assignment.get(&other.abs()) == Some(&Some(other < 0)))
+                && others().all(|other| self.is_forced(-other, depth - 1))
+        });
+        let forced = reason.is_some();
+        self.used.extend(reason);
+        self.forced[slot].1 = forced;
+        forced
+    }
+}
+
+// Learned clause minimization. A learned clause negates the decisions of a
+// conflict, and a decision the others force by unit propagation adds nothing
+// to it. Its literal is left out when some clause has every other literal
+// false under the conflict's assignment, each the negation of a kept
+// decision or forced in turn, the way recursive minimization follows the
+// reasons of a conflict analysis. The literal itself cannot be among the
+// reasons, chains longer than MAX_REASON_DEPTH are not followed and the
+// search stops once max_effort clause visits are spent. Returns the clause
+// with the ids of the clauses that force the literals left out.
+pub(crate) fn minimize(
+    clause: &[i32],
+    db: &ClauseDb,
+    occurrences: &Occurrences,
+    assignment: &Assignment,
+    max_effort: usize,
+) -> (Vec<i32>, Vec<ClauseId>) {
+    let mut kept = clause.to_vec();
+    let mut used = Vec::new();
+    let mut effort = 0;
+    let mut i = 0;
+    while i < kept.len() && effort < max_effort {
+        let decision = -kept[i];
+        let mut reasons = Reasons {
+            db,
+            occurrences,
+            assignment,
+            kept: &kept,
+            excluded: decision,
+            forced: Vec::new(),
+            used: Vec::new(),
+            effort,
+            max_effort,
+        };
+        let redundant = reasons.is_forced(decision, MAX_REASON_DEPTH);
+        effort = reasons.effort;
+        if redundant {
+            used.extend(reasons.used);
+            kept.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+    (kept, used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_lbd() {
+        let levels = hashmap! { 1 => 1, 2 => 3, 3 => 3, 4 => 5 };
+        assert_eq!(lbd(&[1, -2, 3, -4], |lit| levels[&lit.abs()]), 3);
+        assert_eq!(lbd(&[], |_| 0), 0);
+    }
+
+    #[test]
+    fn test_reduce_keeps_core_and_low_lbd() {
+        let mut learned = LearnedClauses::new(ReductionConfig {
+            tier2_lbd: 2,
+            first_reduce: 6,
+            ..Default::default()
+        });
+        learned.learn(vec![1, 2, 3, 4, 5], 5);
+        learned.learn(vec![1, 2], 2);
+        learned.learn(vec![1, 3, 4], 3);
+        learned.learn(vec![2, 3, 4, 5], 4);
+        learned.learn(vec![-1, -2, -3, -4, -5, -6], 6);
+        assert_eq!(learned.len(), 5);
+        assert_eq!(learned.reductions, 0);
+
+        // The sixth conflict triggers a reduction: the two core clauses stay and
+        // the best two of the four others survive
+        learned.learn(vec![-1], 1);
+        assert_eq!(learned.reductions, 1);
+        let kept: Vec<usize> = learned.iter().map(|clause| clause.lbd).collect();
+        assert_eq!(kept, vec![2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_tiers() {
+        let mut learned = LearnedClauses::new(ReductionConfig {
+            first_reduce: usize::MAX,
+            keep_fraction: 0.5,
+            ..Default::default()
+        });
+        learned.learn(vec![1, 2], 2);
+        learned.learn(vec![-1, 3, 4], 3);
+        learned.learn(vec![-2, -6, 5], 4);
+        learned.learn(vec![1, 2, 3, 4, 5, 6, 7], 7);
+        learned.learn(vec![2, 3, 4, 5, 6, 7, 8], 7);
+        let sizes =
+            |learned: &LearnedClauses| [Tier::Core, Tier::Tier2, Tier::Local].map(|tier| learned.tier_len(tier));
+        assert_eq!(sizes(&learned), [1, 2, 2]);
+
+        // New clauses survive their first reduction as used, all but the
+        // worse half of the local ones
+        learned.reduce();
+        assert_eq!(sizes(&learned), [1, 2, 1]);
+
+        // Of the tier-2 clauses only -1 3 4 forces a literal before the next
+        // reduction, -2 -6 5 moves to the local tier, which keeps its better
+        // half
+        let mut assignment: Assignment = (1..=8).map(|var| (var, None)).collect();
+        assignment.insert(1, Some(true));
+        assignment.insert(4, Some(false));
+        assert_eq!(learned.propagate(&mut assignment), Some(1));
+        learned.reduce();
+        assert_eq!(sizes(&learned), [1, 1, 1]);
+        assert_eq!(learned.iter().map(|clause| clause.lbd).collect::<Vec<_>>(), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_learned_propagation() {
+        let mut learned = LearnedClauses::new(ReductionConfig::default());
+        learned.learn(vec![-1, -2], 2);
+        learned.learn(vec![2, 3], 2);
+        let mut assignment: Assignment = hashmap! {
+            1 => Some(true),
+            2 => None,
+            3 => None,
+        }.into();
+        assert_eq!(learned.propagate(&mut assignment), Some(2));
+        assert_eq!(assignment[&2], Some(false));
+        assert_eq!(assignment[&3], Some(true));
+
+        learned.learn(vec![-3], 1);
+        assert_eq!(learned.propagate(&mut assignment), None);
+        learned.retain(|clause| !clause.literals.contains(&-3));
+        assert_eq!(learned.len(), 2);
+        assert_eq!(learned.propagate(&mut assignment), Some(0));
+    }
+
+    #[test]
+    fn test_strengthen() {
+        // Deciding 1, 2 and 3 failed with -1 and -3 alone, so after 1, 2 and
+        // -3 fail too the clause needs neither 3 nor, as nothing forces 2,
+        // anything past 2
+        let mut learned = LearnedClauses::new(ReductionConfig::default());
+        learned.learn(vec![-1, -3], 2);
+        let mut clause = vec![-1, -2, 3];
+        assert_eq!(learned.strengthen(&mut clause), vec![vec![-1, -3]]);
+        assert_eq!(clause, vec![-1, -2]);
+        let mut clause = vec![-1, 3, -2];
+        assert!(learned.strengthen(&mut clause).is_empty());
+        assert_eq!(clause, vec![-1, 3, -2]);
+    }
+
+    #[test]
+    fn test_minimize() {
+        // Decision -1 forces -3 and then -2, so deciding -2 as well adds
+        // nothing, while -4 is needed
+        let db = ClauseDb::new(&[vec![1, -3], vec![3, -2], vec![2, 4, 5]]);
+        let assignment: Assignment = hashmap! {
+            1 => Some(false),
+            2 => Some(false),
+            3 => Some(false),
+            4 => Some(false),
+            5 => Some(true),
+        }.into();
+        let occurrences = Occurrences::new(&db, &assignment);
+        let (kept, used) = minimize(&[1, 2, 4], &db, &occurrences, &assignment, usize::MAX);
+        assert_eq!(kept, vec![1, 4]);
+        let reasons: Vec<&[i32]> = used.iter().map(|&id| db.clause(id)).collect();
+        assert_eq!(reasons, [[1, -3], [3, -2]]);
+        assert_eq!(minimize(&[1, 2, 4], &db, &occurrences, &assignment, 0), (vec![1, 2, 4], vec![]));
+
+        // Two decisions that force each other cannot both go
+        let db = ClauseDb::new(&[vec![1, -2], vec![2, -1], vec![1, 2, 3]]);
+        let assignment: Assignment = hashmap! { 1 => Some(false), 2 => Some(false), 3 => Some(true) }.into();
+        let occurrences = Occurrences::new(&db, &assignment);
+        assert_eq!(minimize(&[1, 2], &db, &occurrences, &assignment, usize::MAX).0, vec![2]);
+    }
+}
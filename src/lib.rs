@@ -1,71 +1,193 @@
-use std::collections::btree_map::BTreeMap;
-use std::collections::HashMap;
-use std::fs::File;
-use std::hash::Hash;
-use std::io::{BufRead, BufReader};
-use std::rc::Rc;
-
-pub fn read_cnf_file(path: &str) -> Vec<Vec<i32>> {
-    let file = File::open(path).expect("Failed to open file");
-    let reader = BufReader::new(file);
-    let mut clauses = Vec::new();
-    for line in reader.lines() {
-        let line = line.expect("Failed to read line");
-        let tokens: Vec<&str> = line.trim().split_whitespace().collect();
-        if tokens.is_empty() || tokens[0] == "c" {
-            // Skip comments and empty lines
-            continue;
-        } else if tokens[0] == "p" {
-            // Parse problem line
-            assert_eq!(tokens.len(), 4, "Invalid problem line");
-            assert_eq!(tokens[1], "cnf", "Invalid problem line");
-            continue;
-        } else {
-            // Parse clauses
-            let mut clause = Vec::new();
-            for token in &tokens {
-                if *token == "0" {
-                    // End of clause
-                    clauses.push(clause);
-                    clause = Vec::new();
-                } else {
-                    // Parse literal
-                    let lit: i32 = token.parse().expect("Failed to parse literal");
-                    clause.push(lit);
-                }
-            }
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::BufRead;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::Rng;
+
+
+pub mod analyze;
+pub mod assignment;
+pub mod bruteforce;
+#[cfg(feature = "serde")]
+pub mod checkpoint;
+pub mod clausedb;
+pub mod count;
+pub mod cube;
+pub mod dimacs;
+pub mod encoders;
+pub mod encodings;
+pub mod fragment;
+pub mod generator;
+pub mod heuristics;
+pub mod implication;
+pub mod inprocess;
+#[cfg(feature = "files")]
+pub mod input;
+pub mod interpolant;
+#[cfg(feature = "ipasir")]
+pub mod ipasir;
+pub mod interrupt;
+pub mod learned;
+pub mod lookahead;
+pub mod maxsat;
+pub mod model;
+pub mod mus;
+pub mod occurrences;
+pub mod opb;
+pub mod parallel;
+pub mod pb;
+pub mod phases;
+pub mod portfolio;
+pub mod preprocess;
+pub mod progress;
+pub mod random;
+pub mod sharing;
+pub mod proof;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod qbf;
+pub mod renumber;
+pub mod restarts;
+pub mod sls;
+pub mod solver;
+pub mod symmetry;
+pub mod tseitin;
+pub mod twosat;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod xor;
+
+pub use analyze::{analyze, FormulaStats};
+pub use assignment::Assignment;
+pub use bruteforce::bruteforce_solve;
+#[cfg(feature = "serde")]
+pub use checkpoint::Checkpointer;
+pub use clausedb::{ClauseDb, ClauseId, Clauses};
+pub use count::{approx_count_models, count_models};
+pub use cube::generate_cubes;
+pub use dimacs::{parse_dimacs, write_cnf, write_cnf_with_vars, Dimacs, DimacsError, ParseMode};
+pub use fragment::{solve_horn, Fragment};
+pub use generator::{parity_chains, pigeonhole, random_ksat, tseitin_expander, tseitin_formula};
+pub use heuristics::{
+    heuristic_by_name, BranchingHeuristic, DlisHeuristic, JeroslowWangHeuristic, MinIndexHeuristic, MomsHeuristic,
+};
+pub use implication::ImplicationGraph;
+pub use inprocess::{InprocessConfig, Inprocessor, Technique};
+#[cfg(feature = "files")]
+pub use input::{decompress, open_input, read_cnf_file, read_gcnf_file, read_wcnf_file};
+pub use interpolant::{interpolant, Gate, Interpolant};
+pub use interrupt::Interrupter;
+pub use learned::{LearnedClause, LearnedClauses, ReductionConfig, Tier};
+pub use lookahead::{lookahead_search, LookAheadConfig};
+pub use maxsat::{
+    parse_wcnf, solve_maxsat, solve_wcnf, solve_wcnf_core_guided, solve_wcnf_with, MaxSatResult, MaxSatStrategy, Wcnf,
+    WcnfError,
+};
+pub use model::{prime_implicant, verify_model, UnsatisfiedClause};
+pub use mus::{find_group_mus, find_mus, parse_gcnf, GroupedFormula};
+pub use occurrences::Occurrences;
+pub use opb::{opb_to_cnf, opb_to_wcnf, parse_opb, Opb, OpbError, PbConstraint, Relation};
+pub use pb::PbSystem;
+pub use phases::{Phase, Rephasing, SavedPhases};
+pub use portfolio::SolverConfig;
+pub use preprocess::{Reconstruction, VivifyConfig};
+pub use progress::{Progress, ProgressReporter};
+pub use random::SearchRng;
+pub use proof::{check_drat, parse_drat, Proof, ProofError, ProofFormat, ProofStep};
+pub use qbf::{parse_qdimacs, solve_qbf, Qbf, Quantifier};
+pub use renumber::Renumbering;
+pub use restarts::{Conflict, GlucoseRestarts, LubyRestarts, RestartPolicy};
+pub use sharing::{ClauseExchange, ClauseSharing, SharingConfig};
+pub use sls::{probsat, walksat, LocalSearch, LocalSearchConfig, ProbSatConfig, WalkSatConfig};
+pub use solver::{Lit, Solver};
+pub use symmetry::{break_symmetries, find_symmetries, Symmetry, SymmetryConfig};
+pub use tseitin::{tseitin, BoolExpr};
+pub use twosat::{is_2sat, solve_2sat};
+#[cfg(feature = "wasm")]
+pub use wasm::solve_dimacs;
+pub use xor::{XorConstraint, XorSystem};
+
+// A CNF formula as a list of clauses of DIMACS literals
+pub type Formula = Vec<Vec<i32>>;
+
+// A total assignment satisfying a formula
+pub type Model = HashMap<i32, bool>;
+
+// Outcome of a search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveResult {
+    Sat(Model),
+    Unsat,
+    // The search was stopped before it could tell
+    Unknown,
+}
+
+impl SolveResult {
+    pub fn is_sat(&self) -> bool {
+        matches!(self, SolveResult::Sat(_))
+    }
+
+    pub fn model(&self) -> Option<&Model> {
+        match self {
+            SolveResult::Sat(model) => Some(model),
+            SolveResult::Unsat | SolveResult::Unknown => None,
         }
     }
+}
 
-    clauses
+// Parse DIMACS CNF from any reader, accepting the common quirks
+pub fn read_cnf(reader: impl BufRead) -> Vec<Vec<i32>> {
+    match parse_dimacs(reader, ParseMode::Lenient) {
+        Ok(dimacs) => dimacs.formula,
+        Err(error) => panic!("Invalid DIMACS: {}", error),
+    }
 }
 
 // Create an initial assignment for the literals in the CNF formula
-pub fn initial_assignment(formula: &Vec<Vec<i32>>) -> HashMap<i32, Option<bool>> {
-    let mut assignment = HashMap::new();
+pub fn initial_assignment(formula: &[Vec<i32>]) -> Assignment {
+    let num_vars = formula.iter().flatten().map(|lit| lit.unsigned_abs() as usize).max().unwrap_or(0);
+    let mut assignment = Assignment::with_num_vars(num_vars);
     for clause in formula.iter() {
         for &lit in clause.iter() {
-            let key = lit.abs();
-            if !assignment.contains_key(&key) {
-                // Assign a random truth value to the literal
-                assignment.insert(key, None);
-            }
+            // Every variable starts out unassigned
+            assignment.add_var(lit.abs());
         }
     }
     assignment
 }
 
 // Remove pure literals from the CNF formula
-pub fn pure_literal_elimination(formula: &Vec<Vec<i32>>, assignment: &mut HashMap<i32, Option<bool>>) -> Vec<Vec<i32>>{
+pub fn pure_literal_elimination(formula: &[Vec<i32>], assignment: &mut Assignment) -> Vec<Vec<i32>>{
+    pure_literal_elimination_keeping(formula, assignment, |_| false)
+}
+
+// Pure literal elimination that leaves the variables kept by keep unassigned,
+// for variables that constraints outside the clauses also restrict
+pub(crate) fn pure_literal_elimination_keeping(
+    formula: &[Vec<i32>],
+    assignment: &mut Assignment,
+    keep: impl Fn(i32) -> bool,
+) -> Formula {
+    assign_pure_literals(formula.iter().map(Vec::as_slice), assignment, &keep);
+    simplify_formula(formula, assignment, keep)
+}
+
+// Make the pure literals among the clauses true
+fn assign_pure_literals<'a>(
+    clauses: impl Iterator<Item = &'a [i32]>,
+    assignment: &mut Assignment,
+    keep: impl Fn(i32) -> bool,
+) {
     let mut pure_literals = HashMap::new();
     let mut removed_literals = HashMap::new();
-    let mut new_formula = Vec::new();
 
     //Find all pure literals in the formula
-    for clause in formula.iter() {
+    for clause in clauses {
         for &lit in clause.iter() {
             let key = lit.abs();
-            if !removed_literals.contains_key(&key) && !removed_literals.contains_key(&-key) && assignment.get(&key).is_none() {
+            if !removed_literals.contains_key(&key) && !removed_literals.contains_key(&-key) && assignment.get(&key) == Some(&None) && !keep(key) {
                 // First occurrence of the literal in the formula
                 if !pure_literals.contains_key(&key) && !pure_literals.contains_key(&-key) {
                     pure_literals.insert(lit, lit > 0);
@@ -84,20 +206,27 @@ pub fn pure_literal_elimination(formula: &Vec<Vec<i32>>, assignment: &mut HashMa
         let abs_key = &key.abs();
         assignment.insert(*abs_key, Some(*val));
     }
-
-    // Simplify the formula with new assignment
-    new_formula = simplify_formula(formula, assignment);
-    // println!("new formula len = {}", new_formula.len());
-    new_formula
 }
 
-// Struct of the node in a tree
+// Struct of the node in a tree. Nodes are owned by the tasklist. A node on the
+// tasklist of a search only keeps the mark of the search's assignment it was
+// branched from, detach copies that state into it so that it can move to
+// another search.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
-    pub formula: Vec<Vec<i32>>,
+    // The clauses left, by their ids in a clause database that the nodes of
+    // a search share
+    pub db: Arc<ClauseDb>,
+    pub clauses: Vec<ClauseId>,
     pub value: Option<bool>,
     pub variable: i32,
-    pub assignment: HashMap<i32, Option<bool>>,
+    // The assignment to start from, None to undo the search's own back to
+    // mark
+    pub assignment: Option<Assignment>,
+    pub mark: usize,
+    // Decision literals from the root, ending with this node's own
+    pub decisions: Vec<i32>,
 }
 
 impl Node {
@@ -106,69 +235,268 @@ impl Node {
         formula: Vec<Vec<i32>>,
         value: Option<bool>,
         variable: i32,
-        assignment: HashMap<i32, Option<bool>>
+        assignment: Assignment
     ) -> Self {
+        let db = ClauseDb::new(&formula);
         Node {
-            formula,
+            clauses: db.ids(),
+            db: Arc::new(db),
             value,
             variable,
-            assignment,
+            assignment: Some(assignment),
+            mark: 0,
+            decisions: Vec::new(),
+        }
+    }
+
+    // The node with the state it starts from copied out of the assignment of
+    // the search it was branched in
+    pub fn detach(mut self, assignment: &Assignment) -> Node {
+        if self.assignment.is_none() {
+            self.assignment = Some(assignment.at_mark(self.mark));
         }
+        self
+    }
+
+    // Number of decisions from the root
+    pub fn depth(&self) -> usize {
+        self.decisions.len()
+    }
+
+    pub fn remaining(&self) -> Clauses<'_> {
+        Clauses::new(&self.db, &self.clauses)
+    }
+
+    // Copies of the clauses left
+    pub fn formula(&self) -> Formula {
+        self.db.formula(&self.clauses)
     }
 }
 
-fn simplify_formula(formula: &Vec<Vec<i32>>, assignment: &mut HashMap<i32, Option<bool>>) -> Vec<Vec<i32>> {
-    let mut new_formula = Vec::new();
-    for clause in formula.iter() {
-        let mut satisfied = false;
-        for &lit in clause.iter() {
-            if lit > 0 {
-                if assignment.get(&lit) == Some(&Some(true)) {
-                    // The literal is already satisfied
-                    satisfied = true;
-                    break;
-                }
-            } else {
-                if assignment.get(&lit.abs()) == Some(&Some(false)) {
-                    // The literal is already satisfied
-                    satisfied = true;
-                    break;
-                }
-            }
+// Counters of the work done by the searches of one state
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    // Calls of build_search_tree, one per node expanded
+    pub nodes: usize,
+    // Branching literals picked by the heuristic
+    pub decisions: usize,
+    // Variables assigned by unit propagation or pure literal elimination
+    pub propagations: usize,
+    pub conflicts: usize,
+    // Rounds of pure literal elimination after propagation
+    pub simplifications: usize,
+    pub restarts: usize,
+    // Most decisions on the path to an expanded node
+    pub max_depth: usize,
+    // Most nodes waiting in the tasklist at once
+    pub max_tasklist: usize,
+    // Literals minimization left out of learned clauses
+    pub minimized_literals: usize,
+    // Values look-ahead found to fail, fixing the variable to the other
+    pub failed_literals: usize,
+    // Variables flipped by local search
+    pub flips: usize,
+    // Times the saved phases were switched, see Rephasing
+    pub rephases: usize,
+    // Conflicts after which the search jumped back over refuted branches
+    pub backjumps: usize,
+    // Conflicts after which it went on with the latest waiting branch, as
+    // the jump would have skipped too few
+    pub chronological_backtracks: usize,
+    // Compactions of the root's clause database, and the bytes they freed
+    pub garbage_collections: usize,
+    pub reclaimed_bytes: usize,
+    // Rounds of simplification between restarts, see Inprocessor
+    pub inprocessing_rounds: usize,
+    // Set when the formula was decided without search
+    pub fragment: Option<Fragment>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(fragment) = self.fragment {
+            writeln!(f, "Fragment: {}", fragment)?;
         }
-        if !satisfied {
-            // Add the clause to the new formula if it is not already satisfied
-            new_formula.push(clause.clone());
+        writeln!(f, "Nodes: {}", self.nodes)?;
+        writeln!(f, "Decisions: {}", self.decisions)?;
+        writeln!(f, "Propagations: {}", self.propagations)?;
+        writeln!(f, "Conflicts: {}", self.conflicts)?;
+        writeln!(f, "Simplification rounds: {}", self.simplifications)?;
+        writeln!(f, "Restarts: {}", self.restarts)?;
+        writeln!(f, "Inprocessing rounds: {}", self.inprocessing_rounds)?;
+        writeln!(f, "Rephases: {}", self.rephases)?;
+        writeln!(f, "Failed literals: {}", self.failed_literals)?;
+        writeln!(f, "Local search flips: {}", self.flips)?;
+        writeln!(f, "Max depth: {}", self.max_depth)?;
+        writeln!(f, "Max tasklist length: {}", self.max_tasklist)?;
+        writeln!(f, "Minimized literals: {}", self.minimized_literals)?;
+        writeln!(f, "Backjumps: {}", self.backjumps)?;
+        writeln!(f, "Chronological backtracks: {}", self.chronological_backtracks)?;
+        writeln!(f, "Garbage collections: {}", self.garbage_collections)?;
+        write!(f, "Reclaimed bytes: {}", self.reclaimed_bytes)
+    }
+}
+
+// Limits on the work of one search, counted from its start. The search stops
+// with Unknown before expanding a node once any limit is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub max_nodes: Option<usize>,
+    pub max_conflicts: Option<usize>,
+    pub max_decisions: Option<usize>,
+}
+
+impl Budget {
+    // Whether the work between the start and now has used up a limit
+    fn exhausted(&self, start: &Stats, now: &Stats) -> bool {
+        let reached = |max: Option<usize>, start: usize, now: usize| max.is_some_and(|max| now - start >= max);
+        reached(self.max_nodes, start.nodes, now.nodes)
+            || reached(self.max_conflicts, start.conflicts, now.conflicts)
+            || reached(self.max_decisions, start.decisions, now.decisions)
+    }
+}
+
+pub type LearnCallback = Box<dyn FnMut(&[i32]) + Send>;
+
+// State shared by all nodes of one search, it is kept across restarts
+#[derive(Default)]
+pub struct SearchState {
+    pub phases: SavedPhases,
+    // Local search run before the tree search, whose best assignment
+    // becomes the saved phases if it finds no model
+    pub walksat: Option<WalkSatConfig>,
+    // Switches the saved phases now and then, off while this is None
+    pub rephasing: Option<Rephasing>,
+    // Source of every random choice, see SearchRng
+    pub rng: SearchRng,
+    // Probability of flipping the polarity of a branching literal
+    pub random_polarity: f64,
+    pub restart_policy: Option<Box<dyn RestartPolicy>>,
+    pub stats: Stats,
+    // The conflict that ended the latest build_search_tree call
+    pub last_conflict: Option<Conflict>,
+    // Clause learning is off while this is None
+    pub learned: Option<LearnedClauses>,
+    // Called with every clause learned from a conflict
+    pub on_learn: Option<LearnCallback>,
+    // A learned clause that refutes this many branches waiting on the
+    // tasklist or fewer leaves them there to fail by propagation, instead
+    // of jumping back over them
+    pub chrono_limit: usize,
+    // Decisions whose branches the latest learned clause refutes, with the
+    // clause
+    backjump: Option<(Vec<i32>, Vec<i32>)>,
+    // Passes learned clauses to and from the other workers of a parallel
+    // search
+    pub sharing: Option<ClauseSharing>,
+    // XOR constraints the models have to satisfy besides the clauses
+    pub xors: Option<XorSystem>,
+    // Pseudo-Boolean constraints propagated natively, likewise
+    pub pbs: Option<PbSystem>,
+    // Clauses removed by preprocessing, replayed on every reported model
+    pub reconstruction: Reconstruction,
+    // Variables preprocessing and inprocessing leave in the formula, each
+    // with the number of freezes not melted yet, see Solver::freeze
+    pub frozen: HashMap<i32, usize>,
+    // Vivify the root formula every this many restarts
+    pub vivify_interval: Option<usize>,
+    pub vivify_config: VivifyConfig,
+    // Simplifies the root formula between restarts, off while this is None
+    pub inprocessing: Option<Inprocessor>,
+    // Collects failed assumptions while the solver searches under some
+    pub(crate) assumption_tracker: Option<solver::AssumptionTracker>,
+    // Refutation of the formula, written as conflicts are found
+    pub proof: Option<Proof>,
+    // Debug builds check every reported model against this formula
+    pub check_models: Option<Formula>,
+    pub progress: Option<ProgressReporter>,
+    // The search gives up once this passes
+    pub deadline: Option<Instant>,
+    pub budget: Budget,
+    pub interrupter: Option<Interrupter>,
+    // Snapshots of the tasklist for resuming the search later
+    #[cfg(feature = "serde")]
+    pub checkpoint: Option<Checkpointer>,
+    // Counters when the current search started, the budget is spent from them
+    budget_start: Stats,
+    // The assignment of the node being expanded. The nodes on the tasklist
+    // undo its trail back to the state they were branched from.
+    pub(crate) assignment: Assignment,
+    // The clauses each literal of the node's database occurs in, counted
+    // under that assignment
+    occurrences: Occurrences,
+    // Set when the search gave up before finishing
+    pub(crate) stopped: bool,
+}
+
+impl SearchState {
+    pub fn new(phase_saving: bool) -> Self {
+        SearchState {
+            phases: SavedPhases::new(phase_saving),
+            ..Default::default()
         }
     }
-    // println!("new formula len = {}", new_formula.len());
-    // if new_formula.len() == 170 {
-    //     println!("{:?}", new_formula);
-    // }
-    let mut unassigned_var = Vec::new();
-    unassigned_var = get_assignment_keys(&assignment);
+}
 
+// Drop the satisfied clauses. Unassigned variables that no longer occur are
+// set true, except for those kept by keep.
+fn simplify_formula(
+    formula: &[Vec<i32>],
+    assignment: &mut Assignment,
+    keep: impl Fn(i32) -> bool,
+) -> Vec<Vec<i32>> {
+    let db = ClauseDb::new(formula);
+    let ids = simplify_clauses(&db, &db.ids(), assignment, keep);
+    db.formula(&ids)
+}
+
+// simplify_formula on the clauses in a ClauseDb, returning the ids of the
+// clauses left
+fn simplify_clauses(
+    db: &ClauseDb,
+    ids: &[ClauseId],
+    assignment: &mut Assignment,
+    keep: impl Fn(i32) -> bool,
+) -> Vec<ClauseId> {
+    // Keep the clause if it is not already satisfied
+    let new_ids: Vec<ClauseId> =
+        ids.iter().copied().filter(|&id| !is_satisfied(db.clause(id), assignment)).collect();
+    assign_absent(Clauses::new(db, &new_ids), assignment, keep);
+    new_ids
+}
+
+// Whether some literal of the clause is already true
+fn is_satisfied(clause: &[i32], assignment: &Assignment) -> bool {
+    clause.iter().any(|&lit| assignment.get(&lit.abs()) == Some(&Some(lit > 0)))
+}
+
+// Set the unassigned variables that none of the clauses contains true,
+// except for those kept by keep
+fn assign_absent<'a>(
+    clauses: impl IntoIterator<Item = &'a [i32]>,
+    assignment: &mut Assignment,
+    keep: impl Fn(i32) -> bool,
+) {
     let mut var_in_formula = Vec::new();
-    for clause in new_formula.iter() {
-        for &lit in clause.iter() {
-            var_in_formula.push(lit);
+    for lit in clauses.into_iter().flatten() {
+        let var = lit.unsigned_abs() as usize;
+        if var >= var_in_formula.len() {
+            var_in_formula.resize(var + 1, false);
         }
+        var_in_formula[var] = true;
     }
-
-    for var in unassigned_var.iter() {
-        if !var_in_formula.contains(var) {
-            assignment.insert(*var, Some(true));
+    for var in get_assignment_keys(assignment) {
+        if !var_in_formula.get(var as usize).copied().unwrap_or(false) && !keep(var) {
+            assignment.insert(var, Some(true));
         }
     }
-
-    new_formula
 }
 
-// Check whether the node could continue
-fn false_check(node: &Rc<Node>) -> i32 {
-    let formula = node.formula.clone();
+// Check whether the node could continue from the assignment
+fn false_check(node: &Node, assignment: &Assignment) -> i32 {
     let mut true_num = 0;
-    for clause in formula.iter() {
+    for clause in node.remaining() {
         let mut false_num = 0;
         let mut lit_num = 0;
         let mut true_flag = false;
@@ -182,16 +510,15 @@ fn false_check(node: &Rc<Node>) -> i32 {
                     } else {
                         false_num += 1;
                     }
-                } else if node.assignment.get(&lit) == Some(&None) {
+                } else if assignment.get(&lit) == Some(&None) {
                     continue;
-                } else if node.assignment.get(&lit) == Some(&Some(true)) {
+                } else if assignment.get(&lit) == Some(&Some(true)) {
                     true_flag = true;
                     break;
-                } else if node.assignment.get(&lit) == Some(&Some(false)) {
+                } else if assignment.get(&lit) == Some(&Some(false)) {
                     false_num += 1;
                 }
             } else {
-                // println!("lit = {}, v = {}", lit, node.variable);
                 if lit == -node.variable {
                     if node.value == Some(false) {
                         true_flag = true;
@@ -199,12 +526,12 @@ fn false_check(node: &Rc<Node>) -> i32 {
                     } else {
                         false_num += 1;
                     }
-                } else if node.assignment.get(&lit.abs()) == Some(&None) {
+                } else if assignment.get(&lit.abs()) == Some(&None) {
                     continue;
-                } else if node.assignment.get(&lit.abs()) == Some(&Some(false)) {
+                } else if assignment.get(&lit.abs()) == Some(&Some(false)) {
                     true_flag = true;
                     break;
-                } else if node.assignment.get(&lit.abs()) == Some(&Some(true)) {
+                } else if assignment.get(&lit.abs()) == Some(&Some(true)) {
                     false_num += 1;
                 }
             }
@@ -212,6 +539,7 @@ fn false_check(node: &Rc<Node>) -> i32 {
         }
         // One clause is false
         if false_num == lit_num {
+            log::trace!("clause {:?} is false with {} = {:?}", clause, node.variable, node.value);
             return 0;
         }
 
@@ -222,7 +550,7 @@ fn false_check(node: &Rc<Node>) -> i32 {
     }
 
     // All clauses are true
-    if true_num == formula.len() {
+    if true_num == node.clauses.len() {
         return 2;
     }
 
@@ -231,169 +559,530 @@ fn false_check(node: &Rc<Node>) -> i32 {
 }
 
 // Add a task to the task list
-fn add_task(node: Rc<Node>, tasklist: &mut Vec<Rc<Node>>) {
+fn add_task(node: Node, tasklist: &mut Vec<Node>) {
     tasklist.push(node);
 }
 
 // Get a task from the task list
-pub fn get_task(tasklist: &mut Vec<Rc<Node>>) -> Option<Rc<Node>> {
+pub fn get_task(tasklist: &mut Vec<Node>) -> Option<Node> {
     tasklist.pop()
 }
 
 // Get all keys from the assignment and put them into a vector in order
-fn get_assignment_keys(assignment: &HashMap<i32, Option<bool>>) -> Vec<i32> {
-    let mut keys = assignment.iter().filter(|(_, val)| val.is_none())
-        .map(|(&key, _)| key)
-        .collect::<Vec<_>>();
-    keys.sort_unstable();
-    // println!("unassignment keys number = {}", keys.len());
-    keys
+pub(crate) fn get_assignment_keys(assignment: &Assignment) -> Vec<i32> {
+    // The assignment iterates by increasing variable already
+    assignment.iter().filter(|(_, val)| val.is_none())
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>()
 }
 
-// build a tree from the root
-pub fn build_search_tree(node: Rc<Node>, tasklist: &mut Vec<Rc<Node>>) -> bool {
-    if node.variable == 0 {
-        let unassigned_var = get_assignment_keys(&node.assignment);
-        let node_t = Rc::new(Node {
-            formula: node.formula.clone(),
-            value: Some(true),
-            variable: unassigned_var[0],
-            assignment: node.assignment.clone(),
-        });
-        let node_f = Rc::new(Node {
-            formula: node.formula.clone(),
-            value: Some(false),
-            variable: unassigned_var[0],
-            assignment: node.assignment.clone(),
-        });
-        add_task(node_f, tasklist);
-        return build_search_tree(node_t, tasklist);
-    } else if false_check(&node) == 0 {
-        return false;
-    } else if false_check(&node) == 2 {
-        let mut solution = node.assignment.clone();
-        solution.insert(node.variable, node.value);
-        for (_, val) in solution.iter_mut() {
-            if val.is_none() {
-                *val = Some(true);
-            }
+// Turn an assignment satisfying the clauses into the model of the search.
+// The XOR constraints fix the variables they still leave open, or turn the
+// assignment into a conflict, as does a violated pseudo-Boolean constraint.
+// Then free variables default to true and variables removed by preprocessing
+// are repaired.
+fn report_solution(mut solution: Assignment, state: &mut SearchState, node: &Node) -> Option<Model> {
+    // The assigned literals alone have to satisfy the pseudo-Boolean
+    // constraints, every value added below keeps them satisfied
+    if state.pbs.as_ref().is_some_and(|pbs| !pbs.is_satisfied(&solution)) {
+        return conflict(state, node, &solution);
+    }
+    if let Some(xors) = state.xors.as_ref() {
+        let Some(values) = xors.complete(&solution) else {
+            return conflict(state, node, &solution);
+        };
+        for lit in values {
+            solution.insert(lit.abs(), Some(lit > 0));
         }
-        let sorted_map: BTreeMap<_, _> = solution.into_iter().collect();
-        for (key, value) in sorted_map {
-            println!("{}: {}", key, value.unwrap());
+    }
+    for val in solution.values_mut() {
+        if val.is_none() {
+            *val = Some(true);
         }
-        // find a solution
-        return true;
+    }
+    state.reconstruction.extend_model(&mut solution);
+    let model: HashMap<i32, bool> = solution.iter().map(|(var, val)| (var, val == Some(true))).collect();
+    if let Some(formula) = state.check_models.as_ref().filter(|_| cfg!(debug_assertions)) {
+        if let Err(unsatisfied) = verify_model(formula, &model) {
+            panic!("Wrong model: {}", unsatisfied);
+        }
+    }
+    log::debug!("model found at depth {}", node.depth());
+    Some(model)
+}
+
+// The literal to branch on: the heuristic's pick while clauses are left,
+// afterwards a literal of a pseudo-Boolean constraint that the assignment
+// does not satisfy yet. None if there is neither.
+fn pick_branch_lit(
+    heuristic: &dyn BranchingHeuristic,
+    clauses: Clauses,
+    assignment: &Assignment,
+    state: &SearchState,
+) -> Option<i32> {
+    match heuristic.pick_branch_var(clauses, assignment) {
+        None if clauses.is_empty() => state.pbs.as_ref()?.pick_branch_var(assignment),
+        lit => lit,
+    }
+}
+
+// Assign the literals that the XOR and pseudo-Boolean constraints force,
+// returning how many there were or None on a conflict
+fn propagate_constraints(state: &SearchState, assignment: &mut Assignment) -> Option<usize> {
+    let xors = state.xors.as_ref().map(|xors| xors.propagate(assignment));
+    let pbs = state.pbs.as_ref().map(|pbs| pbs.propagate(assignment));
+    let mut forced = 0;
+    for implied in [xors, pbs].into_iter().flatten() {
+        for lit in implied? {
+            if assignment.insert(lit.abs(), Some(lit > 0)) == Some(Some(lit < 0)) {
+                return None;
+            }
+            forced += 1;
+        }
+    }
+    Some(forced)
+}
+
+// Polarity of a branching literal: the saved phase, if any, flipped with
+// probability random_polarity
+fn choose_polarity(lit: i32, state: &mut SearchState) -> i32 {
+    let lit = state.phases.apply(lit);
+    if state.random_polarity > 0.0 && state.rng.gen_bool(state.random_polarity) {
+        -lit
     } else {
-        // let new_formula = simplify_formula(&node.formula, &node.assignment);
-        // println!("formula of Node {}:{} is: {:?}",node.variable, node.value.unwrap(),new_formula.clone());
-
-        let mut new_assignment = node.assignment.clone();
-        // println!("{:?}", new_assignment);
-        // println!("{}{}", node.variable, node.value.unwrap());
-        new_assignment.insert(node.variable, node.value);
-        let mut new_formula = simplify_formula(&node.formula, &mut new_assignment);
-        let (result, mut new_formula, mut new_assignment) = unit_propagation(new_formula, &mut new_assignment);
-        let mut pre_formula: Vec<Vec<i32>> = Vec::new();
-        while pre_formula != new_formula.clone() {
-            pre_formula = new_formula.clone();
-            new_formula = pure_literal_elimination(&new_formula, &mut new_assignment);
-            // println!("new formula = {:?}", new_formula);
-            // println!("pre formula = {:?}", pre_formula);
+        lit
+    }
+}
 
+// Queue the second branch of lit and return the node of the first one, both
+// start from the assignment as it is at mark
+fn branch_on(
+    lit: i32,
+    db: &Arc<ClauseDb>,
+    clauses: &[ClauseId],
+    mark: usize,
+    decisions: &[i32],
+    tasklist: &mut Vec<Node>,
+) -> Node {
+    let child = |lit: i32| {
+        let mut decisions = decisions.to_vec();
+        decisions.push(lit);
+        Node {
+            db: db.clone(),
+            clauses: clauses.to_vec(),
+            value: Some(lit > 0),
+            variable: lit.abs(),
+            assignment: None,
+            mark,
+            decisions,
         }
-        if result == 0 {
-            return false;
-        } else if result == 2 {
-            let sorted_map: BTreeMap<_, _> = new_assignment.into_iter().collect();
-            for (key, value) in sorted_map {
-                println!("{}: {}", key, value.unwrap());
+    };
+    add_task(child(-lit), tasklist);
+    child(lit)
+}
+
+// Record a conflict for the restart policy and learn the negation of the
+// node's decisions, minimized, always returns None
+fn conflict(state: &mut SearchState, node: &Node, assignment: &Assignment) -> Option<Model> {
+    let assigned = assignment.values().filter(|val| val.is_some()).count();
+    state.stats.conflicts += 1;
+    log::debug!("conflict at depth {} with {} variables assigned", node.depth(), assigned);
+    state.last_conflict = Some(Conflict {
+        depth: node.depth(),
+        assigned,
+    });
+    if state.rephasing.is_some() {
+        state.phases.save_best(assignment, assigned);
+    }
+    // The clauses that force the decisions minimization leaves out
+    let mut forcing = Vec::new();
+    let clause = state.learned.as_ref().map(|learned| {
+        let clause: Vec<i32> = node.decisions.iter().map(|&lit| -lit).collect();
+        let (mut clause, used) =
+            learned::minimize(&clause, &node.db, &state.occurrences, assignment, learned.minimize_effort);
+        forcing.extend(used.iter().map(|&id| node.db.clause(id).to_vec()));
+        forcing.extend(learned.strengthen(&mut clause));
+        clause
+    });
+    if let Some(proof) = state.proof.as_mut() {
+        // The clause the conflict falsified when propagation did not record it
+        let falsified = || {
+            let clauses = node.remaining().iter().map(<[i32]>::to_vec);
+            let learned = state.learned.iter().flat_map(LearnedClauses::iter).map(|clause| clause.literals.clone());
+            let is_false = |lit: &i32| assignment.get(&lit.abs()) == Some(&Some(*lit < 0));
+            clauses.chain(learned).find(|clause| clause.iter().all(is_false))
+        };
+        let antecedents = proof.antecedents().map(|antecedents| antecedents.analyze(falsified, assignment));
+        let antecedents = antecedents.unwrap_or_default();
+        match clause.as_ref().filter(|clause| clause.len() < node.decisions.len()) {
+            Some(clause) => {
+                proof.add_derived(clause, forcing.iter().chain(antecedents.iter()).map(Vec::as_slice));
+                proof.refute(&node.decisions, [clause.as_slice()]);
             }
-            return true;
+            None => proof.refute(&node.decisions, antecedents.iter().map(Vec::as_slice)),
         }
-        let unassigned_var = get_assignment_keys(&new_assignment);
-        if unassigned_var.is_empty() && !new_formula.is_empty() {
-            return false;
+    }
+    if let Some(tracker) = state.assumption_tracker.as_mut() {
+        tracker.record(&node.decisions, state.learned.as_ref());
+    }
+    if let (Some(learned), Some(clause)) = (state.learned.as_mut(), clause) {
+        state.stats.minimized_literals += node.decisions.len() - clause.len();
+        // Every decision sits on its own level
+        let level = |lit: i32| node.decisions.iter().position(|&d| d == -lit).unwrap();
+        // The clause refutes every branch below its deepest decision, the
+        // search may jump back to there
+        let refuted = clause.iter().map(|&lit| level(lit) + 1).max().unwrap_or(0);
+        state.backjump = Some((node.decisions[..refuted].to_vec(), clause.clone()));
+        let lbd = learned::lbd(&clause, level);
+        if let Some(on_learn) = state.on_learn.as_mut() {
+            on_learn(&clause);
+        }
+        if let Some(sharing) = state.sharing.as_mut() {
+            let clause = LearnedClause { literals: clause.clone(), lbd };
+            for clause in sharing.on_learn(&clause) {
+                learned.import(clause);
+            }
         }
+        learned.learn(clause, lbd);
+    }
+    None
+}
+
+// What expanding a node leads to: the first branch to dive into next, or
+// the end of the dive with the model if it reached one
+enum Step {
+    Dive(Node),
+    Done(Option<Model>),
+}
 
-        let node_t = Rc::new(Node {
-            formula: new_formula.clone(),
-            value: Some(true),
-            variable: unassigned_var[0],
-            assignment: new_assignment.clone(),
-        });
-        let node_f = Rc::new(Node {
-            formula: new_formula.clone(),
-            value: Some(false),
-            variable: unassigned_var[0],
-            assignment: new_assignment.clone(),
-        });
-        // println!("node_f {}:{} ass: {:?}", node_f.variable, node_f.value.unwrap(), node_f.assignment);
-        add_task(node_f, tasklist);
-        return build_search_tree(node_t, tasklist);
+// build a tree from the root, returning the model if the dive reaches one.
+// The dive follows the first branch of every decision in a loop, the second
+// ones wait on the tasklist, so its depth does not grow the stack.
+pub fn build_search_tree(
+    mut node: Node,
+    tasklist: &mut Vec<Node>,
+    heuristic: &dyn BranchingHeuristic,
+    state: &mut SearchState,
+) -> Option<Model> {
+    let mut assignment = std::mem::take(&mut state.assignment);
+    let found = loop {
+        match expand(node, &mut assignment, tasklist, heuristic, state) {
+            Step::Dive(next) => node = next,
+            Step::Done(found) => break found,
+        }
+    };
+    // The branches under the decisions the conflict refuted wait on top of
+    // the tasklist. Jump back over them unless they are few enough to fail
+    // one by one.
+    if let Some((refuted, clause)) = state.backjump.take() {
+        let skipped = tasklist.iter().rev().take_while(|task| task.decisions.starts_with(&refuted)).count();
+        if skipped > state.chrono_limit {
+            tasklist.truncate(tasklist.len() - skipped);
+            state.stats.backjumps += 1;
+            if let Some(proof) = state.proof.as_mut() {
+                proof.refute(&refuted, [clause.as_slice()]);
+            }
+        } else {
+            state.stats.chronological_backtracks += 1;
+        }
     }
+    state.assignment = assignment;
+    found
 }
 
-// Perform unit propagation on the formula
-pub fn unit_propagation(mut formula: Vec<Vec<i32>>, assignment: &mut HashMap<i32, Option<bool>>) -> (i32, Vec<Vec<i32>>, HashMap<i32, Option<bool>>) {
-    let mut new_assignment: HashMap<i32, Option<bool>> = HashMap::new();
-    loop {
-        let mut unit_clause: Option<Vec<i32>> = None;
-        for clause in formula.iter() {
-            let mut unassigned_count = 0;
-            let mut unassigned_literal:i32 = 0;
-            for &lit in clause.iter() {
-                if assignment.contains_key(&lit.abs()) && assignment[&lit.abs()].is_some() {
-                    continue;
-                } else {
-                    unassigned_count += 1;
-                    unassigned_literal = lit;
+// Assign the node's literal, propagate and decide on the next literal
+fn expand(
+    mut node: Node,
+    assignment: &mut Assignment,
+    tasklist: &mut Vec<Node>,
+    heuristic: &dyn BranchingHeuristic,
+    state: &mut SearchState,
+) -> Step {
+    if state.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        || state.budget.exhausted(&state.budget_start, &state.stats)
+        || state.interrupter.as_ref().is_some_and(Interrupter::is_interrupted)
+    {
+        state.stopped = true;
+    }
+    if state.stopped {
+        return Step::Done(None);
+    }
+    // Back up to the state the node was branched from
+    match node.assignment.take() {
+        Some(start) => {
+            *assignment = start;
+            assignment.clear_trail();
+            state.occurrences = Occurrences::new(&node.db, assignment);
+        }
+        None => state.occurrences.undo(&node.db, assignment, node.mark),
+    }
+    if let Some(antecedents) = state.proof.as_mut().and_then(Proof::antecedents) {
+        antecedents.undo(node.depth());
+    }
+    state.stats.nodes += 1;
+    state.stats.max_depth = state.stats.max_depth.max(node.depth());
+    if let Some(progress) = state.progress.as_mut() {
+        progress.on_node(state.stats.nodes, node.depth(), node.clauses.len());
+    }
+    if node.variable != 0 {
+        state.phases.save(node.variable, node.value == Some(true));
+    }
+    if node.variable == 0 {
+        let lit = match pick_branch_lit(heuristic, node.remaining(), assignment, state) {
+            Some(lit) => lit,
+            None if node.clauses.is_empty() => return Step::Done(report_solution(assignment.clone(), state, &node)),
+            // Every variable is assigned but some clause is left unsatisfied
+            None => return Step::Done(conflict(state, &node, assignment)),
+        };
+        state.stats.decisions += 1;
+        let lit = choose_polarity(lit, state);
+        log::debug!("branching on {} at depth {}, {} clauses left", lit, node.depth(), node.clauses.len());
+        let node_first = branch_on(lit, &node.db, &node.clauses, assignment.mark(), &node.decisions, tasklist);
+        Step::Dive(node_first)
+    } else if false_check(&node, assignment) == 0 {
+        assignment.insert(node.variable, node.value);
+        Step::Done(conflict(state, &node, assignment))
+    } else if false_check(&node, assignment) == 2 && state.pbs.is_none() {
+        assignment.insert(node.variable, node.value);
+        // find a solution
+        Step::Done(report_solution(assignment.clone(), state, &node))
+    } else {
+        log::trace!("formula of node {} = {:?}: {:?}", node.variable, node.value, node.formula());
+
+        // The changes from here on are the node's own
+        let start = assignment.mark();
+        log::trace!("assignment before {} = {:?}: {:?}", node.variable, node.value, assignment);
+        assignment.insert(node.variable, node.value);
+        // Simplification must not give the variables of the XOR and
+        // pseudo-Boolean constraints values of its own accord, nor any while
+        // an LRAT proof is written, whose hints can only name clauses
+        let lrat = state.proof.as_ref().is_some_and(|proof| proof.format() == ProofFormat::Lrat);
+        let keep = |var| {
+            lrat || state.xors.as_ref().is_some_and(|xors| xors.contains(var))
+                || state.pbs.as_ref().is_some_and(|pbs| pbs.contains(var))
+        };
+        let db = &node.db;
+        let new_clauses = state.occurrences.simplify(db, &node.clauses, assignment, keep);
+        let antecedents = state.proof.as_mut().and_then(Proof::antecedents);
+        let (mut result, mut new_clauses) = state.occurrences.propagate(db, &new_clauses, assignment, antecedents);
+        // Learned clauses, XOR and pseudo-Boolean constraints can force more
+        // literals, propagate those as well
+        while result != 0 {
+            let antecedents = state.proof.as_mut().and_then(Proof::antecedents);
+            let learned = state.learned.as_mut().map(|learned| learned.propagate_recording(assignment, antecedents));
+            let forced = match learned {
+                None | Some(Some(0)) => propagate_constraints(state, assignment),
+                Some(forced) => forced,
+            };
+            match forced {
+                None => result = 0,
+                Some(0) => break,
+                Some(_) => {
+                    let clauses = state.occurrences.simplify(db, &new_clauses, assignment, keep);
+                    let antecedents = state.proof.as_mut().and_then(Proof::antecedents);
+                    (result, new_clauses) = state.occurrences.propagate(db, &clauses, assignment, antecedents);
                 }
             }
-            if unassigned_count == 1 {
-                // The clause is a unit clause
-                unit_clause = Some(clause.clone());
-                // println!("unassigned_lit = {}", unassigned_literal);
-                if new_assignment.contains_key(&unassigned_literal.abs()) && new_assignment.get(&unassigned_literal.abs()) != Some(&Some(unassigned_literal>0)) {
-                    // println!("false formula = {:?}", formula.clone());
-                    return (0, formula, assignment.clone());
+        }
+        let mut pre_clauses: Vec<ClauseId> = Vec::new();
+        while pre_clauses != new_clauses {
+            pre_clauses = new_clauses.clone();
+            new_clauses = state.occurrences.eliminate_pure(db, &new_clauses, assignment, keep);
+            state.stats.simplifications += 1;
+            log::trace!("pure literal elimination left {} of {} clauses", new_clauses.len(), pre_clauses.len());
+        }
+        for (var, old) in assignment.changes(start) {
+            if let (Some(None), Some(val)) = (old, assignment[&var]) {
+                state.phases.save(var, val);
+                if var != node.variable {
+                    state.stats.propagations += 1;
                 }
-                new_assignment.insert(unassigned_literal.abs(), Some(unassigned_literal > 0));
-                // break;
             }
         }
-        if let Some(clause) = unit_clause {
-            // Remove the unit clause from the formula
-            formula.retain(|c| c != &clause);
-            // Simplify the formula with the new assignment
-            formula = simplify_formula(&formula, &mut new_assignment);
-            // Update the current assignment with the new assignment
-            for (key, val) in new_assignment.iter() {
-                assignment.insert(*key, *val);
+        if result == 0 {
+            return Step::Done(conflict(state, &node, assignment));
+        } else if result == 2 && state.pbs.is_none() {
+            return Step::Done(report_solution(assignment.clone(), state, &node));
+        }
+        let lit = match pick_branch_lit(heuristic, Clauses::new(db, &new_clauses), assignment, state) {
+            Some(lit) => lit,
+            None if new_clauses.is_empty() => return Step::Done(report_solution(assignment.clone(), state, &node)),
+            None => return Step::Done(conflict(state, &node, assignment)),
+        };
+
+        state.stats.decisions += 1;
+        let lit = choose_polarity(lit, state);
+        log::debug!("branching on {} at depth {}, {} clauses left", lit, node.depth(), new_clauses.len());
+        let node_first = branch_on(lit, db, &new_clauses, assignment.mark(), &node.decisions, tasklist);
+        Step::Dive(node_first)
+    }
+}
+
+// Run the tasklist loop from the root until a solution is found or every
+// branch failed, restarting from the root whenever the restart policy asks
+pub fn run_search(root: Node, heuristic: &dyn BranchingHeuristic, state: &mut SearchState) -> SolveResult {
+    let tasklist = vec![root.clone()];
+    continue_search(root, tasklist, heuristic, state)
+}
+
+// run_search from the tasklist an earlier search of the root left behind
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "search", skip_all))]
+pub fn continue_search(
+    mut root: Node,
+    mut tasklist: Vec<Node>,
+    heuristic: &dyn BranchingHeuristic,
+    state: &mut SearchState,
+) -> SolveResult {
+    if let Some(progress) = state.progress.as_mut() {
+        progress.restart_clock();
+    }
+    state.stopped = false;
+    state.budget_start = state.stats.clone();
+    // The stretch of the search from one restart to the next
+    #[cfg(feature = "tracing")]
+    let mut restart_span = Some(tracing::info_span!("restart", restarts = state.stats.restarts).entered());
+    while let Some(node) = get_task(&mut tasklist) {
+        let found = build_search_tree(node, &mut tasklist, heuristic, state);
+        // The tasklist only grows during a dive, so it peaks at its end
+        state.stats.max_tasklist = state.stats.max_tasklist.max(tasklist.len());
+        if let Some(model) = found {
+            return SolveResult::Sat(model);
+        }
+        if state.stopped {
+            return SolveResult::Unknown;
+        }
+        // Every call that does not find a solution ends in a conflict
+        if let Some(phase) = state.rephasing.as_mut().and_then(Rephasing::on_conflict) {
+            state.phases.rephase(phase, &state.assignment, &mut state.rng);
+            state.stats.rephases += 1;
+            log::debug!("rephasing to {:?} phases after {} conflicts", phase, state.stats.conflicts);
+        }
+        let restart = match (state.restart_policy.as_mut(), state.last_conflict.take()) {
+            (Some(policy), Some(conflict)) => policy.on_conflict(&conflict),
+            _ => false,
+        };
+        if restart && !tasklist.is_empty() {
+            tasklist.clear();
+            state.stats.restarts += 1;
+            log::debug!("restart {} after {} conflicts", state.stats.restarts, state.stats.conflicts);
+            #[cfg(feature = "tracing")]
+            {
+                restart_span.take();
+                restart_span = Some(tracing::info_span!("restart", restarts = state.stats.restarts).entered());
             }
-            new_assignment.clear();
-        } else {
-            // No more unit clauses can be found
-            break;
+            collect_garbage(&mut root, state);
+            if !inprocess(&mut root, state) {
+                return SolveResult::Unsat;
+            }
+            if state.vivify_interval.is_some_and(|interval| state.stats.restarts.is_multiple_of(interval)) {
+                let mut formula = root.formula();
+                preprocess::vivify(&mut formula, &state.vivify_config, state.proof.as_mut());
+                if formula.iter().any(Vec::is_empty) {
+                    return SolveResult::Unsat;
+                }
+                root.db = Arc::new(ClauseDb::new(&formula));
+                root.clauses = root.db.ids();
+            }
+            add_task(root.clone(), &mut tasklist);
+        }
+        // Searches under assumptions are not saved, a resumed one could not
+        // tell which of them failed
+        #[cfg(feature = "serde")]
+        if let Some(checkpointer) = state.checkpoint.as_mut().filter(|_| state.assumption_tracker.is_none()) {
+            checkpointer.tick(&root, &tasklist, &state.assignment, &state.stats);
+        }
+    }
+    SolveResult::Unsat
+}
+
+// Share of the literals of a clause database that deleted clauses may take
+// up before it is compacted
+const MAX_GARBAGE: f64 = 0.25;
+
+// Fix the learned unit clauses in the root's assignment for good and delete
+// the root's clauses they satisfy. Those stay behind in the clause database
+// until they take up more than MAX_GARBAGE of it, then the live clauses are
+// copied to a new one. That renumbers them, the occurrence lists are built
+// again for the new database when the search starts over from the root.
+fn collect_garbage(root: &mut Node, state: &mut SearchState) {
+    let (Some(learned), Some(assignment)) = (state.learned.as_ref(), root.assignment.as_mut()) else {
+        return;
+    };
+    for clause in learned.iter().filter(|clause| clause.literals.len() == 1) {
+        let lit = clause.literals[0];
+        if assignment.get(&lit.abs()) == Some(&None) {
+            assignment.insert(lit.abs(), Some(lit > 0));
         }
     }
-    if formula.len() == 0 {
-        return (2, formula, assignment.clone());
+    let db = &root.db;
+    root.clauses.retain(|&id| !is_satisfied(db.clause(id), assignment));
+    let live: usize = root.clauses.iter().map(|&id| db.clause(id).len()).sum();
+    if (db.num_literals() - live) as f64 > MAX_GARBAGE * db.num_literals() as f64 {
+        let compacted = db.compact(&root.clauses);
+        state.stats.garbage_collections += 1;
+        state.stats.reclaimed_bytes += db.bytes().saturating_sub(compacted.bytes());
+        log::debug!("compacted the clause database to {} clauses", compacted.len());
+        root.clauses = compacted.ids();
+        root.db = Arc::new(compacted);
     }
-    // println!("{}, \
-    // {:?}, \
-    // {:?}",true, formula, assignment.clone());
+}
 
-    (1, formula, assignment.clone())
+// Run an inprocessing round on the root's clauses if one is due, false if it
+// refutes them. The variables of the root's assignment, the frozen ones and
+// those of the XOR and pseudo-Boolean constraints are not eliminated, nor any
+// while clauses are shared with other searches. Learned clauses need not hold
+// anymore once a variable of theirs is eliminated, those over variables that
+// left the formula are forgotten.
+fn inprocess(root: &mut Node, state: &mut SearchState) -> bool {
+    let Some(inprocessor) = state.inprocessing.as_mut() else {
+        return true;
+    };
+    if !inprocessor.on_restart() {
+        return true;
+    }
+    let Some(assignment) = root.assignment.as_ref() else {
+        return true;
+    };
+    let vars = |formula: &Formula| -> HashSet<i32> { formula.iter().flatten().map(|lit| lit.abs()).collect() };
+    let mut formula = root.formula();
+    let before = vars(&formula);
+    let (xors, pbs, sharing) = (state.xors.as_ref(), state.pbs.as_ref(), state.sharing.is_some());
+    let frozen = &state.frozen;
+    let keep = |var| {
+        sharing
+            || assignment.get(&var) != Some(&None)
+            || frozen.contains_key(&var)
+            || xors.is_some_and(|xors| xors.contains(var))
+            || pbs.is_some_and(|pbs| pbs.contains(var))
+    };
+    inprocessor.round(&mut formula, &mut state.reconstruction, keep, state.proof.as_mut());
+    state.stats.inprocessing_rounds += 1;
+    if formula.iter().any(Vec::is_empty) {
+        return false;
+    }
+    let after = vars(&formula);
+    if let Some(learned) = state.learned.as_mut() {
+        let left = |lit: &i32| before.contains(&lit.abs()) && !after.contains(&lit.abs());
+        learned.retain(|clause| !clause.literals.iter().any(left));
+    }
+    root.db = Arc::new(ClauseDb::new(&formula));
+    root.clauses = root.db.ids();
+    true
+}
 
+// Perform unit propagation on the formula
+pub fn unit_propagation(formula: Vec<Vec<i32>>, assignment: &mut Assignment) -> (i32, Vec<Vec<i32>>, Assignment) {
+    let db = ClauseDb::new(&formula);
+    let (result, ids) = Occurrences::new(&db, assignment).propagate(&db, &db.ids(), assignment, None);
+    (result, db.formula(&ids), assignment.clone())
 }
 
 
 
 #[cfg(test)]
 mod tests {
-    use std::mem::transmute;
     use super::*;
+    use rand::SeedableRng;
     use maplit::hashmap;
     //
     // #[test]
@@ -410,6 +1099,12 @@ mod tests {
     //     assert_eq!(result, expected);
     // }
 
+    #[test]
+    fn test_read_cnf() {
+        let text = "c comment\np cnf 3 2\n1 -2 0\n\n2 3 0 -1 0\n";
+        assert_eq!(read_cnf(text.as_bytes()), vec![vec![1, -2], vec![2, 3], vec![-1]]);
+    }
+
     #[test]
     fn test_initial_assignment() {
         let formula = vec![
@@ -417,12 +1112,12 @@ mod tests {
             vec![-1, -2],
             vec![-3, 4],
         ];
-        let expected = hashmap! {
+        let expected: Assignment = hashmap! {
             1 => None,
             2 => None,
             3 => None,
             4 => None,
-        };
+        }.into();
         let result = initial_assignment(&formula);
         assert_eq!(result, expected);
     }
@@ -436,12 +1131,12 @@ mod tests {
             vec![-1, -2, 3],
             vec![-4, 2, 3],
         ];
-        let mut assignment = hashmap! {
+        let mut assignment: Assignment = hashmap! {
             1 => None,
             2 => None,
             3 => None,
             4 => None,
-        };
+        }.into();
         let new_formula = pure_literal_elimination(&formula, &mut assignment);
         assert_eq!(assignment.get(&1), Some(&None));
         assert_eq!(assignment.get(&2), Some(&None));
@@ -462,40 +1157,34 @@ mod tests {
             vec![-1, -2, 3],
             vec![-4, 2, 3],
         ];
-        let mut assignment = hashmap! {
+        let assignment: Assignment = hashmap! {
             1 => None,
             2 => Some(true),
             3 => Some(false),
             4 => Some(false),
-        };
-        let node = Rc::new(Node::new(formula, Some(false), 1, assignment));
-        assert_eq!(false_check(&node), 2);
+        }.into();
+        let node = Node::new(formula, Some(false), 1, assignment.clone());
+        assert_eq!(false_check(&node, &assignment), 2);
     }
 
     #[test]
     fn test_add_and_get_task() {
-        let mut tasklist: Vec<Rc<Node>> = vec![];
-
-        let node1 = Rc::new(Node {
-            formula: vec![vec![1, -2], vec![-1, 3], vec![-3, -4]],
-            value: None,
-            variable: 1,
-            assignment: HashMap::new(),
-        });
-
-        let node2 = Rc::new(Node {
-            formula: vec![vec![-2, 3], vec![-1, 3], vec![1, 2]],
-            value: None,
-            variable: 2,
-            assignment: HashMap::new(),
-        });
-
-        let node3 = Rc::new(Node {
-            formula: vec![vec![-1, 3], vec![-1, 3], vec![1, 2]],
-            value: None,
-            variable: 2,
-            assignment: HashMap::new(),
-        });
+        let mut tasklist: Vec<Node> = vec![];
+
+        let node1 = Node {
+            decisions: vec![1],
+            ..Node::new(vec![vec![1, -2], vec![-1, 3], vec![-3, -4]], None, 1, Assignment::new())
+        };
+
+        let node2 = Node {
+            decisions: vec![2],
+            ..Node::new(vec![vec![-2, 3], vec![-1, 3], vec![1, 2]], None, 2, Assignment::new())
+        };
+
+        let node3 = Node {
+            decisions: vec![2],
+            ..Node::new(vec![vec![-1, 3], vec![-1, 3], vec![1, 2]], None, 2, Assignment::new())
+        };
 
 
         add_task(node1.clone(), &mut tasklist);
@@ -503,6 +1192,7 @@ mod tests {
         add_task(node3.clone(), &mut tasklist);
 
         let popped_node = get_task(&mut tasklist).unwrap();
+        assert_eq!(popped_node.formula(), node3.formula());
 
         let popped_node = get_task(&mut tasklist).unwrap();
         assert_eq!(popped_node.variable, 2);
@@ -515,19 +1205,443 @@ mod tests {
         assert!(tasklist.is_empty());
     }
 
+    #[test]
+    fn test_search_is_send() {
+        // The parallel searches move nodes, search states and solvers
+        // between threads
+        fn assert_send<T: Send>() {}
+        assert_send::<Node>();
+        assert_send::<SearchState>();
+        assert_send::<Solver>();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let formula = vec![vec![1, -2], vec![2, 3], vec![-1, -3]];
+        let mut assignment = initial_assignment(&formula);
+        assignment.insert(2, Some(true));
+        let node = Node {
+            decisions: vec![2],
+            ..Node::new(formula.clone(), Some(false), 1, assignment.clone())
+        };
+        let text = serde_json::to_string(&node).unwrap();
+        let copy: Node = serde_json::from_str(&text).unwrap();
+        assert_eq!(copy.formula(), formula);
+        // The trail comes along, marks stay valid
+        assert_eq!(copy.assignment.as_ref().map(Assignment::mark), Some(assignment.mark()));
+        assert_eq!(copy.assignment, Some(assignment));
+        assert_eq!((copy.variable, copy.value, copy.decisions, copy.mark), (1, Some(false), vec![2], 0));
+
+        let mut state = SearchState::default();
+        assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        let stats: Stats = serde_json::from_str(&serde_json::to_string(&state.stats).unwrap()).unwrap();
+        assert_eq!(stats, state.stats);
+    }
+
     #[test]
     fn test_get_assignment_keys() {
-        let mut assignment = hashmap! {
+        let assignment: Assignment = hashmap! {
             4 => Some(false),
             2 => None,
             1 => None,
             3 => None,
-        };
+        }.into();
         let keys = get_assignment_keys(&assignment);
         assert_eq!(keys, vec![1, 2, 3]);
     }
 
+    #[test]
+    fn test_pure_literals_of_unassigned_variables() {
+        // 2 only occurs positively and 3 only negatively, which satisfies
+        // both clauses
+        let formula = vec![vec![1, 2], vec![-1, -3]];
+        let mut assignment = initial_assignment(&formula);
+        let simplified_formula = pure_literal_elimination(&formula, &mut assignment);
+        assert!(simplified_formula.is_empty());
+        assert_eq!((assignment.get(&2), assignment.get(&3)), (Some(&Some(true)), Some(&Some(false))));
+    }
+
+    #[test]
+    fn test_simplify_keeps_negated_variables() {
+        // 1 only occurs negated in the clause left, so only 3 is free to
+        // set
+        let mut assignment = initial_assignment(&[vec![-1, 2], vec![3]]);
+        let formula = vec![vec![-1, 2]];
+        assert_eq!(simplify_formula(&formula, &mut assignment, |_| false), formula);
+        assert_eq!((assignment.get(&1), assignment.get(&3)), (Some(&None), Some(&Some(true))));
+    }
+
+    #[test]
+    fn test_search_without_unassigned_variables() {
+        // Nothing is left to branch on at the root
+        let assignment: Assignment = hashmap! { 1 => Some(true) }.into();
+        let root = Node::new(vec![], None, 0, assignment.clone());
+        let found = build_search_tree(root, &mut Vec::new(), &MinIndexHeuristic, &mut SearchState::default());
+        assert_eq!(found, Some(hashmap! { 1 => true }));
+        let root = Node::new(vec![vec![-1]], None, 0, assignment);
+        let found = build_search_tree(root, &mut Vec::new(), &MinIndexHeuristic, &mut SearchState::default());
+        assert_eq!(found, None);
+    }
+
+    // Branch on the largest unassigned variable, false first
+    struct MaxIndexNegative;
+
+    impl BranchingHeuristic for MaxIndexNegative {
+        fn pick_branch_var(&self, _clauses: Clauses, assignment: &Assignment) -> Option<i32> {
+            get_assignment_keys(assignment).last().map(|&var| -var)
+        }
+    }
+
+    fn solve_with(formula: &Formula, heuristic: &dyn BranchingHeuristic) -> bool {
+        solve_with_state(formula, heuristic, &mut SearchState::default())
+    }
+
+    fn solve_with_state(formula: &Formula, heuristic: &dyn BranchingHeuristic, state: &mut SearchState) -> bool {
+        let mut assignment = initial_assignment(formula);
+        let simplified_formula = pure_literal_elimination(formula, &mut assignment);
+        let root = Node::new(simplified_formula, None, 0, assignment);
+        run_search(root, heuristic, state).is_sat()
+    }
+
+    #[test]
+    fn test_build_search_tree_with_heuristic() {
+        let sat = vec![
+            vec![1, 2, 3],
+            vec![-1, -2],
+            vec![-2, -3],
+            vec![-1, -3],
+            vec![1, -2, 3],
+        ];
+        let unsat = vec![
+            vec![1, 2],
+            vec![1, -2],
+            vec![-1, 2],
+            vec![-1, -2],
+        ];
+        assert!(solve_with(&sat, &MinIndexHeuristic));
+        assert!(solve_with(&sat, &MaxIndexNegative));
+        assert!(!solve_with(&unsat, &MinIndexHeuristic));
+        assert!(!solve_with(&unsat, &MaxIndexNegative));
+        assert!(solve_with(&sat, &JeroslowWangHeuristic::two_sided()));
+        assert!(!solve_with(&unsat, &JeroslowWangHeuristic::one_sided()));
+        assert!(solve_with(&sat, &DlisHeuristic));
+        assert!(!solve_with(&unsat, &DlisHeuristic));
+        assert!(solve_with(&sat, &MomsHeuristic::default()));
+        assert!(!solve_with(&unsat, &MomsHeuristic::new(3)));
+    }
+
+    #[test]
+    fn test_run_search_returns_model() {
+        // 4 does not occur after 1 and 2 are forced, it defaults to true
+        let formula = vec![vec![1], vec![-1, 2], vec![-2, -3], vec![3, 4, -1]];
+        let mut assignment = initial_assignment(&formula);
+        let simplified_formula = pure_literal_elimination(&formula, &mut assignment);
+        let root = Node::new(simplified_formula, None, 0, assignment);
+        let result = run_search(root, &MinIndexHeuristic, &mut SearchState::default());
+        let model = result.model().expect("The formula is satisfiable");
+        assert_eq!(model, &hashmap! { 1 => true, 2 => true, 3 => false, 4 => true });
+    }
+
+    #[test]
+    fn test_long_dive_does_not_overflow() {
+        // Every pair of variables takes a decision of its own, so the one dive
+        // to the model is hundreds of decisions deep, on a small stack
+        let n = 400;
+        let formula: Formula = (1..=n).flat_map(|i| [vec![2 * i - 1, 2 * i], vec![1 - 2 * i, -2 * i]]).collect();
+        let search = move || {
+            let mut state = SearchState::default();
+            assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+            assert_eq!((state.stats.decisions, state.stats.conflicts), (n as usize, 0));
+        };
+        std::thread::Builder::new().stack_size(128 * 1024).spawn(search).unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_search_stats() {
+        // Branching on 1 refutes both branches right away
+        let formula = vec![vec![1, 2], vec![1, -2], vec![-1, 2], vec![-1, -2]];
+        let mut state = SearchState::default();
+        assert!(!solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        let stats = &state.stats;
+        assert_eq!((stats.nodes, stats.decisions, stats.conflicts), (3, 1, 2));
+        assert_eq!((stats.max_depth, stats.max_tasklist, stats.restarts), (1, 1, 0));
+
+        let formula = vec![vec![1, 2, 3], vec![-1, 4], vec![-4, 5], vec![-2, -5]];
+        let mut state = SearchState::default();
+        assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        assert!(state.stats.propagations > 0);
+        assert_eq!(state.stats.conflicts, 0);
+    }
+
+    #[test]
+    fn test_backjumps() {
+        // Under 1, deciding 2 and then 3 both ways fails without 2 taking
+        // part, so the clause of the second conflict refutes 1 and the
+        // branch -2 under it goes unvisited
+        let formula = vec![
+            vec![-1, -3, 2],
+            vec![-1, 3, 2],
+            vec![-1, -3, 4],
+            vec![-1, -3, -4],
+            vec![-1, 3, 5],
+            vec![-1, 3, -5],
+            vec![-2, 6],
+            vec![2, -6],
+            vec![1, 2],
+        ];
+        let run = |chrono_limit: usize| {
+            let mut state = SearchState {
+                learned: Some(LearnedClauses::new(ReductionConfig::default())),
+                chrono_limit,
+                ..Default::default()
+            };
+            assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+            state.stats
+        };
+        let stats = run(0);
+        assert_eq!((stats.conflicts, stats.backjumps, stats.chronological_backtracks), (2, 1, 1));
+        let stats = run(1);
+        assert_eq!((stats.backjumps, stats.chronological_backtracks), (0, stats.conflicts));
+        assert!(stats.conflicts > 2);
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let formula = vec![vec![1, 2, 3], vec![1, -2, 4], vec![1, 3, -4], vec![-1, 5], vec![2, 5, 6]];
+        let mut root = Node::new(formula.clone(), None, 0, initial_assignment(&formula));
+        let mut state = SearchState {
+            learned: Some(LearnedClauses::new(ReductionConfig::default())),
+            ..Default::default()
+        };
+        collect_garbage(&mut root, &mut state);
+        assert_eq!(root.db.len(), 5);
+        // The learned unit 1 satisfies most of the literals, the others move
+        // to a new database
+        state.learned.as_mut().unwrap().learn(vec![1], 1);
+        collect_garbage(&mut root, &mut state);
+        assert_eq!(root.assignment.as_ref().unwrap()[&1], Some(true));
+        assert_eq!(root.formula(), vec![vec![-1, 5], vec![2, 5, 6]]);
+        assert_eq!(root.db.len(), 2);
+        assert_eq!(state.stats.garbage_collections, 1);
+        assert!(state.stats.reclaimed_bytes > 0);
+        let result = run_search(root, &MinIndexHeuristic, &mut state);
+        assert_eq!(verify_model(&formula, result.model().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn test_progress_callback() {
+        use std::sync::{Arc, Mutex};
+
+        // Pigeonhole: three pigeons (rows) in two holes (columns)
+        let formula = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![-1, -3],
+            vec![-1, -5],
+            vec![-3, -5],
+            vec![-2, -4],
+            vec![-2, -6],
+            vec![-4, -6],
+        ];
+        let snapshots = Arc::new(Mutex::new(Vec::new()));
+        let seen = snapshots.clone();
+        let mut state = SearchState {
+            progress: Some(ProgressReporter::new(2, move |progress: &Progress| {
+                seen.lock().unwrap().push(progress.clone());
+            })),
+            ..Default::default()
+        };
+        assert!(!solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        let nodes: Vec<usize> = snapshots.lock().unwrap().iter().map(|progress| progress.nodes).collect();
+        assert_eq!(nodes, (1..=state.stats.nodes / 2).map(|i| 2 * i).collect::<Vec<_>>());
+        assert!(snapshots.lock().unwrap().iter().all(|progress| progress.depth <= state.stats.max_depth));
+    }
+
+    #[test]
+    fn test_budget() {
+        // Pigeonhole: four pigeons in three holes, pigeon p in hole h is 3p + h + 1
+        let var = |p: i32, h: i32| 3 * p + h + 1;
+        let mut formula: Formula = (0..4).map(|p| (0..3).map(|h| var(p, h)).collect()).collect();
+        for h in 0..3 {
+            for p in 0..4 {
+                for q in p + 1..4 {
+                    formula.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        let run = |budget: Budget| {
+            let mut assignment = initial_assignment(&formula);
+            let simplified_formula = pure_literal_elimination(&formula, &mut assignment);
+            let root = Node::new(simplified_formula, None, 0, assignment);
+            let mut state = SearchState {
+                budget,
+                ..Default::default()
+            };
+            (run_search(root, &MinIndexHeuristic, &mut state), state.stats)
+        };
+        let (result, full) = run(Budget::default());
+        assert_eq!(result, SolveResult::Unsat);
+
+        let (result, stats) = run(Budget { max_nodes: Some(3), ..Default::default() });
+        assert_eq!((result, stats.nodes), (SolveResult::Unknown, 3));
+        let (result, stats) = run(Budget { max_conflicts: Some(1), ..Default::default() });
+        assert_eq!((result, stats.conflicts), (SolveResult::Unknown, 1));
+        let (result, stats) = run(Budget { max_decisions: Some(2), ..Default::default() });
+        assert_eq!((result, stats.decisions), (SolveResult::Unknown, 2));
+        // A budget the search fits in changes nothing
+        let (result, stats) = run(Budget { max_nodes: Some(full.nodes + 1), ..Default::default() });
+        assert_eq!((result, stats), (SolveResult::Unsat, full));
+    }
+
+    #[test]
+    fn test_seeded_random_polarity() {
+        let formula = read_cnf_file("72test.cnf");
+        let run = |seed: u64| {
+            let mut state = SearchState {
+                rng: SearchRng::seed_from_u64(seed),
+                random_polarity: 0.5,
+                ..Default::default()
+            };
+            let sat = solve_with_state(&formula, &MinIndexHeuristic, &mut state);
+            (sat, state.stats)
+        };
+        // The answer never depends on the seed, the path only does through it
+        let runs: Vec<_> = (0..4).map(run).collect();
+        assert!(runs.iter().all(|(sat, _)| !sat));
+        assert_eq!(run(2), runs[2]);
+        assert!(runs.iter().any(|(_, stats)| *stats != runs[0].1));
+    }
+
+    #[test]
+    fn test_phase_saving() {
+        // 1 = true forces -2, leaving all four clauses over 3 and 4
+        let formula = vec![
+            vec![-1, -2],
+            vec![2, 3, 4],
+            vec![2, -3, 4],
+            vec![2, 3, -4],
+            vec![2, -3, -4],
+            vec![1, 5, 6],
+            vec![1, -5, 6],
+        ];
+        let mut state = SearchState::new(true);
+        assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        // The true branch of 1 failed, so its last polarity is false
+        assert_eq!(state.phases.get(1), Some(false));
+
+        let mut state = SearchState::new(false);
+        assert!(solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        assert_eq!(state.phases.get(1), None);
+    }
 
+    #[test]
+    fn test_luby_restarts_keep_search_complete() {
+        // Pigeonhole: three pigeons (rows) in two holes (columns)
+        let formula = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![-1, -3],
+            vec![-1, -5],
+            vec![-3, -5],
+            vec![-2, -4],
+            vec![-2, -6],
+            vec![-4, -6],
+        ];
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+        assert!(!solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+        assert!(state.stats.restarts > 0);
 
+        let sat = vec![vec![1, 2], vec![-1, -2], vec![2, 3], vec![-3, -1]];
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+        assert!(solve_with_state(&sat, &MinIndexHeuristic, &mut state));
+    }
 
+    #[test]
+    fn test_glucose_restarts_keep_search_complete() {
+        let formula = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![-1, -3],
+            vec![-1, -5],
+            vec![-3, -5],
+            vec![-2, -4],
+            vec![-2, -6],
+            vec![-4, -6],
+        ];
+        let mut glucose = GlucoseRestarts::default();
+        glucose.window = 2;
+        glucose.margin = 1.0;
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(glucose));
+        assert!(!solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+    }
+
+    #[test]
+    fn test_vivification_during_search() {
+        let formula = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![-1, -3],
+            vec![-1, -5],
+            vec![-3, -5],
+            vec![-2, -4],
+            vec![-2, -6],
+            vec![-4, -6],
+        ];
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+        state.vivify_interval = Some(1);
+        assert!(!solve_with_state(&formula, &MinIndexHeuristic, &mut state));
+
+        let sat = vec![vec![1, 2, 3], vec![-1, 2], vec![-2, 3], vec![-3, -1], vec![1, 3, 4]];
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+        state.vivify_interval = Some(1);
+        assert!(solve_with_state(&sat, &MinIndexHeuristic, &mut state));
+    }
+
+    #[test]
+    fn test_inprocessing_during_search() {
+        let inprocessing = || Some(Inprocessor::new(InprocessConfig { interval: 1, ..Default::default() }));
+        let mut state = SearchState::new(true);
+        state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+        state.inprocessing = inprocessing();
+        assert!(!solve_with_state(&pigeonhole(4), &MinIndexHeuristic, &mut state));
+        assert!(state.stats.inprocessing_rounds > 0);
+
+        // Eliminated variables get their values back from the reconstruction
+        for seed in 0..5 {
+            let formula = random_ksat(30, 120, 3, seed);
+            let mut state = SearchState::new(true);
+            state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+            state.restart_policy = Some(Box::new(LubyRestarts::new(1)));
+            state.inprocessing = inprocessing();
+            let root = Node::new(formula.clone(), None, 0, initial_assignment(&formula));
+            if let SolveResult::Sat(model) = run_search(root, &MinIndexHeuristic, &mut state) {
+                assert_eq!(verify_model(&formula, &model), Ok(()));
+            }
+            assert_eq!(state.stats.inprocessing_rounds, state.stats.restarts);
+        }
+    }
+
+    #[test]
+    fn test_inprocessing_keeps_frozen_variables() {
+        // Eliminating 3 leaves 1 or 2 and not both, the rest goes as well
+        // unless frozen
+        let formula = vec![vec![1, 3], vec![-3, 2], vec![-1, -2]];
+        let mut state = SearchState::new(true);
+        state.inprocessing = Some(Inprocessor::new(InprocessConfig { interval: 1, ..Default::default() }));
+        state.frozen = HashMap::from([(1, 1), (2, 1)]);
+        let mut root = Node::new(formula.clone(), None, 0, initial_assignment(&formula));
+        assert!(inprocess(&mut root, &mut state));
+        let vars: HashSet<i32> = root.formula().iter().flatten().map(|lit| lit.abs()).collect();
+        assert_eq!(vars, HashSet::from([1, 2]));
+    }
 }
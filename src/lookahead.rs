@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::{simplify_formula, unit_propagation, Assignment, Formula, Interrupter, Model, SolveResult, Solver, Stats};
+
+// How much a look-ahead split tries
+#[derive(Clone, Copy, Debug)]
+pub struct LookAheadConfig {
+    // Variables tried per split, the ones occurring most
+    pub candidates: usize,
+    // Variables tried under each value of a candidate to find out whether
+    // the value is a failed literal after all, 0 for single look-ahead only
+    pub double_candidates: usize,
+}
+
+impl Default for LookAheadConfig {
+    fn default() -> Self {
+        LookAheadConfig {
+            candidates: 16,
+            double_candidates: 4,
+        }
+    }
+}
+
+// The clauses left under an assignment, along with it
+pub(crate) type Reduced = (Formula, Assignment);
+
+// The clauses under the assignment extended by lit and unit propagation, or
+// None if some clause ends up falsified or the assignment already has the
+// negation of lit
+fn look_ahead(formula: &Formula, assignment: &Assignment, lit: i32) -> Option<Reduced> {
+    match assignment.get(&lit.abs()) {
+        Some(&Some(value)) if value != (lit > 0) => return None,
+        _ => {}
+    }
+    let mut assignment = assignment.clone();
+    assignment.insert(lit.abs(), Some(lit > 0));
+    let formula = simplify_formula(formula, &mut assignment, |_| true);
+    let (result, formula, assignment) = unit_propagation(formula, &mut assignment);
+    let falsified = |lit: &i32| assignment.get(&lit.abs()) == Some(&Some(*lit < 0));
+    (result != 0 && !formula.iter().any(|clause| clause.iter().all(falsified))).then_some((formula, assignment))
+}
+
+// The unassigned variables occurring most in the formula, ties by the
+// smaller one. The clauses keep the literals the assignment falsifies, so
+// assigned variables are left out here.
+fn candidates(formula: &Formula, assignment: &Assignment, count: usize) -> Vec<i32> {
+    let mut occurrences: HashMap<i32, usize> = HashMap::new();
+    for lit in formula.iter().flatten().filter(|lit| assignment.get(&lit.abs()) == Some(&None)) {
+        *occurrences.entry(lit.abs()).or_default() += 1;
+    }
+    let mut candidates: Vec<(usize, i32)> = occurrences.into_iter().map(|(var, count)| (count, var)).collect();
+    candidates.sort_unstable_by_key(|&(count, var)| (std::cmp::Reverse(count), var));
+    candidates.into_iter().take(count).map(|(_, var)| var).collect()
+}
+
+// The look-ahead of lit, also None if some variable fails both ways under
+// it, trying double_candidates of them
+fn double_look_ahead(formula: &Formula, assignment: &Assignment, lit: i32, double_candidates: usize) -> Option<Reduced> {
+    let (formula, assignment) = look_ahead(formula, assignment, lit)?;
+    let refuted = candidates(&formula, &assignment, double_candidates).into_iter().any(|var| {
+        look_ahead(&formula, &assignment, var).is_none() && look_ahead(&formula, &assignment, -var).is_none()
+    });
+    (!refuted).then_some((formula, assignment))
+}
+
+pub(crate) enum Split {
+    // Both values of some variable fail, the formula has no model under the
+    // assignment
+    Refuted,
+    // One value of lit fails, so the assignment implies lit
+    Forced(i32, Formula, Assignment),
+    // The variable whose values simplify the clauses most, with the clauses
+    // under each
+    Branch(i32, [Reduced; 2]),
+    // Every clause left is satisfied
+    Satisfied,
+}
+
+// Look ahead on both values of the candidate variables, march-style. A value
+// whose look-ahead fails is a failed literal, otherwise the variable scores
+// the product of the clauses each value removes, which favours balance.
+// Without unassigned variables the assignment settles every clause.
+pub(crate) fn split(formula: &Formula, assignment: &Assignment, config: &LookAheadConfig) -> Split {
+    let mut best: Option<(usize, i32, [Reduced; 2])> = None;
+    for var in candidates(formula, assignment, config.candidates) {
+        let look = |lit| double_look_ahead(formula, assignment, lit, config.double_candidates);
+        match (look(var), look(-var)) {
+            (None, None) => return Split::Refuted,
+            (Some((formula, assignment)), None) => return Split::Forced(var, formula, assignment),
+            (None, Some((formula, assignment))) => return Split::Forced(-var, formula, assignment),
+            (Some(positive), Some(negative)) => {
+                let score = (formula.len() - positive.0.len() + 1) * (formula.len() - negative.0.len() + 1);
+                if best.as_ref().is_none_or(|(best, _, _)| score > *best) {
+                    best = Some((score, var, [positive, negative]));
+                }
+            }
+        }
+    }
+    let satisfied = |clause: &Vec<i32>| clause.iter().any(|&lit| assignment.get(&lit.abs()) == Some(&Some(lit > 0)));
+    match best {
+        Some((_, var, branches)) => Split::Branch(var, branches),
+        None if formula.iter().all(satisfied) => Split::Satisfied,
+        None => Split::Refuted,
+    }
+}
+
+// Complete depth-first search that splits every node by look-ahead, with
+// the variables left true in the model once no clause is. Unknown if stop,
+// polled with the counters so far at every node, returns true before it is
+// done.
+pub fn lookahead_search(
+    formula: &Formula,
+    assignment: Assignment,
+    config: &LookAheadConfig,
+    stats: &mut Stats,
+    stop: impl Fn(&Stats) -> bool,
+) -> SolveResult {
+    let mut stack = vec![(formula.clone(), assignment)];
+    while let Some((formula, assignment)) = stack.pop() {
+        if stop(stats) {
+            return SolveResult::Unknown;
+        }
+        stats.nodes += 1;
+        match split(&formula, &assignment, config) {
+            Split::Refuted => stats.conflicts += 1,
+            Split::Forced(_, formula, assignment) => {
+                stats.failed_literals += 1;
+                stack.push((formula, assignment));
+            }
+            Split::Branch(_, [positive, negative]) => {
+                stats.decisions += 1;
+                stack.push(negative);
+                stack.push(positive);
+            }
+            Split::Satisfied => {
+                let model: Model = assignment.iter().map(|(var, val)| (var, val != Some(false))).collect();
+                return SolveResult::Sat(model);
+            }
+        }
+    }
+    SolveResult::Unsat
+}
+
+impl Solver {
+    // Solve by look-ahead search alone, see lookahead_search. Unknown if the
+    // deadline passes, the budget runs out or the interrupter trips first, or
+    // if there are XOR or pseudo-Boolean constraints, which it does not
+    // propagate.
+    pub fn solve_lookahead(&mut self, config: &LookAheadConfig) -> SolveResult {
+        self.failed.clear();
+        self.model = None;
+        if !self.xors.is_empty() || !self.pbs.is_empty() {
+            return SolveResult::Unknown;
+        }
+        let (deadline, interrupter) = (self.state.deadline, self.state.interrupter.clone());
+        let (budget, start) = (self.state.budget, self.state.stats.clone());
+        let stop = |stats: &Stats| {
+            deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                || budget.exhausted(&start, stats)
+                || interrupter.as_ref().is_some_and(Interrupter::is_interrupted)
+        };
+        let assignment = self.initial_assignment();
+        match lookahead_search(&self.formula, assignment, config, &mut self.state.stats, stop) {
+            SolveResult::Sat(model) => self.found_without_search(model),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{initial_assignment, pigeonhole, random_ksat, verify_model, Budget};
+
+    fn solve(formula: &Formula, config: &LookAheadConfig) -> (SolveResult, Stats) {
+        let mut stats = Stats::default();
+        let result = lookahead_search(formula, initial_assignment(formula), config, &mut stats, |_| false);
+        (result, stats)
+    }
+
+    #[test]
+    fn test_lookahead_search() {
+        let formula = random_ksat(40, 150, 3, 1);
+        let mut solver = Solver::from_clauses(&formula);
+        let result = solver.solve_lookahead(&LookAheadConfig::default());
+        assert_eq!(verify_model(&formula, result.model().expect("Satisfiable")), Ok(()));
+
+        let (result, single) = solve(&pigeonhole(4), &LookAheadConfig { double_candidates: 0, ..Default::default() });
+        assert_eq!(result, SolveResult::Unsat);
+        let (result, double) = solve(&pigeonhole(4), &LookAheadConfig::default());
+        assert_eq!(result, SolveResult::Unsat);
+        // Double look-ahead refutes branches before they are taken
+        assert!(double.decisions < single.decisions);
+
+        let mut solver = Solver::from_clauses(&pigeonhole(4));
+        solver.interrupter().interrupt();
+        assert_eq!(solver.solve_lookahead(&LookAheadConfig::default()), SolveResult::Unknown);
+        assert_eq!(solver.stats().nodes, 0);
+
+        let mut solver = Solver::from_clauses(&pigeonhole(4));
+        solver.set_budget(Budget { max_nodes: Some(3), ..Default::default() });
+        assert_eq!(solver.solve_lookahead(&LookAheadConfig::default()), SolveResult::Unknown);
+        assert_eq!(solver.stats().nodes, 3);
+        solver.set_budget(Budget::default());
+        assert_eq!(solver.solve_lookahead(&LookAheadConfig::default()), SolveResult::Unsat);
+    }
+
+    #[test]
+    fn test_assigned_variables() {
+        // The variables of a tautology stay in the clauses once assigned,
+        // and splitting on them again never ended
+        let formula = vec![vec![-5, -6, -1, -4], vec![2, -2, -6, 3]];
+        let mut solver = Solver::from_clauses(&formula);
+        let result = solver.solve_lookahead(&LookAheadConfig::default());
+        assert_eq!(verify_model(&formula, result.model().expect("Satisfiable")), Ok(()));
+
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![2, 2, -3]];
+        let mut assignment = initial_assignment(&formula);
+        assignment.insert(1, Some(true));
+        assert!(look_ahead(&formula, &assignment, -1).is_none());
+        match split(&formula, &assignment, &LookAheadConfig::default()) {
+            Split::Forced(lit, ..) | Split::Branch(lit, _) => assert_ne!(lit.abs(), 1),
+            Split::Refuted | Split::Satisfied => panic!("3 or 2 is left to split on"),
+        }
+        // 1 and not 3 falsify a clause, with or without variables left
+        assignment.insert(3, Some(false));
+        assert!(matches!(split(&formula, &assignment, &LookAheadConfig::default()), Split::Refuted));
+        assignment.insert(2, Some(true));
+        assert!(matches!(split(&formula, &assignment, &LookAheadConfig::default()), Split::Refuted));
+        assignment.insert(3, Some(true));
+        assert!(matches!(split(&formula, &assignment, &LookAheadConfig::default()), Split::Satisfied));
+    }
+}
@@ -1,44 +1,845 @@
-mod lib;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+use clap::{Parser, Subcommand};
+use dpll::encoders::nqueens::{nqueens_with, solve_nqueens};
+use dpll::encoders::sudoku::Grid;
+use dpll::encodings::AmoEncoding;
+use dpll::*;
+use rand::SeedableRng;
 
-use std::rc::Rc;
-use std::time::Instant;
-use lib::*;
-use rayon::prelude::*;
+/// A DPLL SAT solver for DIMACS CNF files
+#[derive(Parser)]
+#[command(name = "dpll", version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// CNF file to solve, possibly compressed, standard input if it is "-" or
+    /// missing
+    path: Option<String>,
+
+    /// Solve by DPLL tree search, by look-ahead search, which splits every
+    /// node on the variable propagating both values simplifies most, both
+    /// complete, or by stochastic local search, which can only find models
+    #[arg(long, default_value = "dpll", value_parser = ["dpll", "lookahead", "sls"])]
+    engine: String,
+
+    /// Local search of --engine sls
+    #[arg(long, default_value = "probsat", value_parser = ["probsat", "walksat"])]
+    sls: String,
+
+    /// Flips --engine sls takes before giving up
+    #[arg(long, value_name = "FLIPS", default_value_t = 10_000_000)]
+    flips: usize,
+
+    /// Noise of --engine sls: the random walk probability of walksat or the
+    /// break base cb of probsat
+    #[arg(long)]
+    noise: Option<f64>,
+
+    /// Branching heuristic: min-index, dlis, jw-one-sided, jw-two-sided, moms or moms:<k>
+    #[arg(long, default_value = "min-index", value_parser = parse_heuristic)]
+    heuristic: String,
+
+    /// Branch on the last value of every variable first
+    #[arg(long)]
+    phase_saving: bool,
+
+    /// Switch the saved phases between the best assignment, random and original ones, first after this many
+    /// conflicts and then further apart each time; turns on phase saving
+    #[arg(long, value_name = "CONFLICTS")]
+    rephase: Option<usize>,
+
+    /// Run WalkSAT for at most this many flips before the search, which starts from its best assignment
+    #[arg(long, value_name = "FLIPS")]
+    walksat: Option<usize>,
+
+    /// Flip branching literals with this probability
+    #[arg(long, value_name = "P", default_value_t = 0.0)]
+    random_polarity: f64,
+
+    /// Seed of every random choice, so runs are reproducible
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Luby restarts with this many conflicts per unit of the sequence
+    #[arg(long, value_name = "CONFLICTS")]
+    restart_unit: Option<usize>,
+
+    /// Glucose-style restarts on the recent conflict quality
+    #[arg(long)]
+    glucose_restarts: bool,
+
+    /// Learn conflict clauses
+    #[arg(long)]
+    learn: bool,
+
+    /// Backtrack chronologically instead of jumping back over at most this many branches
+    #[arg(long, value_name = "BRANCHES", default_value_t = 0)]
+    chrono: usize,
+
+    /// Bounded variable elimination up to this occurrence product
+    #[arg(long, value_name = "MAX_PRODUCT")]
+    bve: Option<usize>,
+
+    /// Probe at most this many failed literals
+    #[arg(long, value_name = "MAX_PROBES")]
+    probe: Option<usize>,
+
+    /// Vivify the clauses before the search
+    #[arg(long)]
+    vivify: bool,
+
+    /// Vivify again every this many restarts
+    #[arg(long, value_name = "RESTARTS")]
+    vivify_interval: Option<usize>,
+
+    /// Every this many restarts subsume, probe, vivify and eliminate
+    /// variables again, each within a bounded effort
+    #[arg(long, value_name = "RESTARTS")]
+    inprocess: Option<usize>,
+
+    /// Eliminate blocked clauses
+    #[arg(long)]
+    bce: bool,
+
+    /// Substitute equivalent literals
+    #[arg(long)]
+    equivalences: bool,
+
+    /// Add clauses breaking symmetries of the formula
+    #[arg(long)]
+    symmetry: bool,
+
+    /// Eliminate autarkies
+    #[arg(long)]
+    autarky: bool,
+
+    /// Renumber the variables densely before solving, models are still
+    /// printed in the original numbering. The formula --write-cnf writes is
+    /// in the dense one.
+    #[arg(long)]
+    compact: bool,
+
+    /// Write the preprocessed formula to this file as DIMACS CNF
+    #[arg(long, value_name = "FILE")]
+    write_cnf: Option<String>,
+
+    /// Write a refutation to this file
+    #[arg(long, value_name = "FILE")]
+    proof: Option<String>,
+
+    /// Format of the refutation
+    #[arg(long, value_name = "FORMAT", default_value = "drat", value_parser = ["drat", "lrat"])]
+    proof_format: String,
+
+    /// Report progress on stderr every this many nodes
+    #[arg(long, value_name = "NODES")]
+    progress: Option<usize>,
+
+    /// Race this many solver configurations on parallel threads, the first
+    /// one as the other options describe
+    #[arg(long, value_name = "SOLVERS")]
+    portfolio: Option<usize>,
+
+    /// Split the formula into up to this many cubes by look-ahead and solve
+    /// them on a thread per core
+    #[arg(long, value_name = "CUBES", conflicts_with = "portfolio")]
+    cubes: Option<usize>,
+
+    /// Search on this many threads that steal pending branches from each
+    /// other
+    #[arg(long, value_name = "THREADS", conflicts_with_all = ["portfolio", "cubes"])]
+    threads: Option<usize>,
+
+    /// Keep the learned clauses of every parallel worker to itself instead
+    /// of passing the short ones on to the others
+    #[arg(long)]
+    no_sharing: bool,
+
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// Save the search to this file every --checkpoint-interval seconds
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<String>,
+
+    /// Seconds between two saves of the search
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "SECONDS", default_value_t = 60.0)]
+    checkpoint_interval: f64,
+
+    /// Go on with the search saved in the --checkpoint file, if there is one
+    #[cfg(feature = "serde")]
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// Reject input that deviates from DIMACS instead of warning about it
+    #[arg(long)]
+    strict: bool,
+
+    /// Print search statistics
+    #[arg(long)]
+    stats: bool,
+
+    /// Print only the answer
+    #[arg(long, short)]
+    quiet: bool,
+
+    /// Log the search on stderr, -v for decisions, conflicts and restarts,
+    /// -vv for every node as well
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Print the time spent parsing, preprocessing, searching and between
+    /// restarts on stderr
+    #[cfg(feature = "tracing")]
+    #[arg(long)]
+    trace: bool,
+
+    /// Print the answer as DIMACS solver output or as one JSON object
+    #[arg(long, value_name = "FORMAT", default_value = "dimacs", value_parser = ["dimacs", "json"])]
+    output: String,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the sizes, clause lengths, occurrence balance, unit and pure
+    /// literals of a CNF file
+    Analyze { cnf: String },
+    /// Verify a DRAT refutation of a CNF file
+    Check { cnf: String, drat: String },
+    /// Print the exact number of models of a CNF file
+    Count { cnf: String },
+    /// Solve a MaxSAT instance in WCNF or CNF
+    Maxsat {
+        path: String,
+        /// Bound the weight of the violated soft clauses from above, from
+        /// below or by binary search, or relax unsatisfiable cores over the
+        /// soft clauses, RC2-style
+        #[arg(long, default_value = "linear", value_parser = ["linear", "unsat-sat", "binary", "core"])]
+        algorithm: String,
+    },
+    /// Print a minimal unsatisfiable subset of a CNF file, or of the groups
+    /// of a group-CNF file
+    Mus { cnf: String },
+    /// Decide a quantified Boolean formula in QDIMACS
+    Qbf { path: String },
+    /// Solve a pseudo-Boolean instance in OPB
+    Opb { path: String },
+    /// Solve a Sudoku grid of any square box size, given row by row with
+    /// '.' or 0 for empty cells
+    Sudoku {
+        path: String,
+        /// Encoding of the at-most-one constraints
+        #[arg(long, default_value = "pairwise", value_parser = ["pairwise", "ladder", "commander", "bimander"])]
+        encoding: String,
+        /// Write the CNF encoding to standard output instead of solving
+        #[arg(long)]
+        cnf: bool,
+    },
+    /// Place n queens on an n by n board so that none attacks another
+    Nqueens {
+        n: usize,
+        /// Encoding of the at-most-one constraints
+        #[arg(long, default_value = "pairwise", value_parser = ["pairwise", "ladder", "commander", "bimander"])]
+        encoding: String,
+        /// Write the CNF encoding to standard output instead of solving
+        #[arg(long)]
+        cnf: bool,
+    },
+    /// Write a uniform random k-SAT instance as DIMACS CNF to standard output
+    Gen {
+        num_vars: usize,
+        num_clauses: usize,
+        /// Literals per clause
+        #[arg(short, default_value_t = 3)]
+        k: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+}
+
+// Accept the names heuristic_by_name knows
+fn parse_heuristic(name: &str) -> Result<String, String> {
+    match heuristic_by_name(name) {
+        Some(_) => Ok(name.to_string()),
+        None => Err(String::from("expected min-index, dlis, jw-one-sided, jw-two-sided, moms or moms:<k>")),
+    }
+}
+
+// Verify a DRAT refutation of a CNF file and exit with 0 if it holds
+fn check_proof(cnf_path: &str, proof_path: &str) -> ! {
+    let formula = read_cnf_file(cnf_path);
+    let text = match std::fs::read_to_string(proof_path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("Failed to read proof file {}: {}", proof_path, error);
+            std::process::exit(1);
+        }
+    };
+    match parse_drat(&text).and_then(|steps| check_drat(&formula, &steps)) {
+        Ok(()) => {
+            println!("s VERIFIED");
+            std::process::exit(0);
+        }
+        Err(error) => {
+            println!("s NOT VERIFIED: {}", error);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Print the measures of a CNF file, see FormulaStats
+fn analyze_file(cnf_path: &str) -> ! {
+    let formula = read_cnf_file(cnf_path);
+    println!("{}", analyze(&formula));
+    std::process::exit(0);
+}
+
+// Print the exact number of models of a CNF file
+fn count(cnf_path: &str) -> ! {
+    let formula = read_cnf_file(cnf_path);
+    println!("s mc {}", count_models(&formula));
+    std::process::exit(0);
+}
+
+// Solve a MaxSAT instance in WCNF or CNF and print the optimum in the
+// MaxSAT Evaluation format: the cost on an o line, the status, and the
+// value of every variable as a string of 0s and 1s on the v line
+fn maxsat(path: &str, algorithm: &str) -> ! {
+    let wcnf = match read_wcnf_file(path) {
+        Ok(wcnf) => wcnf,
+        Err(error) => {
+            eprintln!("Invalid WCNF: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let strategy = match algorithm {
+        "unsat-sat" => MaxSatStrategy::UnsatSat,
+        "binary" => MaxSatStrategy::Binary,
+        "core" => MaxSatStrategy::CoreGuided,
+        _ => MaxSatStrategy::SatUnsat,
+    };
+    let Some(result) = solve_wcnf_with(&wcnf, strategy) else {
+        println!("s UNSATISFIABLE");
+        std::process::exit(20);
+    };
+    println!("o {}", result.cost);
+    println!("s OPTIMUM FOUND");
+    let num_vars = result.model.keys().copied().max().unwrap_or(0);
+    let values: String = (1..=num_vars).map(|var| if result.model[&var] { '1' } else { '0' }).collect();
+    println!("v {}", values);
+    std::process::exit(30);
+}
+
+// Print a minimal unsatisfiable subset of a CNF file, as 1-based clause
+// numbers on the v line, or of the groups of a group-CNF file, as group
+// numbers
+fn mus(cnf_path: &str) -> ! {
+    let formula = read_gcnf_file(cnf_path);
+    let Some(mus) = formula.find_mus() else {
+        println!("s SATISFIABLE");
+        std::process::exit(10);
+    };
+    println!("s UNSATISFIABLE");
+    println!("v {} 0", mus.join(" "));
+    std::process::exit(20);
+}
+
+// Decide a QDIMACS file and print the answer as in QBFEVAL, "s cnf 1" and
+// exit code 10 if the formula is true, "s cnf 0" and 20 if it is false
+fn qbf(path: &str) -> ! {
+    let qbf = match parse_qdimacs(open_input(path), ParseMode::Lenient) {
+        Ok(qbf) => qbf,
+        Err(error) => {
+            eprintln!("Invalid QDIMACS: {}", error);
+            std::process::exit(1);
+        }
+    };
+    if solve_qbf(&qbf) {
+        println!("s cnf 1");
+        std::process::exit(10);
+    }
+    println!("s cnf 0");
+    std::process::exit(20);
+}
+
+// Write a random k-SAT instance to standard output
+fn generate(num_vars: usize, num_clauses: usize, k: usize, seed: u64) -> ! {
+    if k > num_vars {
+        eprintln!("Clauses of {} distinct variables need at least as many variables", k);
+        std::process::exit(2);
+    }
+    let formula = random_ksat(num_vars, num_clauses, k, seed);
+    println!("c random {}-SAT with seed {}", k, seed);
+    write_cnf_with_vars(&formula, num_vars, io::stdout().lock()).expect("Failed to write CNF");
+    std::process::exit(0);
+}
+
+// Solve an OPB file and print the answer in the format of the
+// pseudo-Boolean competition: the objective on an o line, the status, and
+// the model as xN and -xN on the v line
+fn opb(path: &str) -> ! {
+    let text = io::read_to_string(open_input(path)).expect("Failed to read OPB file");
+    let opb = match parse_opb(&text) {
+        Ok(opb) => opb,
+        Err(error) => {
+            eprintln!("Invalid OPB: {}", error);
+            std::process::exit(1);
+        }
+    };
+    // Without an objective the search propagates the constraints natively
+    let model = if opb.objective.is_none() {
+        let mut solver = Solver::new();
+        for constraint in opb.constraints.iter() {
+            solver.add_pb(&constraint.terms, constraint.relation, constraint.rhs);
+        }
+        if !solver.solve() {
+            println!("s UNSATISFIABLE");
+            std::process::exit(20);
+        }
+        println!("s SATISFIABLE");
+        solver.model().cloned().unwrap_or_default()
+    } else {
+        let (wcnf, offset) = opb_to_wcnf(&opb);
+        let Some(result) = solve_wcnf(&wcnf) else {
+            println!("s UNSATISFIABLE");
+            std::process::exit(20);
+        };
+        println!("o {}", result.cost as i64 + offset);
+        println!("s OPTIMUM FOUND");
+        result.model
+    };
+    let values: Vec<String> = (1..=opb.num_vars)
+        .map(|var| if model.get(&var) == Some(&true) { format!("x{}", var) } else { format!("-x{}", var) })
+        .collect();
+    println!("v {}", values.join(" "));
+    std::process::exit(if opb.objective.is_some() { 30 } else { 10 });
+}
+
+// Solve a Sudoku grid and print the completed grid after the status, exit
+// code 10 if it has a completion and 20 if not
+fn sudoku(path: &str, encoding: &str, cnf: bool) -> ! {
+    let text = io::read_to_string(open_input(path)).expect("Failed to read grid file");
+    let grid = match Grid::parse(&text) {
+        Ok(grid) => grid,
+        Err(error) => {
+            eprintln!("Invalid grid: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let encoding = AmoEncoding::from_name(encoding).unwrap_or(AmoEncoding::Pairwise);
+    if cnf {
+        println!("c sudoku {}x{}", grid.size, grid.size);
+        write_cnf(&grid.encode(encoding), io::stdout().lock()).expect("Failed to write CNF");
+        std::process::exit(0);
+    }
+    let Some(solution) = grid.solve(encoding) else {
+        println!("s UNSATISFIABLE");
+        std::process::exit(20);
+    };
+    println!("s SATISFIABLE");
+    println!("{}", solution);
+    std::process::exit(10);
+}
+
+// Place n queens and print the board after the status, exit code 10 if
+// there is a placement and 20 if not
+fn queens(n: usize, encoding: &str, cnf: bool) -> ! {
+    let encoding = AmoEncoding::from_name(encoding).unwrap_or(AmoEncoding::Pairwise);
+    if cnf {
+        println!("c {} queens", n);
+        write_cnf(&nqueens_with(n, encoding), io::stdout().lock()).expect("Failed to write CNF");
+        std::process::exit(0);
+    }
+    let Some(board) = solve_nqueens(n, encoding) else {
+        println!("s UNSATISFIABLE");
+        std::process::exit(20);
+    };
+    println!("s SATISFIABLE");
+    println!("{}", board);
+    std::process::exit(10);
+}
+
+// Log to stderr as DIMACS comments, at the level of the -v flags or as
+// RUST_LOG says
+fn init_logger(verbose: u8) {
+    let level = match verbose {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .format(|buf, record| writeln!(buf, "c {} {}", record.level(), record.args()))
+        .init();
+}
+
+// Print the answer with the exit code, the model of a satisfiable one, the
+// search statistics and the solving time as one JSON object
+fn print_json(exit_code: i32, model: Option<&Model>, stats: &Stats, elapsed_ms: f64) {
+    let status = match exit_code {
+        10 => "sat",
+        20 => "unsat",
+        _ => "unknown",
+    };
+    let model = model.map(|model| {
+        let mut vars: Vec<(&i32, &bool)> = model.iter().collect();
+        vars.sort();
+        let entries = vars.into_iter().map(|(var, &val)| (var.to_string(), serde_json::Value::Bool(val)));
+        entries.collect::<serde_json::Map<_, _>>()
+    });
+    let output = serde_json::json!({
+        "status": status,
+        "model": model,
+        "stats": {
+            "fragment": stats.fragment.map(|fragment| fragment.to_string()),
+            "nodes": stats.nodes,
+            "decisions": stats.decisions,
+            "propagations": stats.propagations,
+            "conflicts": stats.conflicts,
+            "simplifications": stats.simplifications,
+            "restarts": stats.restarts,
+            "max_depth": stats.max_depth,
+            "max_tasklist": stats.max_tasklist,
+            "minimized_literals": stats.minimized_literals,
+            "rephases": stats.rephases,
+            "failed_literals": stats.failed_literals,
+            "flips": stats.flips,
+            "backjumps": stats.backjumps,
+            "chronological_backtracks": stats.chronological_backtracks,
+            "garbage_collections": stats.garbage_collections,
+            "reclaimed_bytes": stats.reclaimed_bytes,
+            "inprocessing_rounds": stats.inprocessing_rounds,
+        },
+        "time_ms": elapsed_ms,
+    });
+    println!("{}", output);
+}
+
+// Print the model as v lines of signed literals in variable order, ten to a
+// line, terminated by 0
+fn print_model(model: &Model) {
+    let mut lits: Vec<i32> = model.iter().map(|(&var, &val)| if val { var } else { -var }).collect();
+    lits.sort_unstable_by_key(|lit| lit.abs());
+    for chunk in lits.chunks(10) {
+        let chunk: Vec<String> = chunk.iter().map(i32::to_string).collect();
+        println!("v {}", chunk.join(" "));
+    }
+    println!("v 0");
+}
 
 fn main() {
-    let path = "700.cnf";
-    let formula = read_cnf_file(path);
-    let mut assignment = initial_assignment(&formula);
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Analyze { cnf }) => analyze_file(&cnf),
+        Some(Command::Check { cnf, drat }) => check_proof(&cnf, &drat),
+        Some(Command::Count { cnf }) => count(&cnf),
+        Some(Command::Maxsat { path, algorithm }) => maxsat(&path, &algorithm),
+        Some(Command::Mus { cnf }) => mus(&cnf),
+        Some(Command::Qbf { path }) => qbf(&path),
+        Some(Command::Opb { path }) => opb(&path),
+        Some(Command::Sudoku { path, encoding, cnf }) => sudoku(&path, &encoding, cnf),
+        Some(Command::Nqueens { n, encoding, cnf }) => queens(n, &encoding, cnf),
+        Some(Command::Gen { num_vars, num_clauses, k, seed }) => generate(num_vars, num_clauses, k, seed),
+        None => {}
+    }
+    init_logger(cli.verbose);
+    #[cfg(feature = "tracing")]
+    if cli.trace {
+        tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .init();
+    }
+    let path = cli.path.as_deref().unwrap_or("-");
+    let json = cli.output == "json";
+    let heuristic = heuristic_by_name(&cli.heuristic).unwrap_or_else(|| Box::new(MinIndexHeuristic));
+    let mut state = SearchState::new(cli.phase_saving);
+    if let Some(unit) = cli.restart_unit {
+        state.restart_policy = Some(Box::new(LubyRestarts::new(unit)));
+    }
+    if cli.glucose_restarts {
+        state.restart_policy = Some(Box::new(GlucoseRestarts::default()));
+    }
+    if let Some(interval) = cli.rephase {
+        state.phases.enabled = true;
+        state.rephasing = Some(Rephasing::new(interval.max(1)));
+    }
+    state.walksat = cli.walksat.map(|max_flips| WalkSatConfig {
+        max_flips,
+        ..Default::default()
+    });
+    state.random_polarity = cli.random_polarity;
+    state.rng = SearchRng::seed_from_u64(cli.seed);
+    if cli.learn {
+        state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+    }
+    state.chrono_limit = cli.chrono;
+    let mode = if cli.strict { ParseMode::Strict } else { ParseMode::Lenient };
+    let dimacs = match parse_dimacs(open_input(path), mode) {
+        Ok(dimacs) => dimacs,
+        Err(error) => {
+            eprintln!("Invalid DIMACS: {}", error);
+            std::process::exit(1);
+        }
+    };
+    for warning in dimacs.warnings.iter() {
+        eprintln!("c warning: {}", warning);
+    }
+    let original = dimacs.formula;
+    let xors = dimacs.xors;
+    // Eliminating variables and writing proofs only account for the clauses
+    let clauses_only = xors.is_empty();
+    let eliminating = cli.bve.is_some() || cli.bce || cli.autarky || cli.equivalences || cli.symmetry;
+    if !clauses_only && (eliminating || cli.proof.is_some()) {
+        eprintln!(
+            "c warning: ignoring --bve, --bce, --autarky, --equivalences, --symmetry and --proof with XOR constraints"
+        );
+    }
+    // Breaking clauses are not implied by the formula
+    if cli.symmetry && cli.proof.is_some() {
+        eprintln!("c warning: ignoring --symmetry with --proof");
+    }
+    let parallel = cli.portfolio.is_some() || cli.cubes.is_some() || cli.threads.is_some();
+    if parallel && cli.proof.is_some() {
+        eprintln!("c warning: ignoring --proof with --portfolio, --cubes and --threads");
+    }
+    let local = cli.engine == "sls";
+    let lookahead = cli.engine == "lookahead";
+    if (local || lookahead) && (parallel || cli.proof.is_some()) {
+        eprintln!("c warning: ignoring --portfolio, --cubes, --threads and --proof with --engine {}", cli.engine);
+    }
+    if (local || lookahead) && !clauses_only {
+        eprintln!("c warning: --engine {} cannot handle XOR constraints, the answer is unknown", cli.engine);
+    }
+    // A refutation has to name the variables of the file
+    if cli.compact && cli.proof.is_some() {
+        eprintln!("c warning: ignoring --compact with --proof");
+    }
+    let renumbering = (cli.compact && cli.proof.is_none()).then(|| {
+        let vars = original.iter().flatten().chain(xors.iter().flat_map(|xor| xor.vars.iter()));
+        Renumbering::of_vars(vars.copied())
+    });
+    #[cfg(feature = "tracing")]
+    let preprocess_span = tracing::info_span!("preprocess").entered();
+    let mut formula = match renumbering.as_ref() {
+        Some(renumbering) => renumbering.apply(&original),
+        None => original.clone(),
+    };
+    let mut proof = cli.proof.as_deref().filter(|_| clauses_only && !parallel && !local && !lookahead).map(|path| {
+        let format = ProofFormat::from_name(&cli.proof_format).unwrap_or(ProofFormat::Drat);
+        let file = File::create(path).expect("Failed to create proof file");
+        Proof::new(format, Box::new(BufWriter::new(file)), &formula)
+    });
+    preprocess::subsume(&mut formula);
+    preprocess::self_subsume(&mut formula, proof.as_mut());
+    if cli.equivalences && clauses_only {
+        let keep = |var| state.frozen.contains_key(&var);
+        preprocess::substitute_equivalences(&mut formula, &mut state.reconstruction, keep, proof.as_mut());
+    }
+    if cli.symmetry && clauses_only && proof.is_none() {
+        let mut next_var = formula.iter().flatten().map(|lit| lit.abs()).max().unwrap_or(0).max(dimacs.num_vars as i32);
+        break_symmetries(&mut formula, &SymmetryConfig::default(), &mut next_var);
+    }
+    if let Some(max_product) = cli.bve.filter(|_| clauses_only) {
+        let keep = |var| state.frozen.contains_key(&var);
+        preprocess::eliminate_variables(&mut formula, &mut state.reconstruction, max_product, keep, proof.as_mut());
+    }
+    if let Some(max_probes) = cli.probe {
+        let config = preprocess::ProbeConfig {
+            max_probes,
+            ..Default::default()
+        };
+        preprocess::probe_failed_literals(&mut formula, &config, proof.as_mut());
+    }
+    if cli.vivify {
+        preprocess::vivify(&mut formula, &preprocess::VivifyConfig::default(), proof.as_mut());
+    }
+    state.vivify_interval = cli.vivify_interval;
+    state.inprocessing = cli.inprocess.map(|interval| {
+        Inprocessor::new(InprocessConfig {
+            interval,
+            ..Default::default()
+        })
+    });
+    if cli.bce && clauses_only {
+        let keep = |var| state.frozen.contains_key(&var);
+        preprocess::eliminate_blocked(&mut formula, &mut state.reconstruction, keep);
+    }
+    if cli.autarky && clauses_only {
+        let keep = |var| state.frozen.contains_key(&var);
+        preprocess::eliminate_autarkies(&mut formula, &mut state.reconstruction, keep);
+    }
+    #[cfg(feature = "tracing")]
+    drop(preprocess_span);
+    if let Some(path) = cli.write_cnf.as_deref() {
+        let file = File::create(path).expect("Failed to create CNF file");
+        write_cnf(&formula, BufWriter::new(file)).expect("Failed to write CNF file");
+    }
+    state.proof = proof;
+    let mut solver = Solver::from_clauses(&formula);
+    // Size the assignment for every variable the header declares
+    let num_vars = renumbering.as_ref().map_or(dimacs.num_vars as i32, Renumbering::num_vars);
+    while solver.num_vars() < num_vars {
+        solver.new_var();
+    }
+    for xor in xors.iter() {
+        match renumbering.as_ref() {
+            Some(renumbering) => solver.add_xor(&renumbering.apply(std::slice::from_ref(&xor.vars))[0], xor.parity),
+            None => solver.add_xor(&xor.vars, xor.parity),
+        }
+    }
+    solver.set_heuristic(heuristic);
+    solver.state = state;
+    if cli.no_sharing {
+        solver.set_clause_sharing(None);
+    }
+    #[cfg(feature = "serde")]
+    if let Some(path) = cli.checkpoint.as_deref() {
+        if parallel {
+            eprintln!("c warning: ignoring --checkpoint with --portfolio, --cubes and --threads");
+        }
+        if cli.resume && std::path::Path::new(path).exists() {
+            if let Err(error) = solver.resume(path) {
+                eprintln!("Cannot resume from {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+        solver.checkpoint(path, Duration::from_secs_f64(cli.checkpoint_interval));
+    }
+    if let Some(interval) = cli.progress {
+        solver.set_progress_callback(interval, |progress| {
+            eprintln!(
+                "c nodes {} depth {} clauses {} elapsed {:.3}s",
+                progress.nodes,
+                progress.depth,
+                progress.clauses,
+                progress.elapsed.as_secs_f64()
+            );
+        });
+    }
+
+    // Ctrl-C stops the search, which still reports what it did
+    let interrupter = solver.interrupter();
+    ctrlc::set_handler(move || interrupter.interrupt()).expect("Failed to set the Ctrl-C handler");
 
     // Start the timer
     let start_time = Instant::now();
 
-    let simplified_formula = pure_literal_elimination(&formula, &mut assignment);
-    let root = Rc::new(Node::new(
-        simplified_formula,
-        None,
-        0,
-        assignment.clone(),
-    ));
-    let mut tasklist: Vec<Rc<Node>> = Vec::new();
-    tasklist.push(root);
-    let mut flag = false;
-    while !tasklist.is_empty() {
-        let node = get_task(&mut tasklist).unwrap();
-        let c = build_search_tree(node.clone(), &mut tasklist);
-        if c {
-            flag = true;
-            break;
+    let timeout = cli.timeout.map(Duration::from_secs_f64).unwrap_or(Duration::MAX);
+    // The configuration the options describe, for the parallel searches
+    let config = SolverConfig {
+        heuristic: cli.heuristic.clone(),
+        phase_saving: cli.phase_saving,
+        random_polarity: cli.random_polarity,
+        seed: cli.seed,
+        learn: cli.learn,
+        restart_unit: cli.restart_unit,
+    };
+    let result = match (cli.portfolio, cli.cubes, cli.threads) {
+        _ if lookahead => {
+            solver.state.deadline = Instant::now().checked_add(timeout);
+            solver.solve_lookahead(&LookAheadConfig::default())
+        }
+        _ if local => {
+            let mut config = LocalSearchConfig::from_name(&cli.sls).expect("Unknown local search");
+            config.set_max_flips(cli.flips);
+            if let Some(noise) = cli.noise {
+                config.set_noise(noise);
+            }
+            solver.state.deadline = Instant::now().checked_add(timeout);
+            solver.solve_local(&config)
+        }
+        (Some(solvers), _, _) => {
+            let mut configs = SolverConfig::diverse(solvers.max(1));
+            configs[0] = config;
+            solver.state.deadline = Instant::now().checked_add(timeout);
+            let (result, winner) = solver.solve_portfolio(&configs);
+            if let Some(i) = winner.filter(|_| !cli.quiet && !json) {
+                println!("c Portfolio winner: {} ({:?})", i, configs[i]);
+            }
+            result
+        }
+        (None, Some(max_cubes), _) => {
+            let workers = std::thread::available_parallelism().map_or(1, usize::from);
+            solver.state.deadline = Instant::now().checked_add(timeout);
+            solver.solve_cubes(max_cubes, workers)
+        }
+        (None, None, Some(threads)) => {
+            solver.state.deadline = Instant::now().checked_add(timeout);
+            solver.solve_parallel(&config, threads)
         }
+        (None, None, None) => solver.solve_with_timeout(timeout),
+    };
+    #[cfg(feature = "serde")]
+    if let Some(error) = solver.checkpoint_error() {
+        eprintln!("c warning: failed to save the search: {}", error);
     }
-    if flag == false {
-        println!("UNSATISFIED");
+    // Exit codes of the SAT competition: 10 satisfiable, 20 unsatisfiable,
+    // 0 unknown
+    let (exit_code, model) = match result {
+        SolveResult::Sat(mut model) => {
+            if let Some(renumbering) = renumbering.as_ref() {
+                model = renumbering.restore(&model);
+            }
+            // Variables that preprocessing removed along with every clause
+            // they constrained can take any value, as can the ones that
+            // never occur
+            for var in 1..=dimacs.num_vars as i32 {
+                model.entry(var).or_insert(true);
+            }
+            // Without the variables of the symmetry breaking clauses
+            if cli.symmetry {
+                let last = original.iter().flatten().map(|lit| lit.abs()).max().unwrap_or(0);
+                model.retain(|&var, _| var <= last.max(dimacs.num_vars as i32));
+            }
+            if cfg!(debug_assertions) {
+                if let Err(unsatisfied) = verify_model(&original, &model) {
+                    panic!("Wrong model: {}", unsatisfied);
+                }
+                assert!(xors.iter().all(|xor| xor.is_satisfied(&model)), "Wrong model for an XOR constraint");
+            }
+            (10, Some(model))
+        }
+        SolveResult::Unsat => (20, None),
+        SolveResult::Unknown => (0, None),
+    };
+    if let Some(Err(error)) = solver.state.proof.as_mut().map(Proof::flush) {
+        eprintln!("c warning: the proof is incomplete, {}", error);
     }
 
     // Stop the timer
     let end_time = Instant::now();
-
     let elapsed_time = end_time.duration_since(start_time).as_secs_f64() * 1000.0;
-    println!("Elapsed time: {:.3} milliseconds", elapsed_time);
 
-}
\ No newline at end of file
+    if json {
+        print_json(exit_code, model.as_ref(), solver.stats(), elapsed_time);
+        std::process::exit(exit_code);
+    }
+    match model.as_ref() {
+        Some(model) => {
+            println!("s SATISFIABLE");
+            if !cli.quiet {
+                print_model(model);
+            }
+        }
+        None if exit_code == 20 => println!("s UNSATISFIABLE"),
+        None => println!("s UNKNOWN"),
+    }
+    if !cli.quiet {
+        println!("c Elapsed time: {:.3} milliseconds", elapsed_time);
+        let interrupted = solver.state.interrupter.as_ref().is_some_and(Interrupter::is_interrupted);
+        if interrupted || cli.stats {
+            for line in solver.stats().to_string().lines() {
+                println!("c {}", line);
+            }
+        }
+    }
+    std::process::exit(exit_code);
+}
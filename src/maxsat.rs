@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::encodings::{at_most_weighted, Totalizer};
+use crate::{Formula, Solver};
+
+// A weighted partial MaxSAT instance: clauses every solution satisfies, and
+// weighted clauses whose violation costs their weight
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Wcnf {
+    pub hard: Formula,
+    pub soft: Vec<(u64, Vec<i32>)>,
+}
+
+impl Wcnf {
+    // Plain MaxSAT: every clause is soft with weight 1
+    pub fn from_clauses(formula: &[Vec<i32>]) -> Self {
+        Wcnf {
+            hard: Vec::new(),
+            soft: formula.iter().map(|clause| (1, clause.clone())).collect(),
+        }
+    }
+
+    fn num_vars(&self) -> i32 {
+        let soft = self.soft.iter().flat_map(|(_, clause)| clause.iter());
+        self.hard.iter().flatten().chain(soft).map(|lit| lit.abs()).max().unwrap_or(0)
+    }
+}
+
+// A line of a WCNF file that does not parse, numbered from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WcnfError {
+    // A "p" line that is neither "p wcnf <vars> <clauses> <top>" nor "p cnf"
+    InvalidHeader(usize),
+    InvalidWeight(usize, String),
+    InvalidLiteral(usize, String),
+}
+
+impl fmt::Display for WcnfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WcnfError::InvalidHeader(line) => write!(f, "line {}: invalid problem line", line),
+            WcnfError::InvalidWeight(line, token) => write!(f, "line {}: invalid weight {:?}", line, token),
+            WcnfError::InvalidLiteral(line, token) => write!(f, "line {}: invalid literal {:?}", line, token),
+        }
+    }
+}
+
+// Parse a WCNF file in either format. The old format has a "p wcnf <vars>
+// <clauses> <top>" line and starts each clause with its weight, a weight of
+// at least top marks a hard clause. The new format has no problem line and
+// starts hard clauses with "h". A "p cnf" file makes every clause soft with
+// weight 1.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn parse_wcnf(text: &str) -> Result<Wcnf, WcnfError> {
+    let mut wcnf = Wcnf::default();
+    let mut top = None;
+    let mut weighted = true;
+    for (number, line) in text.lines().enumerate() {
+        let number = number + 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() || tokens[0] == "c" {
+            continue;
+        }
+        if tokens[0] == "p" {
+            match tokens.get(1).copied() {
+                Some("wcnf") => {
+                    top = match tokens.get(4) {
+                        Some(top) => Some(top.parse::<u64>().map_err(|_| WcnfError::InvalidHeader(number))?),
+                        None => None,
+                    }
+                }
+                Some("cnf") => weighted = false,
+                _ => return Err(WcnfError::InvalidHeader(number)),
+            }
+            continue;
+        }
+        let (weight, lits) = if !weighted {
+            (Some(1), &tokens[..])
+        } else if tokens[0] == "h" {
+            (None, &tokens[1..])
+        } else {
+            let weight: u64 =
+                tokens[0].parse().map_err(|_| WcnfError::InvalidWeight(number, tokens[0].to_string()))?;
+            (if top.is_some_and(|top| weight >= top) { None } else { Some(weight) }, &tokens[1..])
+        };
+        let mut clause = Vec::new();
+        for token in lits.iter() {
+            let lit: i32 = token.parse().map_err(|_| WcnfError::InvalidLiteral(number, token.to_string()))?;
+            if lit == 0 {
+                break;
+            }
+            clause.push(lit);
+        }
+        match weight {
+            Some(weight) => wcnf.soft.push((weight, clause)),
+            None => wcnf.hard.push(clause),
+        }
+    }
+    Ok(wcnf)
+}
+
+// An optimal MaxSAT solution: an assignment of every variable of the
+// instance and the total weight of the soft clauses it leaves unsatisfied,
+// which no assignment satisfying the hard clauses beats
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaxSatResult {
+    pub cost: u64,
+    pub model: HashMap<i32, bool>,
+}
+
+fn satisfied(clause: &[i32], model: &HashMap<i32, bool>) -> bool {
+    clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&(lit > 0)))
+}
+
+// Total weight of the soft clauses the model leaves unsatisfied
+fn cost(wcnf: &Wcnf, model: &HashMap<i32, bool>) -> u64 {
+    soft_cost(&wcnf.soft, model)
+}
+
+fn soft_cost(soft: &[(u64, Vec<i32>)], model: &HashMap<i32, bool>) -> u64 {
+    soft.iter().filter(|(_, clause)| !satisfied(clause, model)).map(|(weight, _)| weight).sum()
+}
+
+// Search for a model of the hard clauses and the soft clauses, each relaxed
+// by its own fresh variable, with the weight of the true relaxation
+// variables at most bound. None when there is none.
+fn solve_relaxed(wcnf: &Wcnf, num_vars: i32, bound: Option<u64>) -> Option<HashMap<i32, bool>> {
+    let mut solver = Solver::new();
+    for clause in wcnf.hard.iter() {
+        solver.add_clause(clause);
+    }
+    let relax: Vec<i32> = (1..=wcnf.soft.len() as i32).map(|i| num_vars + i).collect();
+    for ((_, clause), &r) in wcnf.soft.iter().zip(relax.iter()) {
+        let mut relaxed = clause.clone();
+        relaxed.push(r);
+        solver.add_clause(&relaxed);
+    }
+    if let Some(bound) = bound {
+        let weights: Vec<u64> = wcnf.soft.iter().map(|&(weight, _)| weight).collect();
+        let mut next_var = num_vars + relax.len() as i32;
+        for clause in at_most_weighted(&relax, &weights, bound, &mut next_var) {
+            solver.add_clause(&clause);
+        }
+    }
+    if !solver.solve() {
+        return None;
+    }
+    Some(instance_model(&solver, num_vars))
+}
+
+// The model of the last solve restricted to the variables of the instance
+fn instance_model(solver: &Solver, num_vars: i32) -> HashMap<i32, bool> {
+    let mut model = solver.model().cloned().expect("A satisfiable search records its model");
+    model.retain(|&var, _| var <= num_vars);
+    // Variables that only occur in relaxed clauses may have been left out
+    for var in 1..=num_vars {
+        model.entry(var).or_insert(true);
+    }
+    model
+}
+
+// Weighted partial MaxSAT by linear search from above: every soft clause
+// gets a relaxation variable, and after each model the weight of the
+// relaxation variables that may be true is bounded below the cost of that
+// model, until the bounded formula is unsatisfiable. None if the hard
+// clauses are. The bound is encoded in unary, so this suits small weights.
+pub fn solve_wcnf(wcnf: &Wcnf) -> Option<MaxSatResult> {
+    let num_vars = wcnf.num_vars();
+    let mut best = solve_bounded(wcnf, num_vars, None)?;
+    while best.cost > 0 {
+        let Some(result) = solve_bounded(wcnf, num_vars, Some(best.cost - 1)) else {
+            break;
+        };
+        best = result;
+    }
+    Some(best)
+}
+
+// A model of the relaxed formula with its cost, see solve_relaxed
+fn solve_bounded(wcnf: &Wcnf, num_vars: i32, bound: Option<u64>) -> Option<MaxSatResult> {
+    let model = solve_relaxed(wcnf, num_vars, bound)?;
+    Some(MaxSatResult {
+        cost: cost(wcnf, &model),
+        model,
+    })
+}
+
+// How the search closes in on the optimum
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaxSatStrategy {
+    // Every model bounds the cost below its own, see solve_wcnf
+    SatUnsat,
+    // Bounds the cost by 0, 1, 2 and so on until the bounded formula is
+    // satisfiable
+    UnsatSat,
+    // Bounds the cost halfway between the least cost not yet refuted and
+    // the cost of the best model so far
+    Binary,
+    // See solve_wcnf_core_guided
+    CoreGuided,
+}
+
+// Weighted partial MaxSAT by the strategy given. The strategies bounding
+// the cost encode the bound in unary, like solve_wcnf. None if the hard
+// clauses are unsatisfiable.
+pub fn solve_wcnf_with(wcnf: &Wcnf, strategy: MaxSatStrategy) -> Option<MaxSatResult> {
+    let num_vars = wcnf.num_vars();
+    match strategy {
+        MaxSatStrategy::SatUnsat => solve_wcnf(wcnf),
+        MaxSatStrategy::UnsatSat => {
+            // The unbounded model ends the search if no cheaper one exists
+            let upper = solve_bounded(wcnf, num_vars, None)?;
+            (0..upper.cost).find_map(|bound| solve_bounded(wcnf, num_vars, Some(bound))).or(Some(upper))
+        }
+        MaxSatStrategy::Binary => {
+            let mut best = solve_bounded(wcnf, num_vars, None)?;
+            let mut lower = 0;
+            while lower < best.cost {
+                let bound = lower + (best.cost - lower - 1) / 2;
+                match solve_bounded(wcnf, num_vars, Some(bound)) {
+                    Some(result) => best = result,
+                    None => lower = bound + 1,
+                }
+            }
+            Some(best)
+        }
+        MaxSatStrategy::CoreGuided => solve_wcnf_core_guided(wcnf),
+    }
+}
+
+// Weighted partial MaxSAT guided by unsatisfiable cores, the way RC2 does it
+// with the OLL relaxation. Every soft clause gets a literal assumed true, its
+// own for a unit clause and a fresh selector otherwise, with the clause's
+// weight. A core the solver finds among the assumptions costs at least its
+// least weight, which is taken off each of its assumptions. For that weight
+// a core of several assumptions may then have one of them violated, a
+// totalizer counts the violations and the bound on its count is assumed in
+// turn; a bound that ends up in a core itself gets the next one alongside.
+// The first model under the assumptions left is optimal. None if the hard
+// clauses are unsatisfiable.
+pub fn solve_wcnf_core_guided(wcnf: &Wcnf) -> Option<MaxSatResult> {
+    let num_vars = wcnf.num_vars();
+    let mut solver = Solver::new();
+    while solver.num_vars() < num_vars {
+        solver.new_var();
+    }
+    for clause in wcnf.hard.iter() {
+        solver.add_clause(clause);
+    }
+    minimize_soft(&mut solver, &wcnf.soft, num_vars)
+}
+
+// The core-guided search of solve_wcnf_core_guided on the constraints of the
+// solver, with models over the first num_vars variables. The clauses it adds
+// only constrain variables it introduces, so the models of the solver over
+// the others stay the same.
+pub(crate) fn minimize_soft(solver: &mut Solver, soft: &[(u64, Vec<i32>)], num_vars: i32) -> Option<MaxSatResult> {
+    let mut next_var = solver.num_vars();
+    let mut weights: HashMap<i32, u64> = HashMap::new();
+    for (weight, clause) in soft.iter().filter(|&&(weight, _)| weight > 0) {
+        let assumption = match clause[..] {
+            [lit] => lit,
+            _ => {
+                next_var += 1;
+                let mut guarded = clause.clone();
+                guarded.push(-next_var);
+                solver.add_clause(&guarded);
+                next_var
+            }
+        };
+        *weights.entry(assumption).or_default() += weight;
+    }
+    let mut totalizers: Vec<Totalizer> = Vec::new();
+    // The totalizer each bound assumption belongs to and the violations
+    // it allows
+    let mut bounds: HashMap<i32, (usize, usize)> = HashMap::new();
+    loop {
+        let mut assumptions: Vec<i32> = weights.keys().copied().collect();
+        assumptions.sort_unstable_by_key(|&lit| (lit.abs(), lit));
+        if solver.solve_with_assumptions(&assumptions) {
+            let model = instance_model(solver, num_vars);
+            return Some(MaxSatResult {
+                cost: soft_cost(soft, &model),
+                model,
+            });
+        }
+        let core = solver.failed_assumptions().to_vec();
+        let min = core.iter().map(|lit| weights[lit]).min()?;
+        log::debug!("core of {} assumptions with weight {}", core.len(), min);
+        for lit in core.iter() {
+            let weight = weights.get_mut(lit).unwrap();
+            *weight -= min;
+            if *weight == 0 {
+                weights.remove(lit);
+            }
+            if let Some(&(i, bound)) = bounds.get(lit) {
+                if let Some(&output) = totalizers[i].outputs.get(bound + 1) {
+                    *weights.entry(-output).or_default() += min;
+                    bounds.insert(-output, (i, bound + 1));
+                }
+            }
+        }
+        if let [lit] = core[..] {
+            // Soft units of the solver's own variables stay unconstrained
+            if lit.abs() > num_vars {
+                solver.add_clause(&[-lit]);
+            }
+            continue;
+        }
+        let violations: Vec<i32> = core.iter().map(|lit| -lit).collect();
+        let totalizer = Totalizer::new(&violations, &mut next_var);
+        for clause in totalizer.clauses.iter() {
+            solver.add_clause(clause);
+        }
+        *weights.entry(-totalizer.outputs[1]).or_default() += min;
+        bounds.insert(-totalizer.outputs[1], (totalizers.len(), 1));
+        totalizers.push(totalizer);
+    }
+}
+
+// The assignment that violates the fewest clauses
+pub fn solve_maxsat(formula: &[Vec<i32>]) -> MaxSatResult {
+    solve_wcnf(&Wcnf::from_clauses(formula)).expect("Without hard clauses every assignment is a solution")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pigeonhole;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Least cost of an assignment satisfying the hard clauses
+    fn brute_force_cost(wcnf: &Wcnf) -> Option<u64> {
+        let num_vars = wcnf.num_vars();
+        (0..1u32 << num_vars)
+            .map(|bits| (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect())
+            .filter(|model: &HashMap<i32, bool>| wcnf.hard.iter().all(|clause| satisfied(clause, model)))
+            .map(|model| cost(wcnf, &model))
+            .min()
+    }
+
+    #[test]
+    fn test_solve_maxsat() {
+        let formulas: [Formula; 5] = [
+            vec![vec![1, 2], vec![-1, 2]],
+            vec![vec![1], vec![-1]],
+            vec![vec![1], vec![-1], vec![2], vec![-2], vec![1, 2]],
+            // Three pigeons in two holes
+            vec![vec![1, 2], vec![3, 4], vec![5, 6], vec![-1, -3], vec![-1, -5], vec![-3, -5], vec![-2, -4], vec![-2, -6], vec![-4, -6]],
+            vec![vec![1, 2, 3], vec![-1], vec![-2], vec![-3], vec![1, -2], vec![2, -3], vec![3]],
+        ];
+        for formula in formulas.iter() {
+            let result = solve_maxsat(formula);
+            assert_eq!(Some(result.cost), brute_force_cost(&Wcnf::from_clauses(formula)), "{:?}", formula);
+            assert_eq!(cost(&Wcnf::from_clauses(formula), &result.model), result.cost);
+        }
+        // An empty clause can never be satisfied
+        assert_eq!(solve_maxsat(&[vec![], vec![1]]).cost, 1);
+    }
+
+    #[test]
+    fn test_parse_wcnf() {
+        let old = "c old format\np wcnf 3 4 10\n10 1 2 0\n3 -1 0\n5 -2 0\n10 -3 0\n";
+        let new = "c new format\nh 1 2 0\n3 -1 0\n5 -2 0\nh -3 0\n";
+        let expected = Wcnf {
+            hard: vec![vec![1, 2], vec![-3]],
+            soft: vec![(3, vec![-1]), (5, vec![-2])],
+        };
+        assert_eq!(parse_wcnf(old).unwrap(), expected);
+        assert_eq!(parse_wcnf(new).unwrap(), expected);
+        assert_eq!(parse_wcnf("p cnf 2 2\n1 2 0\n-1 0\n").unwrap(), Wcnf::from_clauses(&[vec![1, 2], vec![-1]]));
+
+        assert_eq!(parse_wcnf("p maxsat 1 1\n1 1 0\n"), Err(WcnfError::InvalidHeader(1)));
+        assert_eq!(parse_wcnf("p wcnf 1 1 top\n1 1 0\n"), Err(WcnfError::InvalidHeader(1)));
+        assert_eq!(parse_wcnf("c weights\nx 1 0\n"), Err(WcnfError::InvalidWeight(2, String::from("x"))));
+        assert_eq!(parse_wcnf("h 1 0\n10 1 x 0\n"), Err(WcnfError::InvalidLiteral(2, String::from("x"))));
+    }
+
+    #[test]
+    fn test_solve_wcnf() {
+        let instances = [
+            parse_wcnf("h 1 2 0\n3 -1 0\n5 -2 0\nh -3 0\n").unwrap(),
+            // Violating the two light clauses is cheaper than the heavy one
+            parse_wcnf("h -1 -2 0\n4 1 0\n2 2 0\n2 2 3 0\n1 -3 0\n").unwrap(),
+            parse_wcnf("h 1 0\n7 -1 0\n2 1 2 0\n3 -2 0\n").unwrap(),
+        ];
+        for wcnf in instances.iter() {
+            let result = solve_wcnf(wcnf).unwrap();
+            assert_eq!(Some(result.cost), brute_force_cost(wcnf), "{:?}", wcnf);
+            assert!(wcnf.hard.iter().all(|clause| satisfied(clause, &result.model)));
+            assert_eq!(cost(wcnf, &result.model), result.cost);
+        }
+        assert_eq!(solve_wcnf(&parse_wcnf("h 1 0\nh -1 0\n1 2 0\n").unwrap()), None);
+    }
+
+    #[test]
+    fn test_strategies() {
+        let instances = [
+            parse_wcnf("h 1 2 0\n3 -1 0\n5 -2 0\nh -3 0\n").unwrap(),
+            parse_wcnf("h -1 -2 0\n4 1 0\n2 2 0\n2 2 3 0\n1 -3 0\n").unwrap(),
+            parse_wcnf("3 1 0\n3 -1 0\n2 2 0\n2 -2 0\n1 3 0\n").unwrap(),
+            Wcnf::from_clauses(&pigeonhole(3)),
+            Wcnf::default(),
+        ];
+        let strategies =
+            [MaxSatStrategy::SatUnsat, MaxSatStrategy::UnsatSat, MaxSatStrategy::Binary, MaxSatStrategy::CoreGuided];
+        for wcnf in instances.iter() {
+            for strategy in strategies {
+                let result = solve_wcnf_with(wcnf, strategy).unwrap();
+                assert_eq!(Some(result.cost), brute_force_cost(wcnf), "{:?} {:?}", strategy, wcnf);
+                assert_eq!(cost(wcnf, &result.model), result.cost);
+            }
+        }
+        for strategy in strategies {
+            assert_eq!(solve_wcnf_with(&parse_wcnf("h 1 0\nh -1 0\n1 2 0\n").unwrap(), strategy), None);
+        }
+    }
+
+    #[test]
+    fn test_core_guided() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut instances = vec![
+            parse_wcnf("h 1 2 0\n3 -1 0\n5 -2 0\nh -3 0\n").unwrap(),
+            parse_wcnf("h -1 -2 0\n4 1 0\n2 2 0\n2 2 3 0\n1 -3 0\n").unwrap(),
+            // Units of both signs, a repeated one and an empty clause
+            parse_wcnf("h 1 0\n7 -1 0\n2 1 2 0\n3 -2 0\n4 2 0\n1 2 0\n6 0\n").unwrap(),
+            Wcnf::from_clauses(&pigeonhole(3)),
+        ];
+        for _ in 0..30 {
+            let clause = |rng: &mut StdRng| -> Vec<i32> {
+                (0..rng.gen_range(1..=3)).map(|_| rng.gen_range(1..=6) * if rng.gen() { 1 } else { -1 }).collect()
+            };
+            instances.push(Wcnf {
+                hard: (0..rng.gen_range(0..4)).map(|_| clause(&mut rng)).collect(),
+                soft: (0..rng.gen_range(1..12)).map(|_| (rng.gen_range(1..6), clause(&mut rng))).collect(),
+            });
+        }
+        for wcnf in instances.iter() {
+            let result = solve_wcnf_core_guided(wcnf);
+            assert_eq!(result.as_ref().map(|result| result.cost), brute_force_cost(wcnf), "{:?}", wcnf);
+            if let Some(result) = result {
+                assert!(wcnf.hard.iter().all(|clause| satisfied(clause, &result.model)));
+                assert_eq!(cost(wcnf, &result.model), result.cost);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// A clause that a claimed model leaves unsatisfied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsatisfiedClause {
+    // Position of the clause in the formula
+    pub index: usize,
+    pub clause: Vec<i32>,
+}
+
+impl fmt::Display for UnsatisfiedClause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "clause {} {:?} is not satisfied", self.index, self.clause)
+    }
+}
+
+impl std::error::Error for UnsatisfiedClause {}
+
+// Check a model against a formula, variables missing from the model satisfy no
+// literal. Returns the first clause that is not satisfied.
+pub fn verify_model(formula: &[Vec<i32>], model: &HashMap<i32, bool>) -> Result<(), UnsatisfiedClause> {
+    for (index, clause) in formula.iter().enumerate() {
+        if !clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&(lit > 0))) {
+            return Err(UnsatisfiedClause {
+                index,
+                clause: clause.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+// Shrink a model of the formula to a prime implicant: the variables it keeps
+// still satisfy every clause, and dropping any one of them would leave some
+// clause unsatisfied. Variables are dropped greedily in increasing order
+// while every clause they satisfy has another kept true literal; the count of
+// those only goes down, so a variable that is needed once stays needed.
+pub fn prime_implicant(
+    formula: &[Vec<i32>],
+    model: &HashMap<i32, bool>,
+) -> Result<HashMap<i32, bool>, UnsatisfiedClause> {
+    verify_model(formula, model)?;
+    let is_true = |lit: i32| model.get(&lit.abs()) == Some(&(lit > 0));
+    let mut true_lits: Vec<usize> = formula
+        .iter()
+        .map(|clause| {
+            let mut lits: Vec<i32> = clause.iter().copied().filter(|&lit| is_true(lit)).collect();
+            lits.sort_unstable();
+            lits.dedup();
+            lits.len()
+        })
+        .collect();
+    let mut satisfies: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, clause) in formula.iter().enumerate() {
+        for &lit in clause.iter().filter(|&&lit| is_true(lit)) {
+            let clauses = satisfies.entry(lit.abs()).or_default();
+            if clauses.last() != Some(&i) {
+                clauses.push(i);
+            }
+        }
+    }
+    let mut vars: Vec<i32> = model.keys().copied().collect();
+    vars.sort_unstable();
+    let mut implicant = model.clone();
+    for var in vars {
+        let clauses = satisfies.get(&var).map(Vec::as_slice).unwrap_or(&[]);
+        if clauses.iter().all(|&i| true_lits[i] > 1) {
+            for &i in clauses.iter() {
+                true_lits[i] -= 1;
+            }
+            implicant.remove(&var);
+        }
+    }
+    Ok(implicant)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_verify_model() {
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![-2, -3]];
+        assert_eq!(verify_model(&formula, &hashmap! { 1 => true, 2 => false, 3 => true }), Ok(()));
+        assert_eq!(
+            verify_model(&formula, &hashmap! { 1 => true, 2 => true, 3 => true }),
+            Err(UnsatisfiedClause { index: 2, clause: vec![-2, -3] })
+        );
+        // A missing variable does not satisfy the clause
+        assert_eq!(verify_model(&formula, &hashmap! { 2 => true, 3 => false }).unwrap_err().index, 1);
+    }
+
+    #[test]
+    fn test_prime_implicant() {
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![2, 3], vec![4, -4]];
+        let model = hashmap! { 1 => true, 2 => true, 3 => true, 4 => false, 5 => true };
+        let implicant = prime_implicant(&formula, &model).unwrap();
+        assert_eq!(implicant, hashmap! { 2 => true, 4 => false, 3 => true });
+        assert_eq!(verify_model(&formula, &implicant), Ok(()));
+        for var in implicant.keys() {
+            let mut smaller = implicant.clone();
+            smaller.remove(var);
+            assert!(verify_model(&formula, &smaller).is_err(), "{} is not needed", var);
+        }
+        assert_eq!(prime_implicant(&formula, &hashmap! { 1 => true }).unwrap_err().index, 1);
+        assert_eq!(prime_implicant(&[], &model), Ok(HashMap::new()));
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use crate::{read_cnf, Formula, Solver};
+
+// A minimal unsatisfiable subset of an unsatisfiable formula, as sorted clause
+// indices: the chosen clauses are unsatisfiable together and dropping any one
+// of them makes the rest satisfiable. None if the formula is satisfiable.
+//
+// Every clause is guarded by a selector, so solving under a set of selectors
+// checks that set of clauses. Deletion tries to drop one clause at a time and
+// keeps it if the others become satisfiable; after an unsatisfiable answer
+// the candidates shrink to the clauses whose selectors failed.
+pub fn find_mus(formula: &[Vec<i32>]) -> Option<Vec<usize>> {
+    let groups: Vec<Formula> = formula.iter().map(|clause| vec![clause.clone()]).collect();
+    find_group_mus(&[], &groups)
+}
+
+// A minimal unsatisfiable set of groups of clauses together with the hard
+// clauses, as sorted group indices, by the deletion of find_mus with one
+// selector for all the clauses of a group. If the hard clauses alone are
+// unsatisfiable it is empty, None if all of them together are satisfiable.
+pub fn find_group_mus(hard: &[Vec<i32>], groups: &[Formula]) -> Option<Vec<usize>> {
+    let mut solver = Solver::new();
+    let num_vars = hard.iter().chain(groups.iter().flatten()).flatten().map(|lit| lit.abs()).max().unwrap_or(0);
+    while solver.num_vars() < num_vars {
+        solver.new_var();
+    }
+    for clause in hard.iter() {
+        solver.add_clause(clause);
+    }
+    let selectors: Vec<i32> = groups
+        .iter()
+        .map(|group| {
+            let selector = solver.new_var();
+            for clause in group.iter() {
+                let mut guarded = clause.clone();
+                guarded.push(-selector);
+                solver.add_clause(&guarded);
+            }
+            selector
+        })
+        .collect();
+    if solver.solve_with_assumptions(&selectors) {
+        return None;
+    }
+    let core = |solver: &Solver, candidates: Vec<usize>| -> Vec<usize> {
+        candidates.into_iter().filter(|&i| solver.failed(selectors[i])).collect()
+    };
+    let mut candidates = core(&solver, (0..groups.len()).collect());
+    // Clauses before position i are known to be needed
+    let mut i = 0;
+    while i < candidates.len() {
+        let rest: Vec<usize> = candidates.iter().copied().filter(|&j| j != candidates[i]).collect();
+        let assumptions: Vec<i32> = rest.iter().map(|&j| selectors[j]).collect();
+        if solver.solve_with_assumptions(&assumptions) {
+            i += 1;
+        } else {
+            candidates = core(&solver, rest);
+        }
+    }
+    Some(candidates)
+}
+
+// Clauses in named groups, and hard clauses outside of any, as group-CNF
+// files lay them out for group MUS extraction
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GroupedFormula {
+    pub hard: Formula,
+    pub groups: Vec<(String, Formula)>,
+}
+
+impl GroupedFormula {
+    // Add the clause to the group of that name, a new last group if there is
+    // none yet
+    pub fn add_clause(&mut self, group: &str, clause: &[i32]) {
+        match self.groups.iter_mut().find(|(name, _)| name == group) {
+            Some((_, clauses)) => clauses.push(clause.to_vec()),
+            None => self.groups.push((group.to_string(), vec![clause.to_vec()])),
+        }
+    }
+
+    pub fn add_hard_clause(&mut self, clause: &[i32]) {
+        self.hard.push(clause.to_vec());
+    }
+
+    // The names of the groups of a minimal unsatisfiable set, see
+    // find_group_mus, in the order the groups were made
+    pub fn find_mus(&self) -> Option<Vec<&str>> {
+        let groups: Vec<Formula> = self.groups.iter().map(|(_, clauses)| clauses.clone()).collect();
+        let mus = find_group_mus(&self.hard, &groups)?;
+        Some(mus.into_iter().map(|i| self.groups[i].0.as_str()).collect())
+    }
+}
+
+// Parse a group-CNF file: a "p gcnf <vars> <clauses> <groups>" line, then
+// clauses that each start with their group, as in "{2} 1 -3 0". Group 0
+// holds the hard clauses. Other input is read as DIMACS CNF with each clause
+// in a group of its own, named by its 1-based number.
+pub fn parse_gcnf(text: &str) -> GroupedFormula {
+    let mut formula = GroupedFormula::default();
+    if !text.lines().any(|line| line.split_whitespace().take(2).eq(["p", "gcnf"])) {
+        for (i, clause) in read_cnf(text.as_bytes()).into_iter().enumerate() {
+            formula.groups.push(((i + 1).to_string(), vec![clause]));
+        }
+        return formula;
+    }
+    // Position of each group in formula.groups
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for line in text.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() || tokens[0] == "c" || tokens[0] == "p" {
+            continue;
+        }
+        let group = tokens[0].strip_prefix('{').and_then(|group| group.strip_suffix('}')).expect("Missing group");
+        let mut clause = Vec::new();
+        for token in tokens[1..].iter() {
+            let lit: i32 = token.parse().expect("Failed to parse literal");
+            if lit == 0 {
+                break;
+            }
+            clause.push(lit);
+        }
+        if group == "0" {
+            formula.hard.push(clause);
+            continue;
+        }
+        let position = *positions.entry(group).or_insert_with(|| {
+            formula.groups.push((group.to_string(), Vec::new()));
+            formula.groups.len() - 1
+        });
+        formula.groups[position].1.push(clause);
+    }
+    formula
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Formula;
+
+    fn satisfiable(formula: &[&Vec<i32>]) -> bool {
+        let num_vars = formula.iter().copied().flatten().map(|lit| lit.abs()).max().unwrap_or(0);
+        (0..1u32 << num_vars).any(|bits| {
+            let value = |lit: i32| ((bits >> (lit.abs() - 1)) & 1 == 1) == (lit > 0);
+            formula.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+        })
+    }
+
+    #[test]
+    fn test_find_mus() {
+        let formulas: [Formula; 4] = [
+            vec![vec![1], vec![2, 3], vec![-1], vec![-2]],
+            // 1 implies 2 implies 3 implies not 1, with 1 forced
+            vec![vec![4, 5], vec![-1, 2], vec![1], vec![-2, 3], vec![-4], vec![-3, -1], vec![5, 6]],
+            vec![vec![1, 2], vec![1, -2], vec![-1, 2], vec![-1, -2], vec![3], vec![-3, 1]],
+            vec![vec![2], vec![], vec![1]],
+        ];
+        for formula in formulas.iter() {
+            let mus = find_mus(formula).unwrap();
+            let clauses: Vec<&Vec<i32>> = mus.iter().map(|&i| &formula[i]).collect();
+            assert!(!satisfiable(&clauses), "{:?}", mus);
+            for skip in 0..clauses.len() {
+                let mut rest = clauses.clone();
+                rest.remove(skip);
+                assert!(satisfiable(&rest), "{:?} without {}", mus, skip);
+            }
+        }
+        assert_eq!(find_mus(&formulas[0]), Some(vec![0, 2]));
+        assert_eq!(find_mus(&formulas[3]), Some(vec![1]));
+        assert_eq!(find_mus(&[vec![1, 2], vec![-1]]), None);
+    }
+
+    #[test]
+    fn test_group_mus() {
+        // Group 1 says 1, 2 and 3 says not 1 and 4 says 2 and not 2, under
+        // the hard clause 1 or 2
+        let formula = parse_gcnf(
+            "p gcnf 3 7 4\n{0} 1 2 0\n{1} 1 0\n{2} -1 3 0\n{2} -3 0\n{3} -1 0\n{4} 2 0\n{4} -2 0\n",
+        );
+        assert_eq!(formula.hard, vec![vec![1, 2]]);
+        assert_eq!(formula.groups.len(), 4);
+        assert_eq!(formula.groups[1], ("2".to_string(), vec![vec![-1, 3], vec![-3]]));
+        let mus = formula.find_mus().unwrap();
+        assert!(mus == ["1", "2"] || mus == ["1", "3"] || mus == ["4"], "{:?}", mus);
+
+        let mut formula = GroupedFormula::default();
+        formula.add_hard_clause(&[1, 2]);
+        formula.add_clause("not 1", &[-1]);
+        formula.add_clause("not 2", &[-2]);
+        formula.add_clause("3", &[3]);
+        formula.add_clause("not 1", &[-3, 1]);
+        assert_eq!(formula.find_mus(), Some(vec!["not 1", "not 2"]));
+        formula.hard = vec![vec![-3]];
+        assert_eq!(formula.find_mus(), Some(vec!["3"]));
+        formula.hard.push(vec![]);
+        assert_eq!(formula.find_mus(), Some(vec![]));
+
+        // Plain CNF groups the clauses one by one
+        let formula = parse_gcnf("p cnf 2 3\n1 2\n0 -1 0\n-2 0\n");
+        assert_eq!(formula.find_mus(), Some(vec!["1", "2", "3"]));
+        assert_eq!(parse_gcnf("p cnf 1 1\n1 0\n").find_mus(), None);
+    }
+}
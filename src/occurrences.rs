@@ -0,0 +1,337 @@
+use crate::proof::Antecedents;
+use crate::{Assignment, ClauseDb, ClauseId};
+
+// The clauses of every literal in a ClauseDb, with counters kept up to date
+// with a search's assignment: how many true and how many unassigned literals
+// each clause has, and how many unsatisfied clauses each literal occurs in.
+// Assigning or undoing a variable only touches the clauses of its literals,
+// so finding the satisfied clauses, the unit clauses, the pure literals and
+// the variables that left the formula does not scan the whole formula at
+// every node.
+#[derive(Clone, Debug, Default)]
+pub struct Occurrences {
+    // Clauses of each literal, by literal index
+    lists: Vec<Vec<ClauseId>>,
+    true_count: Vec<u32>,
+    unassigned_count: Vec<u32>,
+    // Unsatisfied clauses of each literal, by literal index
+    open_count: Vec<u32>,
+    // The value of each variable that the counters are up to date with
+    counted: Vec<Option<bool>>,
+    // The assignment's trail is counted up to here
+    synced: usize,
+    // Variables whose clauses all got satisfied since the last simplify
+    vacated: Vec<i32>,
+    // Unsatisfied clauses that were left with one unassigned literal or none
+    // since the last propagate
+    units: Vec<ClauseId>,
+    // Variables that may have become pure since the last pure literal round
+    pure: Vec<i32>,
+}
+
+fn index(lit: i32) -> usize {
+    2 * lit.unsigned_abs() as usize + usize::from(lit < 0)
+}
+
+impl Occurrences {
+    // Counters for the clauses of the database under the assignment, which
+    // from now on has to change by insert and undo
+    pub fn new(db: &ClauseDb, assignment: &Assignment) -> Self {
+        let vars = db.iter().flatten().map(|lit| lit.abs()).chain(assignment.keys());
+        let num_vars = vars.max().unwrap_or(0) as usize;
+        let mut occurrences = Occurrences {
+            lists: vec![Vec::new(); 2 * num_vars + 2],
+            true_count: vec![0; db.len()],
+            unassigned_count: db.iter().map(|clause| clause.len() as u32).collect(),
+            open_count: vec![0; 2 * num_vars + 2],
+            counted: vec![None; num_vars + 1],
+            synced: assignment.mark(),
+            vacated: Vec::new(),
+            units: Vec::new(),
+            pure: Vec::new(),
+        };
+        for (id, clause) in db.iter().enumerate() {
+            for &lit in clause {
+                occurrences.lists[index(lit)].push(id as ClauseId);
+                occurrences.open_count[index(lit)] += 1;
+            }
+        }
+        for (var, val) in assignment.iter() {
+            occurrences.update(db, var, val);
+        }
+        // Variables that occur in no clause to begin with are gone as well
+        occurrences.vacated = assignment.keys().filter(|&var| occurrences.var_count(var) == 0).collect();
+        occurrences.units = (0..db.len() as ClauseId).filter(|&id| occurrences.is_unit(id)).collect();
+        occurrences.pure = (1..=num_vars as i32).collect();
+        occurrences
+    }
+
+    // The clauses lit occurs in
+    pub fn clauses_of(&self, lit: i32) -> &[ClauseId] {
+        self.lists.get(index(lit)).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn is_satisfied(&self, id: ClauseId) -> bool {
+        self.true_count[id as usize] > 0
+    }
+
+    // Whether the clause is unsatisfied with one unassigned literal or none
+    fn is_unit(&self, id: ClauseId) -> bool {
+        !self.is_satisfied(id) && self.unassigned_count[id as usize] <= 1
+    }
+
+    // Unsatisfied clauses the variable occurs in
+    fn var_count(&self, var: i32) -> u32 {
+        self.open_count[index(var)] + self.open_count[index(-var)]
+    }
+
+    // Count the changes of the assignment since the last update
+    pub fn sync(&mut self, db: &ClauseDb, assignment: &Assignment) {
+        let changed: Vec<i32> = assignment.changes(self.synced).map(|(var, _)| var).collect();
+        for var in changed {
+            self.update(db, var, assignment.get(&var).copied().flatten());
+        }
+        self.synced = assignment.mark();
+    }
+
+    // Undo the assignment back to mark and the counters along with it. The
+    // assignment at mark is one a node branched from, after its propagation
+    // and pure literal rounds, so what was queued for those since is dropped.
+    pub fn undo(&mut self, db: &ClauseDb, assignment: &mut Assignment, mark: usize) {
+        self.sync(db, assignment);
+        self.units.clear();
+        self.pure.clear();
+        let changed: Vec<i32> = assignment.changes(mark).map(|(var, _)| var).collect();
+        assignment.undo(mark);
+        for var in changed {
+            self.update(db, var, assignment.get(&var).copied().flatten());
+        }
+        self.synced = mark;
+    }
+
+    // simplify_clauses through the counters: drop the satisfied clauses and
+    // set the unassigned variables that no clause left contains true, except
+    // for those kept by keep
+    pub fn simplify(
+        &mut self,
+        db: &ClauseDb,
+        ids: &[ClauseId],
+        assignment: &mut Assignment,
+        keep: impl Fn(i32) -> bool,
+    ) -> Vec<ClauseId> {
+        self.sync(db, assignment);
+        let new_ids = ids.iter().copied().filter(|&id| !self.is_satisfied(id)).collect();
+        let mut vacated = std::mem::take(&mut self.vacated);
+        vacated.sort_unstable();
+        vacated.dedup();
+        for var in vacated {
+            let absent = (var as usize) >= self.counted.len() || self.var_count(var) == 0;
+            if absent && assignment.get(&var) == Some(&None) && !keep(var) {
+                assignment.insert(var, Some(true));
+            }
+        }
+        self.sync(db, assignment);
+        new_ids
+    }
+
+    // Unit propagation through the counters: make the last unassigned
+    // literal of every unsatisfied clause true until none is left or a clause
+    // is falsified. The clauses of ids have to be the ones the assignment
+    // leaves unsatisfied, as those of a node are. Returns 0 on a conflict, 2 if
+    // no clause is left and 1 otherwise, along with the ids of the clauses
+    // left. The clauses that force literals or conflict go to antecedents, if
+    // any.
+    pub(crate) fn propagate(
+        &mut self,
+        db: &ClauseDb,
+        ids: &[ClauseId],
+        assignment: &mut Assignment,
+        mut antecedents: Option<&mut Antecedents>,
+    ) -> (i32, Vec<ClauseId>) {
+        self.sync(db, assignment);
+        while let Some(id) = self.units.pop() {
+            if !self.is_unit(id) {
+                continue;
+            }
+            let clause = db.clause(id);
+            let Some(&lit) = clause.iter().find(|lit| self.counted[lit.unsigned_abs() as usize].is_none()) else {
+                if let Some(antecedents) = antecedents {
+                    antecedents.record_conflict(clause);
+                }
+                self.units.clear();
+                let new_ids = ids.iter().copied().filter(|&id| !self.is_satisfied(id)).collect();
+                return (0, new_ids);
+            };
+            if let Some(antecedents) = antecedents.as_deref_mut() {
+                antecedents.record(lit, clause);
+            }
+            assignment.insert(lit.abs(), Some(lit > 0));
+            self.sync(db, assignment);
+        }
+        let new_ids: Vec<ClauseId> = ids.iter().copied().filter(|&id| !self.is_satisfied(id)).collect();
+        (if new_ids.is_empty() { 2 } else { 1 }, new_ids)
+    }
+
+    // A round of pure literal elimination through the counters: make the
+    // literals true whose variables are unassigned and occur in the
+    // unsatisfied clauses with that polarity only, except for those kept by
+    // keep, then simplify the clauses of ids
+    pub fn eliminate_pure(
+        &mut self,
+        db: &ClauseDb,
+        ids: &[ClauseId],
+        assignment: &mut Assignment,
+        keep: impl Fn(i32) -> bool,
+    ) -> Vec<ClauseId> {
+        self.sync(db, assignment);
+        let mut pure = std::mem::take(&mut self.pure);
+        pure.sort_unstable();
+        pure.dedup();
+        for var in pure {
+            if (var as usize) >= self.counted.len() || assignment.get(&var) != Some(&None) || keep(var) {
+                continue;
+            }
+            let (positive, negative) = (self.open_count[index(var)], self.open_count[index(-var)]);
+            if (positive == 0) != (negative == 0) {
+                assignment.insert(var, Some(positive > 0));
+            }
+        }
+        self.simplify(db, ids, assignment, keep)
+    }
+
+    fn update(&mut self, db: &ClauseDb, var: i32, val: Option<bool>) {
+        let slot = var as usize;
+        if slot >= self.counted.len() {
+            // A variable of no clause, it has nothing to count
+            return;
+        }
+        let old = std::mem::replace(&mut self.counted[slot], val);
+        if old == val {
+            return;
+        }
+        if old.is_some() != val.is_some() {
+            for lit in [var, -var] {
+                for &id in self.lists[index(lit)].iter() {
+                    let count = &mut self.unassigned_count[id as usize];
+                    *count = if val.is_some() { *count - 1 } else { *count + 1 };
+                }
+            }
+        }
+        if let Some(old) = old {
+            let lit = if old { var } else { -var };
+            let list = std::mem::take(&mut self.lists[index(lit)]);
+            for &id in list.iter() {
+                self.true_count[id as usize] -= 1;
+                if self.true_count[id as usize] == 0 {
+                    // The clause is open again
+                    for &lit in db.clause(id) {
+                        self.open_count[index(lit)] += 1;
+                    }
+                    if self.is_unit(id) {
+                        self.units.push(id);
+                    }
+                }
+            }
+            self.lists[index(lit)] = list;
+        }
+        if let Some(val) = val {
+            let lit = if val { var } else { -var };
+            let list = std::mem::take(&mut self.lists[index(lit)]);
+            for &id in list.iter() {
+                self.true_count[id as usize] += 1;
+                if self.true_count[id as usize] == 1 {
+                    for &lit in db.clause(id) {
+                        let count = &mut self.open_count[index(lit)];
+                        *count -= 1;
+                        if *count == 0 {
+                            self.pure.push(lit.abs());
+                            if self.var_count(lit) == 0 {
+                                self.vacated.push(lit.abs());
+                            }
+                        }
+                    }
+                }
+            }
+            self.lists[index(lit)] = list;
+            // The clauses that lost an unassigned literal to the other one
+            let units = self.lists[index(-lit)].iter().copied().filter(|&id| self.is_unit(id));
+            self.units.extend(units.collect::<Vec<ClauseId>>());
+        } else {
+            self.pure.push(var);
+            if self.var_count(var) == 0 {
+                self.vacated.push(var);
+            }
+            let units = [var, -var].into_iter().flat_map(|lit| self.lists[index(lit)].iter().copied());
+            self.units.extend(units.filter(|&id| self.is_unit(id)).collect::<Vec<ClauseId>>());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occurrences() {
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![2, -3], vec![4, 1]];
+        let db = ClauseDb::new(&formula);
+        let mut assignment: Assignment = (1..=5).map(|var| (var, None)).collect();
+        assignment.clear_trail();
+        let mut occurrences = Occurrences::new(&db, &assignment);
+        // 5 occurs in no clause and is set right away
+        assert_eq!(occurrences.simplify(&db, &db.ids(), &mut assignment, |_| false), vec![0, 1, 2, 3]);
+        assert_eq!(assignment[&5], Some(true));
+
+        let mark = assignment.mark();
+        assignment.insert(1, Some(true));
+        // Clauses 0 and 3 are satisfied, 4 occurs in neither of the others
+        assert_eq!(occurrences.simplify(&db, &db.ids(), &mut assignment, |_| false), vec![1, 2]);
+        assert_eq!(assignment[&4], Some(true));
+        assert_eq!(assignment[&2], None);
+        // Kept variables stay open
+        assignment.insert(3, Some(false));
+        assert_eq!(occurrences.simplify(&db, &[1, 2], &mut assignment, |var| var == 2), vec![1]);
+        assert_eq!(assignment[&2], None);
+
+        occurrences.undo(&db, &mut assignment, mark);
+        assert_eq!(assignment[&4], None);
+        assert!((0..4).all(|id| !occurrences.is_satisfied(id)));
+        assignment.insert(2, Some(false));
+        assert_eq!(occurrences.simplify(&db, &db.ids(), &mut assignment, |_| false), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_propagate_and_pure_literals() {
+        let formula = vec![vec![1, 2], vec![-1, 3], vec![-3, 4, 5], vec![-4, 5], vec![-5, 6, -2]];
+        let db = ClauseDb::new(&formula);
+        let mut assignment: Assignment = (1..=6).map(|var| (var, None)).collect();
+        assignment.clear_trail();
+        let mut occurrences = Occurrences::new(&db, &assignment);
+        // 6 only occurs positively, the other variables both ways
+        assert_eq!(occurrences.eliminate_pure(&db, &db.ids(), &mut assignment, |_| false), vec![0, 1, 2, 3]);
+        assert_eq!(assignment[&6], Some(true));
+        assert_eq!(assignment[&1], None);
+
+        // -2 forces 1, which forces 3 and leaves 4 5 open
+        let mark = assignment.mark();
+        assignment.insert(2, Some(false));
+        let ids = occurrences.simplify(&db, &[0, 1, 2, 3], &mut assignment, |_| false);
+        assert_eq!(occurrences.propagate(&db, &ids, &mut assignment, None), (1, vec![2, 3]));
+        assert_eq!((assignment[&1], assignment[&3]), (Some(true), Some(true)));
+        // Then -5 forces 4 and falsifies -4 5
+        assignment.insert(5, Some(false));
+        assert_eq!(occurrences.propagate(&db, &[2, 3], &mut assignment, None).0, 0);
+
+        // Undone and assigned again without 2, 5 only occurs positively in
+        // the clauses left
+        occurrences.undo(&db, &mut assignment, mark);
+        assert_eq!(assignment[&1], None);
+        assignment.insert(1, Some(true));
+        assignment.insert(3, Some(true));
+        let ids = occurrences.simplify(&db, &[0, 1, 2, 3], &mut assignment, |_| false);
+        assert_eq!(ids, vec![2, 3]);
+        assert!(occurrences.eliminate_pure(&db, &ids, &mut assignment, |_| false).is_empty());
+        assert_eq!(assignment[&5], Some(true));
+        assert_eq!(occurrences.propagate(&db, &[], &mut assignment, None), (2, vec![]));
+    }
+}
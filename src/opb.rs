@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::encodings::at_most_weighted;
+use crate::maxsat::Wcnf;
+use crate::Formula;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Relation {
+    AtLeast,
+    Equal,
+    AtMost,
+}
+
+// A linear constraint over literals: the sum of the coefficients of the true
+// literals compared with the right-hand side
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PbConstraint {
+    pub terms: Vec<(i64, i32)>,
+    pub relation: Relation,
+    pub rhs: i64,
+}
+
+// A pseudo-Boolean instance: constraints over variables 1 to num_vars and
+// possibly a linear objective to minimize
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Opb {
+    pub num_vars: i32,
+    pub objective: Option<Vec<(i64, i32)>>,
+    pub constraints: Vec<PbConstraint>,
+}
+
+// A line of an OPB file that is not a linear statement, numbered from 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OpbError {
+    Parse(usize),
+    // A product of literals, which the translation to CNF does not support
+    Nonlinear(usize),
+}
+
+impl fmt::Display for OpbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpbError::Parse(line) => write!(f, "line {}: not an OPB statement", line),
+            OpbError::Nonlinear(line) => write!(f, "line {}: nonlinear terms are not supported", line),
+        }
+    }
+}
+
+// Parse "<coefficient> <literal> ..." terms, a literal is xN or ~xN
+fn parse_terms(tokens: &[&str], line: usize) -> Result<Vec<(i64, i32)>, OpbError> {
+    if !tokens.len().is_multiple_of(2) {
+        let literals = tokens.iter().filter(|token| token.trim_start_matches('~').starts_with('x')).count();
+        return Err(if literals > tokens.len() / 2 { OpbError::Nonlinear(line) } else { OpbError::Parse(line) });
+    }
+    tokens
+        .chunks(2)
+        .map(|term| {
+            let coefficient = term[0].parse().map_err(|_| OpbError::Parse(line))?;
+            let (negated, name) = match term[1].strip_prefix('~') {
+                Some(name) => (true, name),
+                None => (false, term[1]),
+            };
+            let var: i32 = name
+                .strip_prefix('x')
+                .and_then(|var| var.parse().ok())
+                .filter(|&var| var > 0)
+                .ok_or(OpbError::Parse(line))?;
+            Ok((coefficient, if negated { -var } else { var }))
+        })
+        .collect()
+}
+
+// Parse the OPB format of the pseudo-Boolean competitions: "*" comments, an
+// optional "min: <terms> ;" objective and one "<terms> <relation> <rhs> ;"
+// constraint per statement, with >=, = or <= as the relation
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn parse_opb(text: &str) -> Result<Opb, OpbError> {
+    let mut opb = Opb::default();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        for statement in line.split(';').map(str::trim).filter(|statement| !statement.is_empty()) {
+            let tokens: Vec<&str> = statement.split_whitespace().collect();
+            if tokens[0] == "min:" {
+                opb.objective = Some(parse_terms(&tokens[1..], i + 1)?);
+                continue;
+            }
+            let position = tokens.iter().position(|token| matches!(*token, ">=" | "=" | "<="));
+            let Some(position) = position.filter(|&position| position + 2 == tokens.len()) else {
+                return Err(OpbError::Parse(i + 1));
+            };
+            let relation = match tokens[position] {
+                ">=" => Relation::AtLeast,
+                "=" => Relation::Equal,
+                _ => Relation::AtMost,
+            };
+            opb.constraints.push(PbConstraint {
+                terms: parse_terms(&tokens[..position], i + 1)?,
+                relation,
+                rhs: tokens[position + 1].parse().map_err(|_| OpbError::Parse(i + 1))?,
+            });
+        }
+    }
+    let objective = opb.objective.iter().flatten();
+    let terms = opb.constraints.iter().flat_map(|constraint| constraint.terms.iter());
+    opb.num_vars = terms.chain(objective).map(|(_, lit)| lit.abs()).max().unwrap_or(0);
+    Ok(opb)
+}
+
+// Clauses for the sum of the coefficients of the true literals being at
+// most bound. A negative coefficient c on l is rewritten as c plus -c on the
+// negation of l, so every weight handed to the encoding is positive.
+fn at_most(terms: &[(i64, i32)], bound: i64, next_var: &mut i32) -> Formula {
+    let mut bound = bound;
+    let mut lits = Vec::with_capacity(terms.len());
+    let mut weights = Vec::with_capacity(terms.len());
+    for &(coefficient, lit) in terms.iter() {
+        if coefficient < 0 {
+            bound -= coefficient;
+            lits.push(-lit);
+        } else {
+            lits.push(lit);
+        }
+        weights.push(coefficient.unsigned_abs());
+    }
+    if bound < 0 {
+        return vec![vec![]];
+    }
+    at_most_weighted(&lits, &weights, bound as u64, next_var)
+}
+
+// Clauses equivalent to the constraints, with fresh variables for the
+// encodings numbered from num_vars + 1. The weighted counter is unary in the
+// right-hand side, so this suits small coefficients.
+pub fn opb_to_cnf(opb: &Opb) -> Formula {
+    let mut next_var = opb.num_vars;
+    let mut clauses = Vec::new();
+    for constraint in opb.constraints.iter() {
+        if constraint.relation != Relation::AtLeast {
+            clauses.extend(at_most(&constraint.terms, constraint.rhs, &mut next_var));
+        }
+        // At least rhs is at most -rhs after negating every coefficient
+        if constraint.relation != Relation::AtMost {
+            let negated: Vec<(i64, i32)> = constraint.terms.iter().map(|&(c, lit)| (-c, lit)).collect();
+            clauses.extend(at_most(&negated, -constraint.rhs, &mut next_var));
+        }
+    }
+    clauses
+}
+
+// The instance as weighted MaxSAT: the constraints as hard clauses and every
+// objective term as a soft unit clause falsified when it counts. The cost of
+// a solution plus the returned offset, the sum of the negative coefficients,
+// is the value of the objective.
+pub fn opb_to_wcnf(opb: &Opb) -> (Wcnf, i64) {
+    let mut wcnf = Wcnf {
+        hard: opb_to_cnf(opb),
+        soft: Vec::new(),
+    };
+    let mut offset = 0;
+    for &(coefficient, lit) in opb.objective.iter().flatten() {
+        // c * l is c - c * -l, so a negative coefficient pays when l is false
+        if coefficient < 0 {
+            offset += coefficient;
+            wcnf.soft.push((coefficient.unsigned_abs(), vec![lit]));
+        } else if coefficient > 0 {
+            wcnf.soft.push((coefficient as u64, vec![-lit]));
+        }
+    }
+    (wcnf, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve_wcnf, Solver};
+    use std::collections::HashMap;
+
+    const EXAMPLE: &str = "* #variable= 3 #constraint= 3
+min: +2 x1 -1 x2 +3 x3 ;
++1 x1 +1 x2 +1 x3 >= 2 ;
++2 x1 -3 ~x2 <= 0 ;
++1 x1 +1 x3 = 1 ;
+";
+
+    fn satisfies(constraint: &PbConstraint, model: &HashMap<i32, bool>) -> bool {
+        let sum: i64 =
+            constraint.terms.iter().filter(|(_, lit)| model[&lit.abs()] == (*lit > 0)).map(|(c, _)| c).sum();
+        match constraint.relation {
+            Relation::AtLeast => sum >= constraint.rhs,
+            Relation::Equal => sum == constraint.rhs,
+            Relation::AtMost => sum <= constraint.rhs,
+        }
+    }
+
+    #[test]
+    fn test_parse_opb() {
+        let opb = parse_opb(EXAMPLE).unwrap();
+        assert_eq!(opb.num_vars, 3);
+        assert_eq!(opb.objective, Some(vec![(2, 1), (-1, 2), (3, 3)]));
+        assert_eq!(
+            opb.constraints[1],
+            PbConstraint {
+                terms: vec![(2, 1), (-3, -2)],
+                relation: Relation::AtMost,
+                rhs: 0
+            }
+        );
+        assert_eq!(parse_opb("+1 x1 x2 >= 1 ;"), Err(OpbError::Nonlinear(1)));
+        assert_eq!(parse_opb("\n+1 x1 > 1 ;"), Err(OpbError::Parse(2)));
+    }
+
+    #[test]
+    fn test_opb_to_cnf() {
+        let opb = parse_opb(EXAMPLE).unwrap();
+        let clauses = opb_to_cnf(&opb);
+        // Every total assignment of the variables satisfies the constraints
+        // exactly when the clauses have a model extending it
+        for bits in 0..1u32 << opb.num_vars {
+            let model: HashMap<i32, bool> = (1..=opb.num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect();
+            let mut solver = Solver::new();
+            for clause in clauses.iter() {
+                solver.add_clause(clause);
+            }
+            let assumptions: Vec<i32> = model.iter().map(|(&var, &val)| if val { var } else { -var }).collect();
+            let expected = opb.constraints.iter().all(|constraint| satisfies(constraint, &model));
+            assert_eq!(solver.solve_with_assumptions(&assumptions), expected, "{:?}", model);
+        }
+
+        // Only x2 and x3 satisfy all three constraints, x1 is false
+        let (wcnf, offset) = opb_to_wcnf(&opb);
+        let result = solve_wcnf(&wcnf).unwrap();
+        assert_eq!(result.cost as i64 + offset, 2);
+        assert_eq!((result.model[&1], result.model[&2], result.model[&3]), (false, true, true));
+    }
+}
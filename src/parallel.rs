@@ -0,0 +1,156 @@
+use std::iter;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+
+use crate::portfolio::thread_pool;
+use crate::{build_search_tree, ClauseSharing, Model, Node, SolveResult, Solver, SolverConfig, Stats};
+
+// A pending node from the shared root queue or from the far end of another
+// worker's deque, where the nodes closest to the root wait
+fn steal(local: &Worker<Node>, injector: &Injector<Node>, stealers: &[Stealer<Node>]) -> Option<Node> {
+    iter::repeat_with(|| injector.steal_batch_and_pop(local).or_else(|| stealers.iter().map(Stealer::steal).collect()))
+        .find(|steal| !steal.is_retry())
+        .and_then(Steal::success)
+}
+
+// Add the counters of a worker to the total
+fn absorb(total: &mut Stats, stats: &Stats) {
+    total.nodes += stats.nodes;
+    total.decisions += stats.decisions;
+    total.propagations += stats.propagations;
+    total.conflicts += stats.conflicts;
+    total.simplifications += stats.simplifications;
+    total.restarts += stats.restarts;
+    total.max_depth = total.max_depth.max(stats.max_depth);
+    total.max_tasklist = total.max_tasklist.max(stats.max_tasklist);
+    total.minimized_literals += stats.minimized_literals;
+    total.rephases += stats.rephases;
+    total.failed_literals += stats.failed_literals;
+    total.flips += stats.flips;
+    total.backjumps += stats.backjumps;
+    total.chronological_backtracks += stats.chronological_backtracks;
+    total.garbage_collections += stats.garbage_collections;
+    total.reclaimed_bytes += stats.reclaimed_bytes;
+    total.inprocessing_rounds += stats.inprocessing_rounds;
+}
+
+impl Solver {
+    // Search the tree on workers threads at once. Every worker dives from
+    // a node as build_search_tree does and keeps the false branches it passes
+    // in a deque of its own, taking the latest first; an idle worker steals
+    // the earliest from another one, the largest subtree left there. The
+    // first model stops every worker through the solver's interrupter, the
+    // formula is unsatisfiable once no node is pending anywhere. Each worker
+    // runs the configuration with its own seed, seed plus its index, and its
+    // own learned clauses, short ones shared with the others, but no
+    // restarts; the stats add up theirs.
+    pub fn solve_parallel(&mut self, config: &SolverConfig, workers: usize) -> SolveResult {
+        self.model = None;
+        self.failed.clear();
+        self.load_constraints();
+        let root = self.root(self.initial_assignment());
+        let interrupter = self.interrupter();
+        let injector = Injector::new();
+        injector.push(root);
+        // Nodes queued or being expanded, the search is over at zero
+        let pending = AtomicUsize::new(1);
+        let stopped = AtomicBool::new(false);
+        let found: Mutex<Option<Model>> = Mutex::new(None);
+        let total = Mutex::new(Stats::default());
+        let deques: Vec<Worker<Node>> = (0..workers.max(1)).map(|_| Worker::new_lifo()).collect();
+        let stealers: Vec<Stealer<Node>> = deques.iter().map(Worker::stealer).collect();
+        let template = self.template();
+        let exchange = self.clause_exchange(deques.len());
+        let check_models = cfg!(debug_assertions).then(|| self.formula.clone());
+        thread_pool(deques.len()).scope(|scope| {
+            for (i, local) in deques.into_iter().enumerate() {
+                let (template, interrupter, injector, stealers, exchange) =
+                    (&template, &interrupter, &injector, &stealers, &exchange);
+                let (pending, stopped, found, total, check_models) = (&pending, &stopped, &found, &total, &check_models);
+                scope.spawn(move |_| {
+                    let mut solver = template.solver(interrupter);
+                    config.apply(&mut solver);
+                    solver.set_seed(config.seed + i as u64);
+                    solver.state.restart_policy = None;
+                    solver.state.check_models = check_models.clone();
+                    solver.state.sharing = exchange.clone().map(|exchange| ClauseSharing::new(exchange, i));
+                    solver.load_constraints();
+                    while !interrupter.is_interrupted() {
+                        let Some(node) = local.pop().or_else(|| steal(&local, injector, stealers)) else {
+                            if pending.load(Ordering::SeqCst) == 0 {
+                                break;
+                            }
+                            std::thread::yield_now();
+                            continue;
+                        };
+                        let mut tasklist = Vec::new();
+                        let model = build_search_tree(node, &mut tasklist, solver.heuristic.as_ref(), &mut solver.state);
+                        // The false branches of the dive, the deepest one on top. Other
+                        // workers can steal them, so they take their state along.
+                        pending.fetch_add(tasklist.len(), Ordering::SeqCst);
+                        for task in tasklist {
+                            local.push(task.detach(&solver.state.assignment));
+                        }
+                        pending.fetch_sub(1, Ordering::SeqCst);
+                        if let Some(model) = model {
+                            found.lock().unwrap().get_or_insert(model);
+                            interrupter.interrupt();
+                        } else if solver.state.stopped {
+                            // A deadline or budget ends the whole search
+                            stopped.store(true, Ordering::SeqCst);
+                            interrupter.interrupt();
+                        }
+                    }
+                    absorb(&mut total.lock().unwrap(), &solver.state.stats);
+                });
+            }
+        });
+        self.state.stats = total.into_inner().unwrap();
+        if let Some(model) = found.into_inner().unwrap() {
+            // Only the search tripped the interrupter, let later calls run
+            interrupter.clear();
+            self.model = Some(model.clone());
+            return SolveResult::Sat(model);
+        }
+        if stopped.into_inner() || pending.into_inner() > 0 {
+            return SolveResult::Unknown;
+        }
+        SolveResult::Unsat
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_model, Formula};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_solve_parallel() {
+        let mut rng = StdRng::seed_from_u64(29);
+        for round in 0..100 {
+            let num_vars = rng.gen_range(3..=10);
+            let formula: Formula = (0..rng.gen_range(1..=45))
+                .map(|_| (0..3).map(|_| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 }).collect())
+                .collect();
+            let satisfiable = (0..1u32 << num_vars).any(|bits| {
+                let model: Model = (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect();
+                verify_model(&formula, &model).is_ok()
+            });
+            let config = SolverConfig {
+                learn: round % 2 == 1,
+                ..SolverConfig::default()
+            };
+            let mut solver = Solver::from_clauses(&formula);
+            let result = solver.solve_parallel(&config, 4);
+            assert_eq!(result.is_sat(), satisfiable, "{:?}", formula);
+            assert_eq!(result == SolveResult::Unsat, !satisfiable, "{:?}", formula);
+            if let SolveResult::Sat(model) = result {
+                assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?}", formula, model);
+            }
+        }
+    }
+}
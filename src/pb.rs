@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use crate::opb::{PbConstraint, Relation};
+use crate::Assignment;
+
+// sum of weights of the true literals >= bound, with positive weights
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AtLeast {
+    terms: Vec<(u64, i32)>,
+    bound: u64,
+}
+
+impl AtLeast {
+    // terms >= bound, where a negative coefficient c on l is rewritten as c
+    // plus -c on the negation of l. None if every assignment satisfies it.
+    fn normalize(terms: &[(i64, i32)], bound: i64) -> Option<Self> {
+        let mut bound = bound;
+        let mut normalized = Vec::with_capacity(terms.len());
+        for &(coefficient, lit) in terms.iter() {
+            if coefficient < 0 {
+                bound -= coefficient;
+                normalized.push((coefficient.unsigned_abs(), -lit));
+            } else if coefficient > 0 {
+                normalized.push((coefficient as u64, lit));
+            }
+        }
+        (bound > 0).then_some(AtLeast {
+            terms: normalized,
+            bound: bound as u64,
+        })
+    }
+
+    // How far the weight of the literals that are not false exceeds the
+    // bound, negative once the constraint can no longer be satisfied
+    fn slack(&self, assignment: &Assignment) -> i128 {
+        let open: u64 = self
+            .terms
+            .iter()
+            .filter(|(_, lit)| value(assignment, *lit) != Some(false))
+            .map(|(weight, _)| weight)
+            .sum();
+        open as i128 - self.bound as i128
+    }
+
+    fn is_satisfied(&self, assignment: &Assignment) -> bool {
+        let sum: u64 = self.terms.iter().filter(|(_, lit)| value(assignment, *lit) == Some(true)).map(|(w, _)| w).sum();
+        sum >= self.bound
+    }
+}
+
+fn value(assignment: &Assignment, lit: i32) -> Option<bool> {
+    assignment.get(&lit.abs()).copied().flatten().map(|val| val == (lit > 0))
+}
+
+// Pseudo-Boolean constraints that the search propagates natively instead of
+// through an encoding in clauses. Each is kept as sums of positive weights
+// bounded from below; once the open weight of a constraint exceeds its bound
+// by less than the weight of an unassigned literal, that literal is forced.
+#[derive(Clone, Debug, Default)]
+pub struct PbSystem {
+    constraints: Vec<AtLeast>,
+    vars: HashSet<i32>,
+}
+
+impl PbSystem {
+    pub fn new(constraints: &[PbConstraint]) -> Self {
+        let mut system = PbSystem::default();
+        for constraint in constraints.iter() {
+            let negated: Vec<(i64, i32)> = constraint.terms.iter().map(|&(c, lit)| (-c, lit)).collect();
+            // At most rhs is at least -rhs after negating every coefficient
+            let sides = match constraint.relation {
+                Relation::AtLeast => vec![AtLeast::normalize(&constraint.terms, constraint.rhs)],
+                Relation::AtMost => vec![AtLeast::normalize(&negated, -constraint.rhs)],
+                Relation::Equal => vec![
+                    AtLeast::normalize(&constraint.terms, constraint.rhs),
+                    AtLeast::normalize(&negated, -constraint.rhs),
+                ],
+            };
+            system.constraints.extend(sides.into_iter().flatten());
+            system.vars.extend(constraint.terms.iter().map(|(_, lit)| lit.abs()));
+        }
+        system
+    }
+
+    // Whether the variable occurs in some constraint
+    pub fn contains(&self, var: i32) -> bool {
+        self.vars.contains(&var)
+    }
+
+    // The literals the constraints force under the assignment. None on a
+    // conflict, including two constraints forcing opposite literals.
+    pub fn propagate(&self, assignment: &Assignment) -> Option<Vec<i32>> {
+        let mut implied: Vec<i32> = Vec::new();
+        for constraint in self.constraints.iter() {
+            let slack = constraint.slack(assignment);
+            if slack < 0 {
+                return None;
+            }
+            for &(weight, lit) in constraint.terms.iter() {
+                if weight as i128 > slack && value(assignment, lit).is_none() && !implied.contains(&lit) {
+                    implied.push(lit);
+                }
+            }
+        }
+        if implied.iter().any(|lit| implied.contains(&-lit)) {
+            return None;
+        }
+        Some(implied)
+    }
+
+    // An unassigned variable of the heaviest term of some constraint the
+    // assigned literals do not satisfy yet, to branch on once the clauses are
+    pub fn pick_branch_var(&self, assignment: &Assignment) -> Option<i32> {
+        let constraint = self.constraints.iter().find(|constraint| !constraint.is_satisfied(assignment))?;
+        let open = constraint.terms.iter().filter(|(_, lit)| value(assignment, *lit).is_none());
+        open.max_by_key(|(weight, _)| *weight).map(|&(_, lit)| lit)
+    }
+
+    // Whether the true literals of the assignment satisfy every constraint
+    pub fn is_satisfied(&self, assignment: &Assignment) -> bool {
+        self.constraints.iter().all(|constraint| constraint.is_satisfied(assignment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pb_propagation() {
+        // 3 x1 + 2 x2 + x3 >= 4
+        let constraint = PbConstraint {
+            terms: vec![(3, 1), (2, 2), (1, 3)],
+            relation: Relation::AtLeast,
+            rhs: 4,
+        };
+        let system = PbSystem::new(&[constraint]);
+        // Without x1 the other two weigh 3 at most
+        assert_eq!(system.propagate(&Assignment::new()), Some(vec![1]));
+        let assignment: Assignment = [(2, Some(false))].into_iter().collect();
+        assert_eq!(system.propagate(&assignment), Some(vec![1, 3]));
+        let assignment: Assignment = [(1, Some(false))].into_iter().collect();
+        assert_eq!(system.propagate(&assignment), None);
+
+        // x1 + x2 <= 1 forces not x2 once x1 holds
+        let at_most = PbConstraint {
+            terms: vec![(1, 1), (1, 2)],
+            relation: Relation::AtMost,
+            rhs: 1,
+        };
+        let system = PbSystem::new(&[at_most]);
+        assert_eq!(system.propagate(&Assignment::new()), Some(vec![]));
+        let assignment: Assignment = [(1, Some(true))].into_iter().collect();
+        assert_eq!(system.propagate(&assignment), Some(vec![-2]));
+    }
+}
@@ -0,0 +1,184 @@
+use rand::Rng;
+
+use crate::Assignment;
+
+// Last polarity of every variable, indexed by variable. When enabled, a
+// variable that is branched on again after backtracking is first tried with
+// the value it had before.
+#[derive(Clone, Debug, Default)]
+pub struct SavedPhases {
+    pub enabled: bool,
+    phases: Vec<Option<bool>>,
+    // The values of the conflict that had the most variables assigned since
+    // the last rephasing to them, and how many that were
+    best: Vec<Option<bool>>,
+    best_assigned: usize,
+}
+
+impl SavedPhases {
+    pub fn new(enabled: bool) -> Self {
+        SavedPhases {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    // Remember the value a variable was just assigned
+    pub fn save(&mut self, var: i32, value: bool) {
+        if self.enabled {
+            set(&mut self.phases, var, Some(value));
+        }
+    }
+
+    // The saved polarity of a variable, if any
+    pub fn get(&self, var: i32) -> Option<bool> {
+        self.phases.get(var as usize).copied().flatten()
+    }
+
+    // Keep the assignment of a conflict if it assigns more variables than
+    // any since the last rephasing to the best phases
+    pub fn save_best(&mut self, assignment: &Assignment, assigned: usize) {
+        if assigned <= self.best_assigned {
+            return;
+        }
+        self.best_assigned = assigned;
+        self.best.clear();
+        for (var, val) in assignment.iter() {
+            set(&mut self.best, var, val);
+        }
+    }
+
+    // Replace the saved phases of the variables of the assignment, the
+    // variables the best assignment left open keep theirs
+    pub fn rephase(&mut self, phase: Phase, assignment: &Assignment, rng: &mut impl Rng) {
+        match phase {
+            Phase::Best => {
+                for (var, &val) in self.best.iter().enumerate() {
+                    if val.is_some() {
+                        set(&mut self.phases, var as i32, val);
+                    }
+                }
+                self.best_assigned = 0;
+            }
+            Phase::Random => {
+                for var in assignment.keys() {
+                    set(&mut self.phases, var, Some(rng.gen_bool(0.5)));
+                }
+            }
+            Phase::Original => self.phases.clear(),
+        }
+    }
+
+    // Flip a branching literal to the saved polarity of its variable
+    pub fn apply(&self, lit: i32) -> i32 {
+        match self.get(lit.abs()) {
+            Some(true) if self.enabled => lit.abs(),
+            Some(false) if self.enabled => -lit.abs(),
+            _ => lit,
+        }
+    }
+}
+
+fn set(phases: &mut Vec<Option<bool>>, var: i32, value: Option<bool>) {
+    let index = var as usize;
+    if index >= phases.len() {
+        phases.resize(index + 1, None);
+    }
+    phases[index] = value;
+}
+
+// The phases a rephasing switches to: those of the best assignment, random
+// ones, or none so every branch takes the polarity the heuristic picked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Best,
+    Random,
+    Original,
+}
+
+// The order rephasings go through, going back to the best phases between
+// the others as Kissat does
+const SCHEDULE: [Phase; 4] = [Phase::Best, Phase::Random, Phase::Best, Phase::Original];
+
+// Rephase after interval conflicts, then after a further interval more each
+// time
+#[derive(Clone, Debug)]
+pub struct Rephasing {
+    pub interval: usize,
+    conflicts: usize,
+    count: usize,
+}
+
+impl Rephasing {
+    pub fn new(interval: usize) -> Self {
+        Rephasing {
+            interval,
+            conflicts: 0,
+            count: 0,
+        }
+    }
+
+    // Called after every conflict, returns the phases to switch to when a
+    // rephasing is due
+    pub fn on_conflict(&mut self) -> Option<Phase> {
+        self.conflicts += 1;
+        if self.conflicts < self.interval * (self.count + 1) {
+            return None;
+        }
+        self.conflicts = 0;
+        let phase = SCHEDULE[self.count % SCHEDULE.len()];
+        self.count += 1;
+        Some(phase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchRng;
+
+    #[test]
+    fn test_saved_phases() {
+        let mut phases = SavedPhases::new(true);
+        assert_eq!(phases.apply(3), 3);
+        phases.save(3, false);
+        phases.save(7, true);
+        assert_eq!(phases.get(3), Some(false));
+        assert_eq!(phases.get(5), None);
+        assert_eq!(phases.apply(3), -3);
+        assert_eq!(phases.apply(-7), 7);
+
+        let mut disabled = SavedPhases::new(false);
+        disabled.save(3, false);
+        assert_eq!(disabled.get(3), None);
+        assert_eq!(disabled.apply(3), 3);
+    }
+
+    #[test]
+    fn test_rephase() {
+        let mut phases = SavedPhases::new(true);
+        let assignment: Assignment = [(1, Some(true)), (2, Some(false)), (3, None)].into_iter().collect();
+        phases.save_best(&assignment, 2);
+        // A conflict with fewer variables assigned is no better
+        phases.save_best(&[(1, Some(false))].into_iter().collect(), 1);
+        phases.save(1, false);
+        phases.save(3, true);
+        phases.rephase(Phase::Best, &assignment, &mut SearchRng::default());
+        assert_eq!((phases.get(1), phases.get(2), phases.get(3)), (Some(true), Some(false), Some(true)));
+
+        phases.rephase(Phase::Random, &assignment, &mut SearchRng::default());
+        assert!((1..=3).all(|var| phases.get(var).is_some()));
+        phases.rephase(Phase::Original, &assignment, &mut SearchRng::default());
+        assert_eq!(phases.apply(-2), -2);
+    }
+
+    #[test]
+    fn test_rephasing_schedule() {
+        let mut rephasing = Rephasing::new(2);
+        let phases: Vec<(usize, Phase)> = (1..=40).filter_map(|i| Some((i, rephasing.on_conflict()?))).collect();
+        assert_eq!(
+            phases,
+            vec![(2, Phase::Best), (6, Phase::Random), (12, Phase::Best), (20, Phase::Original), (30, Phase::Best)]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::{
+    heuristic_by_name, Budget, ClauseSharing, Formula, Interrupter, LearnedClauses, LubyRestarts, PbConstraint,
+    Reconstruction, ReductionConfig, SavedPhases, SolveResult, Solver, Stats, XorConstraint,
+};
+
+// The options of one solver in a portfolio
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolverConfig {
+    // Branching heuristic by the names heuristic_by_name takes
+    pub heuristic: String,
+    pub phase_saving: bool,
+    pub random_polarity: f64,
+    pub seed: u64,
+    pub learn: bool,
+    // Luby restarts with this many conflicts per unit of the sequence
+    pub restart_unit: Option<usize>,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            heuristic: "min-index".to_string(),
+            phase_saving: false,
+            random_polarity: 0.0,
+            seed: 0,
+            learn: false,
+            restart_unit: None,
+        }
+    }
+}
+
+impl SolverConfig {
+    // n configurations that differ in heuristic, phases, learning, restarts
+    // and seed, starting with the default one. Past the heuristics they
+    // branch with random polarities as well.
+    pub fn diverse(n: usize) -> Vec<Self> {
+        const HEURISTICS: [&str; 5] = ["min-index", "jw-two-sided", "dlis", "moms", "jw-one-sided"];
+        (0..n)
+            .map(|i| SolverConfig {
+                heuristic: HEURISTICS[i % HEURISTICS.len()].to_string(),
+                phase_saving: i % 2 == 1,
+                random_polarity: if i < HEURISTICS.len() { 0.0 } else { 0.1 },
+                seed: i as u64,
+                learn: i % 3 == 1,
+                restart_unit: (i % 4 == 3).then_some(100),
+            })
+            .collect()
+    }
+
+    // Set up the solver's heuristic and search state for this configuration
+    pub fn apply(&self, solver: &mut Solver) {
+        solver.set_heuristic(heuristic_by_name(&self.heuristic).expect("Unknown heuristic"));
+        solver.state.phases = SavedPhases::new(self.phase_saving);
+        solver.state.random_polarity = self.random_polarity;
+        solver.set_seed(self.seed);
+        solver.state.learned = self.learn.then(|| LearnedClauses::new(ReductionConfig::default()));
+        if let Some(unit) = self.restart_unit {
+            solver.state.restart_policy = Some(Box::new(LubyRestarts::new(unit)));
+        }
+    }
+}
+
+// What the copies of a solver on other threads start from: its constraints,
+// reconstruction, budget and deadline, but not its learned clauses or proof
+pub(crate) struct SolverTemplate<'a> {
+    formula: &'a Formula,
+    xors: &'a [XorConstraint],
+    pbs: &'a [PbConstraint],
+    num_vars: i32,
+    reconstruction: &'a Reconstruction,
+    budget: Budget,
+    deadline: Option<Instant>,
+}
+
+impl SolverTemplate<'_> {
+    // A copy that the interrupter stops
+    pub(crate) fn solver(&self, interrupter: &Interrupter) -> Solver {
+        let mut solver = Solver::from_clauses(self.formula);
+        solver.xors = self.xors.to_vec();
+        solver.pbs = self.pbs.to_vec();
+        solver.num_vars = self.num_vars;
+        solver.state.reconstruction = self.reconstruction.clone();
+        solver.state.budget = self.budget;
+        solver.state.deadline = self.deadline;
+        solver.state.interrupter = Some(interrupter.clone());
+        solver
+    }
+}
+
+// A pool of n threads, so n tasks all run even on fewer cores
+pub(crate) fn thread_pool(n: usize) -> rayon::ThreadPool {
+    rayon::ThreadPoolBuilder::new().num_threads(n).build().expect("Failed to start threads")
+}
+
+impl Solver {
+    pub(crate) fn template(&self) -> SolverTemplate<'_> {
+        SolverTemplate {
+            formula: &self.formula,
+            xors: &self.xors,
+            pbs: &self.pbs,
+            num_vars: self.num_vars,
+            reconstruction: &self.state.reconstruction,
+            budget: self.state.budget,
+            deadline: self.state.deadline,
+        }
+    }
+
+    // Race a copy of the solver per configuration on threads of their own and
+    // answer with the first one to finish, along with the index of its
+    // configuration. The solver's interrupter then stops the others, and
+    // tripping it stops every copy. Copies that learn share their short
+    // learned clauses. The stats become those of the winner.
+    pub fn solve_portfolio(&mut self, configs: &[SolverConfig]) -> (SolveResult, Option<usize>) {
+        self.model = None;
+        self.failed.clear();
+        let interrupter = self.interrupter();
+        let winner: Mutex<Option<(usize, SolveResult, Stats)>> = Mutex::new(None);
+        let template = self.template();
+        let exchange = self.clause_exchange(configs.len());
+        thread_pool(configs.len()).scope(|scope| {
+            for (i, config) in configs.iter().enumerate() {
+                let (template, interrupter, winner, exchange) = (&template, &interrupter, &winner, &exchange);
+                scope.spawn(move |_| {
+                    let mut solver = template.solver(interrupter);
+                    config.apply(&mut solver);
+                    solver.state.sharing = exchange.clone().map(|exchange| ClauseSharing::new(exchange, i));
+                    let result = solver.search(&[]);
+                    if result == SolveResult::Unknown {
+                        return;
+                    }
+                    let mut winner = winner.lock().unwrap();
+                    if winner.is_none() {
+                        *winner = Some((i, result, solver.state.stats.clone()));
+                        interrupter.interrupt();
+                    }
+                });
+            }
+        });
+        let Some((i, result, stats)) = winner.into_inner().unwrap() else {
+            return (SolveResult::Unknown, None);
+        };
+        // Only the race tripped the interrupter, let later calls run
+        interrupter.clear();
+        self.state.stats = stats;
+        if let SolveResult::Sat(model) = &result {
+            self.model = Some(model.clone());
+        }
+        (result, Some(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_model;
+
+    #[test]
+    fn test_solve_portfolio() {
+        // The pigeonhole formula of four pigeons in three holes
+        let var = |pigeon: i32, hole: i32| pigeon * 3 + hole + 1;
+        let mut formula: Vec<Vec<i32>> = (0..4).map(|p| (0..3).map(|h| var(p, h)).collect()).collect();
+        for h in 0..3 {
+            for p in 0..4 {
+                for q in p + 1..4 {
+                    formula.push(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        let configs = SolverConfig::diverse(6);
+        assert_eq!(configs[0], SolverConfig::default());
+        let mut solver = Solver::from_clauses(&formula);
+        let (result, winner) = solver.solve_portfolio(&configs);
+        assert_eq!(result, SolveResult::Unsat);
+        assert!(winner.is_some_and(|i| i < configs.len()));
+
+        // Without one pigeon it is satisfiable
+        let mut solver = Solver::from_clauses(&formula[1..]);
+        assert!(solver.solve_portfolio(&configs).0.is_sat());
+        assert!(verify_model(&formula[1..], solver.model().unwrap()).is_ok());
+        // The race leaves the solver usable on its own
+        assert!(solver.solve());
+
+        // A tripped interrupter stops every copy
+        solver.interrupter().interrupt();
+        assert_eq!(solver.solve_portfolio(&configs), (SolveResult::Unknown, None));
+    }
+}
@@ -0,0 +1,959 @@
+use std::collections::HashMap;
+
+use crate::implication::ImplicationGraph;
+use crate::proof::Proof;
+use crate::Assignment;
+
+// Bitmask over the variables of a clause, a cheap filter for subset tests
+pub(crate) fn signature(clause: &[i32]) -> u64 {
+    clause.iter().fold(0, |sig, &lit| sig | 1 << (lit.unsigned_abs() % 64))
+}
+
+// Clause indices containing each literal
+pub(crate) fn occurrence_lists(formula: &[Vec<i32>]) -> HashMap<i32, Vec<usize>> {
+    let mut occurrences: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, clause) in formula.iter().enumerate() {
+        for &lit in clause.iter() {
+            let list = occurrences.entry(lit).or_default();
+            if list.last() != Some(&i) {
+                list.push(i);
+            }
+        }
+    }
+    occurrences
+}
+
+// Sorted literals of a clause without repetitions
+fn literal_set(clause: &[i32]) -> Vec<i32> {
+    let mut set = clause.to_vec();
+    set.sort_unstable();
+    set.dedup();
+    set
+}
+
+// Whether every literal of the sorted set a is in the sorted set b
+fn is_subset(a: &[i32], b: &[i32]) -> bool {
+    let mut rest = b.iter();
+    a.iter().all(|lit| rest.any(|other| other == lit))
+}
+
+// Remove every clause that is a superset of another clause, keeping one copy
+// of duplicates. Returns the number of removed clauses.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn subsume(formula: &mut Vec<Vec<i32>>) -> usize {
+    let sets: Vec<Vec<i32>> = formula.iter().map(|clause| literal_set(clause)).collect();
+    let signatures: Vec<u64> = sets.iter().map(|set| signature(set)).collect();
+    let occurrences = occurrence_lists(&sets);
+    let mut removed = vec![false; sets.len()];
+
+    let mut order: Vec<usize> = (0..sets.len()).collect();
+    order.sort_by_key(|&i| (sets[i].len(), formula[i].len()));
+    for &i in order.iter() {
+        if removed[i] {
+            continue;
+        }
+        // Candidates must contain the least frequent literal of the clause
+        let Some(candidates) = sets[i].iter().filter_map(|lit| occurrences.get(lit)).min_by_key(|list| list.len()) else {
+            continue;
+        };
+        for &j in candidates.iter() {
+            if j != i
+                && !removed[j]
+                && sets[j].len() >= sets[i].len()
+                && signatures[i] & !signatures[j] == 0
+                && is_subset(&sets[i], &sets[j])
+            {
+                removed[j] = true;
+            }
+        }
+    }
+
+    let mut index = 0;
+    formula.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+    removed.iter().filter(|&&r| r).count()
+}
+
+// Self-subsuming resolution: when C = C' + l and D = D' + -l with C' a subset
+// of D', resolving on l gives D', so -l is dropped from D. Rounds repeat until
+// nothing changes, and clauses that became subsumed are removed at the end.
+// Returns the number of removed literals. Strengthened clauses are added to
+// the proof, if any.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn self_subsume(formula: &mut Vec<Vec<i32>>, mut proof: Option<&mut Proof>) -> usize {
+    let mut sets: Vec<Vec<i32>> = formula.iter().map(|clause| literal_set(clause)).collect();
+    let mut strengthened = 0;
+    loop {
+        let signatures: Vec<u64> = sets.iter().map(|set| signature(set)).collect();
+        let occurrences = occurrence_lists(&sets);
+        let mut order: Vec<usize> = (0..sets.len()).collect();
+        order.sort_by_key(|&i| sets[i].len());
+
+        let mut changed = false;
+        for &i in order.iter() {
+            for k in 0..sets[i].len() {
+                let lit = sets[i][k];
+                let Some(candidates) = occurrences.get(&-lit) else {
+                    continue;
+                };
+                for &j in candidates.iter() {
+                    let (c, d) = (&sets[i], &sets[j]);
+                    if j == i || d.len() < c.len() || !d.contains(&-lit) || signatures[i] & !signatures[j] != 0 {
+                        continue;
+                    }
+                    // C' has to fit into D without -l, which matters for tautologies
+                    if c.iter().all(|&other| other == lit || (other != -lit && d.contains(&other))) {
+                        sets[j].retain(|&other| other != -lit);
+                        if let Some(proof) = proof.as_deref_mut() {
+                            // The resolvent of C and D
+                            let d: Vec<i32> = sets[j].iter().copied().chain([-lit]).collect();
+                            proof.add_derived(&sets[j], [&sets[i][..], &d]);
+                        }
+                        strengthened += 1;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    if strengthened > 0 {
+        *formula = sets;
+        subsume(formula);
+    }
+    strengthened
+}
+
+// Clauses removed by satisfiability-preserving passes, each with the literal
+// that may be flipped to satisfy it again. Replaying them last to first turns
+// a model of the simplified formula into a model of the original one.
+#[derive(Clone, Debug, Default)]
+pub struct Reconstruction {
+    stack: Vec<(i32, Vec<i32>)>,
+}
+
+impl Reconstruction {
+    pub fn push(&mut self, pivot: i32, clause: Vec<i32>) {
+        self.stack.push((pivot, clause));
+    }
+
+    pub fn len(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    // Repair a total model so that every removed clause is satisfied.
+    // Variables that only occur in removed clauses start out true.
+    pub fn extend_model(&self, model: &mut Assignment) {
+        for lit in self.stack.iter().flat_map(|(_, clause)| clause.iter()) {
+            if !model.contains_key(&lit.abs()) {
+                model.insert(lit.abs(), Some(true));
+            }
+        }
+        for (pivot, clause) in self.stack.iter().rev() {
+            let satisfied = clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&Some(lit > 0)));
+            if !satisfied {
+                model.insert(pivot.abs(), Some(*pivot > 0));
+            }
+        }
+    }
+}
+
+// Whether a clause contains some literal together with its negation
+fn is_tautology(clause: &[i32]) -> bool {
+    clause.iter().any(|&lit| clause.contains(&-lit))
+}
+
+// Resolve c and d on var, None if the resolvent is a tautology
+fn resolve(c: &[i32], d: &[i32], var: i32) -> Option<Vec<i32>> {
+    let mut resolvent: Vec<i32> = c.iter().chain(d.iter()).copied().filter(|lit| lit.abs() != var).collect();
+    resolvent.sort_unstable();
+    resolvent.dedup();
+    if is_tautology(&resolvent) {
+        return None;
+    }
+    Some(resolvent)
+}
+
+// Bounded variable elimination in the style of SatELite: a variable whose
+// positive and negative occurrence counts multiply to at most max_product is
+// replaced by all non-tautological resolvents of its clauses, as long as that
+// does not increase the number of clauses. Variables keep returns true for
+// stay. Removed clauses go onto the reconstruction stack and resolvents to
+// the proof, if any. Returns the number of eliminated variables.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn eliminate_variables(
+    formula: &mut Vec<Vec<i32>>,
+    reconstruction: &mut Reconstruction,
+    max_product: usize,
+    keep: impl Fn(i32) -> bool,
+    mut proof: Option<&mut Proof>,
+) -> usize {
+    // Tautologies are always satisfied and would sit in both occurrence lists
+    let mut clauses: Vec<Option<Vec<i32>>> = formula
+        .iter()
+        .map(|clause| Some(literal_set(clause)).filter(|set| !is_tautology(set)))
+        .collect();
+    let sets: Vec<Vec<i32>> = clauses.iter().map(|clause| clause.clone().unwrap_or_default()).collect();
+    let mut occurrences = occurrence_lists(&sets);
+
+    let mut candidates: Vec<i32> = occurrences.keys().map(|lit| lit.abs()).filter(|&var| !keep(var)).collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+    let count = |occurrences: &HashMap<i32, Vec<usize>>, lit: i32| occurrences.get(&lit).map_or(0, Vec::len);
+    candidates.sort_by_key(|&var| count(&occurrences, var) * count(&occurrences, -var));
+
+    let mut eliminated = 0;
+    for var in candidates {
+        let live = |lit: i32, clauses: &[Option<Vec<i32>>], occurrences: &HashMap<i32, Vec<usize>>| -> Vec<usize> {
+            occurrences
+                .get(&lit)
+                .map(|list| list.iter().copied().filter(|&i| clauses[i].is_some()).collect())
+                .unwrap_or_default()
+        };
+        let pos = live(var, &clauses, &occurrences);
+        let neg = live(-var, &clauses, &occurrences);
+        if pos.len() * neg.len() > max_product {
+            continue;
+        }
+
+        let mut resolvents = Vec::new();
+        for &i in pos.iter() {
+            for &j in neg.iter() {
+                if let Some(resolvent) = resolve(clauses[i].as_ref().unwrap(), clauses[j].as_ref().unwrap(), var) {
+                    resolvents.push((resolvent, i, j));
+                }
+            }
+            if resolvents.len() > pos.len() + neg.len() {
+                break;
+            }
+        }
+        if resolvents.len() > pos.len() + neg.len() {
+            continue;
+        }
+
+        if let Some(proof) = proof.as_deref_mut() {
+            for (resolvent, i, j) in resolvents.iter() {
+                proof.add_derived(resolvent, [clauses[*i].as_deref().unwrap(), clauses[*j].as_deref().unwrap()]);
+            }
+        }
+        for &i in pos.iter() {
+            reconstruction.push(var, clauses[i].take().unwrap());
+        }
+        for &j in neg.iter() {
+            reconstruction.push(-var, clauses[j].take().unwrap());
+        }
+        for (resolvent, _, _) in resolvents {
+            for &lit in resolvent.iter() {
+                occurrences.entry(lit).or_default().push(clauses.len());
+            }
+            clauses.push(Some(resolvent));
+        }
+        eliminated += 1;
+    }
+
+    *formula = clauses.into_iter().flatten().collect();
+    eliminated
+}
+
+// Blocked clause elimination: a clause is blocked on one of its literals l
+// when every resolvent on l with a clause containing -l is a tautology.
+// Removing it preserves satisfiability and the clause is put on the
+// reconstruction stack with l as its pivot. A clause is never blocked on a
+// variable keep returns true for, since a later clause could resolve with it.
+// Repeats until no clause is blocked and returns the number of removed
+// clauses.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn eliminate_blocked(
+    formula: &mut Vec<Vec<i32>>,
+    reconstruction: &mut Reconstruction,
+    keep: impl Fn(i32) -> bool,
+) -> usize {
+    let mut clauses: Vec<Option<Vec<i32>>> = formula.iter().map(|clause| Some(literal_set(clause))).collect();
+    let sets: Vec<Vec<i32>> = clauses.iter().flatten().cloned().collect();
+    let occurrences = occurrence_lists(&sets);
+
+    let mut removed = 0;
+    loop {
+        let mut changed = false;
+        for i in 0..clauses.len() {
+            let Some(clause) = clauses[i].as_ref() else {
+                continue;
+            };
+            let blocked_on = clause.iter().copied().filter(|lit| !keep(lit.abs())).find(|&lit| {
+                occurrences.get(&-lit).is_none_or(|list| {
+                    list.iter().all(|&j| {
+                        j == i || clauses[j].as_ref().is_none_or(|other| resolve(clause, other, lit.abs()).is_none())
+                    })
+                })
+            });
+            if let Some(lit) = blocked_on {
+                reconstruction.push(lit, clauses[i].take().unwrap());
+                removed += 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    *formula = clauses.into_iter().flatten().collect();
+    removed
+}
+
+// Equivalent literal substitution: literals on a cycle of binary clauses
+// imply each other, so every one is replaced by the literal of the smallest
+// variable equivalent to it; variables keep returns true for are left in
+// place. Clauses that turn into tautologies are dropped, rewritten clauses
+// are added to the proof, if any, and a substituted variable goes onto the
+// reconstruction stack as the two clauses tying it to its representative.
+// A literal equivalent to its negation makes the formula a single empty
+// clause. Returns the number of substituted variables.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn substitute_equivalences(
+    formula: &mut Vec<Vec<i32>>,
+    reconstruction: &mut Reconstruction,
+    keep: impl Fn(i32) -> bool,
+    mut proof: Option<&mut Proof>,
+) -> usize {
+    let graph = ImplicationGraph::new(formula);
+    let Some(mut representatives) = graph.equivalences() else {
+        if let Some(proof) = proof {
+            // Such a literal and its negation both fail, and propagating the
+            // negation of one runs into the other
+            let failed = graph.failed_literals();
+            let lit = failed.iter().copied().find(|lit| failed.contains(&-lit)).unwrap();
+            proof.add(&[-lit]);
+            proof.add(&[]);
+        }
+        *formula = vec![Vec::new()];
+        return 0;
+    };
+    for var in 1..representatives.len() as i32 {
+        if keep(var) {
+            representatives[var as usize] = var;
+        }
+    }
+    let substituted: Vec<i32> = (1..representatives.len() as i32)
+        .filter(|&var| representatives[var as usize] != var)
+        .collect();
+    if substituted.is_empty() {
+        return 0;
+    }
+
+    let substitute = |lit: i32| match representatives.get(lit.unsigned_abs() as usize) {
+        Some(&representative) if lit > 0 => representative,
+        Some(&representative) => -representative,
+        None => lit,
+    };
+    let mut rewritten = Vec::with_capacity(formula.len());
+    for clause in formula.iter() {
+        if clause.iter().all(|&lit| substitute(lit) == lit) {
+            rewritten.push(clause.clone());
+            continue;
+        }
+        let clause = literal_set(&clause.iter().map(|&lit| substitute(lit)).collect::<Vec<i32>>());
+        if is_tautology(&clause) {
+            continue;
+        }
+        if let Some(proof) = proof.as_deref_mut() {
+            proof.add(&clause);
+        }
+        rewritten.push(clause);
+    }
+    for &var in substituted.iter() {
+        let representative = representatives[var as usize];
+        reconstruction.push(var, vec![var, -representative]);
+        reconstruction.push(-var, vec![-var, representative]);
+    }
+    *formula = rewritten;
+    substituted.len()
+}
+
+// Autarky elimination: an autarky is a partial assignment that satisfies
+// every clause mentioning one of its variables, so the rest of the formula is
+// satisfiable exactly when the whole is. Starting from the more frequent
+// polarity of every variable, the variables of each clause the assignment
+// touches but does not satisfy are unassigned until none is left; a pure
+// literal always survives, so this generalizes pure literal elimination.
+// Clauses satisfied by the autarky are removed and its literals pushed onto
+// the reconstruction stack. Variables keep returns true for are never part
+// of an autarky. Repeats on what remains until no autarky is found and
+// returns the number of removed clauses.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn eliminate_autarkies(
+    formula: &mut Vec<Vec<i32>>,
+    reconstruction: &mut Reconstruction,
+    keep: impl Fn(i32) -> bool,
+) -> usize {
+    let mut removed = 0;
+    loop {
+        let occurrences = occurrence_lists(formula);
+        let mut values: HashMap<i32, bool> = HashMap::new();
+        for var in formula.iter().flatten().map(|lit| lit.abs()).filter(|&var| !keep(var)) {
+            let count = |lit: i32| occurrences.get(&lit).map_or(0, Vec::len);
+            values.insert(var, count(var) >= count(-var));
+        }
+
+        let mut queue: Vec<usize> = (0..formula.len()).collect();
+        while let Some(i) = queue.pop() {
+            let touched = formula[i].iter().any(|lit| values.contains_key(&lit.abs()));
+            let satisfied = formula[i].iter().any(|lit| values.get(&lit.abs()) == Some(&(*lit > 0)));
+            if !touched || satisfied {
+                continue;
+            }
+            for lit in formula[i].iter() {
+                if values.remove(&lit.abs()).is_some() {
+                    // Clauses satisfied through this variable need another look
+                    for polarity in [lit.abs(), -lit.abs()] {
+                        queue.extend(occurrences.get(&polarity).into_iter().flatten());
+                    }
+                }
+            }
+        }
+        if values.is_empty() {
+            break;
+        }
+
+        let before = formula.len();
+        formula.retain(|clause| !clause.iter().any(|lit| values.contains_key(&lit.abs())));
+        removed += before - formula.len();
+        let mut autarky: Vec<i32> = values.into_iter().map(|(var, val)| if val { var } else { -var }).collect();
+        autarky.sort_unstable_by_key(|lit| lit.abs());
+        for lit in autarky {
+            reconstruction.push(lit, vec![lit]);
+        }
+    }
+    removed
+}
+
+// Unit propagation over every clause of the formula but the skipped one. The
+// number of clause visits is added to effort. Returns false when some clause
+// is falsified.
+pub(crate) fn propagate(formula: &[Vec<i32>], skip: impl Fn(usize) -> bool, values: &mut HashMap<i32, bool>, effort: &mut usize) -> bool {
+    loop {
+        let mut changed = false;
+        for (i, clause) in formula.iter().enumerate() {
+            if skip(i) {
+                continue;
+            }
+            *effort += 1;
+            let mut open = None;
+            let mut open_count = 0;
+            let mut satisfied = false;
+            for &lit in clause.iter() {
+                match values.get(&lit.abs()) {
+                    Some(&val) if val == (lit > 0) => {
+                        satisfied = true;
+                        break;
+                    }
+                    Some(_) => {}
+                    None if open != Some(lit) => {
+                        open = Some(lit);
+                        open_count += 1;
+                    }
+                    None => {}
+                }
+            }
+            if satisfied {
+                continue;
+            }
+            match (open_count, open) {
+                (0, _) => return false,
+                (1, Some(lit)) => {
+                    values.insert(lit.abs(), lit > 0);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        if !changed {
+            return true;
+        }
+    }
+}
+
+// Replace the formula by its simplification under the top-level literals,
+// keeping those literals as unit clauses. The units and every shortened
+// clause are added to the proof, if any.
+fn apply_units(formula: &mut Vec<Vec<i32>>, units: &HashMap<i32, bool>, mut proof: Option<&mut Proof>) {
+    let value = |lit: i32| units.get(&lit.abs()).map(|&val| val == (lit > 0));
+    let mut vars: Vec<i32> = units.keys().copied().collect();
+    vars.sort_unstable();
+    let mut simplified: Vec<Vec<i32>> = Vec::new();
+    for var in vars {
+        let unit = vec![if units[&var] { var } else { -var }];
+        if let Some(proof) = proof.as_deref_mut() {
+            proof.add(&unit);
+        }
+        simplified.push(unit);
+    }
+    for clause in formula.iter() {
+        if clause.iter().any(|&lit| value(lit) == Some(true)) {
+            continue;
+        }
+        let shortened: Vec<i32> = clause.iter().copied().filter(|&lit| value(lit).is_none()).collect();
+        if let Some(proof) = proof.as_deref_mut().filter(|_| shortened.len() < clause.len()) {
+            // The clause and the units falsifying the literals left out
+            let units = clause.iter().filter(|&&lit| value(lit) == Some(false)).map(|&lit| vec![-lit]);
+            let antecedents: Vec<Vec<i32>> = std::iter::once(clause.clone()).chain(units).collect();
+            proof.add_derived(&shortened, antecedents.iter().map(Vec::as_slice));
+        }
+        simplified.push(shortened);
+    }
+    // Units go last, as before
+    simplified.rotate_left(units.len());
+    *formula = simplified;
+}
+
+// Limits that keep probing bounded on large formulas
+#[derive(Clone, Copy, Debug)]
+pub struct ProbeConfig {
+    // Literals probed at most
+    pub max_probes: usize,
+    // Clause visits during propagation before probing stops
+    pub max_effort: usize,
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        ProbeConfig {
+            max_probes: 1000,
+            max_effort: 10_000_000,
+        }
+    }
+}
+
+// Failed-literal probing: assert a literal, propagate, and when that ends in
+// a conflict assert its negation for good. Variables are probed by decreasing
+// number of occurrences, both polarities each. Returns the number of failed
+// literals; a formula found unsatisfiable becomes a single empty clause.
+// Derived clauses are added to the proof, if any.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn probe_failed_literals(formula: &mut Vec<Vec<i32>>, config: &ProbeConfig, mut proof: Option<&mut Proof>) -> usize {
+    let mut units = HashMap::new();
+    let mut effort = 0;
+    if !propagate(formula, |_| false, &mut units, &mut effort) {
+        if let Some(proof) = proof {
+            proof.add(&[]);
+        }
+        *formula = vec![Vec::new()];
+        return 0;
+    }
+
+    let occurrences = occurrence_lists(formula);
+    let mut vars: Vec<i32> = occurrences.keys().map(|lit| lit.abs()).collect();
+    vars.sort_unstable();
+    vars.dedup();
+    let count = |lit: i32| occurrences.get(&lit).map_or(0, Vec::len);
+    vars.sort_by_key(|&var| std::cmp::Reverse(count(var) + count(-var)));
+
+    let mut probes = 0;
+    let mut failed = 0;
+    'probing: for var in vars {
+        for lit in [var, -var] {
+            if units.contains_key(&var) {
+                continue 'probing;
+            }
+            if probes >= config.max_probes || effort >= config.max_effort {
+                break 'probing;
+            }
+            probes += 1;
+            let mut values = units.clone();
+            values.insert(var, lit > 0);
+            if propagate(formula, |_| false, &mut values, &mut effort) {
+                continue;
+            }
+            failed += 1;
+            units.insert(var, lit < 0);
+            if let Some(proof) = proof.as_deref_mut() {
+                proof.add(&[-lit]);
+            }
+            if !propagate(formula, |_| false, &mut units, &mut effort) {
+                if let Some(proof) = proof {
+                    proof.add(&[]);
+                }
+                *formula = vec![Vec::new()];
+                return failed;
+            }
+        }
+    }
+
+    apply_units(formula, &units, proof);
+    failed
+}
+
+// Limits that keep vivification bounded on large formulas
+#[derive(Clone, Copy, Debug)]
+pub struct VivifyConfig {
+    // Clauses vivified at most, longest first
+    pub max_clauses: usize,
+    // Clause visits during propagation before vivification stops
+    pub max_effort: usize,
+}
+
+impl Default for VivifyConfig {
+    fn default() -> Self {
+        VivifyConfig {
+            max_clauses: 1000,
+            max_effort: 10_000_000,
+        }
+    }
+}
+
+// Vivification: falsify the literals of a clause one by one and propagate the
+// rest of the formula. A conflict, or a later literal of the clause becoming
+// true, proves the literals tried so far form an implied clause, and a literal
+// becoming false is dropped. The clause is replaced by what is left, or
+// removed if the others already imply it. Returns the number of clauses
+// strengthened or removed, strengthened clauses are added to the proof, if any.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn vivify(formula: &mut Vec<Vec<i32>>, config: &VivifyConfig, mut proof: Option<&mut Proof>) -> usize {
+    let mut candidates: Vec<usize> = (0..formula.len())
+        .filter(|&i| formula[i].len() > 1 && !is_tautology(&formula[i]))
+        .collect();
+    candidates.sort_by_key(|&i| std::cmp::Reverse(formula[i].len()));
+    candidates.truncate(config.max_clauses);
+
+    let mut removed = vec![false; formula.len()];
+    let mut effort = 0;
+    let mut changed = 0;
+    for i in candidates {
+        if effort >= config.max_effort {
+            break;
+        }
+        let skip = |j: usize| j == i || removed[j];
+        let mut values = HashMap::new();
+        let mut kept = Vec::new();
+        let mut implied = !propagate(formula, skip, &mut values, &mut effort);
+        if !implied {
+            for &lit in formula[i].iter() {
+                match values.get(&lit.abs()) {
+                    Some(&val) if val == (lit > 0) => {
+                        kept.push(lit);
+                        implied = true;
+                        break;
+                    }
+                    Some(_) => continue,
+                    None => {}
+                }
+                kept.push(lit);
+                values.insert(lit.abs(), lit < 0);
+                if !propagate(formula, skip, &mut values, &mut effort) {
+                    implied = true;
+                    break;
+                }
+            }
+        }
+        if implied && kept.len() == formula[i].len() {
+            removed[i] = true;
+        } else if kept.len() < formula[i].len() {
+            if let Some(proof) = proof.as_deref_mut() {
+                proof.add(&kept);
+            }
+            formula[i] = kept;
+        } else {
+            continue;
+        }
+        changed += 1;
+    }
+
+    let mut i = 0;
+    formula.retain(|_| {
+        i += 1;
+        !removed[i - 1]
+    });
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use maplit::hashmap;
+
+    #[test]
+    fn test_subsume() {
+        let mut formula = vec![
+            vec![1, 2, 3],
+            vec![-1, 4],
+            vec![2, 1],
+            vec![-1, 4, -5],
+            vec![3, 3, -2],
+            vec![-2, 3],
+            vec![2, 1],
+            vec![4, 5, 6],
+        ];
+        assert_eq!(subsume(&mut formula), 4);
+        assert_eq!(
+            formula,
+            vec![vec![-1, 4], vec![2, 1], vec![-2, 3], vec![4, 5, 6]]
+        );
+    }
+
+    #[test]
+    fn test_occurrence_lists() {
+        let formula = vec![vec![1, -2, 1], vec![-2, 3]];
+        let occurrences = occurrence_lists(&formula);
+        assert_eq!(occurrences[&1], vec![0]);
+        assert_eq!(occurrences[&-2], vec![0, 1]);
+        assert!(!occurrences.contains_key(&2));
+    }
+
+    #[test]
+    fn test_self_subsume() {
+        let mut formula = vec![
+            vec![1, 2],
+            vec![-1, 2, 3],
+            vec![-2, 4],
+            vec![2, 4, 5],
+        ];
+        // [1, 2] strengthens [-1, 2, 3] to [2, 3], and [-2, 4] turns
+        // [2, 4, 5] into [4, 5]
+        assert_eq!(self_subsume(&mut formula, None), 2);
+        assert_eq!(
+            formula,
+            vec![vec![1, 2], vec![2, 3], vec![-2, 4], vec![4, 5]]
+        );
+
+        // Strengthening cascades and the result subsumes other clauses
+        let mut formula = vec![vec![1], vec![-1, 2], vec![-2, 3], vec![3, 4, 5]];
+        assert_eq!(self_subsume(&mut formula, None), 2);
+        assert_eq!(formula, vec![vec![1], vec![2], vec![3]]);
+
+        let mut formula = vec![vec![1], vec![-1]];
+        self_subsume(&mut formula, None);
+        assert!(formula.iter().any(|clause| clause.is_empty()));
+
+        // Resolving the tautology [7, -7, -5] with [-5, -7, 1] on 7 gives
+        // [-7, -5, 1] back, so nothing may be removed
+        let mut formula = vec![vec![7, -7, -5], vec![-5, -7, 1]];
+        assert_eq!(self_subsume(&mut formula, None), 0);
+    }
+
+    fn satisfies(formula: &[Vec<i32>], model: &Assignment) -> bool {
+        formula.iter().all(|clause| clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&Some(lit > 0))))
+    }
+
+    #[test]
+    fn test_eliminate_variables() {
+        let original = vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![-1, 4],
+            vec![-4, 5],
+            vec![-4, -5, 2],
+            vec![3, -2, 6],
+            vec![-3, -6],
+            vec![-6, 2],
+        ];
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert_eq!(eliminate_variables(&mut formula, &mut reconstruction, 4, |_| true, None), 0);
+        assert!(reconstruction.is_empty());
+        let eliminated = eliminate_variables(&mut formula, &mut reconstruction, 4, |_| false, None);
+        assert!(eliminated > 0);
+        assert!(formula.len() <= original.len());
+        assert!(!reconstruction.is_empty());
+
+        // Every model of the reduced formula extends to a model of the original
+        let mut models = 0;
+        for bits in 0..64 {
+            let mut model: Assignment = (1..=6).map(|var| (var, Some(bits >> (var - 1) & 1 == 1))).collect();
+            if satisfies(&formula, &model) {
+                models += 1;
+                reconstruction.extend_model(&mut model);
+                assert!(satisfies(&original, &model));
+            }
+        }
+        assert!(models > 0);
+    }
+
+    #[test]
+    fn test_eliminate_unsatisfiable() {
+        let mut formula = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]];
+        let mut reconstruction = Reconstruction::default();
+        eliminate_variables(&mut formula, &mut reconstruction, 16, |_| false, None);
+        assert!(formula.iter().any(|clause| clause.is_empty()));
+    }
+
+    #[test]
+    fn test_keep_variables() {
+        // 1 and 2 are equivalent and eliminating either is cheap, but 2 is
+        // frozen and never becomes a pivot of the reconstruction stack
+        let original = vec![vec![-1, 2], vec![-2, 1], vec![1, 3], vec![-3, 4]];
+        let keep = |var| var == 2;
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert_eq!(substitute_equivalences(&mut formula, &mut reconstruction, keep, None), 0);
+        assert_eq!(formula, original);
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert!(eliminate_variables(&mut formula, &mut reconstruction, 16, keep, None) > 0);
+        assert!(reconstruction.stack.iter().all(|(pivot, _)| pivot.abs() != 2));
+
+        // No clause is blocked on a frozen literal nor satisfied by an
+        // autarky over it
+        let mut formula = vec![vec![2, 3]];
+        assert_eq!(eliminate_blocked(&mut formula, &mut Reconstruction::default(), |var| var <= 3), 0);
+        assert_eq!(eliminate_autarkies(&mut formula, &mut Reconstruction::default(), |var| var <= 3), 0);
+        assert_eq!(formula, vec![vec![2, 3]]);
+    }
+
+    #[test]
+    fn test_reconstruction_flips_pivot() {
+        let mut reconstruction = Reconstruction::default();
+        reconstruction.push(3, vec![3, -1]);
+        reconstruction.push(-3, vec![-3, 2]);
+        let mut model: Assignment = hashmap! { 1 => Some(true), 2 => Some(true), 3 => Some(false) }.into();
+        reconstruction.extend_model(&mut model);
+        // [-3, 2] already holds, [3, -1] needs 3 to be true
+        assert_eq!(model[&3], Some(true));
+    }
+
+    #[test]
+    fn test_eliminate_blocked() {
+        // [1, 2] is blocked on 1, its only resolvent [2, -2] is a tautology
+        let original = vec![
+            vec![1, 2],
+            vec![-1, -2],
+            vec![2, 3],
+            vec![-2, -3, 4],
+            vec![-4, 1],
+        ];
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        let removed = eliminate_blocked(&mut formula, &mut reconstruction, |_| false);
+        assert!(removed > 0);
+        assert_eq!(formula.len() + removed, original.len());
+
+        for bits in 0..16 {
+            let mut model: Assignment = (1..=4).map(|var| (var, Some(bits >> (var - 1) & 1 == 1))).collect();
+            if satisfies(&formula, &model) {
+                reconstruction.extend_model(&mut model);
+                assert!(satisfies(&original, &model));
+            }
+        }
+
+        // Nothing is blocked in an unsatisfiable core
+        let mut formula = vec![vec![1, 2], vec![-1, 2], vec![1, -2], vec![-1, -2]];
+        assert_eq!(eliminate_blocked(&mut formula, &mut Reconstruction::default(), |_| false), 0);
+    }
+
+    #[test]
+    fn test_eliminate_autarkies() {
+        // 1 = 2 = 3 = true satisfies the first three clauses although 1 is
+        // not pure, while 4 and 5 form an unsatisfiable core
+        let original = vec![
+            vec![1, 2],
+            vec![1, 3],
+            vec![-1, 2],
+            vec![4, 5],
+            vec![-4, -5],
+            vec![4, -5],
+            vec![-4, 5],
+        ];
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert_eq!(eliminate_autarkies(&mut formula, &mut reconstruction, |_| false), 3);
+        assert_eq!(formula, original[3..].to_vec());
+
+        // Satisfiable: removing the autarky leaves nothing, and any model of
+        // the empty rest is repaired into a model of the original
+        let original = vec![vec![1, -2], vec![2, 3], vec![-1, 3], vec![-3, 1, 2]];
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert_eq!(eliminate_autarkies(&mut formula, &mut reconstruction, |_| false), 4);
+        for bits in 0..8 {
+            let mut model: Assignment = (1..=3).map(|var| (var, Some(bits >> (var - 1) & 1 == 1))).collect();
+            reconstruction.extend_model(&mut model);
+            assert!(satisfies(&original, &model));
+        }
+    }
+
+    #[test]
+    fn test_substitute_equivalences() {
+        // 1, 2 and -3 imply each other around a cycle, 4 does not
+        let original = vec![
+            vec![-1, 2],
+            vec![-2, -3],
+            vec![3, 1],
+            vec![2, 3, 4],
+            vec![-3, -4, 5],
+            vec![1, 4],
+        ];
+        let mut formula = original.clone();
+        let mut reconstruction = Reconstruction::default();
+        assert_eq!(substitute_equivalences(&mut formula, &mut reconstruction, |_| false, None), 2);
+        assert_eq!(formula, vec![vec![-4, 1, 5], vec![1, 4]]);
+        for bits in 0..32 {
+            let mut model: Assignment = [1, 4, 5].iter().map(|&var| (var, Some(bits >> (var - 1) & 1 == 1))).collect();
+            if satisfies(&formula, &model) {
+                reconstruction.extend_model(&mut model);
+                assert!(satisfies(&original, &model));
+            }
+        }
+
+        // Nothing to substitute without a cycle
+        let mut formula = vec![vec![-1, 2], vec![-2, 3]];
+        assert_eq!(substitute_equivalences(&mut formula, &mut Reconstruction::default(), |_| false, None), 0);
+        assert_eq!(formula.len(), 2);
+
+        // 1 is equivalent to -1
+        let mut formula = vec![vec![-1, 2], vec![-2, -1], vec![1, 3], vec![-3, 1]];
+        substitute_equivalences(&mut formula, &mut Reconstruction::default(), |_| false, None);
+        assert_eq!(formula, vec![Vec::<i32>::new()]);
+    }
+
+    #[test]
+    fn test_probe_failed_literals() {
+        // 1 implies 2 and 3, which clash, so 1 has to be false
+        let mut formula = vec![
+            vec![-1, 2],
+            vec![-1, 3],
+            vec![-2, -3],
+            vec![1, 4, 5],
+            vec![-4, 5, 6],
+        ];
+        assert_eq!(probe_failed_literals(&mut formula, &ProbeConfig::default(), None), 1);
+        assert!(formula.contains(&vec![-1]));
+        assert!(formula.contains(&vec![4, 5]));
+        assert!(!formula.iter().any(|clause| clause.contains(&1) || clause.contains(&-1) && clause.len() > 1));
+
+        // Both polarities of 1 fail
+        let mut formula = vec![vec![-1, 2], vec![-1, -2], vec![1, 3], vec![1, -3]];
+        probe_failed_literals(&mut formula, &ProbeConfig::default(), None);
+        assert_eq!(formula, vec![Vec::<i32>::new()]);
+
+        // Without probes the formula is left alone apart from propagation
+        let mut formula = vec![vec![-1, 2], vec![-1, 3], vec![-2, -3], vec![1, 4, 5]];
+        let config = ProbeConfig {
+            max_probes: 0,
+            ..Default::default()
+        };
+        assert_eq!(probe_failed_literals(&mut formula, &config, None), 0);
+        assert_eq!(formula.len(), 4);
+    }
+
+    #[test]
+    fn test_vivify() {
+        // Falsifying 1 forces 2 and then 3, so [1, 3, 4] shrinks to [1, 3] and
+        // [1, 2, 5] to [1, 2], after which the original [1, 2] is redundant
+        let mut formula = vec![vec![1, 3, 4], vec![1, 2], vec![-2, 3], vec![1, 2, 5], vec![-3, 4, 5]];
+        assert_eq!(vivify(&mut formula, &VivifyConfig::default(), None), 3);
+        assert_eq!(formula, vec![vec![1, 3], vec![-2, 3], vec![1, 2], vec![-3, 4, 5]]);
+
+        // No clause can be shortened
+        let mut formula = vec![vec![1, 2], vec![-1, -2], vec![1, 3]];
+        assert_eq!(vivify(&mut formula, &VivifyConfig::default(), None), 0);
+        assert_eq!(formula.len(), 3);
+    }
+}
@@ -0,0 +1,51 @@
+use std::time::{Duration, Instant};
+
+// A snapshot of a running search
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Progress {
+    // Nodes expanded so far
+    pub nodes: usize,
+    // Decisions on the path to the current node
+    pub depth: usize,
+    // Clauses left unsatisfied at the current node
+    pub clauses: usize,
+    // Time since the search started
+    pub elapsed: Duration,
+}
+
+// Calls back every interval expanded nodes with a progress snapshot
+pub struct ProgressReporter {
+    interval: usize,
+    callback: Box<dyn FnMut(&Progress) + Send>,
+    start: Instant,
+}
+
+impl ProgressReporter {
+    pub fn new(interval: usize, callback: impl FnMut(&Progress) + Send + 'static) -> Self {
+        assert!(interval > 0, "The progress interval must be positive");
+        ProgressReporter {
+            interval,
+            callback: Box::new(callback),
+            start: Instant::now(),
+        }
+    }
+
+    // Start measuring elapsed time from now
+    pub(crate) fn restart_clock(&mut self) {
+        self.start = Instant::now();
+    }
+
+    // Called on every expanded node with the node count so far
+    pub(crate) fn on_node(&mut self, nodes: usize, depth: usize, clauses: usize) {
+        if !nodes.is_multiple_of(self.interval) {
+            return;
+        }
+        let progress = Progress {
+            nodes,
+            depth,
+            clauses,
+            elapsed: self.start.elapsed(),
+        };
+        (self.callback)(&progress);
+    }
+}
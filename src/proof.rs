@@ -0,0 +1,731 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::preprocess::propagate;
+use crate::Assignment;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofFormat {
+    Drat,
+    // DRAT with clause ids and the ids of the clauses each lemma follows from
+    Lrat,
+}
+
+impl ProofFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "drat" => Some(ProofFormat::Drat),
+            "lrat" => Some(ProofFormat::Lrat),
+            _ => None,
+        }
+    }
+}
+
+fn negation(decisions: &[i32]) -> Vec<i32> {
+    decisions.iter().map(|&lit| -lit).collect()
+}
+
+fn key(clause: &[i32]) -> Vec<i32> {
+    let mut key = clause.to_vec();
+    key.sort_unstable();
+    key.dedup();
+    key
+}
+
+// The clause that forced each literal the search propagated, in the order
+// they were assigned, so that the lemma of a conflict can name the clauses it
+// follows from
+#[derive(Default)]
+pub(crate) struct Antecedents {
+    // The depth of the node whose propagation forced the literal, the literal
+    // and its clause
+    forced: Vec<(usize, i32, Vec<i32>)>,
+    // The depth of the node being expanded
+    depth: usize,
+    // The clause the latest propagation falsified
+    conflict: Option<Vec<i32>>,
+}
+
+impl Antecedents {
+    pub(crate) fn record(&mut self, lit: i32, clause: &[i32]) {
+        self.forced.push((self.depth, lit, clause.to_vec()));
+    }
+
+    pub(crate) fn record_conflict(&mut self, clause: &[i32]) {
+        self.conflict = Some(clause.to_vec());
+    }
+
+    // Forget the literals forced at the depth or deeper, before a node at
+    // that depth is expanded
+    pub(crate) fn undo(&mut self, depth: usize) {
+        while self.forced.last().is_some_and(|&(at, _, _)| at >= depth) {
+            self.forced.pop();
+        }
+        self.depth = depth;
+        self.conflict = None;
+    }
+
+    // The clauses a conflict follows from under the decisions, in the order
+    // they were used and ending with the falsified clause: the reasons of the
+    // literals it falsifies, followed back to the decisions. The falsified
+    // clause is the one propagation recorded, or else the one found by
+    // falsified. Literals without a reason were fixed before the search by
+    // unit clauses, those are named in their place.
+    pub(crate) fn analyze(
+        &mut self,
+        falsified: impl FnOnce() -> Option<Vec<i32>>,
+        assignment: &Assignment,
+    ) -> Vec<Vec<i32>> {
+        let Some(conflict) = self.conflict.take().or_else(falsified) else {
+            return Vec::new();
+        };
+        let mut needed: HashSet<i32> = conflict.iter().map(|lit| lit.abs()).collect();
+        let mut antecedents = vec![conflict];
+        for (_, lit, clause) in self.forced.iter().rev() {
+            if needed.remove(&lit.abs()) {
+                needed.extend(clause.iter().map(|other| other.abs()).filter(|&var| var != lit.abs()));
+                antecedents.push(clause.clone());
+            }
+        }
+        let mut fixed: Vec<i32> = needed
+            .into_iter()
+            .filter_map(|var| assignment.get(&var).copied().flatten().map(|val| if val { var } else { -var }))
+            .collect();
+        fixed.sort_unstable();
+        antecedents.extend(fixed.into_iter().map(|lit| vec![lit]));
+        antecedents.reverse();
+        antecedents
+    }
+}
+
+// A refutation written while solving. Every added clause must follow from the
+// clauses before it by unit propagation (RUP). In LRAT the propagation steps
+// are written as hints, found among the clauses a lemma is derived from or,
+// without those, among all clauses added so far.
+pub struct Proof {
+    format: ProofFormat,
+    out: Box<dyn Write + Send>,
+    // Live clauses by id, kept for LRAT only
+    clauses: BTreeMap<usize, Vec<i32>>,
+    ids: HashMap<Vec<i32>, Vec<usize>>,
+    // A refuted decision sequence deleted once its sibling was refuted too,
+    // with the refutation of the decisions before the last, which subsumes it
+    resolved: HashMap<Vec<i32>, Vec<i32>>,
+    next_id: usize,
+    // Decision sequences refuted so far whose sibling is not refuted yet
+    refuted: HashSet<Vec<i32>>,
+    antecedents: Antecedents,
+    // The first lemma whose hints were not found, nothing is written after it
+    error: Option<ProofError>,
+}
+
+impl Proof {
+    // Start a proof of the formula as it was read, its clauses get the ids
+    // 1 to formula.len()
+    pub fn new(format: ProofFormat, out: Box<dyn Write + Send>, formula: &[Vec<i32>]) -> Self {
+        let mut proof = Proof {
+            format,
+            out,
+            clauses: BTreeMap::new(),
+            ids: HashMap::new(),
+            resolved: HashMap::new(),
+            next_id: 1,
+            refuted: HashSet::new(),
+            antecedents: Antecedents::default(),
+            error: None,
+        };
+        for clause in formula.iter() {
+            proof.insert(clause);
+        }
+        proof
+    }
+
+    pub fn format(&self) -> ProofFormat {
+        self.format
+    }
+
+    fn insert(&mut self, clause: &[i32]) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        if self.format == ProofFormat::Lrat {
+            self.clauses.insert(id, clause.to_vec());
+            self.ids.entry(key(clause)).or_default().push(id);
+        }
+        id
+    }
+
+    // The reasons of the search's propagations, recorded while the proof is
+    // in LRAT
+    pub(crate) fn antecedents(&mut self) -> Option<&mut Antecedents> {
+        (self.format == ProofFormat::Lrat).then_some(&mut self.antecedents)
+    }
+
+    // The id of a live clause, or of the live refutation that subsumes a
+    // deleted one
+    fn id(&self, clause: &[i32]) -> Option<usize> {
+        let mut key = key(clause);
+        loop {
+            if let Some(&id) = self.ids.get(&key).and_then(|ids| ids.last()) {
+                return Some(id);
+            }
+            key = self.resolved.get(&key)?.clone();
+        }
+    }
+
+    // Ids of the candidate clauses unit propagation goes through from the
+    // negation of the clause to a conflict, in the order they fire. None if
+    // propagation over the candidates does not reach one.
+    fn hints(&self, clause: &[i32], candidates: &[usize]) -> Option<Vec<usize>> {
+        let mut values: HashMap<i32, bool> = clause.iter().map(|&lit| (lit.abs(), lit < 0)).collect();
+        let mut occurrences: HashMap<i32, Vec<usize>> = HashMap::new();
+        for &id in candidates.iter() {
+            for lit in self.clauses[&id].iter() {
+                occurrences.entry(lit.abs()).or_default().push(id);
+            }
+        }
+        // A candidate is looked at again whenever one of its variables is
+        // assigned
+        let mut queue: VecDeque<usize> = candidates.iter().copied().collect();
+        let mut trail: Vec<(i32, usize)> = Vec::new();
+        let conflict = loop {
+            let id = queue.pop_front()?;
+            let mut open = None;
+            let mut open_count = 0;
+            let mut satisfied = false;
+            for &lit in self.clauses[&id].iter() {
+                match values.get(&lit.abs()) {
+                    Some(&val) if val == (lit > 0) => {
+                        satisfied = true;
+                        break;
+                    }
+                    Some(_) => {}
+                    None if open != Some(lit) => {
+                        open = Some(lit);
+                        open_count += 1;
+                    }
+                    None => {}
+                }
+            }
+            if satisfied {
+                continue;
+            }
+            match (open_count, open) {
+                (0, _) => break id,
+                (1, Some(lit)) => {
+                    values.insert(lit.abs(), lit > 0);
+                    trail.push((lit.abs(), id));
+                    queue.extend(occurrences[&lit.abs()].iter().copied());
+                }
+                _ => {}
+            }
+        };
+
+        let mut needed: HashSet<i32> = self.clauses[&conflict].iter().map(|lit| lit.abs()).collect();
+        let mut hints = vec![conflict];
+        for &(var, id) in trail.iter().rev() {
+            if needed.remove(&var) {
+                hints.push(id);
+                needed.extend(self.clauses[&id].iter().map(|lit| lit.abs()));
+            }
+        }
+        hints.reverse();
+        Some(hints)
+    }
+
+    // Add a clause implied by unit propagation over the clauses added so far
+    pub fn add(&mut self, clause: &[i32]) {
+        let candidates: Vec<usize> = if self.format == ProofFormat::Lrat {
+            self.clauses.keys().copied().collect()
+        } else {
+            Vec::new()
+        };
+        self.write_lemma(clause, candidates);
+    }
+
+    // Add a clause implied by unit propagation over the antecedents, clauses
+    // added before. LRAT hints are only looked for among them.
+    pub fn add_derived<'a>(&mut self, clause: &[i32], antecedents: impl IntoIterator<Item = &'a [i32]>) {
+        let candidates: Vec<usize> = if self.format == ProofFormat::Lrat {
+            antecedents.into_iter().filter_map(|antecedent| self.id(antecedent)).collect()
+        } else {
+            Vec::new()
+        };
+        self.write_lemma(clause, candidates);
+    }
+
+    fn write_lemma(&mut self, clause: &[i32], candidates: Vec<usize>) {
+        // A tautology can never become unit, nothing needs it
+        if self.error.is_some() || clause.iter().any(|&lit| clause.contains(&-lit)) {
+            return;
+        }
+        let literals: String = clause.iter().map(|lit| format!("{} ", lit)).collect();
+        match self.format {
+            ProofFormat::Drat => {
+                writeln!(self.out, "{}0", literals).expect("Failed to write proof");
+            }
+            ProofFormat::Lrat => {
+                let Some(hints) = self.hints(clause, &candidates) else {
+                    self.error = Some(ProofError::NoHints(clause.to_vec()));
+                    return;
+                };
+                let hints: String = hints.iter().map(|id| format!("{} ", id)).collect();
+                let id = self.insert(clause);
+                writeln!(self.out, "{} {}0 {}0", id, literals, hints).expect("Failed to write proof");
+            }
+        }
+    }
+
+    pub fn delete(&mut self, clause: &[i32]) {
+        if self.error.is_some() {
+            return;
+        }
+        match self.format {
+            ProofFormat::Drat => {
+                let literals: String = clause.iter().map(|lit| format!("{} ", lit)).collect();
+                writeln!(self.out, "d {}0", literals).expect("Failed to write proof");
+            }
+            ProofFormat::Lrat => {
+                let Some(id) = self.ids.get_mut(&key(clause)).and_then(Vec::pop) else {
+                    return;
+                };
+                self.clauses.remove(&id);
+                writeln!(self.out, "{} d {} 0", self.next_id - 1, id).expect("Failed to write proof");
+            }
+        }
+    }
+
+    // Add the negation of decisions that led to a conflict, derived from the
+    // antecedents. Once both branches of a decision are refuted the decisions
+    // before it are refuted as well, which ends with the empty clause when
+    // the whole tree failed.
+    pub fn refute<'a>(&mut self, decisions: &[i32], antecedents: impl IntoIterator<Item = &'a [i32]>) {
+        let mut decisions = decisions.to_vec();
+        self.add_derived(&negation(&decisions), antecedents);
+        while let Some(&last) = decisions.last() {
+            let mut sibling = decisions.clone();
+            *sibling.last_mut().unwrap() = -last;
+            if !self.refuted.remove(&sibling) {
+                self.refuted.insert(decisions);
+                return;
+            }
+            let children = [negation(&decisions), negation(&sibling)];
+            decisions.pop();
+            let parent = negation(&decisions);
+            self.add_derived(&parent, children.iter().map(Vec::as_slice));
+            for child in children.iter() {
+                self.delete(child);
+                if self.format == ProofFormat::Lrat {
+                    self.resolved.insert(key(child), key(&parent));
+                }
+            }
+        }
+    }
+
+    // Flush the output, or return the error that cut the proof short
+    pub fn flush(&mut self) -> Result<(), ProofError> {
+        self.out.flush().expect("Failed to write proof");
+        self.error.clone().map_or(Ok(()), Err)
+    }
+}
+
+// Proof output that can still be read after the proof took ownership of it
+#[derive(Clone, Default)]
+pub(crate) struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub(crate) fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).expect("Proofs are written as text")
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// One line of a DRAT proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofStep {
+    Add(Vec<i32>),
+    Delete(Vec<i32>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofError {
+    // A line that is not a clause ending in 0, numbered from 1
+    Parse(usize),
+    // The added clause on this line is neither RUP nor RAT
+    NotImplied(usize, Vec<i32>),
+    // The proof ends without deriving the empty clause
+    NoEmptyClause,
+    // A lemma written in LRAT that unit propagation over its antecedents did
+    // not derive
+    NoHints(Vec<i32>),
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProofError::Parse(line) => write!(f, "line {}: not a proof step", line),
+            ProofError::NotImplied(line, clause) => write!(f, "line {}: clause {:?} is not implied", line, clause),
+            ProofError::NoEmptyClause => write!(f, "the proof does not derive the empty clause"),
+            ProofError::NoHints(clause) => write!(f, "no hints derive the lemma {:?}", clause),
+        }
+    }
+}
+
+// Parse a textual DRAT (or DRUP) proof, one step per line, "d" marks deletions
+pub fn parse_drat(text: &str) -> Result<Vec<(usize, ProofStep)>, ProofError> {
+    let mut steps = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() || tokens[0] == "c" {
+            continue;
+        }
+        let delete = tokens[0] == "d";
+        if delete {
+            tokens.remove(0);
+        }
+        if tokens.last() != Some(&"0") {
+            return Err(ProofError::Parse(i + 1));
+        }
+        tokens.pop();
+        let clause = tokens
+            .iter()
+            .map(|token| token.parse::<i32>().ok().filter(|&lit| lit != 0))
+            .collect::<Option<Vec<i32>>>()
+            .ok_or(ProofError::Parse(i + 1))?;
+        steps.push((i + 1, if delete { ProofStep::Delete(clause) } else { ProofStep::Add(clause) }));
+    }
+    Ok(steps)
+}
+
+// Whether unit propagation refutes the negation of the clause
+fn has_rup(clauses: &[Vec<i32>], clause: &[i32]) -> bool {
+    let mut values = HashMap::new();
+    for &lit in clause.iter() {
+        if values.insert(lit.abs(), lit < 0) == Some(lit > 0) {
+            // A tautology
+            return true;
+        }
+    }
+    !propagate(clauses, |_| false, &mut values, &mut 0)
+}
+
+// RAT on the first literal: every resolvent with a clause containing its
+// negation has RUP
+fn has_rat(clauses: &[Vec<i32>], clause: &[i32]) -> bool {
+    let Some(&pivot) = clause.first() else {
+        return false;
+    };
+    clauses.iter().filter(|other| other.contains(&-pivot)).all(|other| {
+        let resolvent: Vec<i32> = clause.iter().chain(other.iter().filter(|&&lit| lit != -pivot)).copied().collect();
+        has_rup(clauses, &resolvent)
+    })
+}
+
+// Check a DRAT refutation of the formula by replaying it forwards: every added
+// clause needs RUP or RAT with respect to the clauses alive at that point, and
+// the empty clause has to be derived
+pub fn check_drat(formula: &[Vec<i32>], steps: &[(usize, ProofStep)]) -> Result<(), ProofError> {
+    let mut clauses: Vec<Vec<i32>> = formula.to_vec();
+    if clauses.iter().any(Vec::is_empty) {
+        return Ok(());
+    }
+    for (line, step) in steps.iter() {
+        match step {
+            ProofStep::Delete(clause) => {
+                let clause = key(clause);
+                if let Some(i) = clauses.iter().position(|other| key(other) == clause) {
+                    clauses.swap_remove(i);
+                }
+            }
+            ProofStep::Add(clause) => {
+                if !has_rup(&clauses, clause) && !has_rat(&clauses, clause) {
+                    return Err(ProofError::NotImplied(*line, clause.clone()));
+                }
+                if clause.is_empty() {
+                    return Ok(());
+                }
+                clauses.push(clause.clone());
+            }
+        }
+    }
+    Err(ProofError::NoEmptyClause)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        initial_assignment, run_search, LearnedClauses, MinIndexHeuristic, Node, ReductionConfig, Reconstruction,
+        SearchState, SolveResult,
+    };
+    use crate::preprocess;
+
+    fn written(buffer: &SharedBuffer) -> Vec<String> {
+        buffer.text().lines().map(str::to_string).collect()
+    }
+
+    // Check an LRAT refutation of the formula hint by hint: under the
+    // negation of a lemma every hint but the last is a live clause with a
+    // single literal left open, which it assigns, and the last one is
+    // falsified. The proof has to end with the empty clause.
+    fn check_lrat(formula: &[Vec<i32>], text: &str) {
+        let mut clauses: HashMap<usize, Vec<i32>> = (1..).zip(formula.iter().cloned()).collect();
+        for line in text.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            assert_eq!(tokens.last(), Some(&"0"), "{}", line);
+            if tokens[1] == "d" {
+                for id in tokens[2..tokens.len() - 1].iter() {
+                    assert!(clauses.remove(&id.parse().unwrap()).is_some(), "{}: {} is not live", line, id);
+                }
+                continue;
+            }
+            let numbers: Vec<i64> = tokens.iter().map(|token| token.parse().unwrap()).collect();
+            let end = numbers[1..].iter().position(|&number| number == 0).unwrap() + 1;
+            let lemma: Vec<i32> = numbers[1..end].iter().map(|&lit| lit as i32).collect();
+            let hints: Vec<usize> = numbers[end + 1..numbers.len() - 1].iter().map(|&id| id as usize).collect();
+            let mut values: HashMap<i32, bool> = lemma.iter().map(|&lit| (lit.abs(), lit < 0)).collect();
+            let is_false = |values: &HashMap<i32, bool>, lit: i32| values.get(&lit.abs()) == Some(&(lit < 0));
+            let (last, units) = hints.split_last().unwrap_or_else(|| panic!("{}: no hints", line));
+            for id in units.iter() {
+                let clause = clauses.get(id).unwrap_or_else(|| panic!("{}: {} is not live", line, id));
+                let open: Vec<i32> = clause.iter().copied().filter(|&lit| !is_false(&values, lit)).collect();
+                assert_eq!(open.len(), 1, "{}: {} is not unit", line, id);
+                assert_eq!(values.insert(open[0].abs(), open[0] > 0), None, "{}: {} is satisfied", line, id);
+            }
+            let clause = clauses.get(last).unwrap_or_else(|| panic!("{}: {} is not live", line, last));
+            assert!(clause.iter().all(|&lit| is_false(&values, lit)), "{}: {} is not falsified", line, last);
+            if lemma.is_empty() {
+                return;
+            }
+            assert_eq!(clauses.insert(numbers[0] as usize, lemma), None, "{}: the id is taken", line);
+        }
+        panic!("The proof does not derive the empty clause");
+    }
+
+    #[test]
+    fn test_refute_merges_siblings() {
+        let formula = vec![vec![1, 2], vec![1, -2], vec![-1, 2], vec![-1, -2]];
+        let buffer = SharedBuffer::default();
+        let refute_all = |proof: &mut Proof| {
+            proof.refute(&[1, 2], [&[-1, -2][..]]);
+            proof.refute(&[1, -2], [&[-1, 2][..]]);
+            proof.refute(&[-1], [&[1, 2][..], &[1, -2]]);
+        };
+        let mut proof = Proof::new(ProofFormat::Drat, Box::new(buffer.clone()), &formula);
+        refute_all(&mut proof);
+        assert_eq!(
+            written(&buffer),
+            vec!["-1 -2 0", "-1 2 0", "-1 0", "d -1 2 0", "d -1 -2 0", "1 0", "0", "d 1 0", "d -1 0"]
+        );
+
+        // The refutation of both branches follows from the two children
+        let buffer = SharedBuffer::default();
+        let mut proof = Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), &formula);
+        refute_all(&mut proof);
+        assert_eq!(proof.flush(), Ok(()));
+        assert_eq!(
+            written(&buffer),
+            vec![
+                "5 -1 -2 0 4 0", "6 -1 2 0 3 0", "7 -1 0 6 5 0", "7 d 6 0", "7 d 5 0", "8 1 0 1 2 0", "9 0 8 7 0",
+                "9 d 8 0", "9 d 7 0",
+            ]
+        );
+        check_lrat(&formula, &written(&buffer).join("\n"));
+    }
+
+    #[test]
+    fn test_lrat_hints() {
+        let formula = vec![vec![1, 2], vec![1, -2], vec![-1, 2], vec![-1, -2]];
+        let buffer = SharedBuffer::default();
+        let mut proof = Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), &formula);
+        proof.add(&[1]);
+        proof.add(&[]);
+        proof.delete(&[1]);
+        assert_eq!(written(&buffer), vec!["5 1 0 1 2 0", "6 0 5 3 4 0", "6 d 5 0"]);
+
+        // Hints are only looked for among the antecedents, a lemma they do
+        // not derive ends the proof with an error
+        let buffer = SharedBuffer::default();
+        let mut proof = Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), &formula);
+        proof.add_derived(&[1], [&[1, 2][..], &[1, -2]]);
+        proof.add_derived(&[-1], [&[-1, 2][..]]);
+        proof.add_derived(&[], [&[1][..], &[-1]]);
+        assert_eq!(proof.flush(), Err(ProofError::NoHints(vec![-1])));
+        assert_eq!(written(&buffer), vec!["5 1 0 1 2 0"]);
+    }
+
+    // n + 1 pigeons in n holes, pigeon p sits in hole h if p * n + h + 1 is
+    // true
+    fn pigeonhole(n: i32) -> Vec<Vec<i32>> {
+        let var = |p: i32, h: i32| p * n + h + 1;
+        let mut formula: Vec<Vec<i32>> = (0..=n).map(|p| (0..n).map(|h| var(p, h)).collect()).collect();
+        for h in 0..n {
+            for p in 0..=n {
+                formula.extend((p + 1..=n).map(|q| vec![-var(p, h), -var(q, h)]));
+            }
+        }
+        formula
+    }
+
+    // Clauses of three distinct variables out of num_vars with random signs,
+    // drawn from a xorshift generator
+    fn random_3sat(num_vars: i32, num_clauses: usize, seed: u64) -> Vec<Vec<i32>> {
+        let mut state = seed * 2 + 1;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        (0..num_clauses)
+            .map(|_| {
+                let mut clause: Vec<i32> = Vec::new();
+                while clause.len() < 3 {
+                    let bits = next();
+                    let var = (bits % num_vars as u64) as i32 + 1;
+                    if !clause.iter().any(|lit| lit.abs() == var) {
+                        clause.push(if bits & (1 << 40) != 0 { var } else { -var });
+                    }
+                }
+                clause
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_search_lrat_checks() {
+        // Conflicts with and without learned clauses name the clauses they
+        // follow from
+        let mut formulas = vec![pigeonhole(2), pigeonhole(3)];
+        formulas.extend((0..20).map(|seed| random_3sat(12, 70, seed)));
+        let mut refuted = 0;
+        for formula in formulas.iter() {
+            for learned in [None, Some(LearnedClauses::new(ReductionConfig::default()))] {
+                let buffer = SharedBuffer::default();
+                let mut state = SearchState {
+                    proof: Some(Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), formula)),
+                    learned,
+                    ..Default::default()
+                };
+                let root = Node::new(formula.clone(), None, 0, initial_assignment(formula));
+                if run_search(root, &MinIndexHeuristic, &mut state).is_sat() {
+                    continue;
+                }
+                assert_eq!(state.proof.as_mut().unwrap().flush(), Ok(()));
+                check_lrat(formula, &written(&buffer).join("\n"));
+                refuted += 1;
+            }
+        }
+        assert!(refuted > 20, "{}", refuted);
+
+        // So do the clauses preprocessing derives before the search
+        for formula in formulas.iter() {
+            let buffer = SharedBuffer::default();
+            let mut proof = Proof::new(ProofFormat::Lrat, Box::new(buffer.clone()), formula);
+            let mut simplified = formula.clone();
+            preprocess::self_subsume(&mut simplified, Some(&mut proof));
+            let mut reconstruction = Reconstruction::default();
+            preprocess::eliminate_variables(&mut simplified, &mut reconstruction, 16, |_| false, Some(&mut proof));
+            preprocess::probe_failed_literals(&mut simplified, &preprocess::ProbeConfig::default(), Some(&mut proof));
+            let mut state = SearchState {
+                proof: Some(proof),
+                learned: Some(LearnedClauses::new(ReductionConfig::default())),
+                ..Default::default()
+            };
+            let root = Node::new(simplified.clone(), None, 0, initial_assignment(&simplified));
+            if !run_search(root, &MinIndexHeuristic, &mut state).is_sat() {
+                assert_eq!(state.proof.as_mut().unwrap().flush(), Ok(()));
+                check_lrat(formula, &written(&buffer).join("\n"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_drat() {
+        let formula = vec![vec![1, 2], vec![1, -2], vec![-1, 2], vec![-1, -2]];
+        let steps = parse_drat("1 0\nd 1 2 0\n0\n").unwrap();
+        assert_eq!(steps[1], (2, ProofStep::Delete(vec![1, 2])));
+        assert_eq!(check_drat(&formula, &steps), Ok(()));
+
+        // 1 does not follow once [1, 2] is gone
+        let steps = parse_drat("d 1 2 0\n1 0\n0\n").unwrap();
+        assert_eq!(check_drat(&formula, &steps), Err(ProofError::NotImplied(2, vec![1])));
+        assert_eq!(check_drat(&formula, &parse_drat("1 0\n").unwrap()), Err(ProofError::NoEmptyClause));
+        assert_eq!(parse_drat("1 2\n"), Err(ProofError::Parse(1)));
+
+        // The definition of a fresh variable 3 is RAT but not RUP
+        let formula = vec![vec![1, 2], vec![-1, -2]];
+        assert!(!has_rup(&formula, &[3, 1]) && has_rat(&formula, &[3, 1]));
+        let steps = parse_drat("3 1 0\n3 2 0\n-3 -1 -2 0\n").unwrap();
+        assert_eq!(check_drat(&formula, &steps), Err(ProofError::NoEmptyClause));
+    }
+
+    #[test]
+    fn test_search_proof_checks() {
+        // Pigeonhole: three pigeons in two holes
+        let formula = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![5, 6],
+            vec![-1, -3],
+            vec![-1, -5],
+            vec![-3, -5],
+            vec![-2, -4],
+            vec![-2, -6],
+            vec![-4, -6],
+        ];
+        let buffer = SharedBuffer::default();
+        let mut state = SearchState {
+            proof: Some(Proof::new(ProofFormat::Drat, Box::new(buffer.clone()), &formula)),
+            ..Default::default()
+        };
+        let root = Node::new(formula.clone(), None, 0, initial_assignment(&formula));
+        assert_eq!(run_search(root, &MinIndexHeuristic, &mut state), SolveResult::Unsat);
+        let steps = parse_drat(&written(&buffer).join("\n")).unwrap();
+        assert_eq!(check_drat(&formula, &steps), Ok(()));
+    }
+
+    #[test]
+    fn test_backjump_proof_checks() {
+        // Both branches of 3 under 1 and 2 fail without 2, the clause learned
+        // from the second refutes 1 and jumps over -2, then -1 fails
+        let formula = vec![
+            vec![-1, -3, 2],
+            vec![-1, 3, 2],
+            vec![-1, -3, 4],
+            vec![-1, -3, -4],
+            vec![-1, 3, 5],
+            vec![-1, 3, -5],
+            vec![-2, 6],
+            vec![2, -6],
+            vec![1, 2],
+            vec![1, -6, 7],
+            vec![1, -6, -7],
+        ];
+        for format in [ProofFormat::Drat, ProofFormat::Lrat] {
+            let buffer = SharedBuffer::default();
+            let mut state = SearchState {
+                proof: Some(Proof::new(format, Box::new(buffer.clone()), &formula)),
+                learned: Some(LearnedClauses::new(ReductionConfig::default())),
+                ..Default::default()
+            };
+            let root = Node::new(formula.clone(), None, 0, initial_assignment(&formula));
+            assert_eq!(run_search(root, &MinIndexHeuristic, &mut state), SolveResult::Unsat);
+            assert_eq!(state.stats.backjumps, 1);
+            if format == ProofFormat::Drat {
+                let steps = parse_drat(&written(&buffer).join("\n")).unwrap();
+                assert_eq!(check_drat(&formula, &steps), Ok(()));
+            }
+        }
+    }
+}
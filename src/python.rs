@@ -0,0 +1,120 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::{heuristic_by_name, Lit, SolveResult};
+
+// The Solver for Python. Clauses and assumptions are lists of DIMACS
+// literals, models come back as the list of the literals true in them.
+#[pyclass(name = "Solver", module = "dpll")]
+pub struct PySolver {
+    // Python objects have to be Sync. The object is borrowed mutably by the
+    // methods that change the solver, which go past the lock, so locking
+    // for the others never waits.
+    solver: Mutex<crate::Solver>,
+}
+
+impl PySolver {
+    fn solver(&mut self) -> &mut crate::Solver {
+        self.solver.get_mut().unwrap()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, crate::Solver> {
+        self.solver.lock().unwrap()
+    }
+}
+
+fn check_lits(lits: &[Lit]) -> PyResult<()> {
+    if lits.contains(&0) {
+        return Err(PyValueError::new_err("0 is not a literal"));
+    }
+    Ok(())
+}
+
+#[pymethods]
+impl PySolver {
+    #[new]
+    #[pyo3(signature = (clauses = Vec::new()))]
+    fn new(clauses: Vec<Vec<Lit>>) -> PyResult<Self> {
+        let mut solver = PySolver { solver: Mutex::new(crate::Solver::new()) };
+        for clause in clauses.iter() {
+            solver.add_clause(clause.clone())?;
+        }
+        Ok(solver)
+    }
+
+    fn add_clause(&mut self, clause: Vec<Lit>) -> PyResult<()> {
+        check_lits(&clause)?;
+        self.solver().add_clause(&clause);
+        Ok(())
+    }
+
+    fn new_var(&mut self) -> Lit {
+        self.solver().new_var()
+    }
+
+    #[getter]
+    fn num_vars(&self) -> Lit {
+        self.lock().num_vars()
+    }
+
+    // One of the heuristics the command line names with --heuristic
+    fn set_heuristic(&mut self, name: &str) -> PyResult<()> {
+        let heuristic =
+            heuristic_by_name(name).ok_or_else(|| PyValueError::new_err(format!("Unknown heuristic {}", name)))?;
+        self.solver().set_heuristic(heuristic);
+        Ok(())
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.solver().set_seed(seed);
+    }
+
+    // True if satisfiable under the assumptions, False if not and None if the
+    // search was stopped. Other Python threads run while it searches.
+    #[pyo3(signature = (assumptions = Vec::new()))]
+    fn solve(&mut self, py: Python<'_>, assumptions: Vec<Lit>) -> PyResult<Option<bool>> {
+        check_lits(&assumptions)?;
+        let solver = self.solver();
+        Ok(match py.allow_threads(|| solver.search(&assumptions)) {
+            SolveResult::Sat(_) => Some(true),
+            SolveResult::Unsat => Some(false),
+            SolveResult::Unknown => None,
+        })
+    }
+
+    // The literals true in the model of the last solve, by variable, None
+    // unless it was satisfiable
+    fn model(&self) -> Option<Vec<Lit>> {
+        let solver = self.lock();
+        let mut lits: Vec<Lit> = solver.model()?.iter().map(|(&var, &val)| if val { var } else { -var }).collect();
+        lits.sort_unstable_by_key(|lit| lit.abs());
+        Some(lits)
+    }
+
+    // The assumptions of the last solve that the formula refutes, see
+    // Solver::failed_assumptions
+    fn failed_assumptions(&self) -> Vec<Lit> {
+        self.lock().failed_assumptions().to_vec()
+    }
+
+    // The search counters summed over every solve so far
+    fn stats(&self) -> HashMap<&'static str, usize> {
+        let solver = self.lock();
+        let stats = solver.stats();
+        HashMap::from([
+            ("nodes", stats.nodes),
+            ("decisions", stats.decisions),
+            ("propagations", stats.propagations),
+            ("conflicts", stats.conflicts),
+            ("restarts", stats.restarts),
+            ("max_depth", stats.max_depth),
+        ])
+    }
+}
+
+#[pymodule]
+fn dpll(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySolver>()
+}
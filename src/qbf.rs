@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::dimacs::{parse_dimacs, DimacsError, ParseMode};
+use crate::Formula;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    Exists,
+    Forall,
+}
+
+// A quantified Boolean formula in prenex CNF: blocks of quantified variables
+// from the outermost in, and the clauses over them. Variables of the matrix
+// that no block quantifies are existential in front of every block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Qbf {
+    pub prefix: Vec<(Quantifier, Vec<i32>)>,
+    pub matrix: Formula,
+}
+
+// Parse QDIMACS: DIMACS CNF with "a" and "e" lines of variables ended by 0
+// between the problem line and the clauses. Adjacent lines with the same
+// quantifier form one block. The matrix is parsed as by parse_dimacs, in
+// lenient mode its warnings are dropped.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn parse_qdimacs(reader: impl BufRead, mode: ParseMode) -> Result<Qbf, DimacsError> {
+    let mut prefix: Vec<(Quantifier, Vec<i32>)> = Vec::new();
+    // The matrix with the quantifier lines blanked, so line numbers still match
+    let mut matrix = String::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| DimacsError::Io(error.to_string()))?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let quantifier = match tokens.first() {
+            Some(&"a") => Quantifier::Forall,
+            Some(&"e") => Quantifier::Exists,
+            _ => {
+                matrix.push_str(&line);
+                matrix.push('\n');
+                continue;
+            }
+        };
+        matrix.push('\n');
+        let mut vars = Vec::new();
+        for token in tokens[1..].iter() {
+            let var: i32 = token
+                .parse()
+                .ok()
+                .filter(|&var: &i32| var >= 0)
+                .ok_or_else(|| DimacsError::InvalidLiteral(i + 1, token.to_string()))?;
+            if var == 0 {
+                break;
+            }
+            vars.push(var);
+        }
+        match prefix.last_mut() {
+            Some((last, block)) if *last == quantifier => block.extend(vars),
+            _ => prefix.push((quantifier, vars)),
+        }
+    }
+    let dimacs = parse_dimacs(matrix.as_bytes(), mode)?;
+    Ok(Qbf {
+        prefix,
+        matrix: dimacs.formula,
+    })
+}
+
+// Block and quantifier of every quantified variable, free variables are in
+// block 0
+struct Levels(HashMap<i32, (usize, Quantifier)>);
+
+impl Levels {
+    fn of(&self, lit: i32) -> (usize, Quantifier) {
+        self.0.get(&lit.abs()).copied().unwrap_or((0, Quantifier::Exists))
+    }
+
+    fn is_universal(&self, lit: i32) -> bool {
+        self.of(lit).1 == Quantifier::Forall
+    }
+}
+
+// Make lit true: drop the clauses it satisfies and its negation from the rest
+fn assign(matrix: &[Vec<i32>], lit: i32) -> Formula {
+    matrix
+        .iter()
+        .filter(|clause| !clause.contains(&lit))
+        .map(|clause| clause.iter().copied().filter(|&other| other != -lit).collect())
+        .collect()
+}
+
+// Universal reduction: a universal literal quantified inside every
+// existential literal of its clause can never help to satisfy it. False if
+// this empties a clause.
+fn reduce(matrix: &mut Formula, levels: &Levels) -> bool {
+    for clause in matrix.iter_mut() {
+        let innermost = clause.iter().filter(|&&lit| !levels.is_universal(lit)).map(|&lit| levels.of(lit).0).max();
+        clause.retain(|&lit| !levels.is_universal(lit) || innermost.is_some_and(|level| levels.of(lit).0 < level));
+        if clause.is_empty() {
+            return false;
+        }
+    }
+    true
+}
+
+// A literal whose value follows from the matrix: the literal of a unit
+// clause, which after reduction is existential, or a pure literal, set true
+// if it is existential and false if it is universal
+fn forced_literal(matrix: &[Vec<i32>], levels: &Levels) -> Option<i32> {
+    if let Some(clause) = matrix.iter().find(|clause| clause.len() == 1) {
+        return Some(clause[0]);
+    }
+    let mut polarities: HashMap<i32, (bool, bool)> = HashMap::new();
+    for &lit in matrix.iter().flatten() {
+        let entry = polarities.entry(lit.abs()).or_default();
+        if lit > 0 {
+            entry.0 = true;
+        } else {
+            entry.1 = true;
+        }
+    }
+    let (&var, &(positive, _)) = polarities.iter().filter(|(_, &(positive, negative))| positive != negative).min()?;
+    let lit = if positive { var } else { -var };
+    Some(if levels.is_universal(lit) { -lit } else { lit })
+}
+
+fn qdpll(mut matrix: Formula, levels: &Levels) -> bool {
+    loop {
+        if !reduce(&mut matrix, levels) {
+            return false;
+        }
+        if matrix.is_empty() {
+            return true;
+        }
+        match forced_literal(&matrix, levels) {
+            Some(lit) => matrix = assign(&matrix, lit),
+            None => break,
+        }
+    }
+    // Branch on an outermost variable that still occurs
+    let var = matrix.iter().flatten().map(|lit| lit.abs()).min_by_key(|&var| (levels.of(var).0, var)).unwrap();
+    let (positive, negative) = (assign(&matrix, var), assign(&matrix, -var));
+    match levels.of(var).1 {
+        Quantifier::Exists => qdpll(positive, levels) || qdpll(negative, levels),
+        Quantifier::Forall => qdpll(positive, levels) && qdpll(negative, levels),
+    }
+}
+
+// Decide a QBF with a QDPLL search: branch on the variables in prefix order,
+// an existential branch succeeds if either value does and a universal one if
+// both do. Universal reduction, unit propagation and pure literals prune the
+// tree.
+pub fn solve_qbf(qbf: &Qbf) -> bool {
+    let mut levels = HashMap::new();
+    for (i, (quantifier, vars)) in qbf.prefix.iter().enumerate() {
+        for &var in vars.iter() {
+            levels.insert(var, (i + 1, *quantifier));
+        }
+    }
+    // Tautologies are always satisfied, reduction would empty them
+    let matrix = qbf.matrix.iter().filter(|clause| !clause.iter().any(|&lit| clause.contains(&-lit)));
+    qdpll(matrix.cloned().collect(), &Levels(levels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Expand the quantifiers one variable at a time
+    fn brute_force(prefix: &[(Quantifier, i32)], matrix: &[Vec<i32>], values: &mut HashMap<i32, bool>) -> bool {
+        let Some((&(quantifier, var), rest)) = prefix.split_first() else {
+            return matrix.iter().all(|clause| clause.iter().any(|&lit| values[&lit.abs()] == (lit > 0)));
+        };
+        let mut branch = |value| {
+            values.insert(var, value);
+            brute_force(rest, matrix, values)
+        };
+        match quantifier {
+            Quantifier::Exists => branch(true) || branch(false),
+            Quantifier::Forall => branch(true) && branch(false),
+        }
+    }
+
+    #[test]
+    fn test_parse_qdimacs() {
+        let text = "c example\np cnf 4 2\na 1 2 0\na 3 0\ne 4 0\n1 -4 0\n-2 3 4 0\n";
+        let qbf = parse_qdimacs(text.as_bytes(), ParseMode::Strict).unwrap();
+        assert_eq!(qbf.prefix, vec![(Quantifier::Forall, vec![1, 2, 3]), (Quantifier::Exists, vec![4])]);
+        assert_eq!(qbf.matrix, vec![vec![1, -4], vec![-2, 3, 4]]);
+        assert_eq!(
+            parse_qdimacs("p cnf 1 1\ne 1 -1 0\n1 0\n".as_bytes(), ParseMode::Lenient),
+            Err(DimacsError::InvalidLiteral(2, "-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_solve_qbf() {
+        let xor = vec![vec![1, 2], vec![-1, -2]];
+        // For every x there is a y that differs from it, but no y differs from every x
+        let forall_exists = Qbf {
+            prefix: vec![(Quantifier::Forall, vec![1]), (Quantifier::Exists, vec![2])],
+            matrix: xor.clone(),
+        };
+        let exists_forall = Qbf {
+            prefix: vec![(Quantifier::Exists, vec![2]), (Quantifier::Forall, vec![1])],
+            matrix: xor,
+        };
+        assert!(solve_qbf(&forall_exists));
+        assert!(!solve_qbf(&exists_forall));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..300 {
+            let num_vars = rng.gen_range(1..=6);
+            let prefix: Vec<(Quantifier, i32)> = (1..=num_vars)
+                .map(|var| (if rng.gen_bool(0.5) { Quantifier::Exists } else { Quantifier::Forall }, var))
+                .collect();
+            let matrix: Formula = (0..rng.gen_range(1..=8))
+                .map(|_| {
+                    let lit = |rng: &mut StdRng| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 };
+                    (0..rng.gen_range(1..=3)).map(|_| lit(&mut rng)).collect()
+                })
+                .collect();
+            let qbf = Qbf {
+                prefix: prefix.iter().map(|&(quantifier, var)| (quantifier, vec![var])).collect(),
+                matrix: matrix.clone(),
+            };
+            assert_eq!(solve_qbf(&qbf), brute_force(&prefix, &matrix, &mut HashMap::new()), "{:?}", qbf);
+        }
+    }
+}
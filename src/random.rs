@@ -0,0 +1,40 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+// The random number generator behind every randomized choice of a search.
+// It starts from seed 0 unless seeded otherwise, so a run with the same seed
+// and options makes exactly the same choices.
+#[derive(Clone, Debug)]
+pub struct SearchRng(StdRng);
+
+impl Default for SearchRng {
+    fn default() -> Self {
+        SearchRng::seed_from_u64(0)
+    }
+}
+
+impl RngCore for SearchRng {
+    fn next_u32(&mut self) -> u32 {
+        self.0.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.0.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.0.try_fill_bytes(dest)
+    }
+}
+
+impl SeedableRng for SearchRng {
+    type Seed = <StdRng as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        SearchRng(StdRng::from_seed(seed))
+    }
+}
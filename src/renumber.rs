@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::{Formula, Model};
+
+// A dense numbering of the variables a formula uses, 1 to n in the order of
+// their original indices, and the way back. Assignments are sized by the
+// largest variable, so a formula that uses few of its variable numbers gets
+// a much smaller one once renumbered.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Renumbering {
+    dense: HashMap<i32, i32>,
+    original: Vec<i32>,
+}
+
+impl Renumbering {
+    pub fn new(formula: &[Vec<i32>]) -> Self {
+        Self::of_vars(formula.iter().flatten().map(|lit| lit.abs()))
+    }
+
+    pub fn of_vars(vars: impl IntoIterator<Item = i32>) -> Self {
+        let mut original: Vec<i32> = vars.into_iter().map(i32::abs).collect();
+        original.sort_unstable();
+        original.dedup();
+        let dense = original.iter().enumerate().map(|(i, &var)| (var, i as i32 + 1)).collect();
+        Renumbering { dense, original }
+    }
+
+    pub fn num_vars(&self) -> i32 {
+        self.original.len() as i32
+    }
+
+    // The literal in the dense numbering, None if its variable is not
+    // numbered
+    pub fn dense(&self, lit: i32) -> Option<i32> {
+        self.dense.get(&lit.abs()).map(|&var| if lit > 0 { var } else { -var })
+    }
+
+    // The literal of the dense numbering in the original one, None past the
+    // numbered variables
+    pub fn original(&self, lit: i32) -> Option<i32> {
+        let var = *self.original.get(lit.unsigned_abs() as usize - 1)?;
+        Some(if lit > 0 { var } else { -var })
+    }
+
+    // The formula in the dense numbering, panics on variables not numbered
+    pub fn apply(&self, formula: &[Vec<i32>]) -> Formula {
+        let dense = |&lit: &i32| self.dense(lit).unwrap_or_else(|| panic!("Variable {} is not renumbered", lit.abs()));
+        formula.iter().map(|clause| clause.iter().map(dense).collect()).collect()
+    }
+
+    // A model of the dense numbering in the original one. Variables past the
+    // numbered ones, such as auxiliary variables added after renumbering,
+    // are left out.
+    pub fn restore(&self, model: &Model) -> Model {
+        model.iter().filter_map(|(&var, &val)| Some((self.original(var)?, val))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{verify_model, Solver};
+
+    #[test]
+    fn test_renumbering() {
+        let formula = vec![vec![1_000_000, -7], vec![7, 42], vec![-42, -1_000_000]];
+        let renumbering = Renumbering::new(&formula);
+        assert_eq!(renumbering.num_vars(), 3);
+        let dense = renumbering.apply(&formula);
+        assert_eq!(dense, vec![vec![3, -1], vec![1, 2], vec![-2, -3]]);
+        assert_eq!((renumbering.dense(-42), renumbering.dense(8)), (Some(-2), None));
+        assert_eq!((renumbering.original(-3), renumbering.original(4)), (Some(-1_000_000), None));
+
+        let mut solver = Solver::from_clauses(&dense);
+        assert!(solver.solve());
+        let mut model = solver.model().unwrap().clone();
+        model.insert(4, true);
+        let restored = renumbering.restore(&model);
+        assert_eq!(restored.len(), 3);
+        assert_eq!(verify_model(&formula, &restored), Ok(()));
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+
+// What the search knew when a branch failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    // Decisions on the path to the failed node
+    pub depth: usize,
+    // Variables assigned when the conflict was found
+    pub assigned: usize,
+}
+
+// Decides when the search abandons its tasklist and starts over from the root
+pub trait RestartPolicy: Send {
+    // Called after every conflict, returns true when the search should restart
+    fn on_conflict(&mut self, conflict: &Conflict) -> bool;
+}
+
+// The Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, 1, 2, ... (1-indexed)
+pub fn luby(i: usize) -> usize {
+    let mut i = i;
+    loop {
+        // Find k with 2^(k-1) <= i < 2^k
+        let mut k = 1;
+        while (1 << k) - 1 < i {
+            k += 1;
+        }
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+// Restart after unit * luby(n) conflicts, n counting the restarts so far
+#[derive(Clone, Debug)]
+pub struct LubyRestarts {
+    pub unit: usize,
+    index: usize,
+    conflicts: usize,
+}
+
+impl LubyRestarts {
+    pub fn new(unit: usize) -> Self {
+        LubyRestarts {
+            unit,
+            index: 1,
+            conflicts: 0,
+        }
+    }
+}
+
+impl RestartPolicy for LubyRestarts {
+    fn on_conflict(&mut self, _conflict: &Conflict) -> bool {
+        self.conflicts += 1;
+        if self.conflicts < self.unit * luby(self.index) {
+            return false;
+        }
+        self.conflicts = 0;
+        self.index += 1;
+        true
+    }
+}
+
+// Glucose-style adaptive restarts using the conflict depth as its quality.
+// The search restarts once the last `window` conflicts are on average deeper
+// than the average over the whole run (scaled by `margin`), and a pending
+// restart is blocked when a conflict assigns clearly more variables than the
+// last `blocking_window` ones did, since the search may be close to a model.
+#[derive(Clone, Debug)]
+pub struct GlucoseRestarts {
+    pub window: usize,
+    pub margin: f64,
+    pub blocking_window: usize,
+    pub blocking_margin: f64,
+    // Conflicts before blocking kicks in
+    pub blocking_after: usize,
+    recent: VecDeque<usize>,
+    recent_sum: usize,
+    depth_sum: usize,
+    conflicts: usize,
+    trail: VecDeque<usize>,
+    trail_sum: usize,
+}
+
+impl Default for GlucoseRestarts {
+    fn default() -> Self {
+        GlucoseRestarts {
+            window: 50,
+            margin: 0.8,
+            blocking_window: 5000,
+            blocking_margin: 1.4,
+            blocking_after: 10000,
+            recent: VecDeque::new(),
+            recent_sum: 0,
+            depth_sum: 0,
+            conflicts: 0,
+            trail: VecDeque::new(),
+            trail_sum: 0,
+        }
+    }
+}
+
+impl GlucoseRestarts {
+    fn clear_recent(&mut self) {
+        self.recent.clear();
+        self.recent_sum = 0;
+    }
+}
+
+impl RestartPolicy for GlucoseRestarts {
+    fn on_conflict(&mut self, conflict: &Conflict) -> bool {
+        self.conflicts += 1;
+        self.depth_sum += conflict.depth;
+
+        self.trail.push_back(conflict.assigned);
+        self.trail_sum += conflict.assigned;
+        if self.trail.len() > self.blocking_window {
+            self.trail_sum -= self.trail.pop_front().unwrap();
+        }
+        let trail_average = self.trail_sum as f64 / self.trail.len() as f64;
+        if self.conflicts > self.blocking_after
+            && self.recent.len() == self.window
+            && conflict.assigned as f64 > self.blocking_margin * trail_average
+        {
+            self.clear_recent();
+        }
+
+        self.recent.push_back(conflict.depth);
+        self.recent_sum += conflict.depth;
+        if self.recent.len() > self.window {
+            self.recent_sum -= self.recent.pop_front().unwrap();
+        }
+        let recent_average = self.recent_sum as f64 / self.recent.len() as f64;
+        let average = self.depth_sum as f64 / self.conflicts as f64;
+        if self.recent.len() == self.window && recent_average * self.margin > average {
+            self.clear_recent();
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_luby() {
+        let sequence: Vec<usize> = (1..=15).map(luby).collect();
+        assert_eq!(sequence, vec![1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn test_luby_restarts() {
+        let mut restarts = LubyRestarts::new(2);
+        let conflict = Conflict { depth: 1, assigned: 1 };
+        let fired: Vec<bool> = (0..10).map(|_| restarts.on_conflict(&conflict)).collect();
+        // Restarts after 2, 2 and then 4 conflicts
+        assert_eq!(
+            fired,
+            vec![false, true, false, true, false, false, false, true, false, true]
+        );
+    }
+
+    #[test]
+    fn test_glucose_restarts() {
+        let shallow = Conflict { depth: 2, assigned: 10 };
+        let deep = Conflict { depth: 10, assigned: 100 };
+
+        let mut glucose = GlucoseRestarts {
+            window: 3,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            assert!(!glucose.on_conflict(&shallow));
+        }
+        // The first deep conflict lifts the recent average above the global one
+        assert!(glucose.on_conflict(&deep));
+
+        let mut blocking = GlucoseRestarts {
+            window: 3,
+            blocking_window: 3,
+            blocking_after: 0,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            assert!(!blocking.on_conflict(&shallow));
+        }
+        // Far more assignments than usual, so the restart is blocked
+        assert!(!blocking.on_conflict(&deep));
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crossbeam_deque::{Injector, Steal};
+
+use crate::learned::LearnedClause;
+
+// Which learned clauses parallel workers pass on to each other, and how often
+// they take in those of their peers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SharingConfig {
+    // Only clauses of at most this many literals and this LBD are exported
+    pub max_size: usize,
+    pub max_lbd: usize,
+    // Clauses waiting for each worker at most, further ones are dropped
+    pub capacity: usize,
+    // Conflicts of a worker between imports
+    pub import_interval: usize,
+}
+
+impl Default for SharingConfig {
+    fn default() -> Self {
+        SharingConfig {
+            max_size: 8,
+            max_lbd: 4,
+            capacity: 1000,
+            import_interval: 50,
+        }
+    }
+}
+
+// A lock-free inbox per worker. Exporting pushes a clause into the inbox of
+// every other worker that is not full, importing takes everything out of
+// one's own.
+pub struct ClauseExchange {
+    config: SharingConfig,
+    inboxes: Vec<(Injector<LearnedClause>, AtomicUsize)>,
+}
+
+impl ClauseExchange {
+    pub fn new(workers: usize, config: SharingConfig) -> Self {
+        ClauseExchange {
+            config,
+            inboxes: (0..workers).map(|_| (Injector::new(), AtomicUsize::new(0))).collect(),
+        }
+    }
+
+    // Pass the clause from worker from to its peers if it is short enough,
+    // returning whether it was
+    pub fn export(&self, from: usize, clause: &LearnedClause) -> bool {
+        if clause.literals.len() > self.config.max_size || clause.lbd > self.config.max_lbd {
+            return false;
+        }
+        let peers = self.inboxes.iter().enumerate().filter(|&(i, _)| i != from);
+        for (_, (inbox, len)) in peers {
+            // Claim a place first so that racing exports cannot overfill it
+            if len.fetch_add(1, Ordering::Relaxed) < self.config.capacity {
+                inbox.push(clause.clone());
+            } else {
+                len.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        true
+    }
+
+    // The clauses waiting for worker to
+    pub fn import(&self, to: usize) -> Vec<LearnedClause> {
+        let (inbox, len) = &self.inboxes[to];
+        let mut clauses = Vec::new();
+        loop {
+            match inbox.steal() {
+                Steal::Success(clause) => clauses.push(clause),
+                Steal::Retry => continue,
+                Steal::Empty => break,
+            }
+        }
+        len.fetch_sub(clauses.len(), Ordering::Relaxed);
+        clauses
+    }
+}
+
+// The end of the exchange one worker holds, kept in its search state
+pub struct ClauseSharing {
+    exchange: Arc<ClauseExchange>,
+    worker: usize,
+    conflicts: usize,
+    pub exported: usize,
+    pub imported: usize,
+}
+
+impl ClauseSharing {
+    pub fn new(exchange: Arc<ClauseExchange>, worker: usize) -> Self {
+        ClauseSharing {
+            exchange,
+            worker,
+            conflicts: 0,
+            exported: 0,
+            imported: 0,
+        }
+    }
+
+    // Offer a clause just learned and, every import interval, return the
+    // clauses the peers exported since the last import
+    pub fn on_learn(&mut self, clause: &LearnedClause) -> Vec<LearnedClause> {
+        if self.exchange.export(self.worker, clause) {
+            self.exported += 1;
+        }
+        self.conflicts += 1;
+        if self.conflicts < self.exchange.config.import_interval {
+            return Vec::new();
+        }
+        self.conflicts = 0;
+        let clauses = self.exchange.import(self.worker);
+        self.imported += clauses.len();
+        clauses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clause_exchange() {
+        let config = SharingConfig {
+            max_size: 2,
+            max_lbd: 2,
+            capacity: 2,
+            import_interval: 3,
+        };
+        let exchange = Arc::new(ClauseExchange::new(3, config));
+        let clause = |literals: Vec<i32>, lbd| LearnedClause { literals, lbd };
+        let mut first = ClauseSharing::new(exchange.clone(), 0);
+        let mut second = ClauseSharing::new(exchange.clone(), 1);
+        // Too long, too high an LBD, then a short one
+        assert!(first.on_learn(&clause(vec![1, 2, 3], 1)).is_empty());
+        assert!(first.on_learn(&clause(vec![1, 2], 3)).is_empty());
+        assert!(first.on_learn(&clause(vec![-1], 1)).is_empty());
+        assert_eq!(first.exported, 1);
+        second.on_learn(&clause(vec![2, 3], 2));
+        second.on_learn(&clause(vec![-3], 1));
+        second.on_learn(&clause(vec![4], 1));
+        // The inbox of the first worker only held two of the three
+        assert_eq!(second.imported, 1);
+        assert_eq!(exchange.import(0), vec![clause(vec![2, 3], 2), clause(vec![-3], 1)]);
+        assert_eq!(exchange.import(2).len(), 2);
+        assert!(exchange.import(0).is_empty());
+    }
+}
@@ -0,0 +1,322 @@
+use rand::Rng;
+
+use crate::{Formula, Model};
+
+// Bounds of a WalkSAT run
+#[derive(Clone, Copy, Debug)]
+pub struct WalkSatConfig {
+    // Flips before giving up
+    pub max_flips: usize,
+    // Probability of flipping a random variable of the picked clause when
+    // every one of them would falsify some other clause
+    pub noise: f64,
+}
+
+impl Default for WalkSatConfig {
+    fn default() -> Self {
+        WalkSatConfig {
+            max_flips: 100_000,
+            noise: 0.5,
+        }
+    }
+}
+
+// Bounds of a probSAT run
+#[derive(Clone, Copy, Debug)]
+pub struct ProbSatConfig {
+    pub max_flips: usize,
+    // Base of the polynomial break weights, the larger the greedier
+    pub cb: f64,
+}
+
+impl Default for ProbSatConfig {
+    // The setting Balint and Schöning found best on random 3-SAT
+    fn default() -> Self {
+        ProbSatConfig {
+            max_flips: 100_000,
+            cb: 2.38,
+        }
+    }
+}
+
+// What a local search found: a model if it reached one, otherwise the
+// assignment that falsified the fewest clauses, and how many those were
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalSearch {
+    pub model: Option<Model>,
+    pub best: Model,
+    pub unsatisfied: usize,
+    pub flips: usize,
+}
+
+// Position of a literal in the occurrence lists
+fn index(lit: i32) -> usize {
+    2 * lit.unsigned_abs() as usize + usize::from(lit < 0)
+}
+
+// A complete assignment of the variables of a formula, with the number of
+// true literals of every clause and the list of the falsified ones
+struct Walk {
+    values: Vec<bool>,
+    occurrences: Vec<Vec<usize>>,
+    true_count: Vec<usize>,
+    unsatisfied: Vec<usize>,
+    // Position of every falsified clause in unsatisfied
+    position: Vec<usize>,
+}
+
+impl Walk {
+    fn new(formula: &Formula, values: Vec<bool>) -> Self {
+        let mut occurrences = vec![Vec::new(); 2 * values.len()];
+        for (i, clause) in formula.iter().enumerate() {
+            for &lit in clause.iter() {
+                occurrences[index(lit)].push(i);
+            }
+        }
+        let mut walk = Walk {
+            values,
+            occurrences,
+            true_count: vec![0; formula.len()],
+            unsatisfied: Vec::new(),
+            position: vec![usize::MAX; formula.len()],
+        };
+        for (i, clause) in formula.iter().enumerate() {
+            walk.true_count[i] = clause.iter().filter(|&&lit| walk.is_true(lit)).count();
+            if walk.true_count[i] == 0 {
+                walk.falsify(i);
+            }
+        }
+        walk
+    }
+
+    fn is_true(&self, lit: i32) -> bool {
+        self.values[lit.unsigned_abs() as usize] == (lit > 0)
+    }
+
+    fn falsify(&mut self, clause: usize) {
+        self.position[clause] = self.unsatisfied.len();
+        self.unsatisfied.push(clause);
+    }
+
+    fn satisfy(&mut self, clause: usize) {
+        let position = self.position[clause];
+        self.unsatisfied.swap_remove(position);
+        if let Some(&moved) = self.unsatisfied.get(position) {
+            self.position[moved] = position;
+        }
+        self.position[clause] = usize::MAX;
+    }
+
+    // The literal of the variable that is true now
+    fn true_literal(&self, var: i32) -> i32 {
+        if self.values[var as usize] {
+            var
+        } else {
+            -var
+        }
+    }
+
+    // Clauses flipping the variable would falsify
+    fn break_count(&self, var: i32) -> usize {
+        let lit = self.true_literal(var);
+        self.occurrences[index(lit)].iter().filter(|&&i| self.true_count[i] == 1).count()
+    }
+
+    fn flip(&mut self, var: i32) {
+        let old = self.true_literal(var);
+        self.values[var as usize] ^= true;
+        // Counting the new true literals first keeps clauses with both from
+        // passing through zero
+        for k in 0..self.occurrences[index(-old)].len() {
+            let i = self.occurrences[index(-old)][k];
+            self.true_count[i] += 1;
+            if self.true_count[i] == 1 {
+                self.satisfy(i);
+            }
+        }
+        for k in 0..self.occurrences[index(old)].len() {
+            let i = self.occurrences[index(old)][k];
+            self.true_count[i] -= 1;
+            if self.true_count[i] == 0 {
+                self.falsify(i);
+            }
+        }
+    }
+}
+
+// The values of the variables occurring in the formula
+fn model_of(formula: &Formula, values: &[bool]) -> Model {
+    formula.iter().flatten().map(|lit| (lit.abs(), values[lit.unsigned_abs() as usize])).collect()
+}
+
+// Flips between two polls of stop
+const STOP_INTERVAL: usize = 1024;
+
+// Local search from a random assignment. Every step picks a random falsified
+// clause and flips the variable of it that pick chooses, until no clause is
+// falsified, max_flips are spent or stop returns true.
+fn local_search<R: Rng>(
+    formula: &Formula,
+    max_flips: usize,
+    rng: &mut R,
+    stop: &dyn Fn() -> bool,
+    mut pick: impl FnMut(&Walk, &[i32], &mut R) -> i32,
+) -> LocalSearch {
+    let num_vars = formula.iter().flatten().map(|lit| lit.unsigned_abs() as usize).max().unwrap_or(0);
+    let values: Vec<bool> = (0..=num_vars).map(|_| rng.gen_bool(0.5)).collect();
+    let mut walk = Walk::new(formula, values);
+    let mut best = (walk.unsatisfied.len(), walk.values.clone());
+    let mut flips = 0;
+    // An empty clause stays falsified whatever is flipped
+    let hopeless = formula.iter().any(Vec::is_empty);
+    while !walk.unsatisfied.is_empty() && flips < max_flips && !hopeless {
+        if flips % STOP_INTERVAL == 0 && stop() {
+            break;
+        }
+        let clause = &formula[walk.unsatisfied[rng.gen_range(0..walk.unsatisfied.len())]];
+        let var = pick(&walk, clause, rng);
+        walk.flip(var);
+        flips += 1;
+        if walk.unsatisfied.len() < best.0 {
+            best = (walk.unsatisfied.len(), walk.values.clone());
+        }
+    }
+    LocalSearch {
+        model: walk.unsatisfied.is_empty().then(|| model_of(formula, &walk.values)),
+        best: model_of(formula, &best.1),
+        unsatisfied: best.0,
+        flips,
+    }
+}
+
+// WalkSAT flips a variable of the clause that falsifies no other clause if
+// there is one, otherwise with probability noise a random one of its
+// variables and the one falsifying the fewest clauses else
+fn walksat_pick(walk: &Walk, clause: &[i32], rng: &mut impl Rng, noise: f64) -> i32 {
+    let (breaks, var) = clause.iter().map(|lit| (walk.break_count(lit.abs()), lit.abs())).min().unwrap();
+    if breaks > 0 && rng.gen_bool(noise) {
+        clause[rng.gen_range(0..clause.len())].abs()
+    } else {
+        var
+    }
+}
+
+// probSAT flips a variable of the clause at random, with a probability that
+// falls polynomially with the clauses that flipping it falsifies
+fn probsat_pick(walk: &Walk, clause: &[i32], rng: &mut impl Rng, cb: f64) -> i32 {
+    let weights: Vec<f64> = clause.iter().map(|lit| (1.0 + walk.break_count(lit.abs()) as f64).powf(-cb)).collect();
+    let mut left = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for (lit, weight) in clause.iter().zip(weights.iter()) {
+        if left < *weight {
+            return lit.abs();
+        }
+        left -= weight;
+    }
+    clause[clause.len() - 1].abs()
+}
+
+pub fn walksat(formula: &Formula, config: &WalkSatConfig, rng: &mut impl Rng) -> LocalSearch {
+    LocalSearchConfig::WalkSat(*config).search(formula, rng, || false)
+}
+
+pub fn probsat(formula: &Formula, config: &ProbSatConfig, rng: &mut impl Rng) -> LocalSearch {
+    LocalSearchConfig::ProbSat(*config).search(formula, rng, || false)
+}
+
+// One of the local searches, as the command line names them
+#[derive(Clone, Copy, Debug)]
+pub enum LocalSearchConfig {
+    WalkSat(WalkSatConfig),
+    ProbSat(ProbSatConfig),
+}
+
+impl LocalSearchConfig {
+    // "walksat" or "probsat" with their default settings
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "walksat" => Some(LocalSearchConfig::WalkSat(WalkSatConfig::default())),
+            "probsat" => Some(LocalSearchConfig::ProbSat(ProbSatConfig::default())),
+            _ => None,
+        }
+    }
+
+    pub fn set_max_flips(&mut self, max_flips: usize) {
+        match self {
+            LocalSearchConfig::WalkSat(config) => config.max_flips = max_flips,
+            LocalSearchConfig::ProbSat(config) => config.max_flips = max_flips,
+        }
+    }
+
+    // The noise of WalkSAT, cb of probSAT
+    pub fn set_noise(&mut self, noise: f64) {
+        match self {
+            LocalSearchConfig::WalkSat(config) => config.noise = noise,
+            LocalSearchConfig::ProbSat(config) => config.cb = noise,
+        }
+    }
+
+    // Run the search, polling stop every so many flips
+    pub fn search(&self, formula: &Formula, rng: &mut impl Rng, stop: impl Fn() -> bool) -> LocalSearch {
+        match self {
+            LocalSearchConfig::WalkSat(config) => {
+                let pick = |walk: &Walk, clause: &[i32], rng: &mut _| walksat_pick(walk, clause, rng, config.noise);
+                local_search(formula, config.max_flips, rng, &stop, pick)
+            }
+            LocalSearchConfig::ProbSat(config) => {
+                let pick = |walk: &Walk, clause: &[i32], rng: &mut _| probsat_pick(walk, clause, rng, config.cb);
+                local_search(formula, config.max_flips, rng, &stop, pick)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pigeonhole, random_ksat, verify_model, SearchRng};
+    use std::cell::Cell;
+
+    #[test]
+    fn test_walksat_finds_model() {
+        // Well below the threshold, random 3-SAT is easy for local search
+        let formula = random_ksat(100, 300, 3, 7);
+        let search = walksat(&formula, &WalkSatConfig::default(), &mut SearchRng::default());
+        let model = search.model.expect("A model within the flips");
+        assert_eq!(verify_model(&formula, &model), Ok(()));
+        assert_eq!((search.unsatisfied, search.best), (0, model));
+    }
+
+    #[test]
+    fn test_walksat_gives_up() {
+        let formula = pigeonhole(3);
+        let config = WalkSatConfig {
+            max_flips: 1000,
+            ..Default::default()
+        };
+        let search = walksat(&formula, &config, &mut SearchRng::default());
+        assert_eq!((search.model, search.flips), (None, 1000));
+        assert!(search.unsatisfied >= 1);
+        assert!(verify_model(&formula, &search.best).is_err());
+
+        let search = walksat(&vec![vec![1], vec![]], &config, &mut SearchRng::default());
+        assert_eq!((search.model, search.flips), (None, 0));
+    }
+
+    #[test]
+    fn test_probsat() {
+        let formula = random_ksat(100, 300, 3, 7);
+        let search = probsat(&formula, &ProbSatConfig::default(), &mut SearchRng::default());
+        assert_eq!(verify_model(&formula, &search.model.expect("A model within the flips")), Ok(()));
+
+        // Asked to stop at the second poll, after one interval of flips
+        let polls = Cell::new(0);
+        let config = LocalSearchConfig::from_name("probsat").unwrap();
+        let stop = || {
+            polls.set(polls.get() + 1);
+            polls.get() == 2
+        };
+        let search = config.search(&pigeonhole(4), &mut SearchRng::default(), stop);
+        assert_eq!((search.model, search.flips), (None, STOP_INTERVAL));
+        assert!(LocalSearchConfig::from_name("gsat").is_none());
+    }
+}
@@ -0,0 +1,1209 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+
+use crate::encodings::{at_least_k, at_most_k, exactly_k};
+use crate::maxsat::minimize_soft;
+#[cfg(feature = "serde")]
+use crate::{continue_search, Checkpointer};
+use crate::{
+    prime_implicant, pure_literal_elimination_keeping, run_search, walksat, Assignment, BranchingHeuristic,
+    Budget, ClauseExchange, Formula, Fragment, Interrupter, LearnedClauses, LocalSearchConfig, MinIndexHeuristic,
+    MaxSatResult, Model, Node,
+    PbConstraint, PbSystem, Progress, ProgressReporter, ReductionConfig, Relation, SearchRng, SearchState,
+    SharingConfig, SolveResult, Stats, XorConstraint, XorSystem,
+};
+
+// A DIMACS literal: a variable index, negated for its negative polarity
+pub type Lit = i32;
+
+// The decisions unit propagation needs to get from them to a conflict, found by
+// propagating them in order and following the reasons back from the
+// falsified clause. None if propagation does not reach a conflict.
+fn conflict_decisions(clauses: &[&[i32]], decisions: &[i32]) -> Option<Vec<i32>> {
+    let mut values: HashMap<i32, bool> = HashMap::new();
+    // Index of the clause that forced each implied variable
+    let mut reasons: HashMap<i32, usize> = HashMap::new();
+    let explain = |start: Vec<i32>, reasons: &HashMap<i32, usize>| {
+        let mut seen: HashSet<i32> = start.iter().copied().collect();
+        let mut stack = start;
+        while let Some(var) = stack.pop() {
+            if let Some(&reason) = reasons.get(&var) {
+                for lit in clauses[reason].iter() {
+                    if seen.insert(lit.abs()) {
+                        stack.push(lit.abs());
+                    }
+                }
+            }
+        }
+        decisions.iter().copied().filter(|lit| seen.contains(&lit.abs())).collect::<Vec<_>>()
+    };
+
+    for &decision in decisions.iter() {
+        match values.get(&decision.abs()) {
+            Some(&val) if val == (decision > 0) => continue,
+            Some(_) => return Some(explain(vec![decision.abs()], &reasons)),
+            None => {
+                values.insert(decision.abs(), decision > 0);
+            }
+        }
+        loop {
+            let mut changed = false;
+            for (i, clause) in clauses.iter().enumerate() {
+                let mut open = None;
+                let mut open_count = 0;
+                let mut satisfied = false;
+                for &lit in clause.iter() {
+                    match values.get(&lit.abs()) {
+                        Some(&val) if val == (lit > 0) => {
+                            satisfied = true;
+                            break;
+                        }
+                        Some(_) => {}
+                        None if open != Some(lit) => {
+                            open = Some(lit);
+                            open_count += 1;
+                        }
+                        None => {}
+                    }
+                }
+                if satisfied {
+                    continue;
+                }
+                match (open_count, open) {
+                    (0, _) => return Some(explain(clause.iter().map(|lit| lit.abs()).collect(), &reasons)),
+                    (1, Some(lit)) => {
+                        values.insert(lit.abs(), lit > 0);
+                        reasons.insert(lit.abs(), i);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+    None
+}
+
+// The assumptions of a running solve and those that took part in one of its
+// conflicts so far
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AssumptionTracker {
+    formula: Formula,
+    assumptions: HashSet<Lit>,
+    failed: HashSet<Lit>,
+}
+
+impl AssumptionTracker {
+    // Record the assumptions behind a conflict under the given decisions. If
+    // propagation alone does not explain it every assumption is counted.
+    pub(crate) fn record(&mut self, decisions: &[i32], learned: Option<&LearnedClauses>) {
+        let mut clauses: Vec<&[i32]> = self.formula.iter().map(Vec::as_slice).collect();
+        if let Some(learned) = learned {
+            clauses.extend(learned.iter().map(|clause| clause.literals.as_slice()));
+        }
+        let used = conflict_decisions(&clauses, decisions).unwrap_or_else(|| decisions.to_vec());
+        self.failed
+            .extend(used.into_iter().filter(|lit| self.assumptions.contains(lit)));
+    }
+}
+
+// Build a formula clause by clause in code and solve it, without going
+// through a DIMACS file
+pub struct Solver {
+    pub(crate) formula: Formula,
+    pub(crate) num_vars: i32,
+    pub(crate) heuristic: Box<dyn BranchingHeuristic>,
+    pub state: SearchState,
+    // Model found by the latest satisfiable answer
+    pub(crate) model: Option<Model>,
+    // Assumptions behind the latest unsatisfiable answer
+    pub(crate) failed: Vec<Lit>,
+    pub(crate) xors: Vec<XorConstraint>,
+    pub(crate) pbs: Vec<PbConstraint>,
+    // Weighted clauses for solve_optimal
+    pub(crate) soft: Vec<(u64, Vec<Lit>)>,
+    // Activation literals of the clause groups, with whether each is enabled
+    pub(crate) groups: Vec<(Lit, bool)>,
+    // Learned clauses the workers of a parallel search pass on, none if None
+    pub(crate) sharing: Option<SharingConfig>,
+    // Root and tasklist of a search to go on with, see resume
+    #[cfg(feature = "serde")]
+    pub(crate) resumed: Option<(Node, Vec<Node>)>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Solver {
+            formula: Vec::new(),
+            num_vars: 0,
+            heuristic: Box::new(MinIndexHeuristic),
+            state: SearchState::default(),
+            model: None,
+            failed: Vec::new(),
+            xors: Vec::new(),
+            pbs: Vec::new(),
+            soft: Vec::new(),
+            groups: Vec::new(),
+            sharing: Some(SharingConfig::default()),
+            #[cfg(feature = "serde")]
+            resumed: None,
+        }
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_clauses(formula: &[Vec<i32>]) -> Self {
+        let mut solver = Self::new();
+        for clause in formula.iter() {
+            solver.add_clause(clause);
+        }
+        solver
+    }
+
+    // Read a DIMACS CNF file, panics if it cannot be read
+    #[cfg(feature = "files")]
+    pub fn from_file(path: &str) -> Self {
+        Self::from_clauses(&crate::read_cnf_file(path))
+    }
+
+    // A fresh variable, returned as its positive literal
+    pub fn new_var(&mut self) -> Lit {
+        self.num_vars += 1;
+        self.num_vars
+    }
+
+    pub fn num_vars(&self) -> i32 {
+        self.num_vars
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    // The model of the last solve call, None unless it was satisfiable
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+
+    // Counters summed over every solve call so far
+    pub fn stats(&self) -> &Stats {
+        &self.state.stats
+    }
+
+    // The assumptions of the last solve_with_assumptions call that together
+    // with the formula are unsatisfiable, empty after a satisfiable answer.
+    // Empty after an unsatisfiable one means the formula alone is.
+    pub fn failed_assumptions(&self) -> &[Lit] {
+        &self.failed
+    }
+
+    // IPASIR-style check of a single assumption
+    pub fn failed(&self, lit: Lit) -> bool {
+        self.failed.contains(&lit)
+    }
+
+    pub fn set_heuristic(&mut self, heuristic: Box<dyn BranchingHeuristic>) {
+        self.heuristic = heuristic;
+    }
+
+    // Restart the random number generator of the search from a seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.state.rng = SearchRng::seed_from_u64(seed);
+    }
+
+    // Which learned clauses the portfolio, cube and parallel tree searches
+    // exchange between their workers, None to keep each worker's to itself
+    pub fn set_clause_sharing(&mut self, config: Option<SharingConfig>) {
+        self.sharing = config;
+    }
+
+    // The exchange for a parallel search on this many workers, if sharing
+    pub(crate) fn clause_exchange(&self, workers: usize) -> Option<Arc<ClauseExchange>> {
+        self.sharing.map(|config| Arc::new(ClauseExchange::new(workers, config)))
+    }
+
+    // Call back every interval expanded nodes with a progress snapshot
+    pub fn set_progress_callback(&mut self, interval: usize, callback: impl FnMut(&Progress) + Send + 'static) {
+        self.state.progress = Some(ProgressReporter::new(interval, callback));
+    }
+
+    // Call back with every clause the search learns, turning clause learning
+    // on if it is off
+    pub fn set_learn_callback(&mut self, callback: impl FnMut(&[Lit]) + Send + 'static) {
+        if self.state.learned.is_none() {
+            self.state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+        }
+        self.state.on_learn = Some(Box::new(callback));
+    }
+
+    // Literals may also name variables that new_var has not returned yet
+    pub fn add_clause(&mut self, clause: &[Lit]) {
+        assert!(!clause.contains(&0), "0 is not a literal");
+        if let Some(max_var) = clause.iter().map(|lit| lit.abs()).max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        self.formula.push(clause.to_vec());
+    }
+
+    // Constrain the variables to an odd number of true ones if parity is set
+    // and to an even number otherwise. The search reasons about every such
+    // constraint by Gaussian elimination instead of through clauses.
+    pub fn add_xor(&mut self, vars: &[Lit], parity: bool) {
+        assert!(vars.iter().all(|&var| var > 0), "XOR constraints take variables");
+        if let Some(&max_var) = vars.iter().max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        self.xors.push(XorConstraint {
+            vars: vars.to_vec(),
+            parity,
+        });
+    }
+
+    // Constrain the sum of the coefficients of the true literals, as in
+    // 3 x1 + 2 x2 + x3 >= 4. The search propagates the constraint directly
+    // instead of encoding it in clauses.
+    pub fn add_pb(&mut self, terms: &[(i64, Lit)], relation: Relation, rhs: i64) {
+        assert!(terms.iter().all(|&(_, lit)| lit != 0), "0 is not a literal");
+        if let Some(max_var) = terms.iter().map(|(_, lit)| lit.abs()).max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        self.pbs.push(PbConstraint {
+            terms: terms.to_vec(),
+            relation,
+            rhs,
+        });
+    }
+
+    // A clause whose violation costs weight in solve_optimal, which the
+    // other solve calls ignore
+    pub fn add_soft_clause(&mut self, clause: &[Lit], weight: u64) {
+        assert!(!clause.contains(&0), "0 is not a literal");
+        if let Some(max_var) = clause.iter().map(|lit| lit.abs()).max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        self.soft.push((weight, clause.to_vec()));
+    }
+
+    // The model that leaves the least total weight of soft clauses violated,
+    // with that weight, by core-guided search, see solve_wcnf_core_guided.
+    // None if there is no model at all, or if the search is stopped early.
+    // The selectors and counters it adds constrain none of the variables
+    // there were before, and later calls add their own.
+    pub fn solve_optimal(&mut self) -> Option<MaxSatResult> {
+        let (soft, num_vars) = (std::mem::take(&mut self.soft), self.num_vars);
+        let result = minimize_soft(self, &soft, num_vars);
+        self.soft = soft;
+        result
+    }
+
+    // Add the clause guarded by a fresh selector variable, returned as the
+    // literal to assume to enable the clause. Solving with a selector in the
+    // assumptions enforces the clause, and a failed selector means the clause
+    // took part in the conflict. Literals of clauses added later must not
+    // name the selector.
+    pub fn add_clause_with_selector(&mut self, clause: &[Lit]) -> Lit {
+        assert!(!clause.contains(&0), "0 is not a literal");
+        if let Some(max_var) = clause.iter().map(|lit| lit.abs()).max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        let selector = self.new_var();
+        let mut guarded = clause.to_vec();
+        guarded.push(-selector);
+        self.formula.push(guarded);
+        selector
+    }
+
+    // Allow at most k of the literals to be true, by the clauses of
+    // encodings::at_most_k
+    pub fn add_at_most_k(&mut self, lits: &[Lit], k: usize) {
+        self.add_encoding(lits, |next_var| at_most_k(lits, k, next_var));
+    }
+
+    pub fn add_at_least_k(&mut self, lits: &[Lit], k: usize) {
+        self.add_encoding(lits, |next_var| at_least_k(lits, k, next_var));
+    }
+
+    pub fn add_exactly_k(&mut self, lits: &[Lit], k: usize) {
+        self.add_encoding(lits, |next_var| exactly_k(lits, k, next_var));
+    }
+
+    // Add the clauses of an encoding over the literals, its fresh variables
+    // are the ones new_var returns next
+    fn add_encoding(&mut self, lits: &[Lit], encode: impl FnOnce(&mut i32) -> Formula) {
+        assert!(!lits.contains(&0), "0 is not a literal");
+        if let Some(max_var) = lits.iter().map(|lit| lit.abs()).max() {
+            self.num_vars = self.num_vars.max(max_var);
+        }
+        let mut next_var = self.num_vars;
+        let clauses = encode(&mut next_var);
+        while self.num_vars < next_var {
+            self.new_var();
+        }
+        for clause in clauses.iter() {
+            self.add_clause(clause);
+        }
+    }
+
+    // Keep pure literal elimination and inprocessing from removing the
+    // variable until it is melted as often as it was frozen, for variables
+    // that assumptions or clauses of later calls are going to name. The
+    // variables of a call's assumptions are kept during that call anyway.
+    pub fn freeze(&mut self, var: Lit) {
+        *self.state.frozen.entry(var.abs()).or_default() += 1;
+    }
+
+    pub fn melt(&mut self, var: Lit) {
+        let count = self.state.frozen.get_mut(&var.abs()).unwrap_or_else(|| panic!("{} is not frozen", var));
+        *count -= 1;
+        if *count == 0 {
+            self.state.frozen.remove(&var.abs());
+        }
+    }
+
+    pub fn is_frozen(&self, var: Lit) -> bool {
+        self.state.frozen.contains_key(&var.abs())
+    }
+
+    // A group of clauses that can be switched off between solve calls, named
+    // by a fresh activation literal. Searches assume the literal of every
+    // enabled group and its negation for every disabled one, before their
+    // own assumptions, so an enabled group may show up among the failed
+    // assumptions. Clauses outside the group must not name the literal. The
+    // local and look-ahead searches take no assumptions and leave every
+    // group free.
+    pub fn new_group(&mut self) -> Lit {
+        let group = self.new_var();
+        self.groups.push((group, true));
+        group
+    }
+
+    // Add a clause that holds while the group is enabled
+    pub fn add_group_clause(&mut self, group: Lit, clause: &[Lit]) {
+        assert!(self.groups.iter().any(|&(other, _)| other == group), "{} is not a group", group);
+        let mut guarded = clause.to_vec();
+        guarded.push(-group);
+        self.add_clause(&guarded);
+    }
+
+    pub fn set_group_enabled(&mut self, group: Lit, enabled: bool) {
+        let entry = self.groups.iter_mut().find(|(other, _)| *other == group);
+        entry.unwrap_or_else(|| panic!("{} is not a group", group)).1 = enabled;
+    }
+
+    // Switch the group off for good: its clauses are deleted along with the
+    // learned clauses that depend on it, which all contain the negation of
+    // its literal
+    pub fn release_group(&mut self, group: Lit) {
+        self.groups.retain(|&(other, _)| other != group);
+        self.formula.retain(|clause| !clause.contains(&-group));
+        if let Some(learned) = self.state.learned.as_mut() {
+            learned.retain(|clause| !clause.literals.contains(&-group));
+        }
+    }
+
+    // Iterate over every model of the formula. Each one assigns every
+    // variable that occurs in a clause, including those that were left free
+    // and defaulted to true, and is blocked as a whole before the next search,
+    // so no model comes up twice.
+    pub fn solutions(self) -> Solutions {
+        Solutions {
+            solver: self,
+            minimize: false,
+            projection: None,
+            done: false,
+        }
+    }
+
+    // Iterate over disjoint cubes of models that together cover every model
+    // of the formula. A cube assigns some variables and leaves the others
+    // free, every assignment of those is a model. Each model found is shrunk
+    // to a prime implicant of the clauses, the blocking clauses of earlier
+    // cubes included, and only its literals are blocked, so formulas with
+    // many variables that do not matter take few searches. With XOR or
+    // pseudo-Boolean constraints the cubes are whole models.
+    pub fn implicants(self) -> Solutions {
+        Solutions {
+            solver: self,
+            minimize: true,
+            projection: None,
+            done: false,
+        }
+    }
+
+    // Iterate over the assignments of vars that extend to models, each once
+    // whatever the other variables are, such as the auxiliary ones of a
+    // Tseitin encoding. Only the projection is blocked after every model.
+    pub fn enumerate_projected(mut self, vars: &[Lit]) -> Solutions {
+        let mut vars: Vec<Lit> = vars.iter().map(|lit| lit.abs()).collect();
+        vars.sort_unstable();
+        vars.dedup();
+        // Variables of no clause are free, they take both values
+        while self.num_vars() < vars.last().copied().unwrap_or(0) {
+            self.new_var();
+        }
+        Solutions {
+            solver: self,
+            minimize: false,
+            projection: Some(vars),
+            done: false,
+        }
+    }
+
+    // Search for a model of the clauses added so far. False after a search
+    // that was stopped early as well, see solve_limited.
+    pub fn solve(&mut self) -> bool {
+        self.solve_with_assumptions(&[])
+    }
+
+    // Search for a model in which every assumed literal is true. The
+    // assumptions are decisions made at the root, they only hold for this call
+    // while learned clauses and saved phases carry over to the next one.
+    pub fn solve_with_assumptions(&mut self, assumptions: &[Lit]) -> bool {
+        self.search(assumptions).is_sat()
+    }
+
+    // Bound the work of every later solve call, which gives up with Unknown
+    // once a limit is reached
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.state.budget = budget;
+    }
+
+    // A handle that stops the running or next solve call when tripped
+    pub fn interrupter(&mut self) -> Interrupter {
+        self.state.interrupter.get_or_insert_with(Interrupter::new).clone()
+    }
+
+    // Search for a model within the budget
+    pub fn solve_limited(&mut self) -> SolveResult {
+        self.search(&[])
+    }
+
+    // Search for a model, giving up with Unknown once the timeout has passed
+    pub fn solve_with_timeout(&mut self, timeout: Duration) -> SolveResult {
+        // A timeout too large to represent never passes
+        self.state.deadline = Instant::now().checked_add(timeout);
+        let result = self.search(&[]);
+        self.state.deadline = None;
+        result
+    }
+
+    // Look for a model by local search alone. Unknown if it finds none within
+    // its flips, the deadline or an interrupt, or if there are XOR or
+    // pseudo-Boolean constraints, which it cannot account for.
+    pub fn solve_local(&mut self, config: &LocalSearchConfig) -> SolveResult {
+        self.failed.clear();
+        self.model = None;
+        if !self.xors.is_empty() || !self.pbs.is_empty() {
+            return SolveResult::Unknown;
+        }
+        let (deadline, interrupter) = (self.state.deadline, self.state.interrupter.as_ref());
+        let stop = || {
+            deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                || interrupter.is_some_and(Interrupter::is_interrupted)
+        };
+        let search = config.search(&self.formula, &mut self.state.rng, stop);
+        self.state.stats.flips += search.flips;
+        match search.model {
+            Some(model) => self.found_without_search(model),
+            None => SolveResult::Unknown,
+        }
+    }
+
+    // Save the search of every later solve call without assumptions to the
+    // file at path whenever the interval has passed since the last save
+    #[cfg(feature = "serde")]
+    pub fn checkpoint(&mut self, path: impl AsRef<std::path::Path>, interval: Duration) {
+        self.state.checkpoint = Some(Checkpointer::new(path.as_ref(), interval));
+    }
+
+    // Why the latest save of the search failed, if it did
+    #[cfg(feature = "serde")]
+    pub fn checkpoint_error(&self) -> Option<&std::io::Error> {
+        self.state.checkpoint.as_ref()?.error.as_ref()
+    }
+
+    // Go on with the search saved at path by the next solve call without
+    // assumptions, instead of starting from the root. The solver has to have
+    // the constraints of the saved one, its configuration is its own.
+    #[cfg(feature = "serde")]
+    pub fn resume(&mut self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let snapshot = crate::checkpoint::Snapshot::read(path.as_ref())?;
+        if snapshot.problem != self.problem() {
+            let message = "The checkpoint is of other constraints";
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+        }
+        self.state.stats = snapshot.stats.clone();
+        self.resumed = Some((snapshot.root.clone(), snapshot.tasklist()));
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    fn problem(&self) -> crate::checkpoint::Problem {
+        crate::checkpoint::Problem {
+            formula: self.formula.clone(),
+            xors: self.xors.clone(),
+            pbs: self.pbs.clone(),
+        }
+    }
+
+    // Every variable of the constraints, unassigned, with room for every
+    // variable the solver knows of
+    pub(crate) fn initial_assignment(&self) -> Assignment {
+        let mut assignment = Assignment::with_num_vars(self.num_vars as usize);
+        let pb_lits = self.pbs.iter().flat_map(|pb| pb.terms.iter().map(|(_, lit)| lit));
+        for lit in self.formula.iter().flatten().chain(pb_lits) {
+            assignment.add_var(lit.abs());
+        }
+        assignment
+    }
+
+    // Hand the XOR and pseudo-Boolean constraints to the search
+    pub(crate) fn load_constraints(&mut self) {
+        self.state.xors = (!self.xors.is_empty()).then(|| XorSystem::new(&self.xors));
+        self.state.pbs = (!self.pbs.is_empty()).then(|| PbSystem::new(&self.pbs));
+    }
+
+    // The root of the search tree under the assignment, after pure literal
+    // elimination, which leaves the frozen variables and those of the loaded
+    // constraints alone
+    pub(crate) fn root(&self, mut assignment: Assignment) -> Node {
+        let (xors, pbs, frozen) = (self.state.xors.as_ref(), self.state.pbs.as_ref(), &self.state.frozen);
+        let keep = |var| {
+            frozen.contains_key(&var)
+                || xors.is_some_and(|xors| xors.contains(var))
+                || pbs.is_some_and(|pbs| pbs.contains(var))
+        };
+        let simplified_formula = pure_literal_elimination_keeping(&self.formula, &mut assignment, keep);
+        Node::new(simplified_formula, None, 0, assignment)
+    }
+
+    // Answer with a model of the formula found some other way, extended to
+    // the variables preprocessing removed
+    pub(crate) fn found_without_search(&mut self, model: Model) -> SolveResult {
+        let mut assignment = model.into_iter().map(|(var, val)| (var, Some(val))).collect();
+        self.state.reconstruction.extend_model(&mut assignment);
+        let model: Model = assignment.into_iter().map(|(var, val)| (var, val == Some(true))).collect();
+        self.model = Some(model.clone());
+        SolveResult::Sat(model)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "solve",
+            skip_all,
+            fields(clauses = self.formula.len(), assumptions = assumptions.len())
+        )
+    )]
+    pub(crate) fn search(&mut self, assumptions: &[Lit]) -> SolveResult {
+        self.failed.clear();
+        self.model = None;
+        let groups = self.groups.iter().map(|&(group, enabled)| if enabled { group } else { -group });
+        let assumptions: Vec<Lit> = groups.chain(assumptions.iter().copied()).collect();
+        let assumptions = &assumptions[..];
+        if cfg!(debug_assertions) {
+            self.state.check_models = Some(self.formula.clone());
+        }
+        // Snapshots record the constraints they were taken of
+        #[cfg(feature = "serde")]
+        if let Some(mut checkpointer) = self.state.checkpoint.take() {
+            checkpointer.problem = self.problem();
+            self.state.checkpoint = Some(checkpointer);
+        }
+        #[cfg(feature = "serde")]
+        if let Some((root, tasklist)) = self.resumed.take_if(|_| assumptions.is_empty()) {
+            self.load_constraints();
+            let result = continue_search(root, tasklist, self.heuristic.as_ref(), &mut self.state);
+            if let SolveResult::Sat(model) = &result {
+                self.model = Some(model.clone());
+            }
+            return result;
+        }
+        // Some formulas need no search, unless the answer has to account for
+        // assumptions, other constraints or a proof
+        let others = !self.xors.is_empty() || !self.pbs.is_empty() || self.state.proof.is_some();
+        let fragment = Fragment::detect(&self.formula).filter(|_| assumptions.is_empty() && !others);
+        self.state.stats.fragment = fragment;
+        if let Some(fragment) = fragment {
+            let Some(model) = fragment.solve(&self.formula) else {
+                return SolveResult::Unsat;
+            };
+            return self.found_without_search(model);
+        }
+        if let Some(config) = self.state.walksat.filter(|_| assumptions.is_empty() && !others) {
+            let search = walksat(&self.formula, &config, &mut self.state.rng);
+            self.state.stats.flips += search.flips;
+            if let Some(model) = search.model {
+                return self.found_without_search(model);
+            }
+            // The hints only count while phases are saved
+            self.state.phases.enabled = true;
+            for (&var, &val) in search.best.iter() {
+                self.state.phases.save(var, val);
+            }
+        }
+        let mut assignment = self.initial_assignment();
+        let mut decisions = Vec::new();
+        for &lit in assumptions.iter() {
+            match assignment.get(&lit.abs()) {
+                Some(&Some(val)) if val != (lit > 0) => {
+                    self.failed = vec![-lit, lit];
+                    return SolveResult::Unsat;
+                }
+                Some(&Some(_)) => {}
+                _ => {
+                    assignment.insert(lit.abs(), Some(lit > 0));
+                    decisions.push(lit);
+                }
+            }
+        }
+        let falsified = |lit: &Lit| assignment.get(&lit.abs()) == Some(&Some(*lit < 0));
+        if let Some(clause) = self.formula.iter().find(|clause| clause.iter().all(falsified)) {
+            self.failed = decisions.iter().copied().filter(|lit| clause.contains(&-lit)).collect();
+            return SolveResult::Unsat;
+        }
+        if !decisions.is_empty() {
+            self.state.assumption_tracker = Some(AssumptionTracker {
+                formula: self.formula.clone(),
+                assumptions: decisions.iter().copied().collect(),
+                failed: HashSet::new(),
+            });
+        }
+        self.load_constraints();
+        let mut root = self.root(assignment);
+        root.decisions = decisions.clone();
+        let result = run_search(root, self.heuristic.as_ref(), &mut self.state);
+        let tracker = self.state.assumption_tracker.take();
+        match &result {
+            SolveResult::Sat(model) => self.model = Some(model.clone()),
+            SolveResult::Unsat => {
+                if let Some(tracker) = tracker {
+                    self.failed = decisions.into_iter().filter(|lit| tracker.failed.contains(lit)).collect();
+                }
+            }
+            SolveResult::Unknown => {}
+        }
+        result
+    }
+}
+
+// Iterator over the models of a formula, see Solver::solutions, over cubes
+// of them, see Solver::implicants, or over their projections, see
+// Solver::enumerate_projected
+pub struct Solutions {
+    solver: Solver,
+    minimize: bool,
+    projection: Option<Vec<Lit>>,
+    done: bool,
+}
+
+impl Iterator for Solutions {
+    type Item = HashMap<i32, bool>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || !self.solver.solve() {
+            self.done = true;
+            return None;
+        }
+        let mut model = self.solver.model.take().expect("A satisfiable search records its model");
+        if self.minimize && self.solver.xors.is_empty() && self.solver.pbs.is_empty() {
+            model = prime_implicant(&self.solver.formula, &model).expect("The model satisfies the clauses");
+        }
+        if let Some(vars) = self.projection.as_ref() {
+            model = vars.iter().map(|&var| (var, model.get(&var) == Some(&true))).collect();
+        }
+        let mut blocking: Vec<Lit> = model.iter().map(|(&var, &val)| if val { -var } else { var }).collect();
+        blocking.sort_unstable_by_key(|lit| lit.abs());
+        // The empty model of an empty formula is its only one, as is the
+        // empty cube of a formula every assignment satisfies
+        self.done = blocking.is_empty();
+        self.solver.add_clause(&blocking);
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pigeonhole, random_ksat, verify_model, LearnedClauses, ReductionConfig, WalkSatConfig};
+
+    #[test]
+    fn test_solver_builds_and_solves() {
+        let mut solver = Solver::new();
+        let (a, b, c) = (solver.new_var(), solver.new_var(), solver.new_var());
+        assert_eq!((a, b, c), (1, 2, 3));
+        solver.add_clause(&[a, b]);
+        solver.add_clause(&[-a, c]);
+        solver.add_clause(&[-b, c]);
+        assert!(solver.solve());
+
+        // Clauses can still be added after solving
+        solver.add_clause(&[-c]);
+        assert!(!solver.solve());
+
+        solver.add_clause(&[7, -a]);
+        assert_eq!(solver.num_vars(), 7);
+        assert_eq!(solver.new_var(), 8);
+    }
+
+    #[test]
+    fn test_solve_with_limits() {
+        let mut solver = Solver::from_file("testcnf.cnf");
+        // Out of time before the root is expanded
+        assert_eq!(solver.solve_with_timeout(Duration::ZERO), SolveResult::Unknown);
+        assert_eq!(solver.stats().nodes, 0);
+        assert_eq!(solver.solve_with_timeout(Duration::from_secs(60)), SolveResult::Unsat);
+
+        // testcnf.cnf needs more than one node
+        solver.set_budget(Budget { max_nodes: Some(1), ..Default::default() });
+        assert_eq!(solver.solve_limited(), SolveResult::Unknown);
+        solver.set_budget(Budget::default());
+        assert_eq!(solver.solve_limited(), SolveResult::Unsat);
+
+        let interrupter = solver.interrupter();
+        interrupter.interrupt();
+        assert_eq!(solver.solve_limited(), SolveResult::Unknown);
+        interrupter.clear();
+        assert_eq!(solver.solve_limited(), SolveResult::Unsat);
+        // Tripped from another thread, or in the middle of a search
+        let remote = solver.interrupter();
+        std::thread::spawn(move || remote.interrupt()).join().unwrap();
+        assert_eq!(solver.solve_limited(), SolveResult::Unknown);
+        interrupter.clear();
+        let nodes = solver.stats().nodes;
+        let remote = solver.interrupter();
+        solver.set_progress_callback(2, move |_| remote.interrupt());
+        assert_eq!(solver.solve_limited(), SolveResult::Unknown);
+        assert_eq!(solver.stats().nodes, nodes + 2);
+
+        let mut solver = Solver::from_clauses(&[vec![1, 2], vec![-1]]);
+        let model = maplit::hashmap! { 1 => false, 2 => true };
+        assert_eq!(solver.solve_with_timeout(Duration::MAX), SolveResult::Sat(model.clone()));
+        assert_eq!(solver.model(), Some(&model));
+    }
+
+    #[test]
+    fn test_solver_from_clauses() {
+        let formula = vec![vec![1, 2], vec![-1, 2], vec![1, -2, 3], vec![-3, -2]];
+        let mut solver = Solver::from_clauses(&formula);
+        assert_eq!(solver.model(), None);
+        assert!(solver.solve());
+        assert_eq!(solver.model(), Some(&maplit::hashmap! { 1 => true, 2 => true, 3 => false }));
+
+        // testcnf.cnf has no solution, proving it takes some conflicts
+        let mut solver = Solver::from_file("testcnf.cnf");
+        assert_eq!(solver.num_vars(), 3);
+        assert!(!solver.solve());
+        assert_eq!(solver.model(), None);
+        assert!(solver.stats().conflicts > 0);
+    }
+
+    #[test]
+    fn test_solve_with_assumptions() {
+        let mut solver = Solver::new();
+        solver.state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+        // a implies b, b implies c, and c excludes d
+        solver.add_clause(&[-1, 2]);
+        solver.add_clause(&[-2, 3]);
+        solver.add_clause(&[-3, -4]);
+        solver.add_clause(&[4, 5, 6]);
+        assert!(solver.solve_with_assumptions(&[1]));
+        assert!(!solver.solve_with_assumptions(&[1, 4]));
+        assert!(!solver.solve_with_assumptions(&[1, -3]));
+        assert!(!solver.solve_with_assumptions(&[5, -5]));
+        assert!(solver.solve_with_assumptions(&[4, -5]));
+        // Nothing learned under assumptions constrains later calls
+        assert!(solver.solve_with_assumptions(&[-1, 4]));
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn test_failed_assumptions() {
+        let mut solver = Solver::new();
+        // a implies b, b implies c, and c excludes d; e and f are unrelated
+        solver.add_clause(&[-1, 2]);
+        solver.add_clause(&[-2, 3]);
+        solver.add_clause(&[-3, -4]);
+        solver.add_clause(&[5, 6, 7]);
+        solver.add_clause(&[-5, -6]);
+        assert!(!solver.solve_with_assumptions(&[5, 1, 6, 4]));
+        assert_eq!(solver.failed_assumptions(), &[5, 6]);
+        assert!(!solver.solve_with_assumptions(&[-7, 1, 4]));
+        assert_eq!(solver.failed_assumptions(), &[1, 4]);
+        assert!(solver.failed(4) && !solver.failed(-7));
+        assert!(!solver.solve_with_assumptions(&[2, -2]));
+        assert_eq!(solver.failed_assumptions(), &[2, -2]);
+        assert!(!solver.solve_with_assumptions(&[-7, 3, 4]));
+        assert_eq!(solver.failed_assumptions(), &[3, 4]);
+        assert!(solver.solve_with_assumptions(&[1, -4]));
+        assert!(solver.failed_assumptions().is_empty());
+
+        // Needs branching: neither assumption alone refutes x or y
+        let mut solver = Solver::new();
+        solver.add_clause(&[-1, 3, 4]);
+        solver.add_clause(&[-1, 3, -4]);
+        solver.add_clause(&[-1, -3, 4]);
+        solver.add_clause(&[-1, -3, -4]);
+        solver.add_clause(&[2, 5]);
+        assert!(!solver.solve_with_assumptions(&[-2, 1, 6]));
+        assert_eq!(solver.failed_assumptions(), &[1]);
+    }
+
+    #[test]
+    fn test_clause_selectors() {
+        let mut solver = Solver::new();
+        solver.new_var();
+        let a = solver.add_clause_with_selector(&[1]);
+        let b = solver.add_clause_with_selector(&[-1]);
+        assert_eq!((a, b), (2, 3));
+        assert!(solver.solve_with_assumptions(&[a]) && solver.solve_with_assumptions(&[b]));
+        assert!(!solver.solve_with_assumptions(&[a, b]));
+        assert_eq!(solver.failed_assumptions(), &[a, b]);
+        // Without its selector a clause is not enforced
+        assert!(solver.solve());
+    }
+
+    #[test]
+    fn test_solver_empty_clause() {
+        let mut solver = Solver::new();
+        assert!(solver.solve());
+        solver.add_clause(&[]);
+        assert!(!solver.solve());
+    }
+
+    #[test]
+    fn test_walksat_warm_start() {
+        let mut solver = Solver::from_clauses(&random_ksat(100, 300, 3, 7));
+        solver.state.walksat = Some(WalkSatConfig::default());
+        assert!(solver.solve());
+        assert_eq!(solver.stats().conflicts, 0);
+        assert!(solver.stats().flips > 0);
+
+        // Without a model the search goes on from the best assignment
+        let mut solver = Solver::from_clauses(&pigeonhole(3));
+        solver.state.walksat = Some(WalkSatConfig {
+            max_flips: 100,
+            ..Default::default()
+        });
+        assert!(!solver.solve());
+        assert_eq!(solver.stats().flips, 100);
+        assert!(solver.state.phases.enabled);
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut solver = Solver::new();
+        solver.add_clause(&[1, 2]);
+        solver.add_clause(&[-1, -2, 3]);
+        // 4 only occurs in a tautology and is free in every model
+        solver.add_clause(&[4, -4]);
+        let mut models: Vec<Vec<(i32, bool)>> = solver
+            .solutions()
+            .map(|model| {
+                let mut model: Vec<(i32, bool)> = model.into_iter().collect();
+                model.sort_unstable();
+                model
+            })
+            .collect();
+        // Five models over 1 to 3, each with both values of 4
+        assert_eq!(models.len(), 10);
+        models.sort();
+        models.dedup();
+        assert_eq!(models.len(), 10);
+        assert!(models.iter().all(|model| model.len() == 4));
+
+        assert_eq!(Solver::new().solutions().count(), 1);
+        let mut solver = Solver::new();
+        solver.add_clause(&[1]);
+        solver.add_clause(&[-1]);
+        assert_eq!(solver.solutions().count(), 0);
+    }
+
+    #[test]
+    fn test_cardinality_constraints() {
+        // Against every assignment of 1 to 5, assumed in turn, with the
+        // number of true literals in bounds, for every prefix of the literals
+        let lits = [1, -2, 3, 4, -5];
+        let assignments: Vec<Vec<Lit>> = (0..1 << 5)
+            .map(|bits: u32| (1..=5).map(|var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect())
+            .collect();
+        for n in 0..=lits.len() {
+            let lits = &lits[..n];
+            for k in 0..=n + 1 {
+                let check = |add: &dyn Fn(&mut Solver), holds: &dyn Fn(usize) -> bool| {
+                    let mut solver = Solver::new();
+                    while solver.num_vars() < 5 {
+                        solver.new_var();
+                    }
+                    add(&mut solver);
+                    for assignment in assignments.iter() {
+                        let true_lits = lits.iter().filter(|lit| assignment.contains(lit)).count();
+                        let sat = solver.solve_with_assumptions(assignment);
+                        assert_eq!(sat, holds(true_lits), "{:?} {} {:?}", lits, k, assignment);
+                    }
+                };
+                check(&|solver| solver.add_at_most_k(lits, k), &|true_lits| true_lits <= k);
+                check(&|solver| solver.add_at_least_k(lits, k), &|true_lits| true_lits >= k);
+                check(&|solver| solver.add_exactly_k(lits, k), &|true_lits| true_lits == k);
+            }
+        }
+
+        // The counter's variables come after those the solver has, and
+        // new_var keeps counting past them
+        let mut solver = Solver::new();
+        let x = solver.new_var();
+        solver.add_clause(&[x, 3]);
+        solver.add_at_most_k(&[1, 2, 3, 4], 2);
+        assert!(solver.num_vars() > 4);
+        let fresh = solver.new_var();
+        assert!(solver.formula().iter().flatten().all(|lit| lit.abs() < fresh));
+        solver.add_clause(&[fresh]);
+        solver.add_exactly_k(&[1, 2, 4, fresh], 3);
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert!(model[&fresh] && [1, 2, 3, 4].iter().filter(|var| model[var]).count() <= 2);
+    }
+
+    #[test]
+    fn test_enumerate_projected() {
+        // 3 = 1 and 2 through its Tseitin clauses, 4 occurs only in 1 4, 5
+        // in no clause at all
+        let formula = vec![vec![-3, 1], vec![-3, 2], vec![3, -1, -2], vec![1, 4]];
+        let mut projections: Vec<Vec<(i32, bool)>> = Solver::from_clauses(&formula)
+            .enumerate_projected(&[1, -2])
+            .map(|model| {
+                let mut model: Vec<(i32, bool)> = model.into_iter().collect();
+                model.sort_unstable();
+                model
+            })
+            .collect();
+        projections.sort();
+        assert_eq!(
+            projections,
+            vec![
+                vec![(1, false), (2, false)],
+                vec![(1, false), (2, true)],
+                vec![(1, true), (2, false)],
+                vec![(1, true), (2, true)],
+            ]
+        );
+        assert_eq!(Solver::from_clauses(&formula).enumerate_projected(&[3, 5]).count(), 4);
+        assert_eq!(Solver::from_clauses(&formula).enumerate_projected(&[]).count(), 1);
+        let mut solver = Solver::from_clauses(&formula);
+        solver.add_clause(&[-1]);
+        solver.add_clause(&[-4]);
+        assert_eq!(solver.enumerate_projected(&[2]).count(), 0);
+    }
+
+    #[test]
+    fn test_implicants() {
+        // 1 and one of 2 and 3, whatever 4 and twelve more variables are
+        let formula = vec![vec![1], vec![2, 3, -4], vec![2, 3, 4]];
+        let mut solver = Solver::from_clauses(&formula);
+        while solver.num_vars() < 16 {
+            solver.new_var();
+        }
+        let cubes: Vec<HashMap<i32, bool>> = solver.implicants().collect();
+        assert_eq!(cubes.len(), 2);
+        // The cubes are disjoint and hold the 3 * 2^13 models between them
+        let size = |cube: &HashMap<i32, bool>| 1usize << (16 - cube.len());
+        assert_eq!(cubes.iter().map(size).sum::<usize>(), 3 << 13);
+        assert!(cubes[0].iter().any(|(var, &val)| cubes[1].get(var) == Some(&!val)));
+        for cube in cubes.iter() {
+            let model: HashMap<i32, bool> =
+                (1..=16).map(|var| (var, cube.get(&var).copied().unwrap_or(false))).collect();
+            assert_eq!(verify_model(&formula, &model), Ok(()));
+        }
+
+        assert_eq!(Solver::new().implicants().collect::<Vec<_>>(), vec![HashMap::new()]);
+        let mut solver = Solver::from_clauses(&[vec![1, 2], vec![-1, -2]]);
+        solver.add_xor(&[1, 2, 3], true);
+        assert_eq!(solver.implicants().count(), 2);
+    }
+
+    #[test]
+    fn test_freeze() {
+        // 1 and 3 are pure
+        let mut solver = Solver::from_clauses(&[vec![1, 2], vec![-2, 3]]);
+        solver.freeze(1);
+        solver.freeze(-1);
+        let root = solver.root(solver.initial_assignment());
+        let assignment = root.assignment.as_ref().unwrap();
+        assert_eq!((assignment.get(&1), assignment.get(&3)), (Some(&None), Some(&Some(true))));
+        solver.melt(1);
+        assert!(solver.is_frozen(1));
+        solver.melt(1);
+        assert!(!solver.is_frozen(1));
+        let root = solver.root(solver.initial_assignment());
+        assert_eq!(root.assignment.as_ref().unwrap().get(&1), Some(&Some(true)));
+    }
+
+    #[test]
+    fn test_clause_groups() {
+        // 1 and 2 in one group, not 3 in another, and 1 and 2 imply 3
+        let mut solver = Solver::from_clauses(&[vec![-1, -2, 3]]);
+        solver.state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+        let first = solver.new_group();
+        solver.add_group_clause(first, &[1]);
+        solver.add_group_clause(first, &[2]);
+        let second = solver.new_group();
+        solver.add_group_clause(second, &[-3]);
+        assert!(!solver.solve());
+        assert!(solver.failed(first) && solver.failed(second));
+        assert!(!solver.solve_with_assumptions(&[4]));
+
+        solver.set_group_enabled(second, false);
+        assert!(solver.solve());
+        assert!(solver.model().unwrap()[&3]);
+        assert!(!solver.solve_with_assumptions(&[-3]));
+        assert!(solver.failed(first) && !solver.failed(-second));
+        solver.set_group_enabled(second, true);
+        assert!(!solver.solve());
+
+        solver.release_group(first);
+        assert_eq!(solver.formula().len(), 2);
+        assert!(solver.state.learned.as_ref().unwrap().iter().all(|clause| !clause.literals.contains(&-first)));
+        assert!(solver.solve_with_assumptions(&[1]));
+        assert!(!solver.model().unwrap()[&2]);
+    }
+
+    #[test]
+    fn test_solve_optimal() {
+        // One of 1 and 2 and at least one of 3 and 4, violating 2 and -3 is
+        // cheapest
+        let mut solver = Solver::from_clauses(&[vec![1, 2], vec![-1, -2]]);
+        solver.add_pb(&[(1, 3), (1, 4)], Relation::AtLeast, 1);
+        for (clause, weight) in [(vec![1], 3), (vec![2], 2), (vec![-3], 1), (vec![-4], 4), (vec![-1, 3, 4], 5)] {
+            solver.add_soft_clause(&clause, weight);
+        }
+        let result = solver.solve_optimal().unwrap();
+        assert_eq!(result.cost, 3);
+        let expected: Model = [(1, true), (2, false), (3, true), (4, false)].into_iter().collect();
+        assert_eq!(result.model, expected);
+        // The soft clauses leave the other searches alone
+        assert!(solver.solve_with_assumptions(&[2, 4]));
+        assert_eq!(solver.solve_optimal().map(|result| result.cost), Some(3));
+
+        solver.add_clause(&[-3]);
+        solver.add_clause(&[-4]);
+        assert_eq!(solver.solve_optimal(), None);
+    }
+
+    #[test]
+    fn test_xor_constraints() {
+        use rand::rngs::StdRng;
+        use rand::Rng;
+
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..500 {
+            let num_vars = rng.gen_range(2..=8);
+            let mut solver = Solver::new();
+            if rng.gen_bool(0.5) {
+                solver.state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+            }
+            let mut clauses = Vec::new();
+            for _ in 0..rng.gen_range(0..=6) {
+                let clause: Vec<i32> = (0..3)
+                    .map(|_| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 })
+                    .collect();
+                solver.add_clause(&clause);
+                clauses.push(clause);
+            }
+            let mut xors = Vec::new();
+            for _ in 0..rng.gen_range(1..=4) {
+                let vars: Vec<i32> = (1..=num_vars).filter(|_| rng.gen_bool(0.5)).collect();
+                let xor = XorConstraint {
+                    vars,
+                    parity: rng.gen_bool(0.5),
+                };
+                solver.add_xor(&xor.vars, xor.parity);
+                xors.push(xor);
+            }
+            let satisfies = |model: &Model| {
+                clauses.iter().all(|clause| clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&(lit > 0))))
+                    && xors.iter().all(|xor| xor.is_satisfied(model))
+            };
+            let expected = (0..1u32 << num_vars)
+                .any(|bits| satisfies(&(1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect()));
+            assert_eq!(solver.solve(), expected, "{:?} {:?}", clauses, xors);
+            if let Some(model) = solver.model() {
+                assert!(satisfies(model), "{:?} {:?} {:?}", clauses, xors, model);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pb_constraints() {
+        use rand::rngs::StdRng;
+        use rand::Rng;
+
+        let mut solver = Solver::new();
+        solver.add_pb(&[(3, 1), (2, 2), (1, 3)], Relation::AtLeast, 4);
+        solver.add_clause(&[-2]);
+        assert!(solver.solve());
+        let model = solver.model().unwrap();
+        assert!(model[&1] && model[&3]);
+
+        let mut rng = StdRng::seed_from_u64(13);
+        for _ in 0..500 {
+            let num_vars = rng.gen_range(2..=7);
+            let mut solver = Solver::new();
+            if rng.gen_bool(0.5) {
+                solver.state.learned = Some(LearnedClauses::new(ReductionConfig::default()));
+            }
+            let mut clauses = Vec::new();
+            for _ in 0..rng.gen_range(0..=4) {
+                let clause: Vec<i32> = (0..3)
+                    .map(|_| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 })
+                    .collect();
+                solver.add_clause(&clause);
+                clauses.push(clause);
+            }
+            let mut pbs = Vec::new();
+            for _ in 0..rng.gen_range(1..=3) {
+                let terms: Vec<(i64, i32)> = (0..rng.gen_range(1..=4))
+                    .map(|_| {
+                        let lit = rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 };
+                        (rng.gen_range(-3..=4), lit)
+                    })
+                    .collect();
+                let relation = [Relation::AtLeast, Relation::Equal, Relation::AtMost][rng.gen_range(0..3)];
+                let pb = PbConstraint {
+                    terms,
+                    relation,
+                    rhs: rng.gen_range(-2..=5),
+                };
+                solver.add_pb(&pb.terms, pb.relation, pb.rhs);
+                pbs.push(pb);
+            }
+            let satisfies = |model: &Model| {
+                let value = |lit: i32| model.get(&lit.abs()) == Some(&(lit > 0));
+                clauses.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+                    && pbs.iter().all(|pb| {
+                        let sum: i64 = pb.terms.iter().filter(|(_, lit)| value(*lit)).map(|(c, _)| c).sum();
+                        match pb.relation {
+                            Relation::AtLeast => sum >= pb.rhs,
+                            Relation::Equal => sum == pb.rhs,
+                            Relation::AtMost => sum <= pb.rhs,
+                        }
+                    })
+            };
+            let expected = (0..1u32 << num_vars)
+                .any(|bits| satisfies(&(1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect()));
+            assert_eq!(solver.solve(), expected, "{:?} {:?}", clauses, pbs);
+            if let Some(model) = solver.model() {
+                assert!(satisfies(model), "{:?} {:?} {:?}", clauses, pbs, model);
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use crate::Formula;
+
+// Limits of the automorphism search
+#[derive(Clone, Copy, Debug)]
+pub struct SymmetryConfig {
+    // Edge visits of partition refinement before the search gives up on
+    // finding more symmetries
+    pub max_effort: usize,
+    // Variables of a symmetry its breaking clauses compare at most
+    pub max_length: usize,
+}
+
+impl Default for SymmetryConfig {
+    fn default() -> Self {
+        SymmetryConfig {
+            max_effort: 50_000_000,
+            max_length: 50,
+        }
+    }
+}
+
+// A permutation of the literals that maps the set of clauses onto itself,
+// and negated literals onto negated images. Each moved variable comes with
+// the literal it goes to, by variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symmetry {
+    pub moved: Vec<(i32, i32)>,
+}
+
+impl Symmetry {
+    pub fn apply(&self, lit: i32) -> i32 {
+        match self.moved.binary_search_by_key(&lit.abs(), |&(var, _)| var) {
+            Ok(i) if lit > 0 => self.moved[i].1,
+            Ok(i) => -self.moved[i].1,
+            Err(_) => lit,
+        }
+    }
+}
+
+// The clause-literal incidence graph: two vertices for each variable that
+// occurs, joined by an edge, and one for each clause, joined to its
+// literals. The clause vertices start out in another colour, so automorphisms
+// of the graph are exactly the symmetries of the formula.
+struct Graph {
+    // The variables that occur, vertices 2i and 2i + 1 are the literals vars[i]
+    // and -vars[i]
+    vars: Vec<i32>,
+    // Neighbours of every vertex, sorted
+    edges: Vec<Vec<usize>>,
+}
+
+impl Graph {
+    fn new(formula: &[Vec<i32>]) -> Self {
+        let mut vars: Vec<i32> = formula.iter().flatten().map(|lit| lit.abs()).collect();
+        vars.sort_unstable();
+        vars.dedup();
+        let index: HashMap<i32, usize> = vars.iter().enumerate().map(|(i, &var)| (var, i)).collect();
+        let vertex = |lit: i32| 2 * index[&lit.abs()] + (lit < 0) as usize;
+        let mut edges: Vec<Vec<usize>> = (0..2 * vars.len()).map(|v| vec![v ^ 1]).collect();
+        for clause in formula.iter() {
+            let c = edges.len();
+            let mut lits: Vec<usize> = clause.iter().map(|&lit| vertex(lit)).collect();
+            lits.sort_unstable();
+            lits.dedup();
+            for &v in lits.iter() {
+                edges[v].push(c);
+            }
+            edges.push(lits);
+        }
+        for list in edges.iter_mut() {
+            list.sort_unstable();
+        }
+        Graph { vars, edges }
+    }
+
+    fn literal(&self, v: usize) -> i32 {
+        let var = self.vars[v / 2];
+        [var, -var][v % 2]
+    }
+
+    // The literals vertices start in colour 0, the clauses in colour 1
+    fn initial_colours(&self) -> Vec<usize> {
+        (0..self.edges.len()).map(|v| (v >= 2 * self.vars.len()) as usize).collect()
+    }
+
+    // Split the colours until every two vertices of a colour have as many
+    // neighbours of each colour, the coarsest such refinement. Neighbour
+    // colours are summed as hashes, which only depend on the colours and not
+    // on how the vertices are numbered. The colours are numbered densely.
+    fn refine(&self, colours: &mut [usize], effort: &mut usize) {
+        let mut count = colours.iter().max().map_or(0, |&colour| colour + 1);
+        loop {
+            let keys: Vec<(usize, u64)> = (0..colours.len())
+                .map(|v| {
+                    let sum = self.edges[v].iter().fold(0u64, |sum, &w| sum.wrapping_add(mix(colours[w])));
+                    (colours[v], sum)
+                })
+                .collect();
+            *effort += self.edges.len() + self.edges.iter().map(Vec::len).sum::<usize>();
+            let refined = rank(&keys, colours);
+            if refined == count {
+                return;
+            }
+            count = refined;
+        }
+    }
+
+    // Give v a colour of its own, just below the rest of its colour, and
+    // refine
+    fn individualize(&self, colours: &[usize], v: usize, effort: &mut usize) -> Vec<usize> {
+        let keys: Vec<usize> = (0..colours.len()).map(|w| 2 * colours[w] + (w != v) as usize).collect();
+        let mut colours = vec![0; keys.len()];
+        rank(&keys, &mut colours);
+        self.refine(&mut colours, effort);
+        colours
+    }
+
+    fn is_automorphism(&self, permutation: &[usize]) -> bool {
+        (0..self.edges.len()).all(|v| {
+            let image = &self.edges[permutation[v]];
+            self.edges[v].iter().all(|&w| image.binary_search(&permutation[w]).is_ok())
+        })
+    }
+}
+
+fn mix(colour: usize) -> u64 {
+    let mut x = (colour as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+// Number the keys densely in increasing order, equal keys alike. Returns the
+// number of distinct keys.
+fn rank<K: Ord + Copy>(keys: &[K], colours: &mut [usize]) -> usize {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_unstable_by_key(|&v| keys[v]);
+    let mut count = 0;
+    for (i, &v) in order.iter().enumerate() {
+        if i == 0 || keys[v] != keys[order[i - 1]] {
+            count += 1;
+        }
+        colours[v] = count - 1;
+    }
+    count
+}
+
+// Vertices of each colour
+fn sizes(colours: &[usize]) -> Vec<usize> {
+    let mut sizes = vec![0; colours.len()];
+    for &colour in colours.iter() {
+        sizes[colour] += 1;
+    }
+    sizes
+}
+
+// A node of the first path of the search tree: the refined colours, the
+// colour split next and the vertex of it the first path individualizes
+struct Level {
+    colours: Vec<usize>,
+    sizes: Vec<usize>,
+    target: usize,
+    chosen: usize,
+}
+
+// Individualization and refinement in the style of nauty. The first path
+// individualizes the first vertex of the first colour with several until
+// every vertex has a colour of its own. Going up from its deepest node, every
+// other vertex of the split colour not yet known to be in the orbit of the
+// chosen one is individualized instead, and the subtree below searched for a
+// leaf that maps onto the first leaf by an automorphism.
+struct Search<'a> {
+    graph: &'a Graph,
+    levels: Vec<Level>,
+    leaf: Vec<usize>,
+    effort: usize,
+    max_effort: usize,
+}
+
+impl Search<'_> {
+    // The permutation that maps the first leaf onto a leaf of these colours
+    fn to_leaf(&self, colours: &[usize]) -> Vec<usize> {
+        let mut vertex = vec![0; colours.len()];
+        for (v, &colour) in colours.iter().enumerate() {
+            vertex[colour] = v;
+        }
+        self.leaf.iter().map(|&colour| vertex[colour]).collect()
+    }
+
+    fn descend(&mut self, depth: usize, colours: Vec<usize>) -> Option<Vec<usize>> {
+        let Some(level) = self.levels.get(depth) else {
+            if sizes(&colours).contains(&0) {
+                return None;
+            }
+            let permutation = self.to_leaf(&colours);
+            return Some(permutation).filter(|permutation| self.graph.is_automorphism(permutation));
+        };
+        if sizes(&colours) != level.sizes {
+            return None;
+        }
+        let target = level.target;
+        for w in (0..colours.len()).filter(|&w| colours[w] == target) {
+            if self.effort >= self.max_effort {
+                return None;
+            }
+            let child = self.graph.individualize(&colours, w, &mut self.effort);
+            if let Some(permutation) = self.descend(depth + 1, child) {
+                return Some(permutation);
+            }
+        }
+        None
+    }
+}
+
+fn find(parent: &mut [usize], v: usize) -> usize {
+    let mut root = v;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    let mut v = v;
+    while parent[v] != root {
+        let next = parent[v];
+        parent[v] = root;
+        v = next;
+    }
+    root
+}
+
+// Generators of symmetries of the formula, not necessarily of all of them
+// when the effort runs out
+pub fn find_symmetries(formula: &[Vec<i32>], config: &SymmetryConfig) -> Vec<Symmetry> {
+    let graph = Graph::new(formula);
+    let mut effort = 0;
+    let mut colours = graph.initial_colours();
+    graph.refine(&mut colours, &mut effort);
+    let mut levels = Vec::new();
+    loop {
+        let counts = sizes(&colours);
+        let Some(target) = counts.iter().position(|&size| size > 1) else {
+            break;
+        };
+        let chosen = colours.iter().position(|&colour| colour == target).unwrap();
+        let child = graph.individualize(&colours, chosen, &mut effort);
+        levels.push(Level {
+            colours: std::mem::replace(&mut colours, child),
+            sizes: counts,
+            target,
+            chosen,
+        });
+    }
+    let mut search = Search {
+        graph: &graph,
+        levels,
+        leaf: colours,
+        effort,
+        max_effort: config.max_effort,
+    };
+
+    // Orbits of the symmetries found so far. Each fixes the vertices the
+    // first path individualizes above the level it was found at, so at every
+    // level they are orbits of symmetries that agree with the first path.
+    let mut orbits: Vec<usize> = (0..graph.edges.len()).collect();
+    let mut generators = Vec::new();
+    for depth in (0..search.levels.len()).rev() {
+        let level = &search.levels[depth];
+        let (colours, target, chosen) = (level.colours.clone(), level.target, level.chosen);
+        for w in (0..colours.len()).filter(|&w| colours[w] == target) {
+            if search.effort >= search.max_effort {
+                break;
+            }
+            if find(&mut orbits, w) == find(&mut orbits, chosen) {
+                continue;
+            }
+            let child = graph.individualize(&colours, w, &mut search.effort);
+            let Some(permutation) = search.descend(depth + 1, child) else {
+                continue;
+            };
+            for (v, &image) in permutation.iter().enumerate() {
+                let (a, b) = (find(&mut orbits, v), find(&mut orbits, image));
+                orbits[a] = b;
+            }
+            generators.push(permutation);
+        }
+    }
+
+    generators
+        .iter()
+        .map(|permutation| Symmetry {
+            moved: (0..graph.vars.len())
+                .map(|i| (graph.vars[i], graph.literal(permutation[2 * i])))
+                .filter(|&(var, image)| var != image)
+                .collect(),
+        })
+        .collect()
+}
+
+// Lex-leader clauses for a symmetry: of every model and its image, only the
+// lexicographically smaller one by the moved variables in increasing order,
+// false before true, is kept, comparing at most max_length of them. Fresh
+// variable e_i is forced true while the first i variables equal their images:
+// e_{i-1} and x_i imply the image y_i, and with x_i = y_i they imply e_i.
+fn lex_leader(symmetry: &Symmetry, max_length: usize, next_var: &mut i32) -> Formula {
+    let mut clauses = Vec::new();
+    let mut equal: Option<i32> = None;
+    let moved = &symmetry.moved[..symmetry.moved.len().min(max_length)];
+    for (i, &(x, y)) in moved.iter().enumerate() {
+        let prefix: Vec<i32> = equal.map(|e| -e).into_iter().collect();
+        if y == -x {
+            // x_i has to be false, and then the two differ
+            clauses.push([prefix.as_slice(), &[-x]].concat());
+            break;
+        }
+        clauses.push([prefix.as_slice(), &[-x, y]].concat());
+        if i + 1 < moved.len() {
+            *next_var += 1;
+            let e = *next_var;
+            clauses.push([prefix.as_slice(), &[-x, e]].concat());
+            clauses.push([prefix.as_slice(), &[y, e]].concat());
+            equal = Some(e);
+        }
+    }
+    clauses
+}
+
+// Symmetry breaking: find symmetries of the formula and add their lex-leader
+// clauses, with fresh variables taken from next_var. Every orbit of models
+// under the symmetries keeps a model, so satisfiability is preserved and a
+// model of the result is one of the formula, but the clauses are not
+// implied and have no place in a proof. Returns the number of symmetries.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(clauses = formula.len())))]
+pub fn break_symmetries(formula: &mut Formula, config: &SymmetryConfig, next_var: &mut i32) -> usize {
+    let symmetries = find_symmetries(formula, config);
+    for symmetry in symmetries.iter() {
+        let clauses = lex_leader(symmetry, config.max_length, next_var);
+        formula.extend(clauses);
+    }
+    symmetries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bruteforce_solve, pigeonhole, SolveResult, Solver};
+
+    fn is_symmetry(formula: &[Vec<i32>], symmetry: &Symmetry) -> bool {
+        let normalize = |clause: &[i32]| {
+            let mut clause = clause.to_vec();
+            clause.sort_unstable();
+            clause.dedup();
+            clause
+        };
+        let clauses: Vec<Vec<i32>> = formula.iter().map(|clause| normalize(clause)).collect();
+        formula.iter().all(|clause| {
+            let image: Vec<i32> = clause.iter().map(|&lit| symmetry.apply(lit)).collect();
+            clauses.contains(&normalize(&image))
+        })
+    }
+
+    #[test]
+    fn test_find_symmetries() {
+        // Swapping 1 and 2 and mapping 3 to -3 are symmetries, 4 is fixed
+        let formula = vec![vec![1, 2, 3], vec![1, 2, -3], vec![-1, 4], vec![-2, 4]];
+        let symmetries = find_symmetries(&formula, &SymmetryConfig::default());
+        assert!(!symmetries.is_empty());
+        assert!(symmetries.iter().all(|symmetry| is_symmetry(&formula, symmetry)));
+        let moves = |var: i32| symmetries.iter().any(|symmetry| symmetry.apply(var) != var);
+        assert!(moves(1) && moves(2) && moves(3) && !moves(4));
+
+        let formula = pigeonhole(4);
+        let symmetries = find_symmetries(&formula, &SymmetryConfig::default());
+        assert!(symmetries.len() >= 2);
+        assert!(symmetries.iter().all(|symmetry| is_symmetry(&formula, symmetry)));
+
+        // A chain has no symmetry other than reversing it, which the unit
+        // clause rules out
+        let formula = vec![vec![-1, 2], vec![-2, 3], vec![-3, 4], vec![1]];
+        assert!(find_symmetries(&formula, &SymmetryConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_break_symmetries() {
+        for formula in [
+            vec![vec![1, 2, 3], vec![1, 2, -3], vec![-1, -2]],
+            vec![vec![1, 2], vec![-1, -2], vec![3, 4], vec![-3, -4], vec![1, 3]],
+        ] {
+            let mut broken = formula.clone();
+            let mut next_var = 4;
+            assert!(break_symmetries(&mut broken, &SymmetryConfig::default(), &mut next_var) > 0);
+            assert!(broken.len() > formula.len());
+            let model = bruteforce_solve(&broken).expect("Breaking symmetries lost every model");
+            assert!(formula.iter().all(|clause| clause.iter().any(|&lit| model.get(&lit.abs()) == Some(&(lit > 0)))));
+        }
+
+        let mut formula = pigeonhole(6);
+        let mut next_var = 42;
+        assert!(break_symmetries(&mut formula, &SymmetryConfig::default(), &mut next_var) > 0);
+        assert_eq!(Solver::from_clauses(&formula).solve_limited(), SolveResult::Unsat);
+    }
+}
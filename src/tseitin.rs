@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use crate::Formula;
+
+// A propositional formula over named variables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoolExpr {
+    Var(String),
+    Not(Box<BoolExpr>),
+    // An empty And is true, an empty Or is false
+    And(Vec<BoolExpr>),
+    Or(Vec<BoolExpr>),
+    Xor(Box<BoolExpr>, Box<BoolExpr>),
+    Implies(Box<BoolExpr>, Box<BoolExpr>),
+    Iff(Box<BoolExpr>, Box<BoolExpr>),
+}
+
+impl BoolExpr {
+    pub fn var(name: &str) -> Self {
+        BoolExpr::Var(name.to_string())
+    }
+
+    // Truth value under an assignment of every variable in the expression
+    pub fn eval(&self, values: &HashMap<String, bool>) -> bool {
+        match self {
+            BoolExpr::Var(name) => values[name],
+            BoolExpr::Not(a) => !a.eval(values),
+            BoolExpr::And(args) => args.iter().all(|arg| arg.eval(values)),
+            BoolExpr::Or(args) => args.iter().any(|arg| arg.eval(values)),
+            BoolExpr::Xor(a, b) => a.eval(values) != b.eval(values),
+            BoolExpr::Implies(a, b) => !a.eval(values) || b.eval(values),
+            BoolExpr::Iff(a, b) => a.eval(values) == b.eval(values),
+        }
+    }
+
+    fn collect_vars(&self, vars: &mut HashMap<String, i32>) {
+        match self {
+            BoolExpr::Var(name) => {
+                let next = vars.len() as i32 + 1;
+                vars.entry(name.clone()).or_insert(next);
+            }
+            BoolExpr::Not(a) => a.collect_vars(vars),
+            BoolExpr::And(args) | BoolExpr::Or(args) => args.iter().for_each(|arg| arg.collect_vars(vars)),
+            BoolExpr::Xor(a, b) | BoolExpr::Implies(a, b) | BoolExpr::Iff(a, b) => {
+                a.collect_vars(vars);
+                b.collect_vars(vars);
+            }
+        }
+    }
+}
+
+struct Encoder<'a> {
+    vars: &'a HashMap<String, i32>,
+    formula: Formula,
+    next_var: i32,
+}
+
+impl Encoder<'_> {
+    fn gate(&mut self) -> i32 {
+        self.next_var += 1;
+        self.next_var
+    }
+
+    // Literal equivalent to the expression, defined by the clauses added
+    fn encode(&mut self, expr: &BoolExpr) -> i32 {
+        match expr {
+            BoolExpr::Var(name) => self.vars[name],
+            BoolExpr::Not(a) => -self.encode(a),
+            BoolExpr::And(args) => {
+                let lits: Vec<i32> = args.iter().map(|arg| self.encode(arg)).collect();
+                let g = self.gate();
+                let mut long = vec![g];
+                for &lit in lits.iter() {
+                    self.formula.push(vec![-g, lit]);
+                    long.push(-lit);
+                }
+                self.formula.push(long);
+                g
+            }
+            BoolExpr::Or(args) => {
+                let lits: Vec<i32> = args.iter().map(|arg| self.encode(arg)).collect();
+                let g = self.gate();
+                let mut long = vec![-g];
+                for &lit in lits.iter() {
+                    self.formula.push(vec![g, -lit]);
+                    long.push(lit);
+                }
+                self.formula.push(long);
+                g
+            }
+            BoolExpr::Xor(a, b) => {
+                let (a, b) = (self.encode(a), self.encode(b));
+                let g = self.gate();
+                self.formula.push(vec![-g, a, b]);
+                self.formula.push(vec![-g, -a, -b]);
+                self.formula.push(vec![g, -a, b]);
+                self.formula.push(vec![g, a, -b]);
+                g
+            }
+            BoolExpr::Implies(a, b) => {
+                let (a, b) = (self.encode(a), self.encode(b));
+                let g = self.gate();
+                self.formula.push(vec![-g, -a, b]);
+                self.formula.push(vec![g, a]);
+                self.formula.push(vec![g, -b]);
+                g
+            }
+            BoolExpr::Iff(a, b) => {
+                let (a, b) = (self.encode(a), self.encode(b));
+                let g = self.gate();
+                self.formula.push(vec![-g, -a, b]);
+                self.formula.push(vec![-g, a, -b]);
+                self.formula.push(vec![g, a, b]);
+                self.formula.push(vec![g, -a, -b]);
+                g
+            }
+        }
+    }
+}
+
+// Tseitin transformation: an equisatisfiable CNF formula with one fresh
+// variable per gate, and the DIMACS variable of every named variable. Named
+// variables are numbered first, in order of appearance, so a model of the
+// formula restricted to them is a model of the expression.
+pub fn tseitin(expr: &BoolExpr) -> (Formula, HashMap<String, i32>) {
+    let mut vars = HashMap::new();
+    expr.collect_vars(&mut vars);
+    let mut encoder = Encoder {
+        vars: &vars,
+        formula: Vec::new(),
+        next_var: vars.len() as i32,
+    };
+    let root = encoder.encode(expr);
+    let mut formula = encoder.formula;
+    formula.push(vec![root]);
+    (formula, vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether the formula has a model agreeing with the fixed literals
+    fn satisfiable_with(formula: &Formula, fixed: &[i32]) -> bool {
+        let num_vars = formula.iter().flatten().map(|lit| lit.abs()).max().unwrap_or(0);
+        (0..1u32 << num_vars).any(|bits| {
+            let value = |lit: i32| ((bits >> (lit.abs() - 1)) & 1 == 1) == (lit > 0);
+            fixed.iter().all(|&lit| value(lit)) && formula.iter().all(|clause| clause.iter().any(|&lit| value(lit)))
+        })
+    }
+
+    #[test]
+    fn test_tseitin_matches_expression() {
+        let (x, y, z) = (BoolExpr::var("x"), BoolExpr::var("y"), BoolExpr::var("z"));
+        let expr = BoolExpr::And(vec![
+            BoolExpr::Implies(Box::new(x.clone()), Box::new(BoolExpr::Or(vec![y.clone(), z.clone()]))),
+            BoolExpr::Xor(Box::new(y.clone()), Box::new(BoolExpr::Not(Box::new(z.clone())))),
+            BoolExpr::Iff(Box::new(x.clone()), Box::new(BoolExpr::Or(vec![]))),
+        ]);
+        let (formula, vars) = tseitin(&expr);
+        assert_eq!(vars["x"], 1);
+        for bits in 0..8 {
+            let values: HashMap<String, bool> =
+                ["x", "y", "z"].iter().enumerate().map(|(i, name)| (name.to_string(), bits >> i & 1 == 1)).collect();
+            let fixed: Vec<i32> =
+                values.iter().map(|(name, &val)| if val { vars[name] } else { -vars[name] }).collect();
+            assert_eq!(satisfiable_with(&formula, &fixed), expr.eval(&values), "{:?}", values);
+        }
+    }
+
+    #[test]
+    fn test_tseitin_constants() {
+        let x = BoolExpr::var("x");
+        let (formula, _) = tseitin(&BoolExpr::And(vec![x.clone(), BoolExpr::Not(Box::new(x))]));
+        assert!(!satisfiable_with(&formula, &[]));
+        let (formula, vars) = tseitin(&BoolExpr::And(vec![]));
+        assert!(vars.is_empty());
+        assert!(satisfiable_with(&formula, &[]));
+        let (formula, _) = tseitin(&BoolExpr::Or(vec![]));
+        assert!(!satisfiable_with(&formula, &[]));
+    }
+}
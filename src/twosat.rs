@@ -0,0 +1,135 @@
+use crate::{Formula, Model};
+
+// Whether every clause has at most two literals
+pub fn is_2sat(formula: &[Vec<i32>]) -> bool {
+    formula.iter().all(|clause| clause.len() <= 2)
+}
+
+// Node of a literal in the implication graph, the two literals of a variable
+// are neighbours
+pub(crate) fn node(lit: i32) -> usize {
+    2 * (lit.unsigned_abs() as usize - 1) + (lit < 0) as usize
+}
+
+// Strongly connected components of the graph by Tarjan's algorithm, without
+// recursion so long implication chains cannot overflow the stack. Components
+// are numbered in the order they are completed, which is a reverse
+// topological order.
+pub(crate) fn components(edges: &[Vec<usize>]) -> Vec<usize> {
+    let n = edges.len();
+    let mut index = vec![usize::MAX; n];
+    let mut lowlink = vec![0; n];
+    let mut component = vec![usize::MAX; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut next_component = 0;
+    for start in 0..n {
+        if index[start] != usize::MAX {
+            continue;
+        }
+        // Nodes being visited, each with the position of its next edge
+        let mut path = vec![(start, 0)];
+        index[start] = next_index;
+        lowlink[start] = next_index;
+        next_index += 1;
+        stack.push(start);
+        while let Some(&mut (current, ref mut edge)) = path.last_mut() {
+            if let Some(&next) = edges[current].get(*edge) {
+                *edge += 1;
+                if index[next] == usize::MAX {
+                    index[next] = next_index;
+                    lowlink[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    path.push((next, 0));
+                } else if component[next] == usize::MAX {
+                    lowlink[current] = lowlink[current].min(index[next]);
+                }
+                continue;
+            }
+            path.pop();
+            if let Some(&(parent, _)) = path.last() {
+                lowlink[parent] = lowlink[parent].min(lowlink[current]);
+            }
+            if lowlink[current] == index[current] {
+                while let Some(member) = stack.pop() {
+                    component[member] = next_component;
+                    if member == current {
+                        break;
+                    }
+                }
+                next_component += 1;
+            }
+        }
+    }
+    component
+}
+
+// Solve a formula of clauses with at most two literals in linear time. Every
+// clause a or b gives the implications -a to b and -b to a; the formula is
+// unsatisfiable exactly when some variable shares a strongly connected
+// component with its negation. Otherwise a literal is true if its component
+// comes after the component of its negation in topological order. The model
+// covers the variables of the formula.
+pub fn solve_2sat(formula: &Formula) -> Option<Model> {
+    assert!(is_2sat(formula), "Clauses of more than two literals");
+    let num_vars = formula.iter().flatten().map(|lit| lit.unsigned_abs() as usize).max().unwrap_or(0);
+    let mut edges = vec![Vec::new(); 2 * num_vars];
+    for clause in formula.iter() {
+        match clause[..] {
+            [] => return None,
+            [a] => edges[node(-a)].push(node(a)),
+            [a, b] => {
+                edges[node(-a)].push(node(b));
+                edges[node(-b)].push(node(a));
+            }
+            _ => unreachable!(),
+        }
+    }
+    let component = components(&edges);
+    let mut model = Model::new();
+    for var in formula.iter().flatten().map(|lit| lit.abs()) {
+        let (positive, negative) = (component[node(var)], component[node(-var)]);
+        if positive == negative {
+            return None;
+        }
+        model.insert(var, positive < negative);
+    }
+    Some(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify_model;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_solve_2sat() {
+        // x1 and x2 differ, x2 and x3 differ and x1 and x3 differ
+        let triangle = vec![vec![1, 2], vec![-1, -2], vec![2, 3], vec![-2, -3], vec![1, 3], vec![-1, -3]];
+        assert_eq!(solve_2sat(&triangle), None);
+        assert_eq!(solve_2sat(&vec![vec![1], vec![-2]]), Some([(1, true), (2, false)].into_iter().collect()));
+        assert!(!is_2sat(&[vec![1, 2, 3]]));
+
+        let mut rng = StdRng::seed_from_u64(17);
+        for _ in 0..300 {
+            let num_vars = rng.gen_range(1..=8);
+            let formula: Formula = (0..rng.gen_range(0..=14))
+                .map(|_| {
+                    let lit = |rng: &mut StdRng| rng.gen_range(1..=num_vars) * if rng.gen_bool(0.5) { 1 } else { -1 };
+                    (0..rng.gen_range(1..=2)).map(|_| lit(&mut rng)).collect()
+                })
+                .collect();
+            let satisfiable = (0..1u32 << num_vars).any(|bits| {
+                let model: Model = (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect();
+                verify_model(&formula, &model).is_ok()
+            });
+            match solve_2sat(&formula) {
+                Some(model) => assert!(verify_model(&formula, &model).is_ok(), "{:?} {:?}", formula, model),
+                None => assert!(!satisfiable, "{:?}", formula),
+            }
+        }
+    }
+}
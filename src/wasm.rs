@@ -0,0 +1,84 @@
+use js_sys::{Array, Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{parse_dimacs, DimacsError, ParseMode, SolveResult, Solver, Stats};
+
+// The outcome of solving a DIMACS text: SAT, UNSAT or UNKNOWN, the model as
+// one literal per variable if there is one, and the search statistics
+#[derive(Debug, PartialEq)]
+struct Answer {
+    result: &'static str,
+    model: Option<Vec<i32>>,
+    stats: Stats,
+}
+
+fn solve(text: &str) -> Result<Answer, DimacsError> {
+    let dimacs = parse_dimacs(text.as_bytes(), ParseMode::Lenient)?;
+    let mut solver = Solver::from_clauses(&dimacs.formula);
+    // Size the assignment for every variable the header declares
+    while solver.num_vars() < dimacs.num_vars as i32 {
+        solver.new_var();
+    }
+    for xor in dimacs.xors.iter() {
+        solver.add_xor(&xor.vars, xor.parity);
+    }
+    let result = solver.solve_limited();
+    let (result, model) = match result {
+        SolveResult::Sat(model) => {
+            // Variables the search did not need to set are free, true as the
+            // command line prints them
+            let lits = (1..=solver.num_vars()).map(|var| if model.get(&var) == Some(&false) { -var } else { var });
+            ("SAT", Some(lits.collect()))
+        }
+        SolveResult::Unsat => ("UNSAT", None),
+        SolveResult::Unknown => ("UNKNOWN", None),
+    };
+    Ok(Answer {
+        result,
+        model,
+        stats: solver.stats().clone(),
+    })
+}
+
+fn set(object: &Object, key: &str, value: impl Into<JsValue>) {
+    Reflect::set(object, &key.into(), &value.into()).expect("Setting a property of a plain object");
+}
+
+// Solve a DIMACS CNF text for JavaScript. The result is an object with
+// result, "SAT", "UNSAT" or "UNKNOWN", model, the array of the literals true
+// in the model or null, and stats with the search counters. Throws on text
+// that is not DIMACS.
+#[wasm_bindgen]
+pub fn solve_dimacs(text: &str) -> Result<JsValue, JsError> {
+    let answer = solve(text).map_err(|error| JsError::new(&error.to_string()))?;
+    let object = Object::new();
+    set(&object, "result", answer.result);
+    let model = match answer.model {
+        Some(lits) => lits.into_iter().map(JsValue::from).collect::<Array>().into(),
+        None => JsValue::NULL,
+    };
+    set(&object, "model", model);
+    let stats = Object::new();
+    set(&stats, "nodes", answer.stats.nodes);
+    set(&stats, "decisions", answer.stats.decisions);
+    set(&stats, "propagations", answer.stats.propagations);
+    set(&stats, "conflicts", answer.stats.conflicts);
+    set(&stats, "restarts", answer.stats.restarts);
+    set(&stats, "max_depth", answer.stats.max_depth);
+    set(&object, "stats", stats);
+    Ok(object.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve() {
+        let answer = solve("p cnf 3 2\n1 -2 0\n-1 0\n").unwrap();
+        assert_eq!(answer.result, "SAT");
+        assert_eq!(answer.model, Some(vec![-1, -2, 3]));
+        assert_eq!(solve(include_str!("../testcnf.cnf")).unwrap().result, "UNSAT");
+        assert!(solve("p cnf x\n").is_err());
+    }
+}
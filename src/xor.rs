@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use crate::Assignment;
+
+// A parity constraint: the XOR of the variables equals parity
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XorConstraint {
+    pub vars: Vec<i32>,
+    pub parity: bool,
+}
+
+impl XorConstraint {
+    // The constraint that an odd number of the literals is true, as in the
+    // "x" lines of CryptoMiniSat: a negative literal flips the parity
+    pub fn from_lits(lits: &[i32]) -> Self {
+        let negative = lits.iter().filter(|&&lit| lit < 0).count();
+        XorConstraint {
+            vars: lits.iter().map(|lit| lit.abs()).collect(),
+            parity: negative % 2 == 0,
+        }
+    }
+
+    pub fn is_satisfied(&self, model: &HashMap<i32, bool>) -> bool {
+        (self.vars.iter().filter(|var| model.get(var) == Some(&true)).count() % 2 == 1) == self.parity
+    }
+}
+
+// One equation of the system, a bit per column
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Row {
+    bits: Vec<u64>,
+    parity: bool,
+}
+
+impl Row {
+    fn get(&self, column: usize) -> bool {
+        self.bits[column / 64] >> (column % 64) & 1 == 1
+    }
+
+    fn clear(&mut self, column: usize) {
+        self.bits[column / 64] &= !(1 << (column % 64));
+    }
+
+    fn add(&mut self, other: &Row) {
+        for (word, other) in self.bits.iter_mut().zip(other.bits.iter()) {
+            *word ^= other;
+        }
+        self.parity ^= other.parity;
+    }
+
+    fn columns(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64).filter(move |bit| word >> bit & 1 == 1).map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+// XOR constraints as a linear system over GF(2), kept in reduced row echelon
+// form. The search substitutes the values it has assigned and eliminates
+// again, which reveals conflicts and implied variables that the constraints
+// only have together, where CNF propagation would see one clause at a time.
+#[derive(Clone, Debug, Default)]
+pub struct XorSystem {
+    // Variable of every column
+    vars: Vec<i32>,
+    columns: HashMap<i32, usize>,
+    rows: Vec<Row>,
+}
+
+// Gauss-Jordan elimination, dropping the rows that become 0 = 0. Each
+// remaining row leads with a pivot column that no other row has. The row
+// 0 = 1 is kept when the system is inconsistent.
+fn eliminate(rows: &mut Vec<Row>) {
+    let mut reduced: Vec<(usize, Row)> = Vec::new();
+    for mut row in rows.drain(..) {
+        for (pivot, other) in reduced.iter() {
+            if row.get(*pivot) {
+                row.add(other);
+            }
+        }
+        let Some(pivot) = row.columns().next() else {
+            if row.parity {
+                reduced.clear();
+                reduced.push((usize::MAX, row));
+                break;
+            }
+            continue;
+        };
+        for (_, other) in reduced.iter_mut() {
+            if other.get(pivot) {
+                other.add(&row);
+            }
+        }
+        reduced.push((pivot, row));
+    }
+    rows.extend(reduced.into_iter().map(|(_, row)| row));
+}
+
+impl XorSystem {
+    pub fn new(constraints: &[XorConstraint]) -> Self {
+        let mut system = XorSystem::default();
+        for constraint in constraints.iter() {
+            for &var in constraint.vars.iter() {
+                if !system.columns.contains_key(&var) {
+                    system.columns.insert(var, system.vars.len());
+                    system.vars.push(var);
+                }
+            }
+        }
+        let words = system.vars.len().div_ceil(64);
+        for constraint in constraints.iter() {
+            let mut row = Row {
+                bits: vec![0; words],
+                parity: constraint.parity,
+            };
+            // A variable twice cancels out
+            for var in constraint.vars.iter() {
+                let column = system.columns[var];
+                row.bits[column / 64] ^= 1 << (column % 64);
+            }
+            system.rows.push(row);
+        }
+        eliminate(&mut system.rows);
+        system
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // Whether the variable occurs in some constraint
+    pub fn contains(&self, var: i32) -> bool {
+        self.columns.contains_key(&var)
+    }
+
+    // The system with the assigned variables replaced by their values,
+    // eliminated again. None if it is inconsistent.
+    fn reduce(&self, assignment: &Assignment) -> Option<Vec<Row>> {
+        let mut rows = self.rows.clone();
+        for row in rows.iter_mut() {
+            for column in row.columns().collect::<Vec<_>>() {
+                if let Some(&Some(val)) = assignment.get(&self.vars[column]) {
+                    row.clear(column);
+                    row.parity ^= val;
+                }
+            }
+        }
+        eliminate(&mut rows);
+        match rows.first() {
+            Some(row) if row.columns().next().is_none() => None,
+            _ => Some(rows),
+        }
+    }
+
+    // The literals the constraints force under the assignment, from the rows
+    // left with a single unassigned variable. None on a conflict.
+    pub fn propagate(&self, assignment: &Assignment) -> Option<Vec<i32>> {
+        let rows = self.reduce(assignment)?;
+        let implied = rows.iter().filter_map(|row| {
+            let mut columns = row.columns();
+            let column = columns.next()?;
+            let var = self.vars[column];
+            columns.next().is_none().then_some(if row.parity { var } else { -var })
+        });
+        Some(implied.collect())
+    }
+
+    // Values of the unassigned variables that satisfy every constraint along
+    // with the assignment: every variable without a row of its own is false
+    // and each row then fixes its pivot. None if there are none.
+    pub fn complete(&self, assignment: &Assignment) -> Option<Vec<i32>> {
+        let rows = self.reduce(assignment)?;
+        let mut values = Vec::new();
+        let mut pivots = Vec::new();
+        for row in rows.iter() {
+            let pivot = row.columns().next().expect("A consistent reduced row has a pivot");
+            pivots.push(pivot);
+            let var = self.vars[pivot];
+            values.push(if row.parity { var } else { -var });
+        }
+        for (column, &var) in self.vars.iter().enumerate() {
+            if assignment.get(&var).is_none_or(Option::is_none) && !pivots.contains(&column) {
+                values.push(-var);
+            }
+        }
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_xor_system() {
+        // x1 ^ x2 = 1, x2 ^ x3 = 1 and x1 ^ x3 = 1 sum to 0 = 1
+        let odd = |vars: Vec<i32>| XorConstraint { vars, parity: true };
+        let triangle = XorSystem::new(&[odd(vec![1, 2]), odd(vec![2, 3]), odd(vec![1, 3])]);
+        assert_eq!(triangle.propagate(&Assignment::new()), None);
+        // Once x1 is known the chain forces x2 and through it x3
+        let chain = XorSystem::new(&[odd(vec![1, 2]), odd(vec![2, 3])]);
+        assert_eq!(chain.propagate(&Assignment::new()), Some(vec![]));
+        let assignment: Assignment = [(1, Some(true))].into_iter().collect();
+        let mut implied = chain.propagate(&assignment).unwrap();
+        implied.sort();
+        assert_eq!(implied, vec![-2, 3]);
+        assert_eq!(XorConstraint::from_lits(&[1, -2]), XorConstraint { vars: vec![1, 2], parity: false });
+    }
+
+    #[test]
+    fn test_complete() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..200 {
+            let num_vars = rng.gen_range(1..=8);
+            let constraints: Vec<XorConstraint> = (0..rng.gen_range(1..=6))
+                .map(|_| XorConstraint {
+                    vars: (1..=num_vars).filter(|_| rng.gen_bool(0.4)).collect(),
+                    parity: rng.gen_bool(0.5),
+                })
+                .collect();
+            let mut assignment = Assignment::new();
+            for var in 1..=num_vars {
+                if rng.gen_bool(0.3) {
+                    assignment.insert(var, Some(rng.gen_bool(0.5)));
+                }
+            }
+            let system = XorSystem::new(&constraints);
+            let satisfiable = (0..1u32 << num_vars).any(|bits| {
+                let model: HashMap<i32, bool> = (1..=num_vars).map(|var| (var, bits >> (var - 1) & 1 == 1)).collect();
+                assignment.iter().all(|(var, val)| Some(model[&var]) == val)
+                    && constraints.iter().all(|constraint| constraint.is_satisfied(&model))
+            });
+            match system.complete(&assignment) {
+                Some(values) => {
+                    let mut model: HashMap<i32, bool> = (1..=num_vars).map(|var| (var, false)).collect();
+                    model.extend(assignment.iter().map(|(var, val)| (var, val.unwrap())));
+                    model.extend(values.iter().map(|&lit| (lit.abs(), lit > 0)));
+                    assert!(constraints.iter().all(|constraint| constraint.is_satisfied(&model)), "{:?}", constraints);
+                }
+                None => assert!(!satisfiable, "{:?} {:?}", constraints, assignment),
+            }
+            assert_eq!(system.propagate(&assignment).is_some(), satisfiable, "{:?}", constraints);
+        }
+    }
+}
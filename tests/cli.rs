@@ -0,0 +1,381 @@
+// The dpll binary run on instances written to a temporary directory
+#![cfg(feature = "cli")]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn dpll(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dpll")).args(args).output().expect("Failed to run dpll")
+}
+
+// Write the text to a file of the name, unique to the test run
+fn input(name: &str, text: &str) -> String {
+    let dir: PathBuf = std::env::temp_dir().join(format!("dpll-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn cnf(formula: &[Vec<i32>]) -> String {
+    let num_vars = formula.iter().flatten().map(|lit| lit.abs()).max().unwrap_or(0);
+    let mut text = format!("p cnf {} {}\n", num_vars, formula.len());
+    for clause in formula.iter() {
+        let lits: Vec<String> = clause.iter().map(i32::to_string).collect();
+        text += &format!("{} 0\n", lits.join(" "));
+    }
+    text
+}
+
+// Pigeons one more than the holes, each in a hole of its own
+fn pigeonhole(holes: i32) -> Vec<Vec<i32>> {
+    let var = |pigeon: i32, hole: i32| pigeon * holes + hole + 1;
+    let mut formula: Vec<Vec<i32>> =
+        (0..=holes).map(|pigeon| (0..holes).map(|hole| var(pigeon, hole)).collect()).collect();
+    for hole in 0..holes {
+        for pigeon in 0..=holes {
+            for other in pigeon + 1..=holes {
+                formula.push(vec![-var(pigeon, hole), -var(other, hole)]);
+            }
+        }
+    }
+    formula
+}
+
+// Clauses of three distinct variables out of num_vars, from a xorshift
+// generator
+fn random_3sat(num_vars: i32, num_clauses: usize, seed: u64) -> Vec<Vec<i32>> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    let mut next = |bound: i32| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as i32
+    };
+    (0..num_clauses)
+        .map(|_| {
+            let mut clause: Vec<i32> = Vec::new();
+            while clause.len() < 3 {
+                let var = next(num_vars) + 1;
+                if !clause.iter().any(|lit| lit.abs() == var) {
+                    clause.push(if next(2) == 0 { var } else { -var });
+                }
+            }
+            clause
+        })
+        .collect()
+}
+
+fn satisfies(formula: &[Vec<i32>], lits: &[i32]) -> bool {
+    formula.iter().all(|clause| clause.iter().any(|lit| lits.contains(lit)))
+}
+
+// Whether some assignment of the variables 1 to num_vars satisfies the
+// formula, by trying them all
+fn satisfiable(formula: &[Vec<i32>], num_vars: i32) -> bool {
+    (0..1u32 << num_vars).any(|bits| {
+        let lits: Vec<i32> = (1..=num_vars).map(|var| if bits >> (var - 1) & 1 == 1 { var } else { -var }).collect();
+        satisfies(formula, &lits)
+    })
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+// The literals of the v lines, without the closing 0
+fn model(output: &Output) -> Vec<i32> {
+    let text = stdout(output);
+    let lits = text.lines().filter_map(|line| line.strip_prefix("v ")).flat_map(|line| {
+        line.split_whitespace().map(|lit| lit.parse::<i32>().unwrap()).collect::<Vec<i32>>()
+    });
+    lits.filter(|&lit| lit != 0).collect()
+}
+
+#[test]
+fn test_answer() {
+    // main prints the answer the search returns, and the model of a
+    // satisfiable formula
+    for seed in 0..6 {
+        let formula = random_3sat(12, 52, seed);
+        let output = dpll(&[&input(&format!("random{}.cnf", seed), &cnf(&formula))]);
+        let answers: Vec<String> =
+            stdout(&output).lines().filter(|line| line.starts_with("s ")).map(String::from).collect();
+        if satisfiable(&formula, 12) {
+            assert_eq!(answers, ["s SATISFIABLE"]);
+            assert!(satisfies(&formula, &model(&output)), "{}", stdout(&output));
+        } else {
+            assert_eq!(answers, ["s UNSATISFIABLE"]);
+            assert!(model(&output).is_empty());
+        }
+    }
+    let output = dpll(&["-q", &input("pigeonhole.cnf", &cnf(&pigeonhole(3)))]);
+    assert_eq!(stdout(&output), "s UNSATISFIABLE\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_interrupt() {
+    // Ctrl-C stops the search, which prints its statistics so far
+    let path = input("interrupted.cnf", &cnf(&pigeonhole(10)));
+    let child = Command::new(env!("CARGO_BIN_EXE_dpll"))
+        .args(["--stats", &path])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let killed = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(killed.success());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).starts_with("s UNKNOWN\n"), "{}", stdout(&output));
+    assert!(stdout(&output).contains("c Conflicts: "));
+}
+
+#[test]
+fn test_command_line() {
+    let help = stdout(&dpll(&["--help"]));
+    for option in ["[PATH]", "--timeout", "--heuristic", "--seed", "--stats", "--quiet", "--proof"] {
+        assert!(help.contains(option), "{}", option);
+    }
+    let path = input("options.cnf", &cnf(&pigeonhole(3)));
+    let output = dpll(&["--heuristic", "dlis", "--seed", "7", "--learn", "--timeout", "60", "--stats", &path]);
+    assert_eq!(output.status.code(), Some(20));
+    assert!(stdout(&output).contains("c Decisions: "));
+
+    // Unknown options and heuristics are usage errors, a missing file fails
+    assert_eq!(dpll(&["--no-such-option", &path]).status.code(), Some(2));
+    assert_eq!(dpll(&["--heuristic", "nope", &path]).status.code(), Some(2));
+    assert_eq!(dpll(&["--heuristic", "moms:x", &path]).status.code(), Some(2));
+    let absent = std::env::temp_dir().join(format!("dpll-cli-{}-absent.cnf", std::process::id()));
+    assert!(!dpll(&[absent.to_str().unwrap()]).status.success());
+}
+
+#[test]
+fn test_check() {
+    let path = input("check.cnf", &cnf(&pigeonhole(3)));
+    let proof = input("check.drat", "");
+    assert_eq!(dpll(&["--proof", &proof, &path]).status.code(), Some(20));
+    let output = dpll(&["check", &path, &proof]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(0), String::from("s VERIFIED\n")));
+
+    let output = dpll(&["check", &path, &input("wrong.drat", "1 0\n")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).starts_with("s NOT VERIFIED"), "{}", stdout(&output));
+    let absent = std::env::temp_dir().join(format!("dpll-cli-{}-absent.drat", std::process::id()));
+    let output = dpll(&["check", &path, absent.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Failed to read proof file"), "{}", stderr(&output));
+
+    // LRAT proofs are written too, other formats are usage errors
+    let lrat = input("check.lrat", "");
+    assert_eq!(dpll(&["--proof", &lrat, "--proof-format", "lrat", &path]).status.code(), Some(20));
+    assert!(!std::fs::read_to_string(&lrat).unwrap().is_empty());
+    assert_eq!(dpll(&["--proof", &lrat, "--proof-format", "nope", &path]).status.code(), Some(2));
+}
+
+#[test]
+fn test_count() {
+    let output = dpll(&["count", &input("count.cnf", "p cnf 3 1\n1 2 3 0\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(0), String::from("s mc 7\n")));
+    let output = dpll(&["count", &input("count_unsat.cnf", &cnf(&pigeonhole(3)))]);
+    assert_eq!(stdout(&output), "s mc 0\n");
+}
+
+#[test]
+fn test_maxsat() {
+    // 1 2 is hard, violating -1 costs least
+    let path = input("maxsat.wcnf", "p wcnf 2 4 10\n10 1 2 0\n3 -1 0\n4 -2 0\n2 1 0\n");
+    for algorithm in ["linear", "unsat-sat", "binary", "core"] {
+        let output = dpll(&["maxsat", "--algorithm", algorithm, &path]);
+        assert_eq!(output.status.code(), Some(30));
+        assert_eq!(stdout(&output), "o 3\ns OPTIMUM FOUND\nv 10\n", "{}", algorithm);
+    }
+    let output = dpll(&["maxsat", &input("maxsat_unsat.wcnf", "p wcnf 1 2 5\n5 1 0\n5 -1 0\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s UNSATISFIABLE\n")));
+
+    let output = dpll(&["maxsat", &input("maxsat_invalid.wcnf", "p wcnf 1 1 10\n10 1 x 0\n")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "Invalid WCNF: line 2: invalid literal \"x\"\n");
+}
+
+#[test]
+fn test_mus() {
+    // The first three clauses conflict, the last two are satisfiable
+    let output = dpll(&["mus", &input("mus.cnf", "p cnf 3 5\n1 0\n-1 2 0\n-2 0\n3 0\n-3 1 0\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s UNSATISFIABLE\nv 1 2 3 0\n")));
+    let output = dpll(&["mus", &input("mus_sat.cnf", "p cnf 2 1\n1 2 0\n")]);
+    assert_eq!(output.status.code(), Some(10));
+}
+
+#[test]
+fn test_exit_codes() {
+    let formula = random_3sat(12, 30, 1);
+    assert!(satisfiable(&formula, 12));
+    let output = dpll(&[&input("exit_sat.cnf", &cnf(&formula))]);
+    assert_eq!(output.status.code(), Some(10));
+    // Every variable once in v lines of at most ten literals, then v 0
+    let text = stdout(&output);
+    let lines: Vec<&str> = text.lines().filter(|line| line.starts_with("v ")).collect();
+    assert_eq!(lines.last(), Some(&"v 0"));
+    assert!(lines.iter().all(|line| line.split_whitespace().count() <= 11));
+    let mut vars: Vec<i32> = model(&output).iter().map(|lit| lit.abs()).collect();
+    vars.sort_unstable();
+    assert_eq!(vars, (1..=12).collect::<Vec<i32>>());
+
+    let unsat = input("exit_unsat.cnf", &cnf(&pigeonhole(3)));
+    assert_eq!(dpll(&[&unsat]).status.code(), Some(20));
+    // Local search cannot refute a formula, nor does the search finish in
+    // no time
+    let output = dpll(&["--engine", "sls", "--flips", "100", &unsat]);
+    assert_eq!((output.status.code(), model(&output)), (Some(0), vec![]));
+    assert!(stdout(&output).starts_with("s UNKNOWN\n"));
+    let output = dpll(&["--timeout", "0", &input("exit_timeout.cnf", &cnf(&pigeonhole(9)))]);
+    assert_eq!((output.status.code(), model(&output)), (Some(0), vec![]));
+    assert!(stdout(&output).starts_with("s UNKNOWN\n"));
+}
+
+#[test]
+fn test_qbf() {
+    // For every 1 there is a 2 that differs, but no 1 that every 2 satisfies
+    // both clauses with
+    let output = dpll(&["qbf", &input("qbf_true.qdimacs", "p cnf 2 2\na 1 0\ne 2 0\n1 2 0\n-1 -2 0\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(10), String::from("s cnf 1\n")));
+    let output = dpll(&["qbf", &input("qbf_false.qdimacs", "p cnf 2 2\ne 1 0\na 2 0\n1 2 0\n-1 -2 0\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s cnf 0\n")));
+    assert!(!dpll(&["qbf", &input("qbf_bad.qdimacs", "p cnf 1 1\nx 1 0\n1 0\n")]).status.success());
+}
+
+#[test]
+fn test_opb() {
+    // Two of the three, not both 1 and 3: 1 and 2 cost least
+    let text = "* #variable= 3 #constraint= 2\nmin: +1 x1 +2 x2 +3 x3 ;\n\
+                +1 x1 +1 x2 +1 x3 >= 2 ;\n+1 x1 +1 x3 <= 1 ;\n";
+    let output = dpll(&["opb", &input("opb.opb", text)]);
+    assert_eq!(output.status.code(), Some(30));
+    assert_eq!(stdout(&output), "o 3\ns OPTIMUM FOUND\nv x1 x2 -x3\n");
+    let output = dpll(&["opb", &input("opb_unsat.opb", "+1 x1 +1 x2 >= 3 ;\n")]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s UNSATISFIABLE\n")));
+}
+
+#[test]
+fn test_gen() {
+    let generate = |args: &[&str]| {
+        let output = dpll(&[&["gen"], args].concat());
+        assert_eq!(output.status.code(), Some(0));
+        stdout(&output)
+    };
+    let text = generate(&["30", "100", "--seed", "4"]);
+    let dimacs = dpll::parse_dimacs(text.as_bytes(), dpll::ParseMode::Strict).unwrap();
+    assert_eq!(dimacs.formula.len(), 100);
+    assert!(dimacs.formula.iter().all(|clause| clause.len() == 3 && clause.iter().all(|lit| lit.abs() <= 30)));
+    assert_eq!(text, generate(&["30", "100", "--seed", "4"]));
+    assert_ne!(text, generate(&["30", "100", "--seed", "5"]));
+    let text = generate(&["30", "100", "-k", "2"]);
+    let dimacs = dpll::parse_dimacs(text.as_bytes(), dpll::ParseMode::Strict).unwrap();
+    assert!(dimacs.formula.iter().all(|clause| clause.len() == 2));
+    assert!(!dpll(&["gen", "2", "5", "-k", "3"]).status.success());
+}
+
+#[test]
+fn test_json_output() {
+    let json = |output: &Output| -> serde_json::Value { serde_json::from_str(&stdout(output)).unwrap() };
+    let formula = random_3sat(12, 30, 1);
+    let output = dpll(&["--output", "json", &input("json_sat.cnf", &cnf(&formula))]);
+    assert_eq!(output.status.code(), Some(10));
+    let value = json(&output);
+    assert_eq!(value["status"], "sat");
+    let model: HashMap<String, bool> = serde_json::from_value(value["model"].clone()).unwrap();
+    let lits: Vec<i32> =
+        model.iter().map(|(var, &val)| var.parse::<i32>().unwrap() * if val { 1 } else { -1 }).collect();
+    assert!(satisfies(&formula, &lits));
+    assert!(value["stats"]["decisions"].is_u64() && value["time_ms"].is_f64());
+
+    let unsat = input("json_unsat.cnf", &cnf(&pigeonhole(3)));
+    let output = dpll(&["--output", "json", &unsat]);
+    assert_eq!(output.status.code(), Some(20));
+    let value = json(&output);
+    assert_eq!((&value["status"], &value["model"]), (&serde_json::json!("unsat"), &serde_json::Value::Null));
+    assert!(value["stats"]["conflicts"].as_u64().unwrap() > 0);
+    let output = dpll(&["--output", "json", "--timeout", "0", &input("json_timeout.cnf", &cnf(&pigeonhole(9)))]);
+    assert_eq!(json(&output)["status"], "unknown");
+}
+
+#[test]
+fn test_verbosity() {
+    // The log goes to stderr, the answer stays alone on stdout
+    let path = input("verbose.cnf", &cnf(&random_3sat(12, 30, 1)));
+    let quiet = dpll(&[&path]);
+    assert!(!stderr(&quiet).contains("DEBUG"));
+    let verbose = dpll(&["-v", &path]);
+    assert!(stderr(&verbose).contains("c DEBUG branching on"), "{}", stderr(&verbose));
+    assert!(!stderr(&verbose).contains("TRACE"));
+    let very_verbose = dpll(&["-vv", &path]);
+    assert!(stderr(&very_verbose).contains("c TRACE"));
+    for output in [quiet, verbose, very_verbose] {
+        assert_eq!(output.status.code(), Some(10));
+        assert!(stdout(&output).starts_with("s SATISFIABLE\n"));
+    }
+}
+
+#[test]
+fn test_analyze() {
+    let output = dpll(&["analyze", &input("analyze.cnf", "p cnf 2 2\n1 2 0\n-1 0\n")]);
+    assert_eq!(output.status.code(), Some(0));
+    let lines: Vec<String> = stdout(&output).lines().map(String::from).collect();
+    for line in ["Variables: 2", "Clauses: 2", "Clause lengths: 1: 1, 2: 1", "Unit literals: 1", "Fragment: 2-SAT"] {
+        assert!(lines.iter().any(|other| other == line), "{}", line);
+    }
+}
+
+#[test]
+fn test_sudoku() {
+    use dpll::encoders::sudoku::Grid;
+
+    let puzzle = "53..7....\n6..195...\n.98....6.\n8...6...3\n4..8.3..1\n7...2...6\n.6....28.\n...419..5\n....8..79\n";
+    let path = input("sudoku.txt", puzzle);
+    for encoding in ["pairwise", "ladder", "commander", "bimander"] {
+        let output = dpll(&["sudoku", "--encoding", encoding, &path]);
+        assert_eq!(output.status.code(), Some(10));
+        let text = stdout(&output);
+        let grid = text.strip_prefix("s SATISFIABLE\n").unwrap();
+        let solution = Grid::parse(grid).unwrap();
+        assert!(solution.is_solved() && Grid::parse(puzzle).unwrap().is_completed_by(&solution));
+    }
+    let output = dpll(&["sudoku", "--cnf", &path]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).lines().any(|line| line.starts_with("p cnf 729 ")));
+
+    // Two 5s in the first row
+    let output = dpll(&["sudoku", &input("sudoku_conflict.txt", &puzzle.replacen("53", "55", 1))]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s UNSATISFIABLE\n")));
+    let output = dpll(&["sudoku", &input("sudoku_bad.txt", "12\n3\n")]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("Invalid grid"));
+}
+
+#[test]
+fn test_nqueens() {
+    let output = dpll(&["nqueens", "4"]);
+    assert_eq!(output.status.code(), Some(10));
+    let text = stdout(&output);
+    let rows: Vec<&str> = text.strip_prefix("s SATISFIABLE\n").unwrap().lines().collect();
+    let cols: Vec<usize> = rows.iter().map(|row| row.split(' ').position(|square| square == "Q").unwrap()).collect();
+    assert!(cols == [1, 3, 0, 2] || cols == [2, 0, 3, 1], "{}", text);
+    for encoding in ["ladder", "commander", "bimander"] {
+        assert_eq!(dpll(&["nqueens", "--encoding", encoding, "8"]).status.code(), Some(10));
+    }
+    let output = dpll(&["nqueens", "3"]);
+    assert_eq!((output.status.code(), stdout(&output)), (Some(20), String::from("s UNSATISFIABLE\n")));
+
+    // Ladder variables come after the 36 squares
+    let output = dpll(&["nqueens", "--cnf", "--encoding", "ladder", "6"]);
+    let text = stdout(&output);
+    let header = text.lines().find(|line| line.starts_with("p cnf ")).unwrap();
+    assert!(header["p cnf ".len()..].split(' ').next().unwrap().parse::<usize>().unwrap() > 36, "{}", header);
+}